@@ -8,6 +8,10 @@ use powdr_ast::{
 };
 use powdr_pilopt::referenced_symbols::ReferencedSymbols;
 
+mod lints;
+
+pub use lints::lint;
+
 type Expression = powdr_ast::asm_analysis::Expression<NamespacedPolynomialReference>;
 
 const MAIN_MACHINE_STR: &str = "::Main";
@@ -0,0 +1,129 @@
+use std::collections::{HashMap, HashSet};
+
+use powdr_ast::asm_analysis::{AnalysisASMFile, Machine};
+use powdr_parser_util::{Lint, Warning};
+
+use super::{
+    machine_callable_body_symbols, machine_remove_unused_instructions,
+    machine_remove_unused_registers, MAIN_MACHINE_STR,
+};
+
+/// Returns warnings about instructions and registers the optimizer would remove and about
+/// operations that are not the target of any link, except for the ones suppressed by a comment.
+/// Machines of the standard library are not linted.
+pub fn lint(asm_file: &AnalysisASMFile) -> Vec<Warning> {
+    let linked_operations = linked_operations(asm_file);
+    asm_file
+        .machines()
+        .filter(|(path, _)| path.parts().next() != Some("std"))
+        .flat_map(|(path, machine)| {
+            let path = path.to_string();
+            let unlinked_operations = machine
+                .operation_definitions()
+                .filter(|op| {
+                    path != MAIN_MACHINE_STR
+                        && !linked_operations.contains(&(path.clone(), op.name.to_string()))
+                })
+                .map(|op| {
+                    op.operation.source.with_warning(
+                        Lint::UnlinkedOperation,
+                        format!(
+                            "Operation `{}` of machine `{path}` is not the target of any link.",
+                            op.name
+                        ),
+                    )
+                })
+                .collect::<Vec<_>>();
+            unused_components(&path, machine)
+                .into_iter()
+                .chain(unlinked_operations)
+        })
+        .filter(|warning| !warning.is_suppressed())
+        .collect()
+}
+
+/// Returns warnings for the instructions and registers that would be removed by
+/// `machine_remove_unused_instructions` and `machine_remove_unused_registers`.
+fn unused_components(path: &str, machine: &Machine) -> Vec<Warning> {
+    let submachine_to_decl: HashMap<String, String> = machine
+        .submachines
+        .iter()
+        .map(|sub| (sub.name.clone(), sub.ty.to_string()))
+        .collect();
+    let symbols_in_callable: HashSet<String> = machine_callable_body_symbols(machine).collect();
+
+    let mut optimized = machine.clone();
+    machine_remove_unused_instructions(&mut optimized, &symbols_in_callable);
+    machine_remove_unused_registers(&mut optimized, &submachine_to_decl);
+
+    let remaining_instructions = optimized
+        .instructions
+        .iter()
+        .map(|ins| &ins.name)
+        .collect::<HashSet<_>>();
+    let remaining_registers = optimized
+        .registers
+        .iter()
+        .map(|reg| &reg.name)
+        .collect::<HashSet<_>>();
+
+    let unused_instructions = machine
+        .instructions
+        .iter()
+        .filter(|ins| !remaining_instructions.contains(&ins.name))
+        .map(|ins| {
+            ins.source.with_warning(
+                Lint::UnusedInstruction,
+                format!(
+                    "Instruction `{}` of machine `{path}` is never used.",
+                    ins.name
+                ),
+            )
+        });
+    let unused_registers = machine
+        .registers
+        .iter()
+        .filter(|reg| !remaining_registers.contains(&reg.name))
+        .map(|reg| {
+            reg.source.with_warning(
+                Lint::UnusedRegister,
+                format!("Register `{}` of machine `{path}` is never used.", reg.name),
+            )
+        });
+    unused_instructions.chain(unused_registers).collect()
+}
+
+/// Returns the machine types and names of all operations that are the target of a link
+/// or of an instruction link.
+fn linked_operations(asm_file: &AnalysisASMFile) -> HashSet<(String, String)> {
+    asm_file
+        .machines()
+        .flat_map(|(_, machine)| {
+            let instance_types: HashMap<&str, String> = machine
+                .submachines
+                .iter()
+                .map(|sub| (sub.name.as_str(), sub.ty.to_string()))
+                .chain(machine.params.0.iter().filter_map(|param| {
+                    Some((param.name.as_str(), param.ty.as_ref()?.to_string()))
+                }))
+                .collect();
+            machine
+                .links
+                .iter()
+                .map(|link| &link.to)
+                .chain(
+                    machine
+                        .instructions
+                        .iter()
+                        .flat_map(|ins| ins.instruction.links.iter().map(|link| &link.link)),
+                )
+                .filter_map(|to| {
+                    Some((
+                        instance_types.get(to.instance.as_str())?.clone(),
+                        to.callable.clone(),
+                    ))
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
@@ -0,0 +1,110 @@
+use powdr_analysis::analyze;
+use powdr_asmopt::lint;
+use powdr_parser::parse_asm;
+use powdr_parser_util::Lint;
+
+fn lint_string(input: &str) -> Vec<(Lint, String)> {
+    let parsed = parse_asm(None, input).unwrap();
+    let analyzed = analyze(parsed).unwrap();
+    lint(&analyzed)
+        .into_iter()
+        .map(|w| (w.lint(), w.message().to_string()))
+        .collect()
+}
+
+#[test]
+fn unused_instructions_and_registers() {
+    let input = r#"
+    machine Main with degree: 8 {
+        reg pc[@pc];
+        reg X[<=];
+        reg A;
+        reg B;
+
+        instr assert_eq X, A { X = A }
+        instr unused X { X = B }
+
+        function main {
+            assert_eq 1, 1;
+            return;
+        }
+    }
+    "#;
+    assert_eq!(
+        lint_string(input),
+        vec![
+            (
+                Lint::UnusedInstruction,
+                "Instruction `unused` of machine `::Main` is never used.".to_string()
+            ),
+            (
+                Lint::UnusedRegister,
+                "Register `B` of machine `::Main` is never used.".to_string()
+            ),
+        ]
+    );
+}
+
+#[test]
+fn unlinked_operation() {
+    let input = r#"
+    machine Main with degree: 8 {
+        Sub sub;
+
+        reg pc[@pc];
+        reg X[<=];
+        reg Y[<=];
+        reg A;
+
+        instr add X -> Y link => Y = sub.add(X);
+
+        function main {
+            A <== add(1);
+            return;
+        }
+    }
+
+    machine Sub with
+        degree: 8,
+        latch: latch,
+        operation_id: operation_id
+    {
+        operation add<0> x -> y;
+        operation double<1> x -> y;
+
+        col fixed latch = [1]*;
+        col witness operation_id;
+        col witness x, y;
+        y = x + 1;
+    }
+    "#;
+    assert_eq!(
+        lint_string(input),
+        vec![(
+            Lint::UnlinkedOperation,
+            "Operation `double` of machine `::Sub` is not the target of any link.".to_string()
+        )]
+    );
+}
+
+#[test]
+fn suppressed() {
+    let input = r#"
+    machine Main with degree: 8 {
+        reg pc[@pc];
+        reg X[<=];
+        reg A;
+        // lint: allow(unused-register)
+        reg B;
+
+        instr assert_eq X, A { X = A }
+        instr unused X { X = B } // lint: allow(unused-instruction)
+
+        function main {
+            assert_eq 1, 1;
+            return;
+        }
+    }
+    "#;
+    assert_eq!(lint_string(input), vec![]);
+}
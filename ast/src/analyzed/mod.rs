@@ -627,6 +627,16 @@ impl SolvedTraitImpls {
             .unwrap()
     }
 
+    /// Returns all solved implementations as tuples of the trait function name,
+    /// the type arguments, the index of the trait implementation and the function.
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &Vec<Type>, usize, &Arc<Expression>)> {
+        self.impls.iter().flat_map(|(name, impls)| {
+            impls
+                .iter()
+                .map(move |(type_args, data)| (name, type_args, data.index, &data.function))
+        })
+    }
+
     pub fn insert(
         &mut self,
         trait_function_name: String,
@@ -120,6 +120,7 @@ pub enum ModuleRef<'a> {
 
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct Import {
+    pub source: SourceRef,
     /// the path imported in the source
     pub path: SymbolPath,
 }
@@ -17,9 +17,13 @@ use powdr_ast::{
         Expression,
     },
 };
-use powdr_executor_utils::VariablySizedColumn;
+use powdr_executor_utils::{FixedColumnValues, VariablySizedColumn};
 use powdr_number::{DegreeType, FieldElement};
 
+mod machine_merging;
+
+pub use machine_merging::{MergedLayout, StackedLayout};
+
 const DUMMY_COLUMN_NAME: &str = "__dummy";
 
 /// Splits a PIL into multiple PILs, one for each "machine".
@@ -29,6 +33,87 @@ const DUMMY_COLUMN_NAME: &str = "__dummy";
 /// 3. Any lookups or permutations that reference multiple namespaces are removed.
 pub fn split_pil<F: FieldElement>(pil: &Analyzed<F>) -> BTreeMap<String, Analyzed<F>> {
     let statements_by_namespace = split_by_namespace(pil);
+    build_machine_pils(pil, statements_by_namespace)
+}
+
+/// A machine consisting of several namespaces of static size, proven instead of the machines
+/// of its namespaces.
+pub struct MergedMachine<F> {
+    /// The namespaces of the machine, in alphabetical order.
+    pub namespaces: Vec<String>,
+    pub layout: MergedLayout,
+    pub pil: Analyzed<F>,
+}
+
+/// Returns the machines of namespaces that are proven together, replacing the machines of
+/// these namespaces in [split_pil]. The namespaces and their layout are chosen by a cost model
+/// that weighs the overhead of each proof against the cells of the columns. Stacked namespaces
+/// get selector columns, which raise the degree of their constraints by one, so this is only
+/// done if the degree stays within `max_degree`, if given.
+/// A merged machine is named after its namespaces, joined by `+`.
+pub fn merged_machine_pils<F: FieldElement>(
+    pil: &Analyzed<F>,
+    fixed: &[(String, VariablySizedColumn<F>)],
+    max_degree: Option<usize>,
+) -> BTreeMap<String, MergedMachine<F>> {
+    let mut statements_by_namespace = split_by_namespace(pil);
+    let mut namespaces_by_machine = BTreeMap::new();
+    let mut merged_machines = BTreeMap::new();
+    for group in machine_merging::merge_groups(&statements_by_namespace, pil, max_degree) {
+        match group {
+            machine_merging::MergeGroup::SideBySide(namespaces) => {
+                let machine_name = namespaces.join("+");
+                let statements = namespaces
+                    .iter()
+                    .flat_map(|namespace| statements_by_namespace.remove(namespace).unwrap())
+                    .collect();
+                statements_by_namespace.insert(machine_name.clone(), statements);
+                namespaces_by_machine.insert(machine_name, namespaces);
+            }
+            machine_merging::MergeGroup::Stacked(candidates) => {
+                let namespaces = candidates
+                    .iter()
+                    .map(|candidate| candidate.name.clone())
+                    .collect::<Vec<_>>();
+                let namespace = format!("__stacked_{}", merged_machines.len());
+                let (layout, pil_string) = machine_merging::stack(namespace, candidates, fixed);
+                let pil = analyze_machine_pil(&pil_string);
+                merged_machines.insert(
+                    namespaces.join("+"),
+                    MergedMachine {
+                        namespaces,
+                        layout: MergedLayout::Stacked(layout),
+                        pil,
+                    },
+                );
+            }
+        }
+    }
+
+    merged_machines.extend(
+        merge_empty_namespaces(statements_by_namespace, pil)
+            .into_iter()
+            .filter_map(|(machine_name, statements)| {
+                let namespaces = namespaces_by_machine.remove(&machine_name)?;
+                let pil = build_machine_pil(pil.clone(), statements);
+                let layout = MergedLayout::SideBySide;
+                Some((
+                    machine_name,
+                    MergedMachine {
+                        namespaces,
+                        layout,
+                        pil,
+                    },
+                ))
+            }),
+    );
+    merged_machines
+}
+
+fn build_machine_pils<F: FieldElement>(
+    pil: &Analyzed<F>,
+    statements_by_namespace: BTreeMap<String, Vec<StatementIdentifier>>,
+) -> BTreeMap<String, Analyzed<F>> {
     let statements_by_machine = merge_empty_namespaces(statements_by_namespace, pil);
 
     statements_by_machine
@@ -51,7 +136,13 @@ pub fn machine_witness_columns<F: FieldElement>(
         machine_pil.committed_polys_in_source_order(),
     );
 
-    let dummy_column_name = format!("{machine_name}::{DUMMY_COLUMN_NAME}");
+    // The dummy column of a merged machine is in its first namespace.
+    let dummy_column_name = machine_pil
+        .committed_polys_in_source_order()
+        .map(|(symbol, _)| &symbol.absolute_name)
+        .find(|name| name.ends_with(&format!("::{DUMMY_COLUMN_NAME}")))
+        .cloned()
+        .unwrap_or_else(|| format!("{machine_name}::{DUMMY_COLUMN_NAME}"));
 
     if machine_columns
         .iter()
@@ -82,10 +173,32 @@ pub fn machine_witness_columns<F: FieldElement>(
 }
 
 /// Given a set of columns and a PIL describing the machine, returns the fixed column that belong to the machine.
+/// Columns that are stored compactly are materialized, see [machine_fixed_column_values]
+/// to avoid this.
 pub fn machine_fixed_columns<'a, F: FieldElement>(
     all_fixed_columns: &'a [(String, VariablySizedColumn<F>)],
     machine_pil: &'a Analyzed<F>,
 ) -> BTreeMap<DegreeType, Vec<(String, &'a [F])>> {
+    select_machine_fixed_columns(all_fixed_columns, machine_pil, |column, size| {
+        column.get_by_size(size).unwrap()
+    })
+}
+
+/// Like [machine_fixed_columns], but returns the (possibly compact) values of the columns.
+pub fn machine_fixed_column_values<'a, F: FieldElement>(
+    all_fixed_columns: &'a [(String, VariablySizedColumn<F>)],
+    machine_pil: &'a Analyzed<F>,
+) -> BTreeMap<DegreeType, Vec<(String, &'a FixedColumnValues<F>)>> {
+    select_machine_fixed_columns(all_fixed_columns, machine_pil, |column, size| {
+        column.get_values_by_size(size).unwrap()
+    })
+}
+
+fn select_machine_fixed_columns<'a, F: FieldElement, C>(
+    all_fixed_columns: &'a [(String, VariablySizedColumn<F>)],
+    machine_pil: &'a Analyzed<F>,
+    get_by_size: impl Fn(&'a VariablySizedColumn<F>, DegreeType) -> C,
+) -> BTreeMap<DegreeType, Vec<(String, C)>> {
     let machine_columns = select_machine_columns(
         all_fixed_columns,
         machine_pil.constant_polys_in_source_order(),
@@ -117,7 +230,7 @@ pub fn machine_fixed_columns<'a, F: FieldElement>(
                 size,
                 machine_columns
                     .iter()
-                    .map(|(name, column)| (name.clone(), column.get_by_size(size).unwrap()))
+                    .map(|(name, column)| (name.clone(), get_by_size(column, size)))
                     .collect::<Vec<_>>(),
             )
         })
@@ -314,7 +427,12 @@ fn build_machine_pil<F: FieldElement>(
         source_order: statements,
         ..pil
     };
-    let pil_string = ensure_dummy_witness_column(&pil.to_string());
+    analyze_machine_pil(&pil.to_string())
+}
+
+/// Parses and analyzes the PIL string of a machine, after adding the dummy column.
+fn analyze_machine_pil<F: FieldElement>(pil_string: &str) -> Analyzed<F> {
+    let pil_string = ensure_dummy_witness_column(pil_string);
     let parsed_string = powdr_parser::parse(None, &pil_string).unwrap();
     powdr_pil_analyzer::analyze_ast(parsed_string).unwrap()
 }
@@ -338,7 +456,7 @@ fn ensure_dummy_witness_column(pil_string: &str) -> String {
         .lines()
         .flat_map(|line| {
             if line.starts_with("namespace") && !has_inserted_dummy_lines {
-                // All namespaces except the first are empty and should stay empty
+                // Only the first namespace gets the dummy column, the machine is named after it
                 has_inserted_dummy_lines = true;
                 vec![line, &dummy_column, &dummy_constraint]
             } else {
@@ -365,4 +483,142 @@ col witness a;
         let split_again = super::split_pil(&split).into_iter().next().unwrap().1;
         assert_eq!(split_str, split_again.to_string());
     }
+
+    #[test]
+    fn merge_machines() {
+        let src = r#"namespace Main(16);
+col witness m[8];
+m[0] = m[1];
+m[2]' = m[3];
+namespace A(8);
+col witness a;
+a * a = a;
+namespace B(4);
+col witness b[2];
+b[0] = b[1];
+namespace C(2);
+col witness c;
+col fixed ONE = [1]*;
+c' = c * ONE;
+namespace D(8..16);
+col witness d;
+d = d;
+namespace E(2);
+col witness e;
+e' = e;
+"#;
+        let pil: Analyzed<GoldilocksField> =
+            powdr_pil_analyzer::analyze_ast(powdr_parser::parse(None, src).unwrap()).unwrap();
+        let merged = super::merged_machine_pils(&pil, &[], None);
+        let columns = merged
+            .iter()
+            .map(|(machine, merged)| {
+                let columns = merged
+                    .pil
+                    .committed_polys_in_source_order()
+                    .chain(merged.pil.constant_polys_in_source_order())
+                    .flat_map(|(symbol, _)| symbol.array_elements())
+                    .map(|(name, _)| name)
+                    .collect::<Vec<_>>();
+                (machine.clone(), merged.namespaces.clone(), columns)
+            })
+            .collect::<Vec<_>>();
+
+        let strings = |strings: &[&str]| strings.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        // C and E have the same size, but use next references, so they are placed side by side.
+        // A and B have different sizes and are stacked, with a selector column each.
+        // Main is too large to be merged with anything, and D has no static size.
+        assert_eq!(
+            columns,
+            vec![
+                (
+                    "A+B".to_string(),
+                    strings(&["A", "B"]),
+                    strings(&[
+                        "__stacked_0::__dummy",
+                        "__stacked_0::witness_0",
+                        "__stacked_0::witness_1",
+                        "__stacked_0::selector_0",
+                        "__stacked_0::selector_1"
+                    ])
+                ),
+                (
+                    "C+E".to_string(),
+                    strings(&["C", "E"]),
+                    strings(&["C::__dummy", "C::c", "E::e", "C::ONE"])
+                ),
+            ]
+        );
+        // The dummy identity and one identity of each stacked namespace.
+        assert_eq!(merged["A+B"].pil.identities.len(), 3);
+
+        let column = |values: &[u64]| {
+            values
+                .iter()
+                .map(|v| GoldilocksField::from(*v))
+                .collect::<Vec<_>>()
+        };
+        let super::MergedLayout::Stacked(layout) = &merged["A+B"].layout else {
+            panic!("A and B should be stacked");
+        };
+        let witness = [
+            ("A::a", column(&[1; 8])),
+            ("B::b[0]", column(&[2; 4])),
+            ("B::b[1]", column(&[3; 4])),
+        ]
+        .map(|(name, values)| (name.to_string(), values));
+        let stacked_witness = layout.witness_columns(&witness);
+        assert_eq!(
+            stacked_witness,
+            vec![
+                (
+                    "__stacked_0::witness_0".to_string(),
+                    column(&[1, 1, 1, 1, 1, 1, 1, 1, 2, 2, 2, 2, 0, 0, 0, 0])
+                ),
+                (
+                    "__stacked_0::witness_1".to_string(),
+                    column(&[0, 0, 0, 0, 0, 0, 0, 0, 3, 3, 3, 3, 0, 0, 0, 0])
+                ),
+            ]
+        );
+        let selectors = layout
+            .fixed_columns::<GoldilocksField>(&[])
+            .into_iter()
+            .map(|(name, values)| (name, values.get_by_size(16).unwrap().to_vec()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            selectors,
+            vec![
+                (
+                    "__stacked_0::selector_0".to_string(),
+                    column(&[1, 1, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0])
+                ),
+                (
+                    "__stacked_0::selector_1".to_string(),
+                    column(&[0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 0, 0, 0, 0])
+                ),
+            ]
+        );
+
+        // The witness of a machine includes the dummy column of its first namespace.
+        let witness = super::machine_witness_columns(&stacked_witness, &merged["A+B"].pil, "A+B")
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            witness,
+            strings(&[
+                "__stacked_0::__dummy",
+                "__stacked_0::witness_0",
+                "__stacked_0::witness_1"
+            ])
+        );
+        let witness = ["C::c", "E::e", "A::a"]
+            .map(|name| (name.to_string(), vec![GoldilocksField::from(1); 2]));
+        let witness = super::machine_witness_columns(&witness, &merged["C+E"].pil, "C+E")
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect::<Vec<_>>();
+        assert_eq!(witness, strings(&["C::__dummy", "C::c", "E::e"]));
+    }
 }
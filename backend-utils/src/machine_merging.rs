@@ -0,0 +1,593 @@
+//! Merging of namespaces into machines that are proven together.
+//!
+//! Namespaces of the same static size can be placed side by side, keeping their columns.
+//! Namespaces of different static sizes can be stacked on top of each other, sharing columns,
+//! with a fixed selector column per namespace that enables its constraints.
+//! Which namespaces are merged and how is decided by a cost model, see [merge_groups].
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use itertools::Itertools;
+use powdr_ast::{
+    analyzed::{
+        AlgebraicExpression, Analyzed, ContainsNextRef, DegreeRange, Identity, LookupIdentity,
+        PermutationIdentity, PhantomLookupIdentity, PhantomPermutationIdentity, PolynomialType,
+        StatementIdentifier, SymbolKind,
+    },
+    parsed::visitor::{AllChildren, ExpressionVisitable},
+};
+use powdr_executor_utils::VariablySizedColumn;
+use powdr_number::{DegreeType, FieldElement};
+
+use crate::{extract_namespace, referenced_namespaces_algebraic_expression};
+
+/// The estimated cost of a proof, in cells, on top of the cells of its columns.
+/// It accounts for the setup, the verification key and the verification of each proof.
+const PROOF_OVERHEAD: u64 = 1 << 16;
+
+/// The maximum size of a machine of stacked namespaces.
+/// The fixed columns of the machine are part of its PIL, so they should not get too large.
+const MAX_STACKED_SIZE: DegreeType = 1 << 12;
+
+/// How the namespaces of a merged machine are laid out.
+pub enum MergedLayout {
+    /// The namespaces all have the same size and keep their columns.
+    SideBySide,
+    /// The namespaces are stacked on top of each other in a new namespace.
+    Stacked(StackedLayout),
+}
+
+/// Namespaces stacked on top of each other in a single namespace, sharing its columns.
+/// The rows of each namespace are marked by a fixed selector column, which all its
+/// constraints are multiplied with. Rows after the last namespace are not selected.
+pub struct StackedLayout {
+    /// The namespace of the machine.
+    namespace: String,
+    /// The size of the machine.
+    size: DegreeType,
+    /// The stacked namespaces, in the order of their rows.
+    regions: Vec<Region>,
+}
+
+/// The rows of a namespace in a [StackedLayout].
+struct Region {
+    offset: usize,
+    size: usize,
+    /// The witness columns of the namespace, stored in the shared witness column of the same index.
+    witness_columns: Vec<String>,
+    /// The fixed columns of the namespace, stored in the shared fixed column of the same index.
+    fixed_columns: Vec<String>,
+}
+
+impl StackedLayout {
+    /// Returns the witness columns of the machine, given the witness columns of all namespaces.
+    pub fn witness_columns<F: FieldElement>(
+        &self,
+        witness: &[(String, Vec<F>)],
+    ) -> Vec<(String, Vec<F>)> {
+        let witness = witness
+            .iter()
+            .map(|(name, values)| (name, values))
+            .collect::<BTreeMap<_, _>>();
+        (0..self.witness_width())
+            .map(|i| {
+                let values = self.stack(|region| {
+                    let name = region.witness_columns.get(i)?;
+                    let values = witness
+                        .get(name)
+                        .unwrap_or_else(|| panic!("Witness column {name} is missing"));
+                    Some(values.as_slice())
+                });
+                (self.witness_column_name(i), values)
+            })
+            .collect()
+    }
+
+    /// Returns the fixed columns of the machine, including the selectors, given the fixed
+    /// columns of all namespaces.
+    pub fn fixed_columns<F: FieldElement>(
+        &self,
+        fixed: &[(String, VariablySizedColumn<F>)],
+    ) -> Vec<(String, VariablySizedColumn<F>)> {
+        let fixed = fixed
+            .iter()
+            .map(|(name, values)| (name, values))
+            .collect::<BTreeMap<_, _>>();
+        let fixed_columns = (0..self.fixed_width()).map(|i| {
+            let values = self.stack(|region| {
+                let name = region.fixed_columns.get(i)?;
+                let values = fixed
+                    .get(name)
+                    .and_then(|column| column.get_by_size(region.size as DegreeType))
+                    .unwrap_or_else(|| {
+                        panic!("Fixed column {name} of size {} is missing", region.size)
+                    });
+                Some(values)
+            });
+            (self.fixed_column_name(i), values)
+        });
+        let selectors = (0..self.regions.len()).map(|i| {
+            let ones = vec![F::one(); self.regions[i].size];
+            let values = self.stack(|region| {
+                (region.offset == self.regions[i].offset).then_some(ones.as_slice())
+            });
+            (self.selector_name(i), values)
+        });
+        fixed_columns
+            .chain(selectors)
+            .map(|(name, values)| (name, values.into()))
+            .collect()
+    }
+
+    /// Builds a column of the machine from the values of each region, padded with zeros.
+    fn stack<'b, F: FieldElement>(
+        &self,
+        mut values: impl FnMut(&Region) -> Option<&'b [F]>,
+    ) -> Vec<F> {
+        let mut column = vec![F::zero(); self.size as usize];
+        for region in &self.regions {
+            if let Some(values) = values(region) {
+                assert_eq!(
+                    values.len(),
+                    region.size,
+                    "Unexpected size of a column of a stacked namespace"
+                );
+                column[region.offset..region.offset + region.size].copy_from_slice(values);
+            }
+        }
+        column
+    }
+
+    /// The number of shared witness columns.
+    fn witness_width(&self) -> usize {
+        self.regions
+            .iter()
+            .map(|region| region.witness_columns.len())
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// The number of shared fixed columns, not counting the selectors.
+    fn fixed_width(&self) -> usize {
+        self.regions
+            .iter()
+            .map(|region| region.fixed_columns.len())
+            .max()
+            .unwrap_or(0)
+    }
+
+    fn witness_column_name(&self, index: usize) -> String {
+        format!("{}::witness_{index}", self.namespace)
+    }
+
+    fn fixed_column_name(&self, index: usize) -> String {
+        format!("{}::fixed_{index}", self.namespace)
+    }
+
+    fn selector_name(&self, index: usize) -> String {
+        format!("{}::selector_{index}", self.namespace)
+    }
+}
+
+/// A namespace of static size that can be merged with other namespaces.
+pub(crate) struct Candidate<F> {
+    pub(crate) name: String,
+    size: DegreeType,
+    witness_columns: Vec<String>,
+    fixed_columns: Vec<String>,
+    /// The constraints of the namespace, with intermediate columns inlined.
+    /// Only set if the namespace can be stacked, see [stacked_constraints].
+    constraints: Option<Vec<AlgebraicExpression<F>>>,
+}
+
+impl<F> Candidate<F> {
+    fn width(&self) -> u64 {
+        (self.witness_columns.len() + self.fixed_columns.len()) as u64
+    }
+}
+
+/// A group of namespaces to be proven as a single machine.
+pub(crate) enum MergeGroup<F> {
+    /// Namespaces of the same size, in alphabetical order, placed side by side.
+    SideBySide(Vec<String>),
+    /// Namespaces to be stacked, in alphabetical order, see [stack].
+    Stacked(Vec<Candidate<F>>),
+}
+
+/// How the namespaces of a group are laid out while grouping.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    SideBySide,
+    Stacked,
+}
+
+/// Indices of candidates, proven as a single machine.
+#[derive(Clone)]
+struct Group {
+    members: Vec<usize>,
+    kind: Kind,
+}
+
+/// Groups the namespaces of static size into machines that are proven together.
+///
+/// Every proof costs [PROOF_OVERHEAD] cells on top of the cells of its columns. Two groups
+/// are merged if this lowers the total cost, either side by side, if all their namespaces
+/// have the same size, or stacked, if all their namespaces can be stacked and neither group
+/// is already placed side by side. The merge saving the most cells is done first. Among merges
+/// saving the same, those of a caller and its callee, connected by a lookup or permutation,
+/// are preferred, so that small block machines are proven as part of their callers.
+/// No machine gets more cells than the largest namespace, which bounds the memory needed by
+/// the prover.
+///
+/// A namespace can be stacked if it only has polynomial identities without next references,
+/// challenges or public references, no later-stage witness columns and no public declarations.
+/// Stacking multiplies the constraints with a selector, so their degree has to stay within
+/// `max_degree`, if given.
+///
+/// Namespaces with dynamic size are never merged, because witness generation chooses their
+/// sizes independently.
+pub(crate) fn merge_groups<F: FieldElement>(
+    statements_by_namespace: &BTreeMap<String, Vec<StatementIdentifier>>,
+    pil: &Analyzed<F>,
+    max_degree: Option<usize>,
+) -> Vec<MergeGroup<F>> {
+    let Some(max_cells) = statements_by_namespace
+        .values()
+        .filter_map(|statements| machine_shape(statements, pil))
+        .map(|shape| shape.columns * shape.degree_range.max)
+        .max()
+    else {
+        return vec![];
+    };
+    let candidates = statements_by_namespace
+        .iter()
+        .filter_map(|(namespace, statements)| candidate(namespace, statements, pil, max_degree))
+        .collect_vec();
+    let connected = connected_namespaces(pil);
+
+    let mut groups = (0..candidates.len())
+        .map(|i| Group {
+            members: vec![i],
+            kind: Kind::SideBySide,
+        })
+        .collect_vec();
+    loop {
+        let best_merge = groups
+            .iter()
+            .enumerate()
+            .tuple_combinations()
+            .filter_map(|((i, a), (j, b))| {
+                let merged = merge(a, b, &candidates, max_cells)?;
+                let saving = (cost(a, &candidates) + cost(b, &candidates))
+                    .checked_sub(cost(&merged, &candidates))
+                    .filter(|saving| *saving > 0)?;
+                let is_connected = a.members.iter().any(|a| {
+                    b.members.iter().any(|b| {
+                        connected
+                            .contains(&(candidates[*a].name.clone(), candidates[*b].name.clone()))
+                    })
+                });
+                Some(((saving, is_connected, std::cmp::Reverse((i, j))), merged))
+            })
+            .max_by_key(|(key, _)| *key);
+        let Some(((_, _, std::cmp::Reverse((i, j))), merged)) = best_merge else {
+            break;
+        };
+        groups[i] = merged;
+        groups.remove(j);
+    }
+
+    let mut candidates = candidates.into_iter().map(Some).collect_vec();
+    groups
+        .into_iter()
+        .filter(|group| group.members.len() >= 2)
+        .map(|group| {
+            let members = group
+                .members
+                .iter()
+                .map(|i| candidates[*i].take().unwrap())
+                .sorted_by(|a, b| a.name.cmp(&b.name));
+            match group.kind {
+                Kind::SideBySide => MergeGroup::SideBySide(members.map(|c| c.name).collect()),
+                Kind::Stacked => MergeGroup::Stacked(members.collect()),
+            }
+        })
+        .collect()
+}
+
+/// Merges two groups in the cheapest possible way, if they can be merged at all.
+fn merge<F>(a: &Group, b: &Group, candidates: &[Candidate<F>], max_cells: u64) -> Option<Group> {
+    let members = a.members.iter().chain(&b.members).copied().collect_vec();
+    let side_by_side = (a.kind == Kind::SideBySide
+        && b.kind == Kind::SideBySide
+        && members.iter().map(|i| candidates[*i].size).all_equal())
+    .then(|| Group {
+        members: members.clone(),
+        kind: Kind::SideBySide,
+    });
+    let can_be_stacked = |group: &Group| group.kind == Kind::Stacked || group.members.len() == 1;
+    let stacked = (can_be_stacked(a)
+        && can_be_stacked(b)
+        && members.iter().all(|i| candidates[*i].constraints.is_some()))
+    .then(|| Group {
+        members,
+        kind: Kind::Stacked,
+    })
+    .filter(|group| stacked_size(group, candidates) <= MAX_STACKED_SIZE);
+
+    side_by_side
+        .into_iter()
+        .chain(stacked)
+        .filter(|group| cells(group, candidates) <= max_cells)
+        .min_by_key(|group| cost(group, candidates))
+}
+
+/// The estimated cost of proving a group, in cells.
+fn cost<F>(group: &Group, candidates: &[Candidate<F>]) -> u64 {
+    PROOF_OVERHEAD + cells(group, candidates)
+}
+
+/// The number of cells of the columns of a group.
+fn cells<F>(group: &Group, candidates: &[Candidate<F>]) -> u64 {
+    let members = group.members.iter().map(|i| &candidates[*i]);
+    match group.kind {
+        Kind::SideBySide => {
+            candidates[group.members[0]].size * members.map(|c| c.width()).sum::<u64>()
+        }
+        Kind::Stacked => {
+            let witness_columns = members.clone().map(|c| c.witness_columns.len()).max();
+            let fixed_columns = members.map(|c| c.fixed_columns.len()).max();
+            let columns = witness_columns.unwrap() + fixed_columns.unwrap() + group.members.len();
+            stacked_size(group, candidates) * columns as u64
+        }
+    }
+}
+
+/// The size of a machine of stacked namespaces.
+fn stacked_size<F>(group: &Group, candidates: &[Candidate<F>]) -> DegreeType {
+    group
+        .members
+        .iter()
+        .map(|i| candidates[*i].size)
+        .sum::<DegreeType>()
+        .next_power_of_two()
+}
+
+/// Stacks the namespaces into a machine in the given namespace.
+/// Returns the layout and the PIL of the machine, without the dummy column.
+pub(crate) fn stack<F: FieldElement>(
+    namespace: String,
+    namespaces: Vec<Candidate<F>>,
+    fixed: &[(String, VariablySizedColumn<F>)],
+) -> (StackedLayout, String) {
+    let mut offset = 0;
+    let (regions, constraints): (Vec<_>, Vec<_>) = namespaces
+        .into_iter()
+        .map(|candidate| {
+            let region = Region {
+                offset,
+                size: candidate.size as usize,
+                witness_columns: candidate.witness_columns,
+                fixed_columns: candidate.fixed_columns,
+            };
+            offset += region.size;
+            (region, candidate.constraints.unwrap())
+        })
+        .unzip();
+    let layout = StackedLayout {
+        size: (offset as DegreeType).next_power_of_two(),
+        namespace,
+        regions,
+    };
+
+    let short_name = |name: String| name.rsplit("::").next().unwrap().to_string();
+    let mut lines = vec![format!("namespace {}({});", layout.namespace, layout.size)];
+    if layout.witness_width() > 0 {
+        lines.push(format!(
+            "    col witness {};",
+            (0..layout.witness_width())
+                .map(|i| short_name(layout.witness_column_name(i)))
+                .join(", ")
+        ));
+    }
+    for (name, values) in layout.fixed_columns(fixed) {
+        let values = values.get_by_size(layout.size).unwrap();
+        lines.push(format!(
+            "    col fixed {} = [{}];",
+            short_name(name),
+            values.iter().map(|v| v.to_arbitrary_integer()).join(", ")
+        ));
+    }
+    for (i, (region, constraints)) in layout.regions.iter().zip_eq(constraints).enumerate() {
+        let names = region
+            .witness_columns
+            .iter()
+            .enumerate()
+            .map(|(j, name)| (name.clone(), layout.witness_column_name(j)))
+            .chain(
+                region
+                    .fixed_columns
+                    .iter()
+                    .enumerate()
+                    .map(|(j, name)| (name.clone(), layout.fixed_column_name(j))),
+            )
+            .collect::<BTreeMap<_, _>>();
+        for mut constraint in constraints {
+            constraint.pre_visit_expressions_mut(&mut |e| {
+                if let AlgebraicExpression::Reference(reference) = e {
+                    reference.name = names[&reference.name].clone();
+                }
+            });
+            lines.push(format!(
+                "    {} * ({constraint}) = 0;",
+                layout.selector_name(i)
+            ));
+        }
+    }
+    (layout, lines.join("\n"))
+}
+
+/// The number of columns and the degree range of a namespace.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct MachineShape {
+    columns: u64,
+    degree_range: DegreeRange,
+}
+
+/// Returns the shape of the namespace, if it has columns that all have the same degree range.
+fn machine_shape<F>(statements: &[StatementIdentifier], pil: &Analyzed<F>) -> Option<MachineShape> {
+    let columns = statements
+        .iter()
+        .filter_map(|statement| match statement {
+            StatementIdentifier::Definition(name) => pil.definitions.get(name),
+            _ => None,
+        })
+        .map(|(symbol, _)| symbol)
+        .filter(|symbol| {
+            matches!(
+                symbol.kind,
+                SymbolKind::Poly(PolynomialType::Committed | PolynomialType::Constant)
+            )
+        })
+        .collect_vec();
+    let degree_range = columns
+        .iter()
+        .map(|symbol| symbol.degree)
+        .unique()
+        .exactly_one()
+        .ok()??;
+    Some(MachineShape {
+        columns: columns
+            .iter()
+            .map(|symbol| symbol.length.unwrap_or(1))
+            .sum(),
+        degree_range,
+    })
+}
+
+/// Returns the namespace as a merge candidate, if it has a static size.
+fn candidate<F: FieldElement>(
+    namespace: &str,
+    statements: &[StatementIdentifier],
+    pil: &Analyzed<F>,
+    max_degree: Option<usize>,
+) -> Option<Candidate<F>> {
+    let degree_range = machine_shape(statements, pil)?.degree_range;
+    if degree_range.min != degree_range.max {
+        return None;
+    }
+    let columns = |ptype| {
+        statements
+            .iter()
+            .filter_map(|statement| match statement {
+                StatementIdentifier::Definition(name) => pil.definitions.get(name),
+                _ => None,
+            })
+            .filter(move |(symbol, _)| symbol.kind == SymbolKind::Poly(ptype))
+            .flat_map(|(symbol, _)| symbol.array_elements().map(|(name, _)| name))
+            .collect_vec()
+    };
+    let mut candidate = Candidate {
+        name: namespace.to_string(),
+        size: degree_range.max,
+        witness_columns: columns(PolynomialType::Committed),
+        fixed_columns: columns(PolynomialType::Constant),
+        constraints: None,
+    };
+    candidate.constraints = stacked_constraints(&candidate, statements, pil, max_degree);
+    Some(candidate)
+}
+
+/// Returns the constraints of the namespace, with intermediate columns inlined, if the
+/// namespace can be stacked, see [merge_groups].
+fn stacked_constraints<F: FieldElement>(
+    candidate: &Candidate<F>,
+    statements: &[StatementIdentifier],
+    pil: &Analyzed<F>,
+    max_degree: Option<usize>,
+) -> Option<Vec<AlgebraicExpression<F>>> {
+    let has_later_stage_columns = statements.iter().any(|statement| match statement {
+        StatementIdentifier::Definition(name) => pil
+            .definitions
+            .get(name)
+            .is_some_and(|(symbol, _)| symbol.stage.unwrap_or(0) > 0),
+        _ => false,
+    });
+    let has_publics = pil
+        .public_declarations_in_source_order()
+        .any(|(_, public)| extract_namespace(&public.referenced_poly().name) == candidate.name);
+    if has_later_stage_columns || has_publics {
+        return None;
+    }
+
+    let intermediate_definitions = pil.intermediate_definitions();
+    let columns = candidate
+        .witness_columns
+        .iter()
+        .chain(&candidate.fixed_columns)
+        .collect::<BTreeSet<_>>();
+    statements
+        .iter()
+        .filter_map(|statement| match statement {
+            StatementIdentifier::ProofItem(i) => Some(&pil.identities[*i]),
+            _ => None,
+        })
+        .map(|identity| {
+            if identity.contains_next_ref(&intermediate_definitions)
+                || max_degree.is_some_and(|max_degree| {
+                    identity.degree(&intermediate_definitions) >= max_degree
+                })
+            {
+                return None;
+            }
+            let Identity::Polynomial(identity) = identity else {
+                return None;
+            };
+            let mut expression = identity.expression.clone();
+            expression.pre_visit_expressions_mut(&mut |e| {
+                while let Some(definition) = match &*e {
+                    AlgebraicExpression::Reference(reference) => {
+                        intermediate_definitions.get(&reference.to_thin())
+                    }
+                    _ => None,
+                } {
+                    *e = definition.clone();
+                }
+            });
+            expression
+                .all_children()
+                .all(|e| match e {
+                    AlgebraicExpression::Reference(reference) => columns.contains(&reference.name),
+                    AlgebraicExpression::PublicReference(_) | AlgebraicExpression::Challenge(_) => {
+                        false
+                    }
+                    AlgebraicExpression::Number(_)
+                    | AlgebraicExpression::BinaryOperation(_)
+                    | AlgebraicExpression::UnaryOperation(_) => true,
+                })
+                .then_some(expression)
+        })
+        .collect()
+}
+
+/// Returns the pairs of namespaces connected by a lookup or permutation, in both orders.
+fn connected_namespaces<F: FieldElement>(pil: &Analyzed<F>) -> BTreeSet<(String, String)> {
+    pil.identities
+        .iter()
+        .filter_map(|identity| match identity {
+            Identity::Lookup(LookupIdentity { left, right, .. })
+            | Identity::PhantomLookup(PhantomLookupIdentity { left, right, .. })
+            | Identity::Permutation(PermutationIdentity { left, right, .. })
+            | Identity::PhantomPermutation(PhantomPermutationIdentity { left, right, .. }) => {
+                Some((left, right))
+            }
+            _ => None,
+        })
+        .flat_map(|(left, right)| {
+            referenced_namespaces_algebraic_expression(left)
+                .into_iter()
+                .cartesian_product(referenced_namespaces_algebraic_expression(right))
+        })
+        .flat_map(|(a, b)| [(a.clone(), b.clone()), (b, a)])
+        .collect()
+}
@@ -9,15 +9,23 @@ use std::{
 
 use itertools::Itertools;
 use powdr_ast::analyzed::{Analyzed, ContainsNextRef};
-use powdr_backend_utils::{machine_fixed_columns, machine_witness_columns};
-use powdr_executor::{constant_evaluator::VariablySizedColumn, witgen::WitgenCallback};
+use powdr_backend_utils::{
+    machine_fixed_columns, machine_witness_columns, MergedLayout, StackedLayout,
+};
+use powdr_executor::{
+    constant_evaluator::VariablySizedColumn,
+    witgen::{extract_publics, WitgenCallback},
+};
 use powdr_number::{DegreeType, FieldElement};
 use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
 use serde::{Deserialize, Serialize};
 
 use crate::{Backend, BackendFactory, BackendOptions, Error, Proof};
 
-use self::sub_prover::RunStatus;
+use self::{
+    proof_cache::{CacheKey, ProofCache},
+    sub_prover::RunStatus,
+};
 
 /// Maps each size to the corresponding verification key.
 type VerificationKeyBySize = BTreeMap<DegreeType, Vec<u8>>;
@@ -30,7 +38,7 @@ struct CompositeVerificationKey {
 }
 
 /// A proof for a single machine.
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 struct MachineProof {
     /// The (dynamic) size of the machine.
     size: DegreeType,
@@ -78,7 +86,25 @@ impl<F: FieldElement, B: BackendFactory<F>> BackendFactory<F> for CompositeBacke
             unimplemented!();
         }
 
-        let pils = powdr_backend_utils::split_pil(&pil);
+        let (composite_options, backend_options) = parse_options(backend_options);
+        let mut pils = powdr_backend_utils::split_pil(&pil);
+        let mut stacked_machines = BTreeMap::new();
+        if composite_options.merge_machines {
+            let merged_machines = powdr_backend_utils::merged_machine_pils(
+                &pil,
+                &fixed,
+                self.factory.max_constraint_degree(),
+            );
+            for (machine_name, merged) in merged_machines {
+                for namespace in &merged.namespaces {
+                    pils.remove(namespace);
+                }
+                if let MergedLayout::Stacked(layout) = merged.layout {
+                    stacked_machines.insert(machine_name.clone(), layout);
+                }
+                pils.insert(machine_name, merged.pil);
+            }
+        }
 
         // Read the setup once (if any) to pass to all backends.
         let setup_bytes = setup.map(|setup| {
@@ -108,7 +134,11 @@ impl<F: FieldElement, B: BackendFactory<F>> BackendFactory<F> for CompositeBacke
             .zip_eq(verification_keys.into_iter())
             .map(|((machine_name, pil), verification_key)| {
                 let pil = Arc::new(pil);
-                machine_fixed_columns(&fixed, &pil)
+                let machine_fixed = match stacked_machines.get(&machine_name) {
+                    Some(layout) => Arc::new(layout.fixed_columns(&fixed)),
+                    None => fixed.clone(),
+                };
+                machine_fixed_columns(&machine_fixed, &pil)
                     .into_iter()
                     .map(|(size, fixed)| {
                         let fixed = fixed
@@ -149,6 +179,13 @@ impl<F: FieldElement, B: BackendFactory<F>> BackendFactory<F> for CompositeBacke
                             (
                                 size,
                                 MachineData {
+                                    machine_hash: composite_options.proof_cache.then(|| {
+                                        proof_cache::machine_hash(
+                                            &pil,
+                                            &backend_options,
+                                            setup_bytes.as_deref(),
+                                        )
+                                    }),
                                     pil,
                                     backend: Mutex::new(backend),
                                 },
@@ -160,12 +197,56 @@ impl<F: FieldElement, B: BackendFactory<F>> BackendFactory<F> for CompositeBacke
             })
             .collect::<Result<BTreeMap<_, _>, _>>()?;
 
-        Ok(Box::new(CompositeBackend { machine_data }))
+        Ok(Box::new(CompositeBackend {
+            machine_data,
+            stacked_machines,
+            proof_cache: composite_options
+                .proof_cache
+                .then(|| ProofCache::load(output_dir)),
+        }))
     }
 
     fn generate_setup(&self, size: DegreeType, output: &mut dyn io::Write) -> Result<(), Error> {
         self.factory.generate_setup(size, output)
     }
+
+    fn max_constraint_degree(&self) -> Option<usize> {
+        self.factory.max_constraint_degree()
+    }
+}
+
+/// The option enabling the merging of machines, see [powdr_backend_utils::merged_machine_pils].
+const MERGE_MACHINES_OPTION: &str = "merge_machines";
+/// The option enabling the reuse of machine proofs from earlier runs, see [ProofCache].
+const PROOF_CACHE_OPTION: &str = "proof_cache";
+
+/// The options of the composite backend itself.
+#[derive(Default)]
+struct CompositeOptions {
+    merge_machines: bool,
+    proof_cache: bool,
+}
+
+/// Removes the composite backend's own options from the comma-separated list of options,
+/// returning them and the options for the underlying backend.
+fn parse_options(options: BackendOptions) -> (CompositeOptions, BackendOptions) {
+    let mut composite_options = CompositeOptions::default();
+    let other_options = options
+        .split(',')
+        .filter(|option| match *option {
+            "" => false,
+            MERGE_MACHINES_OPTION => {
+                composite_options.merge_machines = true;
+                false
+            }
+            PROOF_CACHE_OPTION => {
+                composite_options.proof_cache = true;
+                false
+            }
+            _ => true,
+        })
+        .collect::<Vec<_>>();
+    (composite_options, other_options.join(","))
 }
 
 fn log_machine_stats<T: FieldElement>(machine_name: &str, pil: &Analyzed<T>) {
@@ -205,6 +286,9 @@ fn log_machine_stats<T: FieldElement>(machine_name: &str, pil: &Analyzed<T>) {
 
 struct MachineData<F> {
     pil: Arc<Analyzed<F>>,
+    /// The digest of the machine setup, used to identify cached proofs.
+    /// Only computed if the proof cache is enabled.
+    machine_hash: Option<CacheKey>,
     // Mutex is needed because Backend is not Sync, so during proof we need to
     // ensure the type system that each backend is only used by one thread at a
     // time.
@@ -216,6 +300,48 @@ pub(crate) struct CompositeBackend<F> {
     /// Note that it is essential that we use BTreeMap here to ensure that the machines are
     /// deterministically ordered.
     machine_data: BTreeMap<String, BTreeMap<DegreeType, MachineData<F>>>,
+    /// The layout of each machine of stacked namespaces, whose witness has to be built from
+    /// the witnesses of its namespaces.
+    stacked_machines: BTreeMap<String, StackedLayout>,
+    /// Proofs of previous runs, reused for machines whose witness did not change.
+    /// Only used if enabled by the `proof_cache` option.
+    proof_cache: Option<ProofCache>,
+}
+
+impl<F: FieldElement> CompositeBackend<F> {
+    /// Returns the cached proof of the machine for the given key, if there is one and it
+    /// verifies for the public values of the witness. Invalid proofs are removed from the cache.
+    fn cached_proof(
+        &self,
+        machine_name: &str,
+        machine_data: &MachineData<F>,
+        key: &CacheKey,
+        witness: &[(String, Vec<F>)],
+    ) -> Option<MachineProof> {
+        let proof_cache = self.proof_cache.as_ref()?;
+        let proof = proof_cache.get(machine_name, key)?;
+        // Publics that depend on later-stage witnesses are not known yet, but the
+        // proofs of such machines are never cached.
+        let publics = extract_publics(
+            witness.iter().map(|(name, values)| (name, values)),
+            &machine_data.pil,
+        )
+        .into_values()
+        .collect::<Option<Vec<_>>>()?;
+        match machine_data
+            .backend
+            .lock()
+            .unwrap()
+            .verify(&proof.proof, &[publics])
+        {
+            Ok(()) => Some(proof),
+            Err(e) => {
+                log::warn!("Discarding the cached proof of machine {machine_name}: {e}");
+                proof_cache.remove(machine_name);
+                None
+            }
+        }
+    }
 }
 
 /// Makes sure that all columns in the machine PIL have the provided degree, cloning
@@ -248,6 +374,7 @@ fn set_size<F: Clone>(pil: Arc<Analyzed<F>>, degree: DegreeType) -> Arc<Analyzed
     Arc::new(Analyzed { definitions, ..pil })
 }
 
+mod proof_cache;
 mod sub_prover;
 
 fn accumulate_challenges<F: FieldElement>(into: &mut BTreeMap<u64, F>, from: BTreeMap<u64, F>) {
@@ -316,20 +443,27 @@ impl<F: FieldElement> Backend<F> for CompositeBackend<F> {
             .machine_data
             .par_iter()
             .map(|(machine_name, machine_data)| {
-                let (witness, size) =
-                    process_witness_for_machine(machine_name, machine_data, witness);
+                let (witness, size) = match self.stacked_machines.get(machine_name) {
+                    Some(layout) => process_witness_for_machine(
+                        machine_name,
+                        machine_data,
+                        &layout.witness_columns(witness),
+                    ),
+                    None => process_witness_for_machine(machine_name, machine_data, witness),
+                };
                 (machine_name.clone(), (witness, size))
             })
             .collect::<BTreeMap<_, _>>();
 
         // We use scoped threads to be able to share non-'static references.
         thread::scope(|scope| {
+            let mut reused_proofs = BTreeMap::new();
             let mut proofs_status = self
                 .machine_data
                 .iter()
                 .filter_map(|machine_entry| {
                     let (machine, machine_data) = machine_entry;
-                    let (witness, size) = witness_by_machine[machine].clone();
+                    let (witness, size) = witness_by_machine.get(machine)?.clone();
                     if size == 0 {
                         // If a machine has no rows, remove it entirely.
                         return None;
@@ -338,11 +472,23 @@ impl<F: FieldElement> Backend<F> for CompositeBackend<F> {
                         .get(&size)
                         .expect("Machine does not support the given size");
 
+                    let cache_key = inner_machine_data
+                        .machine_hash
+                        .as_ref()
+                        .map(|machine_hash| proof_cache::cache_key(machine_hash, size, &witness));
+                    if let Some(proof) = cache_key.as_ref().and_then(|cache_key| {
+                        self.cached_proof(machine, inner_machine_data, cache_key, &witness)
+                    }) {
+                        log::info!("== Reusing cached proof of machine: {machine} (size {size})");
+                        reused_proofs.insert(machine, proof);
+                        return None;
+                    }
+
                     let status = time_stage(machine, size, 0, || {
                         sub_prover::run(scope, &inner_machine_data.backend, witness)
                     });
 
-                    Some((status, machine_entry, size))
+                    Some((status, machine_entry, size, cache_key))
                 })
                 .collect::<Vec<_>>();
 
@@ -353,9 +499,20 @@ impl<F: FieldElement> Backend<F> for CompositeBackend<F> {
                 let mut challenges = BTreeMap::new();
                 let waiting_provers = std::mem::take(&mut proofs_status)
                     .into_iter()
-                    .filter_map(|(status, machine_data, size)| match status {
+                    .filter_map(|(status, machine_data, size, cache_key)| match status {
                         sub_prover::RunStatus::Completed(result) => {
                             let (machine_name, _) = machine_data;
+                            // Only proofs completed in stage 0 are cached, as they do not
+                            // depend on the challenges of other machines.
+                            if let (Ok(proof), Some(cache_key), Some(proof_cache)) =
+                                (&result, cache_key, &self.proof_cache)
+                            {
+                                let proof = MachineProof {
+                                    size,
+                                    proof: proof.clone(),
+                                };
+                                proof_cache.insert(machine_name, cache_key, proof);
+                            }
                             assert!(proof_results.insert(machine_name, (result, size)).is_none());
                             None
                         }
@@ -374,8 +531,8 @@ impl<F: FieldElement> Backend<F> for CompositeBackend<F> {
                 witness_by_machine = self
                     .machine_data
                     .par_iter()
-                    .map(|(machine_name, machine_data)| {
-                        let (machine_witness, size) = witness_by_machine.get(machine_name).unwrap();
+                    .filter_map(|(machine_name, machine_data)| {
+                        let (machine_witness, size) = witness_by_machine.get(machine_name)?;
                         let machine_data = machine_data.get(size).unwrap();
                         let new_witness = witgen_callback.next_stage_witness(
                             &machine_data.pil,
@@ -383,7 +540,7 @@ impl<F: FieldElement> Backend<F> for CompositeBackend<F> {
                             challenges.clone(),
                             stage,
                         );
-                        (machine_name.clone(), (new_witness, *size))
+                        Some((machine_name.clone(), (new_witness, *size)))
                     })
                     .collect();
 
@@ -398,11 +555,19 @@ impl<F: FieldElement> Backend<F> for CompositeBackend<F> {
                         let status =
                             time_stage(machine_name, size, stage, move || prover.resume(witness));
 
-                        (status, machine_entry, size)
+                        (status, machine_entry, size, None)
                     })
                     .collect();
             }
 
+            if self.proof_cache.is_some() {
+                log::info!(
+                    "Reused {} of {} machine proofs from the cache",
+                    reused_proofs.len(),
+                    reused_proofs.len() + proof_results.len()
+                );
+            }
+
             let proofs = proof_results
                 .into_iter()
                 .map(|(machine_name, (proof, size))| match proof {
@@ -412,7 +577,15 @@ impl<F: FieldElement> Backend<F> for CompositeBackend<F> {
                         Err(e)
                     }
                 })
+                .chain(
+                    reused_proofs
+                        .into_iter()
+                        .map(|(machine_name, proof)| Ok((machine_name.clone(), proof))),
+                )
                 .collect::<Result<BTreeMap<_, _>, _>>()?;
+            if let Some(proof_cache) = &self.proof_cache {
+                proof_cache.save()?;
+            }
 
             let proof = CompositeProof { proofs };
             Ok(bincode::serialize(&proof).unwrap())
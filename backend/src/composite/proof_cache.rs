@@ -0,0 +1,268 @@
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{BufReader, BufWriter},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use powdr_number::{DegreeType, FieldElement, LargeInt};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::Error;
+
+use super::MachineProof;
+
+/// The name of the file the cache is persisted to, within the output directory.
+const PROOF_CACHE_FILE: &str = "proof_cache.bin";
+
+/// The version of the cache key computation and of the cache file.
+/// It needs to be incremented whenever either of them changes.
+const PROOF_CACHE_VERSION: u32 = 1;
+
+/// The SHA-256 digest identifying the inputs of a machine proof.
+pub(crate) type CacheKey = [u8; 32];
+
+/// Feeds values into a SHA-256 digest. Variable-length data is prefixed with its length,
+/// so that different sequences of values never result in the same input.
+struct KeyHasher(Sha256);
+
+impl KeyHasher {
+    fn new() -> Self {
+        let mut hasher = KeyHasher(Sha256::new());
+        hasher.u64(PROOF_CACHE_VERSION as u64);
+        hasher
+    }
+
+    fn u64(&mut self, value: u64) {
+        self.0.update(value.to_le_bytes());
+    }
+
+    fn bytes(&mut self, bytes: &[u8]) {
+        self.u64(bytes.len() as u64);
+        self.0.update(bytes);
+    }
+
+    fn finish(self) -> CacheKey {
+        self.0.finalize().into()
+    }
+}
+
+/// Computes the cache key of a machine proof from the digest of the machine setup
+/// (see [machine_hash]), the field, the size of the machine and its stage-0 witness.
+pub(crate) fn cache_key<F: FieldElement>(
+    machine_hash: &CacheKey,
+    size: DegreeType,
+    witness: &[(String, Vec<F>)],
+) -> CacheKey {
+    let mut hasher = KeyHasher::new();
+    hasher.bytes(machine_hash);
+    hasher.bytes(&F::modulus().to_arbitrary_integer().to_bytes_le());
+    hasher.u64(size);
+    hasher.u64(witness.len() as u64);
+    for (name, values) in witness {
+        hasher.bytes(name.as_bytes());
+        hasher.u64(values.len() as u64);
+        for value in values {
+            hasher.bytes(&value.to_bytes_le());
+        }
+    }
+    hasher.finish()
+}
+
+/// Computes the digest of everything a machine proof depends on apart from the witness:
+/// the printed machine PIL (which determines the fixed columns and the constraints),
+/// the options of the underlying backend and the setup.
+pub(crate) fn machine_hash(
+    pil: &impl std::fmt::Display,
+    backend_options: &str,
+    setup: Option<&[u8]>,
+) -> CacheKey {
+    let mut hasher = KeyHasher::new();
+    hasher.bytes(pil.to_string().as_bytes());
+    hasher.bytes(backend_options.as_bytes());
+    match setup {
+        Some(setup) => {
+            hasher.u64(1);
+            hasher.bytes(setup);
+        }
+        None => hasher.u64(0),
+    }
+    hasher.finish()
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedProof {
+    key: CacheKey,
+    proof: MachineProof,
+}
+
+/// A cache of the proofs of machines that are proven in a single stage, i.e. whose proof
+/// only depends on their own witness. It keeps the latest proof of each machine and is
+/// persisted to the output directory, if there is one, so that it survives across runs.
+///
+/// Proofs of machines with later stages depend on the challenges of all machines and are
+/// never cached. The cache is only used if the composite backend is given the
+/// `proof_cache` option, and cached proofs are verified before they are reused.
+pub(crate) struct ProofCache {
+    path: Option<PathBuf>,
+    proofs: Mutex<BTreeMap<String, CachedProof>>,
+}
+
+impl ProofCache {
+    /// Loads the cache from the output directory, starting with an empty cache if
+    /// there is no output directory or the file cannot be read or has a different version.
+    pub(crate) fn load(output_dir: Option<PathBuf>) -> Self {
+        let path = output_dir.map(|output_dir| output_dir.join(PROOF_CACHE_FILE));
+        let proofs = path
+            .as_ref()
+            .filter(|path| path.exists())
+            .and_then(|path| match read_cache_file(path) {
+                Ok(proofs) => Some(proofs),
+                Err(e) => {
+                    log::warn!("Ignoring proof cache at {}: {e}", path.display());
+                    None
+                }
+            })
+            .unwrap_or_default();
+        Self {
+            path,
+            proofs: Mutex::new(proofs),
+        }
+    }
+
+    /// Returns the cached proof of the machine, if it was computed for the same key.
+    pub(crate) fn get(&self, machine_name: &str, key: &CacheKey) -> Option<MachineProof> {
+        self.proofs
+            .lock()
+            .unwrap()
+            .get(machine_name)
+            .filter(|cached| &cached.key == key)
+            .map(|cached| cached.proof.clone())
+    }
+
+    /// Replaces the cached proof of the machine.
+    pub(crate) fn insert(&self, machine_name: &str, key: CacheKey, proof: MachineProof) {
+        self.proofs
+            .lock()
+            .unwrap()
+            .insert(machine_name.to_string(), CachedProof { key, proof });
+    }
+
+    /// Removes the cached proof of the machine.
+    pub(crate) fn remove(&self, machine_name: &str) {
+        self.proofs.lock().unwrap().remove(machine_name);
+    }
+
+    /// Writes the cache to the output directory, if there is one.
+    pub(crate) fn save(&self) -> Result<(), Error> {
+        if let Some(path) = &self.path {
+            let mut file = BufWriter::new(File::create(path)?);
+            bincode::serialize_into(&mut file, &PROOF_CACHE_VERSION)
+                .and_then(|_| bincode::serialize_into(file, &*self.proofs.lock().unwrap()))
+                .map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    }
+}
+
+/// Reads the cached proofs from the file, which starts with the cache version.
+fn read_cache_file(path: &Path) -> Result<BTreeMap<String, CachedProof>, String> {
+    let mut file = BufReader::new(File::open(path).map_err(|e| e.to_string())?);
+    let version: u32 = bincode::deserialize_from(&mut file).map_err(|e| e.to_string())?;
+    if version != PROOF_CACHE_VERSION {
+        return Err(format!(
+            "Unsupported version {version} (expected {PROOF_CACHE_VERSION})"
+        ));
+    }
+    bincode::deserialize_from(file).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Write;
+
+    use mktemp::Temp;
+    use powdr_number::{Bn254Field, GoldilocksField};
+
+    use super::*;
+
+    fn witness(value: u64) -> Vec<(String, Vec<GoldilocksField>)> {
+        vec![("main::x".to_string(), vec![value.into(); 4])]
+    }
+
+    #[test]
+    fn key_depends_on_inputs() {
+        let machine = machine_hash(&"pil", "", None);
+        let key = cache_key(&machine, 4, &witness(0));
+        assert_eq!(key, cache_key(&machine, 4, &witness(0)));
+        assert_ne!(
+            key,
+            cache_key(&machine_hash(&"pil", "", Some(&[])), 4, &witness(0))
+        );
+        assert_ne!(
+            key,
+            cache_key(&machine_hash(&"pil2", "", None), 4, &witness(0))
+        );
+        assert_ne!(key, cache_key(&machine, 8, &witness(0)));
+        assert_ne!(key, cache_key(&machine, 4, &witness(1)));
+        let bn254_witness = vec![("main::x".to_string(), vec![Bn254Field::from(0); 4])];
+        assert_ne!(key, cache_key(&machine, 4, &bn254_witness));
+        // Splitting the same values differently between the columns changes the key.
+        let split = |at: usize| {
+            let values = (0..4).map(GoldilocksField::from).collect::<Vec<_>>();
+            vec![
+                ("x".to_string(), values[..at].to_vec()),
+                ("y".to_string(), values[at..].to_vec()),
+            ]
+        };
+        assert_ne!(
+            cache_key(&machine, 4, &split(1)),
+            cache_key(&machine, 4, &split(2))
+        );
+    }
+
+    #[test]
+    fn persisted() {
+        let dir = Temp::new_dir().unwrap();
+        let key = cache_key(&machine_hash(&"pil", "", None), 4, &witness(0));
+        let other_key = cache_key(&machine_hash(&"pil", "", None), 4, &witness(1));
+        let proof = MachineProof {
+            size: 4,
+            proof: vec![1, 2, 3],
+        };
+
+        let cache = ProofCache::load(Some(dir.to_path_buf()));
+        assert!(cache.get("main", &key).is_none());
+        cache.insert("main", key, proof);
+        cache.save().unwrap();
+
+        let cache = ProofCache::load(Some(dir.to_path_buf()));
+        assert_eq!(cache.get("main", &key).unwrap().proof, vec![1, 2, 3]);
+        assert!(cache.get("main", &other_key).is_none());
+        assert!(cache.get("other", &key).is_none());
+
+        cache.remove("main");
+        assert!(cache.get("main", &key).is_none());
+    }
+
+    #[test]
+    fn other_version_is_ignored() {
+        let dir = Temp::new_dir().unwrap();
+        let mut file = File::create(dir.join(PROOF_CACHE_FILE)).unwrap();
+        bincode::serialize_into(&mut file, &(PROOF_CACHE_VERSION + 1)).unwrap();
+        file.write_all(&[1, 2, 3]).unwrap();
+        drop(file);
+
+        assert_eq!(
+            read_cache_file(&dir.join(PROOF_CACHE_FILE)).err(),
+            Some(format!(
+                "Unsupported version {} (expected {PROOF_CACHE_VERSION})",
+                PROOF_CACHE_VERSION + 1
+            ))
+        );
+        let cache = ProofCache::load(Some(dir.to_path_buf()));
+        assert!(cache.proofs.lock().unwrap().is_empty());
+    }
+}
@@ -71,6 +71,19 @@ macro_rules! generalize_factory {
                     _ => panic!("Unsupported field type: {:?}", TypeId::of::<F>()),
                 }
             }
+
+            fn max_constraint_degree(&self) -> std::option::Option<usize> {
+                use std::any::TypeId;
+                match TypeId::of::<F>() {
+                    $(
+                        id if id == TypeId::of::<$supported_type>() => {
+                            return <$restricted_factory as crate::BackendFactory<$supported_type>>::
+                                max_constraint_degree(&$restricted_factory)
+                        }
+                    )*
+                    _ => panic!("Unsupported field type: {:?}", TypeId::of::<F>()),
+                }
+            }
         }
     };
 }
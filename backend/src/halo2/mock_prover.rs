@@ -16,7 +16,9 @@ pub fn mock_prove<T: FieldElement>(
     witgen_callback: WitgenCallback<T>,
 ) -> Result<(), String> {
     if !matches!(T::known_field(), Some(KnownField::Bn254Field)) {
-        panic!("powdr modulus doesn't match halo2 modulus. Make sure you are using Bn254");
+        return Err(
+            "powdr modulus doesn't match halo2 modulus. Make sure you are using Bn254".to_string(),
+        );
     }
 
     // double the row count in order to make space for the cells introduced by the backend
@@ -11,9 +11,11 @@ mod composite;
 mod field_filter;
 mod mock;
 
+pub use mock::inspect;
+
 use powdr_ast::analyzed::Analyzed;
 use powdr_executor::{constant_evaluator::VariablySizedColumn, witgen::WitgenCallback};
-use powdr_number::{DegreeType, FieldElement};
+use powdr_number::{DegreeType, FieldElement, KnownField};
 use std::{io, path::PathBuf, sync::Arc};
 use strum::{Display, EnumString, EnumVariantNames};
 
@@ -119,6 +121,40 @@ impl BackendType {
             }
         }
     }
+
+    /// Returns whether the backend can generate proofs over the given field.
+    // `field` is unused if only the mock backend is enabled.
+    #[allow(unused_variables)]
+    pub fn supports_field(&self, field: KnownField) -> bool {
+        match self {
+            BackendType::Mock => true,
+            #[cfg(feature = "halo2")]
+            BackendType::Halo2
+            | BackendType::Halo2Composite
+            | BackendType::Halo2Mock
+            | BackendType::Halo2MockComposite => field == KnownField::Bn254Field,
+            #[cfg(feature = "estark-polygon")]
+            BackendType::EStarkPolygon | BackendType::EStarkPolygonComposite => {
+                field == KnownField::GoldilocksField
+            }
+            #[cfg(feature = "estark-starky")]
+            BackendType::EStarkStarky | BackendType::EStarkStarkyComposite => {
+                field == KnownField::GoldilocksField
+            }
+            #[cfg(feature = "estark-starky")]
+            BackendType::EStarkDump | BackendType::EStarkDumpComposite => true,
+            #[cfg(feature = "plonky3")]
+            BackendType::Plonky3 | BackendType::Plonky3Composite => matches!(
+                field,
+                KnownField::BabyBearField
+                    | KnownField::KoalaBearField
+                    | KnownField::GoldilocksField
+                    | KnownField::Mersenne31Field
+            ),
+            #[cfg(feature = "stwo")]
+            BackendType::Stwo | BackendType::StwoComposite => field == KnownField::Mersenne31Field,
+        }
+    }
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -178,6 +214,11 @@ pub trait BackendFactory<F: FieldElement> {
     fn generate_setup(&self, _size: DegreeType, _output: &mut dyn io::Write) -> Result<(), Error> {
         Err(Error::NoSetupAvailable)
     }
+
+    /// The maximum degree of the constraints the backend can prove, if it is bounded.
+    fn max_constraint_degree(&self) -> Option<usize> {
+        None
+    }
 }
 
 /// Dynamic interface for a backend.
@@ -27,7 +27,7 @@ pub enum ConnectionKind {
 
 /// A connection between two machines.
 pub struct Connection<F> {
-    identity: Identity<F>,
+    pub identity: Identity<F>,
     pub left: SelectedExpressions<F>,
     pub right: SelectedExpressions<F>,
     /// For [ConnectionKind::Permutation], rows of `left` are a permutation of rows of `right`. For [ConnectionKind::Lookup], all rows in `left` are in `right`.
@@ -0,0 +1,404 @@
+//! Inspection of a trace machine by machine, e.g. to find out why the mock backend
+//! rejects it. This is the model behind `powdr inspect`.
+
+use std::{borrow::Cow, collections::BTreeMap, sync::Arc};
+
+use powdr_ast::{
+    analyzed::{
+        AlgebraicBinaryOperator, AlgebraicExpression, AlgebraicReference, AlgebraicUnaryOperator,
+        Analyzed, SelectedExpressions,
+    },
+    parsed::{
+        visitor::Children, BinaryOperation, Expression, IndexAccess, Number, PilStatement,
+        UnaryOperation, UnaryOperator,
+    },
+};
+use powdr_executor::{
+    constant_evaluator::{FixedColumnValues, VariablySizedColumn},
+    witgen::WitgenCallback,
+};
+use powdr_executor_utils::expression_evaluator::ExpressionEvaluator;
+use powdr_number::FieldElement;
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+
+use super::{
+    bus_checker::BusInteraction, challenges, connection_constraint_checker::Connection,
+    machine::Machine, polynomial_constraint_checker::PolynomialConstraintChecker,
+};
+
+/// A trace (PIL, fixed columns and witness), split into machines.
+pub struct Trace<F> {
+    machine_to_pil: BTreeMap<String, Analyzed<F>>,
+    fixed: Arc<Vec<(String, VariablySizedColumn<F>)>>,
+    witness: Arc<Vec<(String, Vec<F>)>>,
+    connections: Vec<Connection<F>>,
+    bus_interactions: Vec<BusInteraction<F>>,
+}
+
+impl<F: FieldElement> Trace<F> {
+    pub fn new(
+        pil: &Analyzed<F>,
+        fixed: Arc<Vec<(String, VariablySizedColumn<F>)>>,
+        witness: Arc<Vec<(String, Vec<F>)>>,
+    ) -> Self {
+        let machine_to_pil = powdr_backend_utils::split_pil(pil);
+        let connections = Connection::get_all(pil, &machine_to_pil);
+        let bus_interactions = BusInteraction::get_all(pil, &machine_to_pil);
+        Self {
+            machine_to_pil,
+            fixed,
+            witness,
+            connections,
+            bus_interactions,
+        }
+    }
+
+    /// Generates the later-stage witnesses (using the same challenges as the mock backend)
+    /// and checks the polynomial identities of all machines.
+    pub fn inspect(&self, witgen_callback: &WitgenCallback<F>) -> TraceInspector<'_, F> {
+        let challenges = challenges(&self.machine_to_pil);
+        let machines = self
+            .machine_to_pil
+            .par_iter()
+            .filter_map(|(machine_name, pil)| {
+                Machine::try_new(
+                    machine_name.clone(),
+                    &self.witness,
+                    &self.fixed,
+                    pil,
+                    witgen_callback,
+                    &challenges,
+                )
+            })
+            .map(|machine| (machine.machine_name.clone(), machine))
+            .collect::<BTreeMap<_, _>>();
+        let errors = machines
+            .iter()
+            .map(|(machine_name, machine)| {
+                let errors = PolynomialConstraintChecker::new(machine)
+                    .failing_constraints()
+                    .errors_by_row();
+                (machine_name.clone(), errors)
+            })
+            .collect();
+        TraceInspector {
+            trace: self,
+            machines,
+            errors,
+        }
+    }
+}
+
+/// An interaction of a row with a row of another machine, through a lookup, a permutation
+/// or a bus interaction.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Link {
+    /// The identity creating the interaction.
+    pub identity: String,
+    /// The other machine.
+    pub machine: String,
+    /// The matching row in the other machine, if there is one.
+    pub row: Option<usize>,
+}
+
+/// Gives access to the values of the (non-empty) machines of a trace, the rows where
+/// polynomial identities fail and the interactions between machines.
+pub struct TraceInspector<'a, F> {
+    trace: &'a Trace<F>,
+    machines: BTreeMap<String, Machine<'a, F>>,
+    /// The failing identities by row, for each machine.
+    errors: BTreeMap<String, BTreeMap<usize, Vec<String>>>,
+}
+
+impl<F: FieldElement> TraceInspector<'_, F> {
+    pub fn machine_names(&self) -> impl Iterator<Item = &str> {
+        self.machines.keys().map(|name| name.as_str())
+    }
+
+    pub fn size(&self, machine: &str) -> usize {
+        self.machines[machine].size
+    }
+
+    /// Returns the witness and fixed columns of the machine, in source order.
+    /// Fixed columns that are not stored densely are materialized.
+    pub fn columns(&self, machine: &str) -> Vec<(String, Cow<'_, [F]>)> {
+        let machine = &self.machines[machine];
+        machine
+            .pil
+            .committed_polys_in_source_order()
+            .chain(machine.pil.constant_polys_in_source_order())
+            .flat_map(|(symbol, _)| symbol.array_elements())
+            .filter_map(|(name, poly_id)| {
+                let values = match machine.values.trace.get(&poly_id) {
+                    Some(values) => Cow::Borrowed(values.as_slice()),
+                    None => match machine.values.fixed.get(&poly_id)? {
+                        FixedColumnValues::Dense(values) => Cow::Borrowed(values.as_slice()),
+                        values => Cow::Owned(values.to_vec()),
+                    },
+                };
+                Some((name, values))
+            })
+            .collect()
+    }
+
+    /// Returns the failing polynomial identities of the machine by row.
+    pub fn errors(&self, machine: &str) -> &BTreeMap<usize, Vec<String>> {
+        &self.errors[machine]
+    }
+
+    /// Evaluates a PIL expression on a row of the machine. Column names are resolved
+    /// relative to the namespace of the machine.
+    pub fn evaluate(&self, machine: &str, row: usize, expression: &str) -> Result<F, String> {
+        let machine = &self.machines[machine];
+        let expression = parse_expression(expression)?;
+        let expression = to_algebraic(&expression, machine)?;
+        let mut evaluator =
+            ExpressionEvaluator::new(machine.values.row(row), &machine.intermediate_definitions);
+        Ok(evaluator.evaluate(&expression))
+    }
+
+    /// Returns the interactions of a row of the machine with other machines. A row only
+    /// interacts if the selector (or multiplicity) of the interaction is not zero on that row.
+    pub fn links(&self, machine_name: &str, row: usize) -> Vec<Link> {
+        let machine = &self.machines[machine_name];
+        let connection_links = self.trace.connections.iter().filter_map(|connection| {
+            let (caller, callee) = (connection.caller(), connection.callee());
+            let (this, other, other_machine) = if caller.as_deref() == Some(machine_name) {
+                (&connection.left, &connection.right, callee?)
+            } else if callee.as_deref() == Some(machine_name) {
+                (&connection.right, &connection.left, caller?)
+            } else {
+                return None;
+            };
+            let tuple = selected_tuple(machine, row, this)?;
+            let row = self.machines.get(&other_machine).and_then(|target| {
+                (0..target.size)
+                    .find(|row| selected_tuple(target, *row, other).as_ref() == Some(&tuple))
+            });
+            Some(Link {
+                identity: connection.identity.to_string(),
+                machine: other_machine,
+                row,
+            })
+        });
+
+        let bus_links = self
+            .trace
+            .bus_interactions
+            .iter()
+            .filter(|interaction| interaction.machine == machine_name)
+            .filter_map(|interaction| {
+                let (is_send, tuple) = bus_tuple(machine, row, interaction)?;
+                // Find the first row receiving the tuple sent on this row, or vice versa.
+                self.trace
+                    .bus_interactions
+                    .iter()
+                    .filter(|other| other != &interaction)
+                    .find_map(|other| {
+                        let other_machine = self.machines.get(&other.machine)?;
+                        let row = (0..other_machine.size).find(|row| {
+                            bus_tuple(other_machine, *row, other) == Some((!is_send, tuple.clone()))
+                        })?;
+                        Some(Link {
+                            identity: interaction.identity.to_string(),
+                            machine: other.machine.clone(),
+                            row: Some(row),
+                        })
+                    })
+            });
+
+        connection_links.chain(bus_links).collect()
+    }
+}
+
+/// Evaluates the selected expressions on a row, if the selector is not zero.
+fn selected_tuple<F: FieldElement>(
+    machine: &Machine<F>,
+    row: usize,
+    selected_expressions: &SelectedExpressions<F>,
+) -> Option<Vec<F>> {
+    let mut evaluator =
+        ExpressionEvaluator::new(machine.values.row(row), &machine.intermediate_definitions);
+    if evaluator.evaluate(&selected_expressions.selector).is_zero() {
+        return None;
+    }
+    Some(
+        selected_expressions
+            .expressions
+            .iter()
+            .map(|expression| evaluator.evaluate(expression))
+            .collect(),
+    )
+}
+
+/// Evaluates the bus ID and the payload of a bus interaction on a row, if the multiplicity
+/// is not zero, together with whether the tuple is sent (rather than received).
+fn bus_tuple<F: FieldElement>(
+    machine: &Machine<F>,
+    row: usize,
+    interaction: &BusInteraction<F>,
+) -> Option<(bool, Vec<F>)> {
+    let identity = &interaction.identity;
+    let mut evaluator =
+        ExpressionEvaluator::new(machine.values.row(row), &machine.intermediate_definitions);
+    let multiplicity = evaluator.evaluate(&identity.multiplicity);
+    if multiplicity.is_zero() {
+        return None;
+    }
+    let tuple = std::iter::once(&identity.bus_id)
+        .chain(identity.payload.children())
+        .map(|e| evaluator.evaluate(e))
+        .collect();
+    Some((multiplicity.is_in_lower_half(), tuple))
+}
+
+fn parse_expression(expression: &str) -> Result<Expression, String> {
+    let input = format!("{expression};");
+    let file = powdr_parser::parse(None, &input).map_err(|e| e.message().to_string())?;
+    match &file.0[..] {
+        [PilStatement::Expression(_, expression)] => Ok(expression.clone()),
+        _ => Err(format!("Expected an expression, got: {expression}")),
+    }
+}
+
+/// Converts a parsed expression into an algebraic expression, resolving column names
+/// (possibly without namespace) in the machine. Only arithmetic on columns and numbers is
+/// supported.
+fn to_algebraic<F: FieldElement>(
+    expression: &Expression,
+    machine: &Machine<F>,
+) -> Result<AlgebraicExpression<F>, String> {
+    let reference = |name: String, next| {
+        let name_to_poly_id = machine.pil.name_to_poly_id();
+        let qualified = format!("{}::{name}", machine.machine_name);
+        [name, qualified]
+            .into_iter()
+            .find_map(|name| {
+                let poly_id = *name_to_poly_id.get(&name)?;
+                Some(AlgebraicExpression::Reference(AlgebraicReference {
+                    name,
+                    poly_id,
+                    next,
+                }))
+            })
+            .ok_or_else(|| format!("Unknown column in machine {}", machine.machine_name))
+    };
+    let column_name = |expression: &Expression| match expression {
+        Expression::Reference(_, r) => Ok(r.path.to_string()),
+        Expression::IndexAccess(_, IndexAccess { array, index }) => match (&**array, &**index) {
+            (Expression::Reference(_, r), Expression::Number(_, Number { value, .. })) => {
+                Ok(format!("{}[{value}]", r.path))
+            }
+            _ => Err(format!("Unsupported index access: {expression}")),
+        },
+        _ => Err(format!("Unsupported expression: {expression}")),
+    };
+
+    Ok(match expression {
+        Expression::Reference(..) | Expression::IndexAccess(..) => {
+            reference(column_name(expression)?, false)?
+        }
+        Expression::Number(_, Number { value, .. }) => AlgebraicExpression::Number(
+            F::checked_from(value.clone()).ok_or_else(|| format!("Number too large: {value}"))?,
+        ),
+        Expression::UnaryOperation(_, UnaryOperation { op, expr }) => match op {
+            UnaryOperator::Minus => AlgebraicExpression::new_unary(
+                AlgebraicUnaryOperator::Minus,
+                to_algebraic(expr, machine)?,
+            ),
+            UnaryOperator::Next => reference(column_name(expr)?, true)?,
+            UnaryOperator::LogicalNot => {
+                return Err(format!("Unsupported expression: {expression}"))
+            }
+        },
+        Expression::BinaryOperation(_, BinaryOperation { left, op, right }) => {
+            let op = AlgebraicBinaryOperator::try_from(*op)?;
+            AlgebraicExpression::new_binary(
+                to_algebraic(left, machine)?,
+                op,
+                to_algebraic(right, machine)?,
+            )
+        }
+        _ => return Err(format!("Unsupported expression: {expression}")),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use powdr_executor::witgen::WitgenCallback;
+    use powdr_number::GoldilocksField;
+
+    use super::*;
+
+    type F = GoldilocksField;
+
+    fn column(name: &str, values: &[u64]) -> (String, Vec<F>) {
+        (
+            name.to_string(),
+            values.iter().map(|v| F::from(*v)).collect(),
+        )
+    }
+
+    #[test]
+    fn inspect() {
+        let pil = powdr_pil_analyzer::analyze_string::<F>(
+            "
+            namespace main(4);
+                col witness a, b;
+                a' = a + 1;
+                [b] in [table::x];
+            namespace table(4);
+                col fixed x = [0, 2, 4, 6];
+            ",
+        )
+        .unwrap();
+        let fixed = Arc::new(vec![(
+            "table::x".to_string(),
+            VariablySizedColumn::from([0u64, 2, 4, 6].into_iter().map(F::from).collect::<Vec<_>>()),
+        )]);
+        let witness = Arc::new(vec![
+            column("main::a", &[0, 1, 5, 3]),
+            column("main::b", &[4, 2, 6, 0]),
+        ]);
+        let trace = Trace::new(&pil, fixed, witness);
+        let callback = WitgenCallback::new(Arc::new(|_, _, _, _| unreachable!()));
+        let inspector = trace.inspect(&callback);
+
+        assert_eq!(
+            inspector.machine_names().collect::<Vec<_>>(),
+            vec!["main", "table"]
+        );
+        let names = inspector
+            .columns("main")
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["main::a", "main::b"]);
+
+        // `a' = a + 1` fails going from row 1 to row 2, row 2 to row 3 and row 3 to row 0.
+        let failing_rows = inspector.errors("main").keys().collect::<Vec<_>>();
+        assert_eq!(failing_rows, vec![&1, &2, &3]);
+
+        assert_eq!(inspector.evaluate("main", 1, "a' - b"), Ok(F::from(3u64)));
+        assert_eq!(
+            inspector.evaluate("main", 2, "main::a * 2"),
+            Ok(F::from(10u64))
+        );
+        assert!(inspector.evaluate("main", 0, "c").is_err());
+
+        let links = inspector.links("main", 2);
+        assert_eq!(links.len(), 1);
+        assert_eq!(links[0].machine, "table");
+        assert_eq!(links[0].row, Some(3));
+        assert_eq!(
+            inspector.links("table", 1),
+            vec![Link {
+                identity: links[0].identity.clone(),
+                machine: "main".to_string(),
+                row: Some(1)
+            }]
+        );
+    }
+}
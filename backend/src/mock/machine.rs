@@ -2,7 +2,7 @@ use std::collections::BTreeMap;
 
 use itertools::Itertools;
 use powdr_ast::analyzed::{AlgebraicExpression, AlgebraicReferenceThin, Analyzed};
-use powdr_backend_utils::{machine_fixed_columns, machine_witness_columns};
+use powdr_backend_utils::{machine_fixed_column_values, machine_witness_columns};
 use powdr_executor::constant_evaluator::VariablySizedColumn;
 use powdr_executor_utils::{expression_evaluator::OwnedTerminalValues, WitgenCallback};
 use powdr_number::{DegreeType, FieldElement};
@@ -45,12 +45,12 @@ impl<'a, F: FieldElement> Machine<'a, F> {
                 witgen_callback.next_stage_witness(pil, &witness, challenges.clone(), stage as u8);
         }
 
-        let fixed = machine_fixed_columns(fixed, pil);
+        let fixed = machine_fixed_column_values(fixed, pil);
         let fixed = fixed.get(&(size as DegreeType)).unwrap();
         let fixed = fixed
             .iter()
-            // TODO: Avoid clone?
-            .map(|(name, col)| (name.clone(), col.to_vec()))
+            // TODO: Avoid clone of dense columns?
+            .map(|(name, col)| (name.clone(), (*col).clone()))
             .collect::<Vec<_>>();
 
         let intermediate_definitions = pil.intermediate_definitions();
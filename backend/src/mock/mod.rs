@@ -22,6 +22,7 @@ use crate::{Backend, BackendFactory, BackendOptions, Error, Proof};
 
 mod bus_checker;
 mod connection_constraint_checker;
+pub mod inspect;
 mod machine;
 mod polynomial_constraint_checker;
 mod utils;
@@ -65,6 +66,26 @@ impl<F: FieldElement> BackendFactory<F> for MockBackendFactory {
     }
 }
 
+/// Returns the values of all challenges used by the machines.
+fn challenges<F: FieldElement>(machine_to_pil: &BTreeMap<String, Analyzed<F>>) -> BTreeMap<u64, F> {
+    machine_to_pil
+        .values()
+        .flat_map(|pil| pil.identities.iter())
+        .flat_map(|identity| identity.all_children())
+        .filter_map(|expr| match expr {
+            AlgebraicExpression::Challenge(challenge) => {
+                // Use the hash of the ID as the challenge.
+                // This way, if the same challenge is used by different machines, they will
+                // have the same value.
+                let mut hasher = DefaultHasher::new();
+                challenge.id.hash(&mut hasher);
+                Some((challenge.id, F::from(hasher.finish())))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
 pub(crate) struct MockBackend<F> {
     machine_to_pil: BTreeMap<String, Analyzed<F>>,
     fixed: Arc<Vec<(String, VariablySizedColumn<F>)>>,
@@ -83,23 +104,7 @@ impl<F: FieldElement> Backend<F> for MockBackend<F> {
             unimplemented!();
         }
 
-        let challenges = self
-            .machine_to_pil
-            .values()
-            .flat_map(|pil| pil.identities.iter())
-            .flat_map(|identity| identity.all_children())
-            .filter_map(|expr| match expr {
-                AlgebraicExpression::Challenge(challenge) => {
-                    // Use the hash of the ID as the challenge.
-                    // This way, if the same challenge is used by different machines, they will
-                    // have the same value.
-                    let mut hasher = DefaultHasher::new();
-                    challenge.id.hash(&mut hasher);
-                    Some((challenge.id, F::from(hasher.finish())))
-                }
-                _ => None,
-            })
-            .collect::<BTreeMap<_, _>>();
+        let challenges = challenges(&self.machine_to_pil);
 
         let start = std::time::Instant::now();
         let machines = self
@@ -20,6 +20,13 @@ impl<'a, F: FieldElement> PolynomialConstraintChecker<'a, F> {
     }
 
     pub fn check(&self) -> MachineResult<'a, F> {
+        let result = self.failing_constraints();
+        result.log();
+        result
+    }
+
+    /// Like [PolynomialConstraintChecker::check], but without logging the errors.
+    pub fn failing_constraints(&self) -> MachineResult<'a, F> {
         // We'd only expect to see polynomial identities here, because we're only validating one machine.
         // But if they do appear (because of a lookup / permutation within a namespace), they are handled
         // by the ConnectionConstraintChecker.
@@ -36,12 +43,10 @@ impl<'a, F: FieldElement> PolynomialConstraintChecker<'a, F> {
             .flat_map(|row| self.check_row(row, &polynomial_identities))
             .collect();
 
-        let result = MachineResult {
+        MachineResult {
             machine_name: self.machine.machine_name.clone(),
             errors,
-        };
-        result.log();
-        result
+        }
     }
 
     fn check_row(
@@ -131,4 +136,16 @@ impl<F: fmt::Display> MachineResult<'_, F> {
     pub fn has_errors(&self) -> bool {
         !self.errors.is_empty()
     }
+
+    /// Returns the failing identities (with the values of the referenced variables) by row.
+    pub fn errors_by_row(&self) -> BTreeMap<usize, Vec<String>> {
+        let mut errors_by_row: BTreeMap<_, Vec<_>> = BTreeMap::new();
+        for error in &self.errors {
+            errors_by_row
+                .entry(error.row)
+                .or_default()
+                .push(error.to_string());
+        }
+        errors_by_row
+    }
 }
@@ -45,6 +45,10 @@ where
                 p3.set_proving_key(pk);
                 p3.set_verifying_key(vk);
             }
+            // Only a verification key: the backend can verify, but not prove.
+            (None, Some(vk)) => {
+                p3.set_verifying_key(vk);
+            }
             _ => {
                 p3.setup();
             }
@@ -52,6 +56,10 @@ where
 
         Ok(p3)
     }
+
+    fn max_constraint_degree(&self) -> Option<usize> {
+        Some(T::degree_bound())
+    }
 }
 
 generalize_factory!(Factory <- RestrictedFactory, [BabyBearField, KoalaBearField, GoldilocksField, Mersenne31Field]);
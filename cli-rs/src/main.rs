@@ -8,10 +8,13 @@ use env_logger::{Builder, Target};
 use log::LevelFilter;
 
 use powdr::number::{
-    BabyBearField, BigUint, Bn254Field, FieldElement, GoldilocksField, KnownField, KoalaBearField,
+    BabyBearField, BigUint, Bls12_381Field, Bn254Field, FieldElement, GoldilocksField, KnownField,
+    KoalaBearField,
 };
 use powdr::riscv::{CompilerOptions, RuntimeLibs};
-use powdr::riscv_executor::{write_executor_csv, ProfilerOptions};
+use powdr::riscv_executor::{
+    first_divergence, write_executor_csv, InstructionTrace, ProfilerOptions,
+};
 use powdr::Pipeline;
 
 use std::ffi::OsStr;
@@ -32,6 +35,8 @@ pub enum FieldArgument {
     Gl,
     #[strum(serialize = "bn254")]
     Bn254,
+    #[strum(serialize = "bls12_381")]
+    Bls12_381,
 }
 
 impl FieldArgument {
@@ -41,6 +46,7 @@ impl FieldArgument {
             FieldArgument::Kb => KnownField::KoalaBearField,
             FieldArgument::Gl => KnownField::GoldilocksField,
             FieldArgument::Bn254 => KnownField::Bn254Field,
+            FieldArgument::Bls12_381 => KnownField::Bls12_381Field,
         }
     }
 }
@@ -91,6 +97,11 @@ enum Commands {
         /// Maximum trace length for powdr machines (2 ^ max_degree_log).
         #[arg(long)]
         max_degree_log: Option<u8>,
+        /// Annotate the generated code with the original RISC-V instructions,
+        /// which are shown in instruction traces (see `--instruction-trace`).
+        #[arg(long)]
+        #[arg(default_value_t = false)]
+        debug_instructions: bool,
     },
     /// Translate a RISC-V statically linked executable to powdr assembly.
     RiscvElf {
@@ -117,6 +128,12 @@ enum Commands {
         #[arg(short, long)]
         #[arg(default_value_t = false)]
         continuations: bool,
+
+        /// Annotate the generated code with the original RISC-V instructions,
+        /// which are shown in instruction traces (see `--instruction-trace`).
+        #[arg(long)]
+        #[arg(default_value_t = false)]
+        debug_instructions: bool,
     },
     /// Execute a RISCV powdr-asm file with given inputs.
     /// Does not generate a witness.
@@ -149,6 +166,12 @@ enum Commands {
         #[arg(long)]
         #[arg(default_value_t = false)]
         generate_callgrind: bool,
+
+        /// Write an instruction-level execution trace ("[file].trace"). The original RISC-V
+        /// instructions are only included if the file was compiled with `--debug-instructions`.
+        #[arg(long)]
+        #[arg(default_value_t = false)]
+        instruction_trace: bool,
     },
     /// Execute and generate a valid witness for a RISCV powdr-asm file with the given inputs.
     Witgen {
@@ -190,6 +213,25 @@ enum Commands {
         #[arg(long)]
         #[arg(default_value_t = false)]
         generate_callgrind: bool,
+
+        /// Write an instruction-level execution trace ("[file].trace"). The original RISC-V
+        /// instructions are only included if the file was compiled with `--debug-instructions`.
+        #[arg(long)]
+        #[arg(default_value_t = false)]
+        instruction_trace: bool,
+    },
+    /// Compare two instruction traces and show where they diverge.
+    TraceDiff {
+        /// The first trace file
+        left: String,
+
+        /// The second trace file
+        right: String,
+
+        /// Number of matching instructions to show before the divergence
+        #[arg(long)]
+        #[arg(default_value_t = 10)]
+        context: usize,
     },
 }
 
@@ -252,6 +294,7 @@ fn run_command(command: Commands) {
             coprocessors,
             continuations,
             max_degree_log,
+            debug_instructions,
         } => compile_rust(
             &file,
             field.as_known_field(),
@@ -259,6 +302,7 @@ fn run_command(command: Commands) {
             coprocessors,
             continuations,
             max_degree_log,
+            debug_instructions,
         ),
         Commands::RiscvElf {
             file,
@@ -266,12 +310,14 @@ fn run_command(command: Commands) {
             output_directory,
             coprocessors,
             continuations,
+            debug_instructions,
         } => compile_riscv_elf(
             &file,
             field.as_known_field(),
             Path::new(&output_directory),
             coprocessors,
             continuations,
+            debug_instructions,
         ),
         Commands::Execute {
             file,
@@ -280,8 +326,9 @@ fn run_command(command: Commands) {
             output_directory,
             generate_flamegraph,
             generate_callgrind,
+            instruction_trace,
         } => {
-            let profiling = if generate_callgrind || generate_flamegraph {
+            let profiling = if generate_callgrind || generate_flamegraph || instruction_trace {
                 Some(ProfilerOptions {
                     file_stem: Path::new(&file)
                         .file_stem()
@@ -290,6 +337,7 @@ fn run_command(command: Commands) {
                     output_directory: output_directory.clone(),
                     flamegraph: generate_flamegraph,
                     callgrind: generate_callgrind,
+                    instruction_trace,
                 })
             } else {
                 None
@@ -310,8 +358,9 @@ fn run_command(command: Commands) {
             executor_csv,
             generate_flamegraph,
             generate_callgrind,
+            instruction_trace,
         } => {
-            let profiling = if generate_callgrind || generate_flamegraph {
+            let profiling = if generate_callgrind || generate_flamegraph || instruction_trace {
                 Some(ProfilerOptions {
                     file_stem: Path::new(&file)
                         .file_stem()
@@ -320,6 +369,7 @@ fn run_command(command: Commands) {
                     output_directory: output_directory.clone(),
                     flamegraph: generate_flamegraph,
                     callgrind: generate_callgrind,
+                    instruction_trace,
                 })
             } else {
                 None
@@ -333,6 +383,11 @@ fn run_command(command: Commands) {
                 profiling
             ))
         }
+        Commands::TraceDiff {
+            left,
+            right,
+            context,
+        } => trace_diff(Path::new(&left), Path::new(&right), context),
     };
     if let Err(errors) = result {
         for error in errors {
@@ -350,12 +405,16 @@ fn compile_rust(
     coprocessors: Option<String>,
     continuations: bool,
     max_degree_log: Option<u8>,
+    debug_instructions: bool,
 ) -> Result<(), Vec<String>> {
     let libs = coprocessors_to_options(coprocessors)?;
     let mut options = CompilerOptions::new(field, libs, continuations);
     if let Some(max_degree_log) = max_degree_log {
         options = options.with_max_degree_log(max_degree_log);
     }
+    if debug_instructions {
+        options = options.with_debug_instructions();
+    }
     powdr::riscv::compile_rust(file_name, options, output_dir, true, None)
         .ok_or_else(|| vec!["could not compile rust".to_string()])?;
 
@@ -368,9 +427,13 @@ fn compile_riscv_elf(
     output_dir: &Path,
     coprocessors: Option<String>,
     continuations: bool,
+    debug_instructions: bool,
 ) -> Result<(), Vec<String>> {
     let libs = coprocessors_to_options(coprocessors)?;
-    let options = CompilerOptions::new(field, libs, continuations);
+    let mut options = CompilerOptions::new(field, libs, continuations);
+    if debug_instructions {
+        options = options.with_debug_instructions();
+    }
     powdr::riscv::compile_riscv_elf(input_file, Path::new(input_file), options, output_dir, true)
         .ok_or_else(|| vec!["could not translate RISC-V executable".to_string()])?;
 
@@ -427,7 +490,7 @@ fn execute<F: FieldElement>(
 
     if continuations {
         let dry_run =
-            powdr::riscv::continuations::rust_continuations_dry_run(&mut pipeline, profiling);
+            powdr::riscv::continuations::rust_continuations_dry_run(&mut pipeline, profiling)?;
         powdr::riscv::continuations::rust_continuations(&mut pipeline, generate_witness, dry_run)?;
     } else {
         let fixed = pipeline.compute_fixed_cols().unwrap().clone();
@@ -474,6 +537,49 @@ fn execute<F: FieldElement>(
     Ok(())
 }
 
+fn trace_diff(left: &Path, right: &Path, context: usize) -> Result<(), Vec<String>> {
+    let read = |path: &Path| {
+        InstructionTrace::read(path)
+            .map_err(|e| vec![format!("Could not read trace {}: {e}", path.display())])
+    };
+    let left_trace = read(left)?;
+    let right_trace = read(right)?;
+
+    let Some(index) = first_divergence(&left_trace, &right_trace) else {
+        log::info!(
+            "Traces are equal ({} instructions)",
+            left_trace.instructions.len()
+        );
+        return Ok(());
+    };
+
+    log::info!("Traces diverge at instruction {index}");
+    let render = |trace: &InstructionTrace, i: usize| {
+        trace
+            .instructions
+            .get(i)
+            .map(|insn| match trace.location(insn) {
+                Some(loc) => format!("{insn} ({loc})"),
+                None => insn.to_string(),
+            })
+    };
+    for i in index.saturating_sub(context)..index {
+        log::info!(" {}", render(&left_trace, i).unwrap());
+    }
+    let end = "<end of trace>".to_string();
+    log::info!(
+        "-{}",
+        render(&left_trace, index).unwrap_or_else(|| end.clone())
+    );
+    log::info!("+{}", render(&right_trace, index).unwrap_or(end));
+
+    Err(vec![format!(
+        "{} and {} diverge at instruction {index}",
+        left.display(),
+        right.display()
+    )])
+}
+
 fn coprocessors_to_options(coprocessors: Option<String>) -> Result<RuntimeLibs, Vec<String>> {
     let mut libs = RuntimeLibs::new();
     if let Some(list) = coprocessors {
@@ -0,0 +1,402 @@
+//! `powdr inspect`: an interactive viewer for witness traces in the terminal.
+
+use std::{io, path::Path};
+
+use powdr::backend::inspect::{Link, Trace, TraceInspector};
+use powdr::number::FieldElement;
+use powdr::Pipeline;
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind},
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph, Row, Table, Wrap},
+    DefaultTerminal, Frame,
+};
+
+const HELP: &str = "q: quit  ↑↓/PgUp/PgDn/Home/End: rows  ←→: columns  Tab: machine  \
+                    n/N: next/previous failure  e: expression  [/]: select link  \
+                    Enter: follow link  b: back";
+
+/// The number of lines of the details panel.
+const DETAILS_HEIGHT: u16 = 12;
+
+/// Loads the PIL, the fixed columns and the witness from the directory and opens the viewer.
+pub fn inspect_trace<F: FieldElement>(file: &Path, dir: &Path) -> Result<(), Vec<String>> {
+    let mut pipeline = Pipeline::<F>::default()
+        .from_maybe_pil_object(file.to_path_buf())?
+        .read_witness(dir)
+        .map_err(|e| vec![e])?;
+    let pil = pipeline.compute_optimized_pil()?;
+    let fixed = pipeline.compute_fixed_cols()?;
+    let witness = pipeline.compute_witness()?;
+    let witgen_callback = pipeline.witgen_callback()?;
+
+    let trace = Trace::new(&pil, fixed, witness);
+    let inspector = trace.inspect(&witgen_callback);
+    if inspector.machine_names().next().is_none() {
+        return Err(vec![
+            "The trace does not contain any non-empty machine.".to_string()
+        ]);
+    }
+
+    let mut terminal = ratatui::init();
+    let result = App::new(&inspector).run(&mut terminal);
+    ratatui::restore();
+    result.map_err(|e| vec![e.to_string()])
+}
+
+enum Mode {
+    Navigate,
+    /// Editing the expression, with the text typed so far.
+    EditExpression(String),
+}
+
+struct App<'a, 'b, F> {
+    inspector: &'b TraceInspector<'a, F>,
+    machines: Vec<&'b str>,
+    /// The index of the current machine in `machines`.
+    machine: usize,
+    row: usize,
+    /// The index of the leftmost column shown.
+    first_column: usize,
+    /// The number of rows shown in the table, updated on each draw.
+    page_size: usize,
+    mode: Mode,
+    /// The expression evaluated on the current row.
+    expression: Option<String>,
+    /// The links of the current row, computed on demand, and the selected link.
+    links: Option<Vec<Link>>,
+    selected_link: usize,
+    /// Previous positions (machine and row), to go back to after following a link.
+    history: Vec<(usize, usize)>,
+    message: Option<String>,
+    quit: bool,
+}
+
+impl<'a, 'b, F: FieldElement> App<'a, 'b, F> {
+    fn new(inspector: &'b TraceInspector<'a, F>) -> Self {
+        Self {
+            inspector,
+            machines: inspector.machine_names().collect(),
+            machine: 0,
+            row: 0,
+            first_column: 0,
+            page_size: 1,
+            mode: Mode::Navigate,
+            expression: None,
+            links: None,
+            selected_link: 0,
+            history: vec![],
+            message: None,
+            quit: false,
+        }
+    }
+
+    fn run(mut self, terminal: &mut DefaultTerminal) -> io::Result<()> {
+        while !self.quit {
+            terminal.draw(|frame| self.draw(frame))?;
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press {
+                    self.handle_key(key);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn machine_name(&self) -> &'b str {
+        self.machines[self.machine]
+    }
+
+    fn size(&self) -> usize {
+        self.inspector.size(self.machine_name())
+    }
+
+    fn go_to(&mut self, machine: usize, row: usize) {
+        if machine != self.machine {
+            self.first_column = 0;
+        }
+        self.machine = machine;
+        self.row = row.min(self.size() - 1);
+        self.links = None;
+        self.selected_link = 0;
+    }
+
+    fn move_rows(&mut self, delta: isize) {
+        let row = self.row.saturating_add_signed(delta);
+        self.go_to(self.machine, row);
+    }
+
+    fn links(&mut self) -> &[Link] {
+        let inspector = self.inspector;
+        let (machine, row) = (self.machine_name(), self.row);
+        self.links
+            .get_or_insert_with(|| inspector.links(machine, row))
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) {
+        self.message = None;
+        if let Mode::EditExpression(input) = &mut self.mode {
+            match key.code {
+                KeyCode::Enter => {
+                    let input = std::mem::take(input);
+                    self.expression = (!input.trim().is_empty()).then_some(input);
+                    self.mode = Mode::Navigate;
+                }
+                KeyCode::Esc => self.mode = Mode::Navigate,
+                KeyCode::Backspace => {
+                    input.pop();
+                }
+                KeyCode::Char(c) => input.push(c),
+                _ => {}
+            }
+            return;
+        }
+
+        let page = self.page_size as isize;
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => self.quit = true,
+            KeyCode::Down | KeyCode::Char('j') => self.move_rows(1),
+            KeyCode::Up | KeyCode::Char('k') => self.move_rows(-1),
+            KeyCode::PageDown => self.move_rows(page),
+            KeyCode::PageUp => self.move_rows(-page),
+            KeyCode::Home => self.go_to(self.machine, 0),
+            KeyCode::End => self.go_to(self.machine, usize::MAX),
+            KeyCode::Right | KeyCode::Char('l') => self.first_column += 1,
+            KeyCode::Left | KeyCode::Char('h') => {
+                self.first_column = self.first_column.saturating_sub(1)
+            }
+            KeyCode::Tab => self.go_to((self.machine + 1) % self.machines.len(), 0),
+            KeyCode::BackTab => self.go_to(
+                (self.machine + self.machines.len() - 1) % self.machines.len(),
+                0,
+            ),
+            KeyCode::Char('n') => self.next_failure(true),
+            KeyCode::Char('N') => self.next_failure(false),
+            KeyCode::Char('e') => {
+                self.mode = Mode::EditExpression(self.expression.clone().unwrap_or_default())
+            }
+            KeyCode::Char(']') => {
+                let count = self.links().len();
+                if count > 0 {
+                    self.selected_link = (self.selected_link + 1) % count;
+                }
+            }
+            KeyCode::Char('[') => {
+                let count = self.links().len();
+                if count > 0 {
+                    self.selected_link = (self.selected_link + count - 1) % count;
+                }
+            }
+            KeyCode::Enter => self.follow_link(),
+            KeyCode::Char('b') => {
+                if let Some((machine, row)) = self.history.pop() {
+                    self.go_to(machine, row);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Moves to the next (or previous) row with a failing identity, wrapping around.
+    fn next_failure(&mut self, forward: bool) {
+        let inspector = self.inspector;
+        let errors = inspector.errors(self.machine_name());
+        let row = if forward {
+            errors.range(self.row + 1..).chain(errors.iter()).next()
+        } else {
+            errors
+                .range(..self.row)
+                .next_back()
+                .or_else(|| errors.iter().next_back())
+        };
+        match row {
+            Some((row, _)) => self.go_to(self.machine, *row),
+            None => self.message = Some("No failing identities in this machine.".to_string()),
+        }
+    }
+
+    fn follow_link(&mut self) {
+        let selected_link = self.selected_link;
+        let link = self
+            .links()
+            .get(selected_link)
+            .map(|link| (link.machine.clone(), link.row));
+        let Some((target_machine, target_row)) = link else {
+            self.message = Some("This row does not interact with other machines.".to_string());
+            return;
+        };
+        match (
+            self.machines.iter().position(|m| *m == target_machine),
+            target_row,
+        ) {
+            (Some(machine), Some(row)) => {
+                self.history.push((self.machine, self.row));
+                self.go_to(machine, row);
+            }
+            _ => {
+                self.message = Some(format!("No matching row in machine {target_machine}."));
+            }
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [title_area, table_area, details_area, status_area] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Min(3),
+            Constraint::Length(DETAILS_HEIGHT),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        let inspector = self.inspector;
+        let machine = self.machine_name();
+        let errors = inspector.errors(machine);
+        frame.render_widget(
+            Line::from(format!(
+                "Machine {machine} ({}/{}), row {}/{}, {} failing rows",
+                self.machine + 1,
+                self.machines.len(),
+                self.row,
+                self.size(),
+                errors.len()
+            ))
+            .style(Style::default().add_modifier(Modifier::BOLD)),
+            title_area,
+        );
+        self.draw_table(frame, table_area);
+        self.draw_details(frame, details_area);
+
+        let status = match &self.mode {
+            Mode::EditExpression(input) => format!("Expression: {input}_"),
+            Mode::Navigate => self.message.clone().unwrap_or_else(|| HELP.to_string()),
+        };
+        frame.render_widget(Line::from(status), status_area);
+    }
+
+    fn draw_table(&mut self, frame: &mut Frame, area: Rect) {
+        let inspector = self.inspector;
+        let machine = self.machine_name();
+        let errors = inspector.errors(machine);
+        let columns = inspector.columns(machine);
+        self.first_column = self.first_column.min(columns.len().saturating_sub(1));
+
+        // The header and the borders take three lines.
+        self.page_size = (area.height as usize).saturating_sub(3).max(1);
+        let first_row = self
+            .row
+            .saturating_sub(self.page_size / 2)
+            .min(self.size().saturating_sub(self.page_size));
+        let rows = first_row..(first_row + self.page_size).min(self.size());
+
+        // Show as many columns as fit, each as wide as its name or widest visible value.
+        let row_width = self.size().to_string().len().max(3) as u16;
+        let mut available = area.width.saturating_sub(row_width + 2);
+        let visible_columns = columns[self.first_column..]
+            .iter()
+            .map(|(name, values)| {
+                let values = values[rows.clone()]
+                    .iter()
+                    .map(|v| v.to_string())
+                    .collect::<Vec<_>>();
+                let width = values
+                    .iter()
+                    .map(|v| v.len())
+                    .chain([name.len()])
+                    .max()
+                    .unwrap() as u16;
+                (name, values, width)
+            })
+            .take_while(|(_, _, width)| {
+                let fits = available > 0;
+                available = available.saturating_sub(width + 1);
+                fits
+            })
+            .collect::<Vec<_>>();
+
+        let header = Row::new(
+            ["Row"]
+                .into_iter()
+                .chain(visible_columns.iter().map(|(name, _, _)| name.as_str()))
+                .map(String::from),
+        )
+        .style(Style::default().add_modifier(Modifier::BOLD));
+        let table_rows = rows.clone().enumerate().map(|(i, row)| {
+            let mut style = Style::default();
+            if errors.contains_key(&row) {
+                style = style.fg(Color::Red);
+            }
+            if row == self.row {
+                style = style.add_modifier(Modifier::REVERSED);
+            }
+            Row::new(
+                [row.to_string()].into_iter().chain(
+                    visible_columns
+                        .iter()
+                        .map(|(_, values, _)| values[i].clone()),
+                ),
+            )
+            .style(style)
+        });
+        let widths = [row_width]
+            .into_iter()
+            .chain(visible_columns.iter().map(|(_, _, width)| *width))
+            .map(Constraint::Length);
+
+        frame.render_widget(
+            Table::new(table_rows, widths)
+                .header(header)
+                .block(Block::default().borders(Borders::ALL)),
+            area,
+        );
+    }
+
+    fn draw_details(&mut self, frame: &mut Frame, area: Rect) {
+        let inspector = self.inspector;
+        let (machine, row) = (self.machine_name(), self.row);
+        let mut lines = vec![];
+
+        for error in inspector.errors(machine).get(&row).into_iter().flatten() {
+            lines.extend(
+                error
+                    .lines()
+                    .map(|line| Line::styled(line.to_string(), Style::default().fg(Color::Red))),
+            );
+        }
+
+        if let Some(expression) = &self.expression {
+            let value = match inspector.evaluate(machine, row, expression) {
+                Ok(value) => value.to_string(),
+                Err(e) => format!("error: {e}"),
+            };
+            lines.push(Line::from(format!("{expression} = {value}")));
+        }
+
+        let selected_link = self.selected_link;
+        for (i, link) in self.links().iter().enumerate() {
+            let target = match link.row {
+                Some(row) => format!("{} row {row}", link.machine),
+                None => format!("{} (no matching row)", link.machine),
+            };
+            let style = if i == selected_link {
+                Style::default().add_modifier(Modifier::REVERSED)
+            } else {
+                Style::default()
+            };
+            lines.push(Line::from(vec![
+                Span::styled(format!("-> {target}"), style),
+                Span::raw(format!("  via {}", link.identity)),
+            ]));
+        }
+
+        frame.render_widget(
+            Paragraph::new(lines).wrap(Wrap { trim: false }).block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(format!("Row {row}")),
+            ),
+            area,
+        );
+    }
+}
@@ -1,19 +1,23 @@
 //! The powdr CLI tool
 
+mod inspect;
 mod util;
 
-use clap::{CommandFactory, Parser, Subcommand};
+use clap::{Args, CommandFactory, Parser, Subcommand};
 use env_logger::fmt::Color;
 use env_logger::{Builder, Target};
+use inspect::inspect_trace;
 use log::{max_level, LevelFilter};
 use powdr::backend::BackendType;
 use powdr::number::{buffered_write_file, read_polys_csv_file, CsvRenderMode};
 use powdr::number::{
-    BabyBearField, BigUint, Bn254Field, FieldElement, GoldilocksField, KoalaBearField,
-    Mersenne31Field,
+    BabyBearField, BigUint, Bls12_381Field, Bn254Field, FieldElement, GoldilocksField,
+    KoalaBearField, Mersenne31Field,
 };
+use powdr::pilopt::{optimize_with, OptimizerOutput, PassManager};
 use powdr::pipeline::pipeline::{DegreeMode, LinkerMode, LinkerParams};
 use powdr::pipeline::test_runner;
+use powdr::pipeline::witness_export::{parse_row_range, WitnessExport, WitnessExportFormat};
 use powdr::Pipeline;
 use std::io;
 use std::path::PathBuf;
@@ -36,6 +40,7 @@ fn bind_cli_args<F: FieldElement>(
     export_witness: bool,
     export_all_columns: bool,
     csv_mode: CsvRenderModeCLI,
+    witness_export: Option<WitnessExport>,
 ) -> Pipeline<F> {
     let witness_values = witness_values
         .map(|csv_path| {
@@ -56,6 +61,11 @@ fn bind_cli_args<F: FieldElement>(
         .with_witness_csv_settings(export_witness, export_all_columns, csv_mode)
         .with_prover_inputs(inputs.clone());
 
+    let pipeline = match witness_export {
+        Some(witness_export) => pipeline.with_witness_export(witness_export),
+        None => pipeline,
+    };
+
     if pilo {
         pipeline.with_pil_object()
     } else {
@@ -75,6 +85,8 @@ pub enum FieldArgument {
     Gl,
     #[strum(serialize = "bn254")]
     Bn254,
+    #[strum(serialize = "bls12_381")]
+    Bls12_381,
 }
 
 #[derive(Clone, Copy, EnumString, EnumVariantNames, Display)]
@@ -87,6 +99,76 @@ pub enum CsvRenderModeCLI {
     Hex,
 }
 
+#[derive(Clone, Copy, EnumString, EnumVariantNames, Display)]
+pub enum WitnessExportFormatCLI {
+    #[strum(serialize = "csv")]
+    Csv,
+    #[strum(serialize = "jsonl")]
+    JsonLines,
+}
+
+/// Options to export selected parts of the witness, one file per machine.
+#[derive(Args, Default)]
+struct WitnessExportArgs {
+    /// Export the witness of the given comma-separated namespaces, one file per namespace.
+    #[arg(long)]
+    export_namespaces: Option<String>,
+
+    /// Export the witness columns whose full name matches the given glob pattern, e.g. `main::*`.
+    #[arg(long)]
+    export_columns: Option<String>,
+
+    /// Export the given range of rows of the witness, e.g. `100..200`, `100..` or `..200`.
+    #[arg(long)]
+    export_rows: Option<String>,
+
+    /// The file format of the exported witness.
+    #[arg(long)]
+    #[arg(value_parser = clap_enum_variants!(WitnessExportFormatCLI))]
+    export_format: Option<WitnessExportFormatCLI>,
+
+    /// Include the fixed columns of the exported namespaces.
+    #[arg(long)]
+    #[arg(default_value_t = false)]
+    export_fixed: bool,
+}
+
+impl WitnessExportArgs {
+    /// Returns the witness export, if any of the options is set.
+    fn into_export(self) -> Result<Option<WitnessExport>, Vec<String>> {
+        if self.export_namespaces.is_none()
+            && self.export_columns.is_none()
+            && self.export_rows.is_none()
+            && self.export_format.is_none()
+            && !self.export_fixed
+        {
+            return Ok(None);
+        }
+        Ok(Some(WitnessExport {
+            namespaces: self
+                .export_namespaces
+                .map(|namespaces| {
+                    namespaces
+                        .split(',')
+                        .map(|n| n.trim().to_string())
+                        .collect()
+                })
+                .unwrap_or_default(),
+            columns: self.export_columns,
+            rows: self
+                .export_rows
+                .map(|rows| parse_row_range(&rows))
+                .transpose()
+                .map_err(|e| vec![e])?,
+            include_fixed: self.export_fixed,
+            format: match self.export_format {
+                Some(WitnessExportFormatCLI::Csv) | None => WitnessExportFormat::Csv,
+                Some(WitnessExportFormatCLI::JsonLines) => WitnessExportFormat::JsonLines,
+            },
+        }))
+    }
+}
+
 #[derive(Parser)]
 #[command(name = "powdr", author, version, about, long_about = None)]
 struct Cli {
@@ -152,6 +234,9 @@ enum Commands {
 
         /// Backend options. Halo2: "poseidon", "snark_single" or "snark_aggr".
         /// EStark and PilStarkCLI: "stark_gl", "stark_bn" or "snark_bn".
+        /// Composite backends also accept "merge_machines" to prove machines of static
+        /// size together, e.g. "merge_machines,poseidon", and "proof_cache" to reuse
+        /// the verified proofs of unchanged machines from earlier runs.
         #[arg(long)]
         backend_options: Option<String>,
 
@@ -179,6 +264,45 @@ enum Commands {
         #[arg(default_value_t = CsvRenderModeCLI::Hex)]
         #[arg(value_parser = clap_enum_variants!(CsvRenderModeCLI))]
         csv_mode: CsvRenderModeCLI,
+
+        #[command(flatten)]
+        witness_export: WitnessExportArgs,
+
+        /// Record all prover queries and their responses into the given replay file.
+        #[arg(long)]
+        record_queries: Option<String>,
+
+        /// Answer all prover queries from the given replay file instead of the inputs.
+        #[arg(long)]
+        #[arg(conflicts_with_all = ["inputs", "record_queries"])]
+        replay: Option<String>,
+
+        /// Comma-separated list of optimizer passes to run, in this order.
+        /// If all passes are prefixed with `+` or `-`, they are added to or
+        /// removed from the default passes instead.
+        #[arg(long)]
+        optimizer_passes: Option<String>,
+
+        /// Write the PIL file after each optimizer pass into the given directory.
+        #[arg(long)]
+        dump_optimizer_passes: Option<String>,
+
+        /// Reduce the degree of all constraints to at most this value by introducing
+        /// witness columns. The `reduce-degree` optimizer pass without this flag
+        /// reduces it to the degree bound of the backend.
+        #[arg(long)]
+        max_constraint_degree: Option<usize>,
+
+        /// Check the generated witness against the identities of the unoptimized PIL.
+        #[arg(long)]
+        #[arg(default_value_t = false)]
+        check_optimizer: bool,
+
+        /// Solve rows on which witness generation gets stuck by searching over the
+        /// range-constrained unknown cells. Cells are only set if the solution is unique.
+        #[arg(long)]
+        #[arg(default_value_t = false)]
+        witgen_solver_fallback: bool,
     },
     Prove {
         /// Input PIL file
@@ -202,6 +326,9 @@ enum Commands {
 
         /// Backend options. Halo2: "poseidon", "snark_single" or "snark_aggr".
         /// EStark and PilStarkCLI: "stark_gl", "stark_bn" or "snark_bn".
+        /// Composite backends also accept "merge_machines" to prove machines of static
+        /// size together, e.g. "merge_machines,poseidon", and "proof_cache" to reuse
+        /// the verified proofs of unchanged machines from earlier runs.
         #[arg(long)]
         backend_options: Option<String>,
 
@@ -222,6 +349,24 @@ enum Commands {
         #[arg(long)]
         params: Option<String>,
     },
+    /// Opens an interactive viewer for the witness of a PIL file in the terminal.
+    /// Rows where polynomial identities fail are highlighted, and lookups and bus
+    /// interactions can be followed to the matching row of the other machine.
+    Inspect {
+        /// Input PIL file
+        file: String,
+
+        /// Directory to find the committed values
+        #[arg(short, long)]
+        #[arg(default_value_t = String::from("."))]
+        dir: String,
+
+        /// The field to use
+        #[arg(long)]
+        #[arg(default_value_t = FieldArgument::Gl)]
+        #[arg(value_parser = clap_enum_variants!(FieldArgument))]
+        field: FieldArgument,
+    },
     Verify {
         /// Input PIL file
         file: String,
@@ -244,6 +389,9 @@ enum Commands {
 
         /// Backend options. Halo2: "poseidon", "snark_single" or "snark_aggr".
         /// EStark and PilStarkCLI: "stark_gl", "stark_bn" or "snark_bn".
+        /// Composite backends also accept "merge_machines" to prove machines of static
+        /// size together, e.g. "merge_machines,poseidon", and "proof_cache" to reuse
+        /// the verified proofs of unchanged machines from earlier runs.
         #[arg(long)]
         backend_options: Option<String>,
 
@@ -287,6 +435,9 @@ enum Commands {
 
         /// Backend options. Halo2: "poseidon", "snark_single" or "snark_aggr".
         /// EStark and PilStarkCLI: "stark_gl", "stark_bn" or "snark_bn".
+        /// Composite backends also accept "merge_machines" to prove machines of static
+        /// size together, e.g. "merge_machines,poseidon", and "proof_cache" to reuse
+        /// the verified proofs of unchanged machines from earlier runs.
         #[arg(long)]
         backend_options: Option<String>,
 
@@ -323,6 +474,9 @@ enum Commands {
 
         /// Backend options. Halo2: "poseidon", "snark_single" or "snark_aggr".
         /// EStark and PilStarkCLI: "stark_gl", "stark_bn" or "snark_bn".
+        /// Composite backends also accept "merge_machines" to prove machines of static
+        /// size together, e.g. "merge_machines,poseidon", and "proof_cache" to reuse
+        /// the verified proofs of unchanged machines from earlier runs.
         #[arg(long)]
         backend_options: Option<String>,
 
@@ -373,10 +527,28 @@ enum Commands {
         #[arg(default_value_t = FieldArgument::Gl)]
         #[arg(value_parser = clap_enum_variants!(FieldArgument))]
         field: FieldArgument,
+
+        /// Comma-separated list of optimizer passes to run, in this order.
+        /// If all passes are prefixed with `+` or `-`, they are added to or
+        /// removed from the default passes instead.
+        #[arg(long)]
+        optimizer_passes: Option<String>,
+
+        /// Write the PIL file after each optimizer pass into the given directory.
+        #[arg(long)]
+        dump_optimizer_passes: Option<String>,
+
+        /// Reduce the degree of all constraints to at most this value by introducing
+        /// witness columns. The `reduce-degree` optimizer pass without this flag
+        /// reduces it to the degree bound of the backend.
+        #[arg(long)]
+        max_constraint_degree: Option<usize>,
     },
 
     /// Executes all functions starting with `test_` in every module called
     /// `test` (or sub-module thereof) starting from the given module.
+    /// Symbols of type `std::testing::MachineTest` in these modules are run as
+    /// machine tests through witness generation and the mock backend.
     Test {
         /// Input file.
         file: String,
@@ -387,6 +559,21 @@ enum Commands {
         #[arg(value_parser = clap_enum_variants!(FieldArgument))]
         field: FieldArgument,
     },
+
+    /// Prints warnings about unused symbols, imports, columns, registers,
+    /// instructions and operations, shadowed names and ignored query results.
+    /// Individual warnings can be suppressed with a `// lint: allow(<lint>)` comment
+    /// on the line of the warning or on the line before.
+    Lint {
+        /// Input file (.asm or .pil).
+        file: String,
+
+        /// The field to use
+        #[arg(long)]
+        #[arg(default_value_t = FieldArgument::Gl)]
+        #[arg(value_parser = clap_enum_variants!(FieldArgument))]
+        field: FieldArgument,
+    },
 }
 
 fn split_inputs<T: FieldElement>(inputs: &str) -> Vec<T> {
@@ -459,9 +646,19 @@ fn run_command(command: Commands) {
             };
             Ok(())
         }
-        Commands::OptimizePIL { file, field } => {
-            call_with_field!(optimize_and_output::<field>(&file));
-            Ok(())
+        Commands::OptimizePIL {
+            file,
+            field,
+            optimizer_passes,
+            dump_optimizer_passes,
+            max_constraint_degree,
+        } => {
+            call_with_field!(optimize_and_output::<field>(
+                &file,
+                optimizer_passes,
+                dump_optimizer_passes,
+                max_constraint_degree
+            ))
         }
         Commands::Pil {
             file,
@@ -479,6 +676,14 @@ fn run_command(command: Commands) {
             export_witness_csv,
             export_all_columns_csv,
             csv_mode,
+            witness_export,
+            record_queries,
+            replay,
+            optimizer_passes,
+            dump_optimizer_passes,
+            max_constraint_degree,
+            check_optimizer,
+            witgen_solver_fallback,
         } => {
             call_with_field!(run_pil::<field>(
                 file,
@@ -494,12 +699,23 @@ fn run_command(command: Commands) {
                 degree_mode,
                 export_witness_csv,
                 export_all_columns_csv,
-                csv_mode
+                csv_mode,
+                witness_export,
+                record_queries,
+                replay,
+                optimizer_passes,
+                dump_optimizer_passes,
+                max_constraint_degree,
+                check_optimizer,
+                witgen_solver_fallback
             ))
         }
         Commands::Test { file, field } => {
             call_with_field!(run_test::<field>(&file))
         }
+        Commands::Lint { file, field } => {
+            call_with_field!(lint::<field>(&file))
+        }
         Commands::Prove {
             file,
             dir,
@@ -524,6 +740,11 @@ fn run_command(command: Commands) {
                 params
             ))
         }
+        Commands::Inspect { file, dir, field } => {
+            let pil = Path::new(&file);
+            let dir = Path::new(&dir);
+            call_with_field!(inspect_trace::<field>(pil, dir))
+        }
         Commands::Verify {
             file,
             dir,
@@ -687,10 +908,18 @@ fn run_pil<F: FieldElement>(
     export_witness: bool,
     export_all_columns: bool,
     csv_mode: CsvRenderModeCLI,
+    witness_export: WitnessExportArgs,
+    record_queries: Option<String>,
+    replay: Option<String>,
+    optimizer_passes: Option<String>,
+    dump_optimizer_passes: Option<String>,
+    max_constraint_degree: Option<usize>,
+    check_optimizer: bool,
+    witgen_solver_fallback: bool,
 ) -> Result<(), Vec<String>> {
     let inputs = split_inputs::<F>(&inputs);
 
-    let pipeline = bind_cli_args(
+    let mut pipeline = bind_cli_args(
         Pipeline::<F>::default()
             .from_file(PathBuf::from(&file))
             .with_linker_params(LinkerParams {
@@ -705,7 +934,25 @@ fn run_pil<F: FieldElement>(
         export_witness,
         export_all_columns,
         csv_mode,
+        witness_export.into_export()?,
     );
+    if let Some(record_queries) = record_queries {
+        pipeline = pipeline.with_query_recording(PathBuf::from(record_queries));
+    }
+    if let Some(replay) = replay {
+        pipeline = pipeline.with_query_replay(Path::new(&replay))?;
+    }
+    pipeline = pipeline.with_optimizer_passes(pass_manager(
+        optimizer_passes,
+        dump_optimizer_passes,
+        max_constraint_degree,
+    )?);
+    if check_optimizer {
+        pipeline = pipeline.with_optimizer_check();
+    }
+    if witgen_solver_fallback {
+        pipeline = pipeline.with_witgen_solver_fallback();
+    }
     run(pipeline, prove_with, params, backend_options)?;
     Ok(())
 }
@@ -717,14 +964,16 @@ fn run<F: FieldElement>(
     backend_options: Option<String>,
 ) -> Result<(), Vec<String>> {
     pipeline = pipeline.with_setup_file(params.map(PathBuf::from));
+    // The backend is set before witness generation, because the optimizer
+    // can reduce the constraint degree to the degree bound of the backend.
+    if let Some(backend) = prove_with {
+        pipeline = pipeline.with_backend(backend, backend_options.clone());
+    }
 
     pipeline.compute_witness().unwrap();
 
-    if let Some(backend) = prove_with {
-        pipeline
-            .with_backend(backend, backend_options.clone())
-            .compute_proof()
-            .unwrap();
+    if prove_with.is_some() {
+        pipeline.compute_proof().unwrap();
     }
     Ok(())
 }
@@ -735,6 +984,18 @@ fn run_test<T: FieldElement>(file: &str) -> Result<(), Vec<String>> {
     Ok(())
 }
 
+#[allow(clippy::print_stderr)]
+fn lint<T: FieldElement>(file: &str) -> Result<(), Vec<String>> {
+    let warnings = Pipeline::<T>::default()
+        .from_file(PathBuf::from(file))
+        .lint()?;
+    for warning in &warnings {
+        warning.output_to_stderr();
+    }
+    eprintln!("{} warning(s) in {file}", warnings.len());
+    Ok(())
+}
+
 #[allow(clippy::too_many_arguments)]
 fn read_and_prove<T: FieldElement>(
     file: &Path,
@@ -791,15 +1052,49 @@ fn read_and_verify<T: FieldElement>(
     Ok(())
 }
 
+fn pass_manager(
+    optimizer_passes: Option<String>,
+    dump_optimizer_passes: Option<String>,
+    max_constraint_degree: Option<usize>,
+) -> Result<PassManager, Vec<String>> {
+    let mut pass_manager = match optimizer_passes {
+        Some(passes) => passes.parse().map_err(|e| vec![e])?,
+        None => PassManager::default(),
+    };
+    if let Some(max_degree) = max_constraint_degree {
+        pass_manager = pass_manager.with_max_degree(max_degree);
+    }
+    pass_manager.validate().map_err(|e| vec![e])?;
+    Ok(match dump_optimizer_passes {
+        Some(directory) => pass_manager.with_dump_directory(PathBuf::from(directory)),
+        None => pass_manager,
+    })
+}
+
 #[allow(clippy::print_stdout)]
-fn optimize_and_output<T: FieldElement>(file: &str) {
-    println!(
-        "{}",
-        Pipeline::<T>::default()
-            .from_file(PathBuf::from(file))
-            .compute_optimized_pil()
-            .unwrap()
-    );
+fn optimize_and_output<T: FieldElement>(
+    file: &str,
+    optimizer_passes: Option<String>,
+    dump_optimizer_passes: Option<String>,
+    max_constraint_degree: Option<usize>,
+) -> Result<(), Vec<String>> {
+    let pass_manager = pass_manager(
+        optimizer_passes,
+        dump_optimizer_passes,
+        max_constraint_degree,
+    )?;
+    let analyzed = Pipeline::<T>::default()
+        .from_file(PathBuf::from(file))
+        .compute_analyzed_pil()?
+        .clone();
+    let OptimizerOutput {
+        pil_file,
+        statistics,
+        ..
+    } = optimize_with(analyzed, &pass_manager).map_err(|e| vec![e])?;
+    log::info!("Optimizer statistics:\n{statistics}");
+    println!("{pil_file}");
+    Ok(())
 }
 
 #[cfg(test)]
@@ -830,6 +1125,14 @@ mod test {
             export_witness_csv: false,
             export_all_columns_csv: true,
             csv_mode: CsvRenderModeCLI::Hex,
+            witness_export: Default::default(),
+            record_queries: None,
+            replay: None,
+            optimizer_passes: None,
+            dump_optimizer_passes: None,
+            max_constraint_degree: None,
+            check_optimizer: false,
+            witgen_solver_fallback: false,
         };
         run_command(pil_command);
 
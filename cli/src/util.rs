@@ -18,6 +18,7 @@ macro_rules! call_with_field {
             FieldArgument::M31 => $function::<Mersenne31Field>($($args),*),
             FieldArgument::Gl => $function::<GoldilocksField>($($args),*),
             FieldArgument::Bn254 => $function::<Bn254Field>($($args),*),
+            FieldArgument::Bls12_381 => $function::<Bls12_381Field>($($args),*),
         }
     };
 }
@@ -11,6 +11,8 @@ use powdr_ast::analyzed::{
 use powdr_number::FieldElement;
 use powdr_number::LargeInt;
 
+use crate::FixedColumnValues;
+
 /// Accessor for terminal symbols.
 pub trait TerminalAccess<T> {
     fn get(&self, _poly_ref: &AlgebraicReference) -> T {
@@ -26,7 +28,10 @@ pub trait TerminalAccess<T> {
 
 /// A simple container for trace values.
 pub struct OwnedTerminalValues<F> {
+    /// The values of the witness columns.
     pub trace: BTreeMap<PolyID, Vec<F>>,
+    /// The values of the fixed columns, which are not materialized if stored compactly.
+    pub fixed: BTreeMap<PolyID, FixedColumnValues<F>>,
     pub public_values: BTreeMap<String, F>,
     pub challenge_values: BTreeMap<u64, F>,
 }
@@ -37,28 +42,33 @@ pub struct RowValues<'a, F> {
     row: usize,
 }
 
-impl<F: std::fmt::Debug> OwnedTerminalValues<F> {
+impl<F: PartialEq + Copy + std::fmt::Debug> OwnedTerminalValues<F> {
     pub fn new(
         pil: &Analyzed<F>,
         witness_columns: Vec<(String, Vec<F>)>,
-        fixed_columns: Vec<(String, Vec<F>)>,
+        fixed_columns: Vec<(String, FixedColumnValues<F>)>,
     ) -> Self {
-        let mut columns_by_name = witness_columns
-            .into_iter()
-            .chain(fixed_columns)
-            .collect::<BTreeMap<_, _>>();
+        let mut witness_by_name = witness_columns.into_iter().collect::<BTreeMap<_, _>>();
         let trace = pil
             .committed_polys_in_source_order()
-            .chain(pil.constant_polys_in_source_order())
             .flat_map(|(symbol, _)| symbol.array_elements())
             .filter_map(|(name, poly_id)| {
-                columns_by_name
+                witness_by_name
                     .remove(&name)
                     .map(|column| (poly_id, column))
             })
             .collect();
+        let mut fixed_by_name = fixed_columns.into_iter().collect::<BTreeMap<_, _>>();
+        let fixed = pil
+            .constant_polys_in_source_order()
+            .flat_map(|(symbol, _)| symbol.array_elements())
+            .filter_map(|(name, poly_id)| {
+                fixed_by_name.remove(&name).map(|column| (poly_id, column))
+            })
+            .collect();
         Self {
             trace,
+            fixed,
             public_values: Default::default(),
             challenge_values: Default::default(),
         }
@@ -79,20 +89,25 @@ impl<F: std::fmt::Debug> OwnedTerminalValues<F> {
         self.trace
             .values()
             .map(|v| v.len())
+            .chain(self.fixed.values().map(|v| v.len()))
             .unique()
             .exactly_one()
             .unwrap()
     }
 
-    /// The length of a given column.
+    /// The length of a given witness or fixed column.
     pub fn column_length(&self, poly_id: &PolyID) -> usize {
-        self.trace.get(poly_id).unwrap().len()
+        match self.trace.get(poly_id) {
+            Some(column) => column.len(),
+            None => self.fixed[poly_id].len(),
+        }
     }
 
     pub fn row(&self, row: usize) -> RowValues<F> {
         RowValues { values: self, row }
     }
 
+    /// Returns the values of the witness columns.
     pub fn into_trace(self) -> BTreeMap<PolyID, Vec<F>> {
         self.trace
     }
@@ -101,11 +116,16 @@ impl<F: std::fmt::Debug> OwnedTerminalValues<F> {
 impl<F: FieldElement, T: From<F>> TerminalAccess<T> for RowValues<'_, F> {
     fn get(&self, column: &AlgebraicReference) -> T {
         match column.poly_id.ptype {
-            PolynomialType::Committed | PolynomialType::Constant => {
+            PolynomialType::Committed => {
                 let column_values = self.values.trace.get(&column.poly_id).unwrap();
                 let row = (self.row + column.next as usize) % column_values.len();
                 column_values[row].into()
             }
+            PolynomialType::Constant => {
+                let column_values = self.values.fixed.get(&column.poly_id).unwrap();
+                let row = (self.row + column.next as usize) % column_values.len();
+                column_values.get(row).into()
+            }
             PolynomialType::Intermediate => unreachable!(
                 "Intermediate polynomials should have been handled by ExpressionEvaluator"
             ),
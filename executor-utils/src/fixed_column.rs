@@ -0,0 +1,267 @@
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+/// Columns are only stored as periodic if their period is at most this long.
+const MAX_PERIOD: usize = 1 << 12;
+/// The search for a period gives up after comparing this many times the number of rows.
+const MAX_PERIOD_SEARCH_PASSES: usize = 4;
+/// Columns are only stored as sparse if at most this fraction of their rows are exceptions.
+const MAX_EXCEPTIONS_RATIO: usize = 4;
+
+/// The values of a fixed column of a single size. Columns such as `is_first`, periodic
+/// selectors or ROM columns padded with a constant value are stored compactly, so that they
+/// do not have to be materialized unless a dense representation is needed.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FixedColumnValues<F> {
+    /// The same value in all rows.
+    Constant { value: F, len: usize },
+    /// `values` repeated until the column has `len` rows.
+    Periodic { values: Vec<F>, len: usize },
+    /// `default` in all rows apart from the `exceptions`, which are sorted by row.
+    Sparse {
+        default: F,
+        exceptions: Vec<(usize, F)>,
+        len: usize,
+    },
+    /// The values of all rows.
+    Dense(Vec<F>),
+}
+
+impl<F: PartialEq + Copy> FixedColumnValues<F> {
+    /// Chooses the most compact representation for the given values.
+    pub fn compact(values: Vec<F>) -> Self {
+        let len = values.len();
+        let Some(first) = values.first().copied() else {
+            return FixedColumnValues::Dense(values);
+        };
+        if values.iter().all(|v| *v == first) {
+            return FixedColumnValues::Constant { value: first, len };
+        }
+        if let Some((default, exceptions)) = exceptions(&values) {
+            return FixedColumnValues::Sparse {
+                default,
+                exceptions,
+                len,
+            };
+        }
+        if let Some(period) = smallest_period(&values) {
+            return FixedColumnValues::Periodic {
+                values: values[..period].to_vec(),
+                len,
+            };
+        }
+        FixedColumnValues::Dense(values)
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            FixedColumnValues::Constant { len, .. }
+            | FixedColumnValues::Periodic { len, .. }
+            | FixedColumnValues::Sparse { len, .. } => *len,
+            FixedColumnValues::Dense(values) => values.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the value in the given row. Panics if the row is out of bounds.
+    pub fn get(&self, row: usize) -> F {
+        assert!(row < self.len(), "Row {row} out of bounds.");
+        match self {
+            FixedColumnValues::Constant { value, .. } => *value,
+            FixedColumnValues::Periodic { values, .. } => values[row % values.len()],
+            FixedColumnValues::Sparse {
+                default,
+                exceptions,
+                ..
+            } => exceptions
+                .binary_search_by_key(&row, |(row, _)| *row)
+                .map_or(*default, |i| exceptions[i].1),
+            FixedColumnValues::Dense(values) => values[row],
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = F> + '_ {
+        (0..self.len()).map(|row| self.get(row))
+    }
+
+    /// Returns the values that occur in the column, possibly with duplicates, without
+    /// iterating over all rows.
+    pub fn occurring_values(&self) -> Box<dyn Iterator<Item = &F> + '_> {
+        match self {
+            FixedColumnValues::Constant { value, .. } => Box::new(std::iter::once(value)),
+            FixedColumnValues::Periodic { values, .. } | FixedColumnValues::Dense(values) => {
+                Box::new(values.iter())
+            }
+            FixedColumnValues::Sparse {
+                default,
+                exceptions,
+                len,
+            } => Box::new(
+                // The default value does not occur if all rows are exceptions.
+                (exceptions.len() < *len)
+                    .then_some(default)
+                    .into_iter()
+                    .chain(exceptions.iter().map(|(_, value)| value)),
+            ),
+        }
+    }
+
+    /// Materializes the values of all rows.
+    pub fn to_vec(&self) -> Vec<F> {
+        match self {
+            FixedColumnValues::Dense(values) => values.clone(),
+            _ => self.iter().collect(),
+        }
+    }
+
+    /// If all rows except the first and the last have the same value, returns it.
+    pub fn constant_inner_value(&self) -> Option<F> {
+        match self {
+            FixedColumnValues::Constant { value, .. } => Some(*value),
+            FixedColumnValues::Dense(values) => values[1..values.len() - 1]
+                .iter()
+                .all_equal_value()
+                .ok()
+                .cloned(),
+            _ => (1..self.len() - 1)
+                .map(|row| self.get(row))
+                .all_equal_value()
+                .ok(),
+        }
+    }
+
+    /// The name of the representation, for logging.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            FixedColumnValues::Constant { .. } => "constant",
+            FixedColumnValues::Periodic { .. } => "periodic",
+            FixedColumnValues::Sparse { .. } => "sparse",
+            FixedColumnValues::Dense(_) => "dense",
+        }
+    }
+}
+
+impl<F: PartialEq + Copy> From<Vec<F>> for FixedColumnValues<F> {
+    fn from(values: Vec<F>) -> Self {
+        Self::compact(values)
+    }
+}
+
+/// Returns the smallest period of the values, if it is small and repeats at least twice.
+/// Only periods that continue with the first value are checked, and the search gives up
+/// once the candidates that turned out to be wrong took too many comparisons, so that
+/// columns that only differ near their end do not cost `MAX_PERIOD` passes.
+fn smallest_period<F: PartialEq>(values: &[F]) -> Option<usize> {
+    let mut budget = MAX_PERIOD_SEARCH_PASSES * values.len();
+    for period in 2..=MAX_PERIOD.min(values.len() / 2) {
+        if values[period] != values[0] {
+            continue;
+        }
+        match values[period..]
+            .iter()
+            .zip(values)
+            .position(|(a, b)| a != b)
+        {
+            None => return Some(period),
+            Some(compared) => budget = budget.checked_sub(compared + 1)?,
+        }
+    }
+    None
+}
+
+/// Returns a default value and the rows that differ from it, if there are only few of them.
+/// The candidates for the default value are the values in the first and in the last row.
+fn exceptions<F: PartialEq + Copy>(values: &[F]) -> Option<(F, Vec<(usize, F)>)> {
+    let max_exceptions = values.len() / MAX_EXCEPTIONS_RATIO;
+    [*values.last()?, values[0]]
+        .into_iter()
+        .filter_map(|default| {
+            let exceptions = values
+                .iter()
+                .enumerate()
+                .filter(|(_, value)| **value != default)
+                .take(max_exceptions + 1)
+                .map(|(row, value)| (row, *value))
+                .collect::<Vec<_>>();
+            (exceptions.len() <= max_exceptions).then_some((default, exceptions))
+        })
+        .min_by_key(|(_, exceptions)| exceptions.len())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn representations() {
+        let compact = |values: &[u64]| FixedColumnValues::compact(values.to_vec());
+
+        assert_eq!(
+            compact(&[7; 8]),
+            FixedColumnValues::Constant { value: 7, len: 8 }
+        );
+        assert_eq!(
+            compact(&[0, 1, 2, 0, 1, 2, 0, 1]),
+            FixedColumnValues::Periodic {
+                values: vec![0, 1, 2],
+                len: 8
+            }
+        );
+        assert_eq!(
+            compact(&[1, 0, 0, 0, 0, 0, 0, 0]),
+            FixedColumnValues::Sparse {
+                default: 0,
+                exceptions: vec![(0, 1)],
+                len: 8
+            }
+        );
+        assert_eq!(
+            compact(&[3, 1, 4, 1, 5, 9, 2, 6]),
+            FixedColumnValues::Dense(vec![3, 1, 4, 1, 5, 9, 2, 6])
+        );
+    }
+
+    #[test]
+    fn large_is_last() {
+        let len = 1 << 20;
+        let is_last = (0..len)
+            .map(|i| u64::from(i == len - 1))
+            .collect::<Vec<_>>();
+        assert_eq!(smallest_period(&is_last), None);
+        assert_eq!(
+            FixedColumnValues::compact(is_last),
+            FixedColumnValues::Sparse {
+                default: 0,
+                exceptions: vec![(len - 1, 1)],
+                len
+            }
+        );
+    }
+
+    #[test]
+    fn access() {
+        for values in [
+            vec![7; 8],
+            vec![0, 1, 2, 0, 1, 2, 0, 1],
+            vec![5, 6, 0, 0, 0, 0, 0, 0],
+            vec![3, 1, 4, 1, 5, 9, 2, 6],
+        ] {
+            let column = FixedColumnValues::compact(values.clone());
+            assert_eq!(column.len(), values.len());
+            assert_eq!(column.to_vec(), values);
+            assert_eq!(
+                column.constant_inner_value(),
+                values[1..7].iter().all_equal_value().ok().cloned()
+            );
+            assert!(values
+                .iter()
+                .all(|value| column.occurring_values().any(|v| v == value)));
+            assert!(column
+                .occurring_values()
+                .all(|value| values.contains(value)));
+        }
+    }
+}
@@ -3,11 +3,14 @@ use powdr_ast::analyzed::Analyzed;
 use serde::{Deserialize, Serialize};
 use std::collections::{BTreeMap, BTreeSet};
 
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
 
 use powdr_number::{DegreeType, FieldElement};
 
 pub mod expression_evaluator;
+mod fixed_column;
+
+pub use fixed_column::FixedColumnValues;
 
 /// A callback that computes an updated witness, given:
 /// - The PIL for the current machine.
@@ -41,7 +44,11 @@ impl<T: FieldElement> WitgenCallback<T> {
 
 #[derive(Serialize, Deserialize)]
 pub struct VariablySizedColumn<F> {
-    column_by_size: BTreeMap<DegreeType, Vec<F>>,
+    column_by_size: BTreeMap<DegreeType, FixedColumnValues<F>>,
+    /// The materialized values of the sizes that are not stored densely, only computed
+    /// on demand by [VariablySizedColumn::get_by_size].
+    #[serde(skip)]
+    dense_by_size: OnceLock<BTreeMap<DegreeType, OnceLock<Vec<F>>>>,
     /// If this is Some(x), then all sizes of this column have this value
     /// in all rows except the first and the last.
     constant_inner_value: Option<F>,
@@ -51,24 +58,24 @@ pub struct VariablySizedColumn<F> {
 pub struct HasMultipleSizesError;
 
 impl<F> VariablySizedColumn<F> {
-    /// Create a view where each column has a single size. Fails if any column has multiple sizes.
-    pub fn get_uniquely_sized(&self) -> Result<&Vec<F>, HasMultipleSizesError> {
-        if self.column_by_size.len() != 1 {
-            return Err(HasMultipleSizesError);
-        }
-        Ok(self.column_by_size.values().next().unwrap())
-    }
-
     /// Returns the set of available sizes.
     pub fn available_sizes(&self) -> BTreeSet<DegreeType> {
         self.column_by_size.keys().cloned().collect()
     }
 
-    /// Clones and returns the column with the given size.
-    pub fn get_by_size(&self, size: DegreeType) -> Option<&[F]> {
+    /// Returns the (possibly compact) values of the column with the given size.
+    pub fn get_values_by_size(&self, size: DegreeType) -> Option<&FixedColumnValues<F>> {
+        self.column_by_size.get(&size)
+    }
+
+    /// Returns the (possibly compact) values of the column, if it has a single size.
+    pub fn get_values_uniquely_sized(
+        &self,
+    ) -> Result<&FixedColumnValues<F>, HasMultipleSizesError> {
         self.column_by_size
-            .get(&size)
-            .map(|column| column.as_slice())
+            .values()
+            .exactly_one()
+            .map_err(|_| HasMultipleSizesError)
     }
 
     /// If this returns Some(x), then all sizes of this column have this value
@@ -78,38 +85,68 @@ impl<F> VariablySizedColumn<F> {
     }
 }
 
+impl<F: PartialEq + Copy> VariablySizedColumn<F> {
+    /// Create a view where each column has a single size. Fails if any column has multiple sizes.
+    /// Materializes the column if it is not stored densely.
+    pub fn get_uniquely_sized(&self) -> Result<&Vec<F>, HasMultipleSizesError> {
+        let size = self
+            .column_by_size
+            .keys()
+            .exactly_one()
+            .map_err(|_| HasMultipleSizesError)?;
+        Ok(self.dense(*size).unwrap())
+    }
+
+    /// Returns the column with the given size, materializing it if it is not stored densely.
+    /// The materialized values are kept until the column is dropped.
+    pub fn get_by_size(&self, size: DegreeType) -> Option<&[F]> {
+        self.dense(size).map(|column| column.as_slice())
+    }
+
+    fn dense(&self, size: DegreeType) -> Option<&Vec<F>> {
+        Some(match self.column_by_size.get(&size)? {
+            FixedColumnValues::Dense(values) => values,
+            values => self.dense_by_size.get_or_init(|| {
+                self.column_by_size
+                    .keys()
+                    .map(|size| (*size, OnceLock::new()))
+                    .collect()
+            })[&size]
+                .get_or_init(|| values.to_vec()),
+        })
+    }
+}
+
 impl<F: PartialEq + Copy> From<Vec<F>> for VariablySizedColumn<F> {
     fn from(column: Vec<F>) -> Self {
-        let constant_inner_value = constant_inner_value(&column);
-        VariablySizedColumn {
-            column_by_size: [(column.len() as DegreeType, column)].into_iter().collect(),
-            constant_inner_value,
-        }
+        vec![column].into()
     }
 }
 
 impl<F: PartialEq + Copy> From<Vec<Vec<F>>> for VariablySizedColumn<F> {
     fn from(columns: Vec<Vec<F>>) -> Self {
-        let constant_inner_values = columns
+        columns
+            .into_iter()
+            .map(FixedColumnValues::compact)
+            .collect::<Vec<_>>()
+            .into()
+    }
+}
+
+impl<F: PartialEq + Copy> From<Vec<FixedColumnValues<F>>> for VariablySizedColumn<F> {
+    fn from(columns: Vec<FixedColumnValues<F>>) -> Self {
+        let constant_inner_value = columns
             .iter()
-            .map(|v| constant_inner_value(v))
-            .collect::<Option<Vec<_>>>();
-        let constant_inner_value =
-            constant_inner_values.and_then(|v| v.iter().all_equal_value().ok().cloned());
+            .map(|column| column.constant_inner_value())
+            .collect::<Option<Vec<_>>>()
+            .and_then(|v| v.iter().all_equal_value().ok().cloned());
         VariablySizedColumn {
             column_by_size: columns
                 .into_iter()
                 .map(|column| (column.len() as DegreeType, column))
                 .collect(),
+            dense_by_size: OnceLock::new(),
             constant_inner_value,
         }
     }
 }
-
-fn constant_inner_value<F: PartialEq + Copy>(column: &[F]) -> Option<F> {
-    column[1..column.len() - 1]
-        .iter()
-        .all_equal_value()
-        .ok()
-        .cloned()
-}
@@ -1,7 +1,7 @@
-pub use powdr_executor_utils::{HasMultipleSizesError, VariablySizedColumn};
+pub use powdr_executor_utils::{FixedColumnValues, HasMultipleSizesError, VariablySizedColumn};
 
 /// Returns all columns with their unique sizes. Fails if any column has multiple sizes.
-pub fn get_uniquely_sized<F>(
+pub fn get_uniquely_sized<F: PartialEq + Copy>(
     column: &[(String, VariablySizedColumn<F>)],
 ) -> Result<Vec<(String, &Vec<F>)>, HasMultipleSizesError> {
     column
@@ -10,7 +10,7 @@ pub fn get_uniquely_sized<F>(
         .collect()
 }
 
-pub fn get_uniquely_sized_cloned<F: Clone>(
+pub fn get_uniquely_sized_cloned<F: PartialEq + Copy>(
     column: &[(String, VariablySizedColumn<F>)],
 ) -> Result<Vec<(String, Vec<F>)>, HasMultipleSizesError> {
     get_uniquely_sized(column).map(|column| {
@@ -11,7 +11,7 @@ use powdr_ast::{
     },
 };
 use powdr_number::{BigInt, BigUint, DegreeType, FieldElement};
-use powdr_pil_analyzer::evaluator::{self, Definitions, SymbolLookup, Value};
+use powdr_pil_analyzer::evaluator::{self, CodeCache, Definitions, SymbolLookup, Value};
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
 
 /// Evaluates the fixed polynomial `name` on all values from 0 to `degree - 1`
@@ -28,6 +28,7 @@ pub fn generate_values<T: FieldElement>(
         symbols: &analyzed.definitions,
         solved_impls: &analyzed.solved_impls,
         cache: Arc::new(RwLock::new(Default::default())),
+        code_cache: Default::default(),
         degree,
     };
     let result = match body {
@@ -118,6 +119,7 @@ pub struct CachedSymbols<'a, T> {
     symbols: &'a HashMap<String, (Symbol, Option<FunctionValueDefinition>)>,
     solved_impls: &'a SolvedTraitImpls,
     cache: Arc<RwLock<SymbolCache<'a, T>>>,
+    code_cache: Arc<CodeCache<'a, T>>,
     degree: DegreeType,
 }
 
@@ -156,4 +158,8 @@ impl<'a, T: FieldElement> SymbolLookup<'a, T> for CachedSymbols<'a, T> {
     fn degree(&self) -> Result<Arc<Value<'a, T>>, evaluator::EvalError> {
         Ok(Value::Integer(self.degree.into()).into())
     }
+
+    fn code_cache(&self) -> Option<&CodeCache<'a, T>> {
+        Some(&self.code_cache)
+    }
 }
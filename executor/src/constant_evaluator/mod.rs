@@ -1,4 +1,6 @@
-pub use data_structures::{get_uniquely_sized, get_uniquely_sized_cloned, VariablySizedColumn};
+pub use data_structures::{
+    get_uniquely_sized, get_uniquely_sized_cloned, FixedColumnValues, VariablySizedColumn,
+};
 use itertools::Itertools;
 use powdr_ast::analyzed::Analyzed;
 use powdr_number::FieldElement;
@@ -62,6 +64,7 @@ pub fn generate<T: FieldElement>(analyzed: &Analyzed<T>) -> Vec<(String, Variabl
     if !used_interpreter && !fixed_cols.is_empty() {
         log::info!("All columns were generated using JIT-code.");
     }
+    log_representations(fixed_cols.values());
 
     fixed_cols
         .into_iter()
@@ -70,6 +73,30 @@ pub fn generate<T: FieldElement>(analyzed: &Analyzed<T>) -> Vec<(String, Variabl
         .collect()
 }
 
+/// Logs how many of the generated columns are stored in which representation.
+fn log_representations<'a, T: FieldElement>(
+    columns: impl IntoIterator<Item = &'a VariablySizedColumn<T>>,
+) {
+    let counts = columns
+        .into_iter()
+        .flat_map(|column| {
+            column
+                .available_sizes()
+                .into_iter()
+                .map(|size| column.get_values_by_size(size).unwrap().kind())
+                .collect::<Vec<_>>()
+        })
+        .counts();
+    log::debug!(
+        "Fixed column representations: {}",
+        counts
+            .iter()
+            .sorted()
+            .map(|(kind, count)| format!("{count} {kind}"))
+            .join(", ")
+    );
+}
+
 /// Generates the fixed column values only using JIT-compiled code.
 /// Might not return all fixed columns.
 pub fn generate_only_via_jit<T: FieldElement>(
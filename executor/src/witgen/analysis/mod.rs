@@ -93,7 +93,7 @@ fn try_to_period<T: FieldElement>(
 
     let degree = fixed_data.common_degree_range(once(&poly.poly_id)).max;
 
-    let values = fixed_data.fixed_cols[&poly.poly_id].values(degree);
+    let values = fixed_data.fixed_cols[&poly.poly_id].compact_values(degree);
 
     let offset = values.iter().position(|v| v.is_one())?;
     let period = 1 + values.iter().skip(offset + 1).position(|v| v.is_one())?;
@@ -111,7 +111,7 @@ fn try_to_period<T: FieldElement>(
             } else {
                 0.into()
             };
-            *v == expected
+            v == expected
         })
         .then_some((offset, period))
 }
@@ -3,11 +3,15 @@ use powdr_number::{DegreeType, FieldElement};
 use super::{
     affine_expression::AlgebraicVariable,
     data_structures::{identity::Identity, mutable_state::MutableState},
+    diagnostics::FailureKind,
+    fallback_solver::FallbackSolver,
     machines::MachineParts,
     processor::{OuterQuery, Processor, SolverState},
     rows::{RowIndex, UnknownStrategy},
-    sequence_iterator::{Action, ProcessingSequenceIterator, SequenceStep},
-    EvalError, EvalValue, FixedData, IncompleteCause, QueryCallback,
+    sequence_iterator::{
+        Action, DefaultSequenceIterator, ProcessingSequenceIterator, SequenceStep,
+    },
+    Constraints, EvalError, EvalValue, FixedData, IncompleteCause, QueryCallback,
 };
 
 /// A basic processor that knows how to determine a unique satisfying witness
@@ -19,6 +23,8 @@ pub struct BlockProcessor<'a, 'c, T: FieldElement, Q: QueryCallback<T>> {
     processor: Processor<'a, 'c, T, Q>,
     /// The list of identities
     identities: &'c [&'a Identity<T>],
+    /// The solver for rows on which no more progress is made, if enabled.
+    fallback_solver: Option<&'c mut FallbackSolver<'a, T>>,
 }
 
 impl<'a, 'c, T: FieldElement, Q: QueryCallback<T>> BlockProcessor<'a, 'c, T, Q> {
@@ -41,6 +47,7 @@ impl<'a, 'c, T: FieldElement, Q: QueryCallback<T>> BlockProcessor<'a, 'c, T, Q>
         Self {
             processor,
             identities: &parts.identities,
+            fallback_solver: None,
         }
     }
 
@@ -51,6 +58,7 @@ impl<'a, 'c, T: FieldElement, Q: QueryCallback<T>> BlockProcessor<'a, 'c, T, Q>
         Self {
             processor,
             identities,
+            fallback_solver: None,
         }
     }
 
@@ -62,6 +70,18 @@ impl<'a, 'c, T: FieldElement, Q: QueryCallback<T>> BlockProcessor<'a, 'c, T, Q>
         Self { processor, ..self }
     }
 
+    /// Hands the incomplete identities of rows on which no more progress is made to the
+    /// fallback solver, see [FallbackSolver::solve].
+    pub fn with_fallback_solver(
+        self,
+        fallback_solver: Option<&'c mut FallbackSolver<'a, T>>,
+    ) -> BlockProcessor<'a, 'c, T, Q> {
+        Self {
+            fallback_solver,
+            ..self
+        }
+    }
+
     /// Figures out unknown values.
     /// Returns the assignments to outer query columns.
     pub fn solve(
@@ -70,27 +90,80 @@ impl<'a, 'c, T: FieldElement, Q: QueryCallback<T>> BlockProcessor<'a, 'c, T, Q>
     ) -> Result<EvalValue<AlgebraicVariable<'a>, T>, EvalError<T>> {
         let mut outer_assignments = vec![];
 
+        // Whether an identity is complete on a row, None if it was not processed there.
         let mut is_identity_complete =
-            vec![vec![false; self.identities.len()]; self.processor.len()];
+            vec![vec![None; self.identities.len()]; self.processor.len()];
+        let mut outer_query_row = None;
 
+        self.run_sequence(
+            sequence_iterator,
+            &mut is_identity_complete,
+            &mut outer_query_row,
+            &mut outer_assignments,
+        )?;
+        while self.try_fallback_solver(&is_identity_complete) {
+            // Propagate the values determined by the fallback solver.
+            let mut sequence_iterator =
+                ProcessingSequenceIterator::Default(DefaultSequenceIterator::new(
+                    self.processor.len() - 2,
+                    self.identities.len(),
+                    outer_query_row,
+                ));
+            self.run_sequence(
+                &mut sequence_iterator,
+                &mut is_identity_complete,
+                &mut outer_query_row,
+                &mut outer_assignments,
+            )?;
+        }
+
+        match self.processor.finished_outer_query() {
+            true => Ok(EvalValue::complete(outer_assignments)),
+            false => Ok(EvalValue::incomplete_with_constraints(
+                outer_assignments,
+                IncompleteCause::BlockMachineLookupIncomplete,
+            )),
+        }
+    }
+
+    /// Runs the steps of the sequence iterator and records the completeness of the identities
+    /// and the row of the outer query.
+    fn run_sequence(
+        &mut self,
+        sequence_iterator: &mut ProcessingSequenceIterator,
+        is_identity_complete: &mut [Vec<Option<bool>>],
+        outer_query_row: &mut Option<i64>,
+        outer_assignments: &mut Constraints<AlgebraicVariable<'a>, T>,
+    ) -> Result<(), EvalError<T>> {
         while let Some(SequenceStep { row_delta, action }) = sequence_iterator.next() {
             let row_index = (1 + row_delta) as usize;
             let progress = match action {
                 Action::InternalIdentity(identity_index) => {
-                    if is_identity_complete[row_index][identity_index] {
+                    if is_identity_complete[row_index][identity_index] == Some(true) {
                         // The identity has been completed already, there is no point in processing it again.
                         false
                     } else {
-                        let res = self.processor.process_identity(
-                            row_index,
-                            self.identities[identity_index],
-                            UnknownStrategy::Unknown,
-                        )?;
-                        is_identity_complete[row_index][identity_index] = res.is_complete;
+                        let identity = self.identities[identity_index];
+                        let res = self
+                            .processor
+                            .process_identity(row_index, identity, UnknownStrategy::Unknown)
+                            .map_err(|e| -> EvalError<T> {
+                                self.processor
+                                    .diagnose(
+                                        row_index,
+                                        FailureKind::Unsatisfiable,
+                                        self.identities,
+                                        &[(Some(identity), e)],
+                                    )
+                                    .to_string()
+                                    .into()
+                            })?;
+                        is_identity_complete[row_index][identity_index] = Some(res.is_complete);
                         res.progress
                     }
                 }
                 Action::OuterQuery => {
+                    *outer_query_row = Some(row_delta);
                     let (progress, new_outer_assignments) =
                         self.processor.process_outer_query(row_index)?;
                     outer_assignments.extend(new_outer_assignments);
@@ -100,14 +173,41 @@ impl<'a, 'c, T: FieldElement, Q: QueryCallback<T>> BlockProcessor<'a, 'c, T, Q>
             };
             sequence_iterator.report_progress(progress);
         }
+        Ok(())
+    }
 
-        match self.processor.finished_outer_query() {
-            true => Ok(EvalValue::complete(outer_assignments)),
-            false => Ok(EvalValue::incomplete_with_constraints(
-                outer_assignments,
-                IncompleteCause::BlockMachineLookupIncomplete,
-            )),
+    /// If the fallback solver is enabled, tries to determine the unknown cells of the
+    /// identities that are still incomplete on a row with it.
+    /// Returns true if cells were set.
+    fn try_fallback_solver(&mut self, is_identity_complete: &[Vec<Option<bool>>]) -> bool {
+        let Some(fallback_solver) = &mut self.fallback_solver else {
+            return false;
+        };
+        let mut progress = false;
+        // The solver works on row pairs, so the last row has no pair of its own.
+        for (row_index, complete) in is_identity_complete
+            .iter()
+            .enumerate()
+            .take(self.processor.len() - 1)
+        {
+            let incomplete = self
+                .identities
+                .iter()
+                .zip(complete)
+                .filter(|(_, complete)| **complete == Some(false))
+                .map(|(identity, _)| *identity)
+                .collect::<Vec<_>>();
+            if incomplete.is_empty() {
+                continue;
+            }
+            if let Some(cells) =
+                fallback_solver.solve(&self.processor.scratch_rows(row_index), &incomplete)
+            {
+                self.processor.set_cells(row_index, &cells);
+                progress = true;
+            }
         }
+        progress
     }
 
     /// Returns the updated data and values for publics
@@ -128,6 +228,7 @@ mod tests {
         constant_evaluator::generate,
         witgen::{
             data_structures::finalizable_data::FinalizableData,
+            diagnostics::FailureKind,
             machines::MachineParts,
             processor::SolverState,
             rows::{Row, RowIndex},
@@ -257,4 +358,47 @@ mod tests {
 
         solve_and_assert::<GoldilocksField>(src, &[(7, "Fibonacci::y", 34)]);
     }
+
+    #[test]
+    fn unsatisfiable() {
+        let src = r#"
+            namespace main(4);
+                col witness a, b, c;
+                a = 1;
+                c = 5;
+                b = a + 1;
+                b = 3;
+        "#;
+
+        do_with_processor(
+            src,
+            unused_query_callback::<GoldilocksField>(),
+            |mut processor, _, degree, num_identities| {
+                let mut sequence_iterator = ProcessingSequenceIterator::Default(
+                    DefaultSequenceIterator::new(degree as usize - 2, num_identities, None),
+                );
+                let error = processor.solve(&mut sequence_iterator).err().unwrap();
+                assert!(error
+                    .to_string()
+                    .contains("These identities already fail on their own:"));
+
+                let failing = processor.identities[3];
+                let diagnostic = processor.processor.diagnose(
+                    1,
+                    FailureKind::Unsatisfiable,
+                    processor.identities,
+                    &[(Some(failing), error)],
+                );
+                // `c = 5` is not needed to explain the conflict, and neither are the
+                // values of `a` and `b`, which the remaining identities derive.
+                assert_eq!(diagnostic.core.len(), 3);
+                assert!(diagnostic
+                    .core
+                    .iter()
+                    .all(|identity| !identity.identity.contains("main::c")));
+                assert!(diagnostic.assumptions.is_empty());
+                assert!(diagnostic.to_json().contains("\"kind\": \"unsatisfiable\""));
+            },
+        )
+    }
 }
@@ -24,16 +24,17 @@ mod fp4;
 pub type InteractionColumns<T> = (Vec<Vec<T>>, Vec<Vec<T>>, Vec<Vec<T>>);
 
 /// Generates the second-stage columns for the bus accumulator.
+/// Fails if there is no extension field implemented for the field.
 pub fn generate_bus_accumulator_columns<'a, T>(
     pil: &'a Analyzed<T>,
     witness_columns: &'a [(String, Vec<T>)],
     fixed_columns: &'a [(String, VariablySizedColumn<T>)],
     challenges: BTreeMap<u64, T>,
-) -> Vec<(String, Vec<T>)>
+) -> Result<Vec<(String, Vec<T>)>, String>
 where
     T: FieldElement,
 {
-    match T::known_field().unwrap() {
+    Ok(match T::known_field().unwrap() {
         KnownField::GoldilocksField => BusAccumulatorGenerator::<T, Fp2<T>>::new(
             pil,
             witness_columns,
@@ -50,8 +51,12 @@ where
             )
             .generate()
         }
-        KnownField::Bn254Field => unimplemented!(),
-    }
+        field @ (KnownField::Bn254Field | KnownField::Bls12_381Field) => {
+            return Err(format!(
+                "The bus accumulator is not implemented for the {field} field."
+            ));
+        }
+    })
 }
 
 /// Witness generator for the second-stage bus accumulator.
@@ -83,7 +88,7 @@ impl<'a, T: FieldElement, Ext: ExtensionField<T> + Sync> BusAccumulatorGenerator
         let fixed_columns = fixed_columns
             .iter()
             .filter(|(n, _)| fixed_column_names.contains(n))
-            .map(|(n, v)| (n.clone(), v.get_by_size(size).unwrap()));
+            .map(|(n, v)| (n.clone(), v.get_values_by_size(size).unwrap()));
 
         let bus_interactions = pil
             .identities
@@ -109,7 +114,7 @@ impl<'a, T: FieldElement, Ext: ExtensionField<T> + Sync> BusAccumulatorGenerator
             pil,
             witness_columns.to_vec(),
             fixed_columns
-                .map(|(name, values)| (name, values.to_vec()))
+                .map(|(name, values)| (name, values.clone()))
                 .collect(),
         )
         .with_challenges(challenges);
@@ -5,6 +5,7 @@ use std::{
 
 use bit_vec::BitVec;
 use powdr_number::FieldElement;
+use powdr_pil_analyzer::evaluator::CodeCache;
 
 use crate::witgen::{
     global_constraints::RangeConstraintSet,
@@ -13,13 +14,15 @@ use crate::witgen::{
     AffineExpression, AlgebraicVariable, EvalError, EvalResult, QueryCallback,
 };
 
-/// The container and access method for machines and the query callback.
+/// The container and access method for machines, the query callback and the compiled
+/// code of prover functions and queries.
 /// The machines contain the actual data tables.
 /// This struct uses interior mutability for accessing the machines.
 pub struct MutableState<'a, T: FieldElement, Q: QueryCallback<T>> {
     machines: Vec<RefCell<KnownMachine<'a, T>>>,
     bus_to_machine_index: BTreeMap<T, usize>,
     query_callback: &'a Q,
+    code_cache: CodeCache<'a, T>,
 }
 
 impl<'a, T: FieldElement, Q: QueryCallback<T>> MutableState<'a, T, Q> {
@@ -34,6 +37,7 @@ impl<'a, T: FieldElement, Q: QueryCallback<T>> MutableState<'a, T, Q> {
             machines,
             bus_to_machine_index,
             query_callback,
+            code_cache: Default::default(),
         }
     }
 
@@ -120,4 +124,9 @@ impl<'a, T: FieldElement, Q: QueryCallback<T>> MutableState<'a, T, Q> {
     pub fn query_callback(&self) -> &Q {
         self.query_callback
     }
+
+    /// The compiled code of prover functions and queries, shared by all machines and rows.
+    pub fn code_cache(&self) -> &CodeCache<'a, T> {
+        &self.code_cache
+    }
 }
@@ -0,0 +1,351 @@
+//! Structured diagnostics for rows on which witness generation fails.
+
+use std::{
+    collections::BTreeSet,
+    fmt::{self, Display},
+    fs::File,
+    io::BufWriter,
+    path::Path,
+};
+
+use powdr_ast::parsed::SourceReference;
+use powdr_number::{DegreeType, FieldElement};
+use powdr_parser_util::SourceRef;
+use serde::Serialize;
+
+use super::{
+    data_structures::identity::Identity,
+    machines::{machine_extractor::suggest_machine_name, MachineParts},
+    rows::{Cell, ScratchRows},
+    EvalError, FixedData,
+};
+
+/// If set, the diagnostic of a failing witness generation is written as JSON to the
+/// file this environment variable points to.
+pub static WITGEN_DIAGNOSTICS_ENV: &str = "POWDR_WITGEN_DIAGNOSTICS";
+
+/// An error that occurred while processing a row, together with the identity that caused
+/// it, if it was caused by an identity (and not by e.g. a prover query).
+pub type Failure<'a, T> = (Option<&'a Identity<T>>, EvalError<T>);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailureKind {
+    /// The values that were uniquely determined violate some identities.
+    Unsatisfiable,
+    /// Some columns could not be determined, and setting them to zero violates some identities.
+    UnderConstrained,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct DiagnosticIdentity {
+    /// The ID of the identity, None for failures not caused by an identity.
+    pub id: Option<u64>,
+    pub identity: String,
+    /// The location of the identity in the source, as `file:line:column`.
+    pub source: Option<String>,
+    /// The error the identity failed with, if it was one of the failing identities.
+    pub error: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct DiagnosticVariable {
+    /// The column name, with a `'` suffix for cells in the next row.
+    pub name: String,
+    pub row: DegreeType,
+    pub value: Option<String>,
+    pub range_constraint: Option<String>,
+}
+
+/// Describes why witness generation could not make progress on a row.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct WitgenDiagnostic {
+    pub machine: String,
+    pub row: DegreeType,
+    pub kind: FailureKind,
+    /// The failing identities and the errors they failed with.
+    pub failures: Vec<DiagnosticIdentity>,
+    /// The known cells referenced by the involved identities.
+    pub known: Vec<DiagnosticVariable>,
+    /// The unknown cells referenced by the involved identities, with their range constraints.
+    pub unknown: Vec<DiagnosticVariable>,
+    /// A minimal subset of the identities of the machine that already fails on its own,
+    /// starting from the values in [WitgenDiagnostic::assumptions].
+    pub core: Vec<DiagnosticIdentity>,
+    /// The known cells the identities in [WitgenDiagnostic::core] need to fail. All other
+    /// values are derived by the core identities themselves.
+    pub assumptions: Vec<String>,
+}
+
+impl WitgenDiagnostic {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    /// Writes the diagnostic as JSON to the file given by [WITGEN_DIAGNOSTICS_ENV], if set.
+    pub fn write_if_requested(&self) {
+        if let Ok(path) = std::env::var(WITGEN_DIAGNOSTICS_ENV) {
+            match self.write_json(Path::new(&path)) {
+                Ok(()) => log::info!("Wrote witgen diagnostic to {path}"),
+                Err(e) => log::warn!("Could not write witgen diagnostic to {path}: {e}"),
+            }
+        }
+    }
+
+    fn write_json(&self, path: &Path) -> Result<(), String> {
+        let file = BufWriter::new(File::create(path).map_err(|e| e.to_string())?);
+        serde_json::to_writer_pretty(file, self).map_err(|e| e.to_string())
+    }
+}
+
+impl Display for WitgenDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason = match self.kind {
+            FailureKind::Unsatisfiable => "some identities are not satisfiable",
+            FailureKind::UnderConstrained => {
+                "some columns could not be determined, but setting them to zero does not satisfy the identities"
+            }
+        };
+        writeln!(f, "Machine {}, row {}: {reason}.", self.machine, self.row)?;
+        writeln!(f, "Failing identities:")?;
+        for failure in &self.failures {
+            write_identity(f, failure)?;
+        }
+        write_variables(f, "Known values", &self.known)?;
+        write_variables(f, "Unknown values", &self.unknown)?;
+        if !self.core.is_empty() {
+            match self.assumptions.is_empty() {
+                true => writeln!(f, "These identities already fail on their own:")?,
+                false => writeln!(
+                    f,
+                    "These identities already fail given {}:",
+                    self.assumptions.join(", ")
+                )?,
+            }
+            for identity in &self.core {
+                write_identity(f, identity)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn write_identity(f: &mut fmt::Formatter<'_>, identity: &DiagnosticIdentity) -> fmt::Result {
+    write!(f, "    {}", identity.identity)?;
+    if let Some(source) = &identity.source {
+        write!(f, "  (at {source})")?;
+    }
+    writeln!(f)?;
+    if let Some(error) = &identity.error {
+        writeln!(f, "        => {}", error.replace('\n', "\n           "))?;
+    }
+    Ok(())
+}
+
+fn write_variables(
+    f: &mut fmt::Formatter<'_>,
+    title: &str,
+    variables: &[DiagnosticVariable],
+) -> fmt::Result {
+    if variables.is_empty() {
+        return Ok(());
+    }
+    writeln!(f, "{title}:")?;
+    for variable in variables {
+        write!(f, "    {} (row {})", variable.name, variable.row)?;
+        if let Some(value) = &variable.value {
+            write!(f, " = {value}")?;
+        }
+        if let Some(range_constraint) = &variable.range_constraint {
+            write!(f, "  (range constraint: {range_constraint})")?;
+        }
+        writeln!(f)?;
+    }
+    Ok(())
+}
+
+/// The row pair on which witness generation failed, from which a [WitgenDiagnostic] is built.
+pub(crate) struct FailedRow<'b, 'a, T: FieldElement> {
+    pub rows: ScratchRows<'b, 'a, T>,
+    pub fixed_data: &'a FixedData<'a, T>,
+    pub parts: &'b MachineParts<'a, T>,
+}
+
+impl<'a, T: FieldElement> FailedRow<'_, 'a, T> {
+    /// Builds the diagnostic for the given failures. `identities` are the identities of the
+    /// machine that are searched for a minimal failing subset. This is only done for
+    /// unsatisfiable rows, as each identity failing on an under-constrained row already
+    /// fails on its own if the unknown cells are set to zero.
+    pub fn diagnose(
+        &self,
+        kind: FailureKind,
+        identities: &[&'a Identity<T>],
+        failures: &[Failure<'a, T>],
+    ) -> WitgenDiagnostic {
+        let failing_identities = failures
+            .iter()
+            .filter_map(|(identity, _)| *identity)
+            .collect::<Vec<_>>();
+        let (core, assumptions) = match kind {
+            FailureKind::Unsatisfiable => self.minimal_core(identities).unwrap_or_else(|| {
+                (
+                    failing_identities.clone(),
+                    self.known_cells(&failing_identities),
+                )
+            }),
+            FailureKind::UnderConstrained => (vec![], BTreeSet::new()),
+        };
+
+        let (known, unknown): (Vec<_>, Vec<_>) = self
+            .rows
+            .referenced_cells(failing_identities.iter().chain(&core).copied())
+            .into_iter()
+            .map(|cell| self.variable(cell))
+            .partition(|variable| variable.value.is_some());
+
+        WitgenDiagnostic {
+            machine: suggest_machine_name(self.parts),
+            row: self.rows.row_index().into(),
+            kind,
+            failures: failures
+                .iter()
+                .map(|(identity, error)| match identity {
+                    Some(identity) => DiagnosticIdentity {
+                        error: Some(error.to_string()),
+                        ..self.identity(identity)
+                    },
+                    None => DiagnosticIdentity {
+                        id: None,
+                        identity: "(no identity)".to_string(),
+                        source: None,
+                        error: Some(error.to_string()),
+                    },
+                })
+                .collect(),
+            known,
+            unknown,
+            core: core
+                .iter()
+                .map(|identity| self.identity(identity))
+                .collect(),
+            assumptions: assumptions
+                .iter()
+                .map(|cell| self.rows.cell_name(cell))
+                .collect(),
+        }
+    }
+
+    /// Finds a minimal subset of the polynomial identities and of the known cells they
+    /// reference, such that propagating the identities from only these known cells fails.
+    /// First drops all known cells that are not needed (because they can be derived again),
+    /// then all identities that are not needed.
+    /// Returns None if the polynomial identities do not fail at all, e.g. because the
+    /// failure was caused by a machine call.
+    fn minimal_core(
+        &self,
+        identities: &[&'a Identity<T>],
+    ) -> Option<(Vec<&'a Identity<T>>, BTreeSet<Cell>)> {
+        let mut core = identities
+            .iter()
+            .filter(|identity| matches!(identity, Identity::Polynomial(_)))
+            .copied()
+            .collect::<Vec<_>>();
+        let mut assumptions = self.known_cells(&core);
+        if !self.fails(&core, &assumptions) {
+            return None;
+        }
+
+        for cell in assumptions.clone() {
+            assumptions.remove(&cell);
+            if !self.fails(&core, &assumptions) {
+                assumptions.insert(cell);
+            }
+        }
+        let mut i = 0;
+        while i < core.len() {
+            let identity = core.remove(i);
+            if !self.fails(&core, &assumptions) {
+                core.insert(i, identity);
+                i += 1;
+            }
+        }
+        Some((core, assumptions))
+    }
+
+    /// Returns whether propagating the identities fails on a copy of the row pair, in which
+    /// only the `assumptions` of all known cells referenced by the identities are known.
+    fn fails(&self, identities: &[&'a Identity<T>], assumptions: &BTreeSet<Cell>) -> bool {
+        let mut rows = self.rows.clone();
+        for cell in self.known_cells(identities).difference(assumptions) {
+            rows.reset(cell);
+        }
+        rows.propagate(identities).is_err()
+    }
+
+    /// Returns the cells referenced by the identities that are known on the failed row pair.
+    fn known_cells(&self, identities: &[&'a Identity<T>]) -> BTreeSet<Cell> {
+        self.rows
+            .referenced_cells(identities.iter().copied())
+            .into_iter()
+            .filter(|cell| self.rows.value(cell).is_some())
+            .collect()
+    }
+
+    fn variable(&self, cell: Cell) -> DiagnosticVariable {
+        DiagnosticVariable {
+            name: self.rows.cell_name(&cell),
+            row: (self.rows.row_index() + cell.1 as u64).into(),
+            value: self.rows.value(&cell).map(|v| v.to_string()),
+            range_constraint: self.rows.range_constraint(&cell).map(|rc| rc.to_string()),
+        }
+    }
+
+    /// Describes an identity, preferring the identity as written in the source (e.g. a
+    /// lookup instead of the bus send it was converted to).
+    fn identity(&self, identity: &Identity<T>) -> DiagnosticIdentity {
+        let id = identity.id();
+        let analyzed = self
+            .fixed_data
+            .analyzed
+            .identities
+            .iter()
+            .find(|analyzed| analyzed.id() == id);
+        DiagnosticIdentity {
+            id: Some(id),
+            identity: analyzed
+                .map_or_else(|| identity.to_string(), |analyzed| analyzed.to_string()),
+            source: analyzed.and_then(|analyzed| source_location(analyzed.source_reference())),
+            error: None,
+        }
+    }
+}
+
+/// Formats a source reference as `file:line:column`, if it points into a known source.
+fn source_location(source: &SourceRef) -> Option<String> {
+    let contents = source.file_contents.as_deref()?;
+    let prefix = contents.get(..source.start)?;
+    let line = prefix.matches('\n').count() + 1;
+    let column = prefix.len() - prefix.rfind('\n').map_or(0, |i| i + 1) + 1;
+    let file_name = source.file_name.as_deref().unwrap_or("input");
+    Some(format!("{file_name}:{line}:{column}"))
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use super::*;
+
+    #[test]
+    fn source_locations() {
+        let source = |start| SourceRef {
+            file_name: Some(Arc::from("main.pil")),
+            file_contents: Some(Arc::from("let x;\n  x = 1;\n")),
+            start,
+            end: start,
+        };
+        assert_eq!(source_location(&source(0)).unwrap(), "main.pil:1:1");
+        assert_eq!(source_location(&source(9)).unwrap(), "main.pil:2:3");
+        assert_eq!(source_location(&SourceRef::unknown()), None);
+    }
+}
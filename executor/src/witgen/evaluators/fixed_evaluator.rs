@@ -28,14 +28,14 @@ impl<T: FieldElement> SymbolicVariables<T> for FixedEvaluator<'_, T> {
                     poly.is_fixed(),
                     "Can only access fixed columns in the fixed evaluator, got column of type {:?}.", poly.poly_id.ptype
                 );
-                let col_data = self.fixed_data.fixed_cols[&poly.poly_id].values(self.size);
+                let col_data = self.fixed_data.fixed_cols[&poly.poly_id].compact_values(self.size);
                 let degree = col_data.len();
                 let row = if poly.next {
                     (self.row + 1) % degree
                 } else {
                     self.row
                 };
-                Ok(col_data[row].into())
+                Ok(col_data.get(row).into())
             }
             AlgebraicVariable::Public(public_name) => {
                 panic!(
@@ -58,10 +58,11 @@ where
                     PolynomialType::Committed => self.witness_access.value(var),
                     PolynomialType::Constant => {
                         // Constant polynomial (or something else)
-                        let values = self.fixed_data.fixed_cols[&poly.poly_id].values(self.size);
+                        let values =
+                            self.fixed_data.fixed_cols[&poly.poly_id].compact_values(self.size);
                         let row = if poly.next { self.row + 1 } else { self.row }
                             % (values.len() as DegreeType);
-                        Ok(values[row as usize].into())
+                        Ok(values.get(row as usize).into())
                     }
                     PolynomialType::Intermediate => unreachable!(
                         "ExpressionEvaluator should have resolved intermediate polynomials"
@@ -0,0 +1,280 @@
+//! A fallback for rows on which the regular, affine solving cannot make progress.
+
+use std::collections::{BTreeMap, HashSet};
+
+use powdr_ast::analyzed::AlgebraicExpression as Expression;
+use powdr_number::{DegreeType, FieldElement, LargeInt};
+
+use super::{
+    data_structures::identity::{BusSend, Identity},
+    machines::FixedLookup,
+    range_constraints::RangeConstraint,
+    rows::{Cell, ScratchRows},
+    FixedData,
+};
+
+/// Cells are only searched over if their range constraint allows at most this many values.
+const MAX_DOMAIN_SIZE: u64 = 1 << 16;
+/// The maximal number of (partial) assignments that are tried on a single row.
+const MAX_SEARCH_NODES: usize = 1 << 16;
+
+/// Determines the unknown cells of a row pair on which the regular solving got stuck,
+/// e.g. because the identities are non-linear in these cells.
+///
+/// The problem consists of the incomplete identities of the row, which have to be
+/// polynomial identities or lookups into fixed tables. It is solved by a backtracking search
+/// over the values allowed by the range constraints of the unknown cells, propagating the
+/// polynomial identities after each guess. This way, cells without a small range constraint
+/// can still be determined from the guessed ones. Lookups are checked as soon as all their
+/// values are known.
+///
+/// The cells are only set if the problem has a unique solution.
+pub(crate) struct FallbackSolver<'a, T: FieldElement> {
+    fixed_data: &'a FixedData<'a, T>,
+    /// The tables of the fixed lookups, by bus ID, computed on first use.
+    tables: BTreeMap<T, HashSet<Vec<T>>>,
+    /// The rows on which cells were determined by the fallback solver.
+    solved_rows: Vec<DegreeType>,
+}
+
+/// Stops the search, because it exceeded [MAX_SEARCH_NODES] or found an assignment that
+/// leaves some cells undetermined.
+struct GiveUp;
+
+/// The constraints of a single search problem.
+struct Problem<'b, 'a, T: FieldElement> {
+    identities: &'b [&'a Identity<T>],
+    lookups: Vec<&'a BusSend<T>>,
+    /// The unknown cells, which all have to be determined by a solution.
+    unknown: Vec<Cell>,
+    /// The unknown cells with a small range constraint, with the smallest domain first.
+    branching: Vec<Cell>,
+}
+
+impl<'a, T: FieldElement> FallbackSolver<'a, T> {
+    pub fn new(fixed_data: &'a FixedData<'a, T>) -> Self {
+        Self {
+            fixed_data,
+            tables: BTreeMap::new(),
+            solved_rows: vec![],
+        }
+    }
+
+    /// Tries to determine the unknown cells referenced by the (incomplete) identities.
+    /// Returns the values of all these cells if there is a unique solution, and None
+    /// if there is no solution, more than one, some identity is not supported or the
+    /// search is too large.
+    pub fn solve(
+        &mut self,
+        rows: &ScratchRows<'_, 'a, T>,
+        identities: &[&'a Identity<T>],
+    ) -> Option<Vec<(Cell, T)>> {
+        let mut lookups = vec![];
+        for &identity in identities {
+            match identity {
+                Identity::Polynomial(_) => {}
+                Identity::BusSend(send) => lookups.push(self.fixed_lookup(send)?),
+                Identity::Connect(_) => return None,
+            }
+        }
+
+        let unknown = rows
+            .referenced_cells(identities.iter().copied())
+            .into_iter()
+            .filter(|cell| rows.value(cell).is_none())
+            .collect::<Vec<_>>();
+        let mut branching = unknown
+            .iter()
+            .filter_map(|cell| {
+                let domain_size = domain(&rows.range_constraint(cell)?)?.len();
+                Some((domain_size, *cell))
+            })
+            .collect::<Vec<_>>();
+        if branching.is_empty() {
+            return None;
+        }
+        branching.sort();
+        let problem = Problem {
+            identities,
+            lookups,
+            unknown,
+            branching: branching.into_iter().map(|(_, cell)| cell).collect(),
+        };
+
+        let mut nodes = 0;
+        let mut solutions = vec![];
+        if self
+            .search(&problem, rows.clone(), &mut nodes, &mut solutions)
+            .is_err()
+        {
+            log::debug!(
+                "Fallback solver gave up on row {} after {nodes} assignments.",
+                rows.row_index()
+            );
+            return None;
+        }
+        if solutions.len() != 1 {
+            log::debug!(
+                "Fallback solver found {} solutions on row {}.",
+                if solutions.is_empty() {
+                    "no"
+                } else {
+                    "multiple"
+                },
+                rows.row_index()
+            );
+            return None;
+        }
+
+        let solution = solutions.pop().unwrap();
+        log::debug!(
+            "Fallback solver determined {} on row {}.",
+            problem
+                .unknown
+                .iter()
+                .map(|cell| format!(
+                    "{} = {}",
+                    rows.cell_name(cell),
+                    solution.value(cell).unwrap()
+                ))
+                .collect::<Vec<_>>()
+                .join(", "),
+            rows.row_index()
+        );
+        self.solved_rows.push(rows.row_index().into());
+        Some(
+            problem
+                .unknown
+                .iter()
+                .map(|cell| (*cell, solution.value(cell).unwrap()))
+                .collect(),
+        )
+    }
+
+    /// Logs the rows on which the fallback solver determined cells, if any.
+    pub fn report(&self, machine_name: &str) {
+        if self.solved_rows.is_empty() {
+            return;
+        }
+        const MAX_LISTED_ROWS: usize = 10;
+        let rows = self
+            .solved_rows
+            .iter()
+            .take(MAX_LISTED_ROWS)
+            .map(|row| row.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let more = if self.solved_rows.len() > MAX_LISTED_ROWS {
+            ", ..."
+        } else {
+            ""
+        };
+        log::info!(
+            "The fallback solver determined cells of {} rows in machine {machine_name}: {rows}{more}",
+            self.solved_rows.len()
+        );
+    }
+
+    /// Returns the send if it is a lookup into a fixed table, making sure its table is cached.
+    fn fixed_lookup(&mut self, send: &'a BusSend<T>) -> Option<&'a BusSend<T>> {
+        let receive = send.try_match_static(&self.fixed_data.bus_receives)?;
+        if !FixedLookup::is_responsible(receive) {
+            return None;
+        }
+        self.tables
+            .entry(receive.bus_id)
+            .or_insert_with(|| FixedLookup::table(self.fixed_data, receive));
+        Some(send)
+    }
+
+    /// Searches for solutions, stopping as soon as two are found.
+    fn search<'b>(
+        &self,
+        problem: &Problem<'_, 'a, T>,
+        mut rows: ScratchRows<'b, 'a, T>,
+        nodes: &mut usize,
+        solutions: &mut Vec<ScratchRows<'b, 'a, T>>,
+    ) -> Result<(), GiveUp> {
+        *nodes += 1;
+        if *nodes > MAX_SEARCH_NODES {
+            return Err(GiveUp);
+        }
+        if rows.propagate(problem.identities).is_err() || !self.lookups_hold(problem, &rows) {
+            return Ok(());
+        }
+        let Some(cell) = problem
+            .branching
+            .iter()
+            .find(|cell| rows.value(cell).is_none())
+        else {
+            if problem
+                .unknown
+                .iter()
+                .any(|cell| rows.value(cell).is_none())
+            {
+                return Err(GiveUp);
+            }
+            solutions.push(rows);
+            return Ok(());
+        };
+        let Some(values) = rows.range_constraint(cell).as_ref().and_then(domain) else {
+            return Err(GiveUp);
+        };
+        for value in values {
+            let mut guess = rows.clone();
+            guess.set_value(cell, value);
+            self.search(problem, guess, nodes, solutions)?;
+            if solutions.len() > 1 {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that the lookups whose selector and values are known are contained in
+    /// their tables.
+    fn lookups_hold(&self, problem: &Problem<'_, 'a, T>, rows: &ScratchRows<'_, 'a, T>) -> bool {
+        let rows = rows.row_pair();
+        let known = |expression: &'a Expression<T>| {
+            rows.evaluate(expression)
+                .ok()
+                .and_then(|value| value.constant_value())
+        };
+        problem.lookups.iter().all(|send| {
+            let payload = &send.selected_payload;
+            match known(&payload.selector) {
+                Some(selector) if selector.is_zero() => true,
+                Some(_) => match payload
+                    .expressions
+                    .iter()
+                    .map(known)
+                    .collect::<Option<Vec<_>>>()
+                {
+                    Some(tuple) => {
+                        let receive = send
+                            .try_match_static(&self.fixed_data.bus_receives)
+                            .unwrap();
+                        self.tables[&receive.bus_id].contains(&tuple)
+                    }
+                    None => true,
+                },
+                None => true,
+            }
+        })
+    }
+}
+
+/// Returns the values allowed by a range constraint, if there are few enough of them.
+fn domain<T: FieldElement>(range_constraint: &RangeConstraint<T>) -> Option<Vec<T>> {
+    let width = range_constraint
+        .range_width()
+        .try_into_u64()
+        .filter(|width| *width <= MAX_DOMAIN_SIZE)?;
+    let (min, _) = range_constraint.range();
+    Some(
+        (0..width)
+            .map(|i| min + T::from(i))
+            .filter(|value| range_constraint.allows_value(*value))
+            .collect(),
+    )
+}
@@ -11,6 +11,7 @@ use powdr_ast::analyzed::{
 
 use powdr_number::FieldElement;
 
+use crate::constant_evaluator::FixedColumnValues;
 use crate::witgen::data_structures::column_map::{FixedColumnMap, WitnessColumnMap};
 
 use super::affine_expression::AlgebraicVariable;
@@ -123,7 +124,7 @@ pub fn set_global_constraints<T: FieldElement>(fixed_data: FixedData<T>) -> Fixe
     // It allows us to completely remove some lookups.
     let mut full_span = BTreeSet::new();
     for (poly_id, col) in fixed_data.fixed_cols.iter() {
-        if let Some((cons, full)) = process_fixed_column(col.values_max_size()) {
+        if let Some((cons, full)) = process_fixed_column(col.compact_values_max_size()) {
             assert!(known_constraints.insert(poly_id, cons).is_none());
             if full {
                 full_span.insert(poly_id);
@@ -198,7 +199,9 @@ pub fn set_global_constraints<T: FieldElement>(fixed_data: FixedData<T>) -> Fixe
 /// Analyzes a fixed column and checks if its values correspond exactly
 /// to a certain bit pattern.
 /// TODO do this on the symbolic definition instead of the values.
-fn process_fixed_column<T: FieldElement>(fixed: &[T]) -> Option<(RangeConstraint<T>, bool)> {
+fn process_fixed_column<T: FieldElement>(
+    fixed: &FixedColumnValues<T>,
+) -> Option<(RangeConstraint<T>, bool)> {
     if let Some(bit) = smallest_period_candidate(fixed) {
         let mask = T::Integer::from((1u64 << bit) - 1);
         if fixed
@@ -210,7 +213,7 @@ fn process_fixed_column<T: FieldElement>(fixed: &[T]) -> Option<(RangeConstraint
         }
     }
     let mut mask = T::Integer::zero();
-    for v in fixed.iter() {
+    for v in fixed.occurring_values() {
         mask |= v.to_integer();
     }
 
@@ -399,12 +402,13 @@ fn try_transfer_constraints<T: FieldElement>(
         .collect()
 }
 
-fn smallest_period_candidate<T: FieldElement>(fixed: &[T]) -> Option<u64> {
-    if fixed.first() != Some(&0.into()) {
+fn smallest_period_candidate<T: FieldElement>(fixed: &FixedColumnValues<T>) -> Option<u64> {
+    if fixed.is_empty() || fixed.get(0) != 0.into() {
         return None;
     }
+    let last = fixed.get(fixed.len() - 1);
     let max_bits = T::BITS.min(64);
-    (1..max_bits as u64).find(|bit| fixed.last() == Some(&((1u64 << bit) - 1).into()))
+    (1..max_bits as u64).find(|bit| last == ((1u64 << bit) - 1).into())
 }
 
 #[cfg(test)]
@@ -416,10 +420,7 @@ mod test {
     use pretty_assertions::assert_eq;
     use test_log::test;
 
-    use crate::{
-        constant_evaluator::get_uniquely_sized,
-        witgen::data_structures::identity::convert_identities,
-    };
+    use crate::witgen::data_structures::identity::convert_identities;
 
     use super::*;
 
@@ -427,7 +428,7 @@ mod test {
     fn all_zeros() {
         let fixed = [0.into(); 4];
         assert_eq!(
-            process_fixed_column::<GoldilocksField>(&fixed),
+            process_fixed_column::<GoldilocksField>(&fixed.to_vec().into()),
             Some((RangeConstraint::from_value(0.into()), false))
         );
     }
@@ -436,7 +437,7 @@ mod test {
     fn zero_one() {
         let fixed = [0, 1, 0, 1].map(|v| v.into());
         assert_eq!(
-            process_fixed_column::<GoldilocksField>(&fixed),
+            process_fixed_column::<GoldilocksField>(&fixed.to_vec().into()),
             Some((RangeConstraint::from_mask(1_u32), true))
         );
     }
@@ -445,7 +446,7 @@ mod test {
     fn zero_one_two_three() {
         let fixed = [0, 1, 2, 3].map(|v| v.into());
         assert_eq!(
-            process_fixed_column::<GoldilocksField>(&fixed),
+            process_fixed_column::<GoldilocksField>(&fixed.to_vec().into()),
             Some((RangeConstraint::from_mask(3_u32), true))
         );
     }
@@ -454,7 +455,7 @@ mod test {
     fn various_with_bit_mask() {
         let fixed = [0, 6, 0x0100, 0x1100, 2].map(|v| v.into());
         assert_eq!(
-            process_fixed_column::<GoldilocksField>(&fixed),
+            process_fixed_column::<GoldilocksField>(&fixed.to_vec().into()),
             Some((RangeConstraint::from_mask(0x1106_u32), false))
         );
     }
@@ -495,7 +496,6 @@ namespace Global(2**20);
 ";
         let analyzed = powdr_pil_analyzer::analyze_string::<GoldilocksField>(pil_source).unwrap();
         let constants = crate::constant_evaluator::generate(&analyzed);
-        let constants = get_uniquely_sized(&constants).unwrap();
         let fixed_polys = (0..constants.len())
             .map(|i| constant_poly_id(i as u64))
             .collect::<Vec<_>>();
@@ -503,7 +503,8 @@ namespace Global(2**20);
             .iter()
             .zip(&constants)
             .filter_map(|(&poly_id, (_, values))| {
-                process_fixed_column(values).map(|(constraint, full)| (poly_id, (constraint, full)))
+                process_fixed_column(values.get_values_uniquely_sized().unwrap())
+                    .map(|(constraint, full)| (poly_id, (constraint, full)))
             })
             .collect::<BTreeMap<_, _>>();
         let full_span = constraints_and_spans
@@ -587,7 +588,6 @@ namespace Global(2**20);
 ";
         let analyzed = powdr_pil_analyzer::analyze_string::<GoldilocksField>(pil_source).unwrap();
         let constants = crate::constant_evaluator::generate(&analyzed);
-        let constants = get_uniquely_sized(&constants).unwrap();
         let fixed_polys = (0..constants.len())
             .map(|i| constant_poly_id(i as u64))
             .collect::<Vec<_>>();
@@ -595,7 +595,8 @@ namespace Global(2**20);
             .iter()
             .zip(&constants)
             .filter_map(|(&poly_id, (_, values))| {
-                process_fixed_column(values).map(|(constraint, full)| (poly_id, (constraint, full)))
+                process_fixed_column(values.get_values_uniquely_sized().unwrap())
+                    .map(|(constraint, full)| (poly_id, (constraint, full)))
             })
             .collect::<BTreeMap<_, _>>();
         let full_span = constraints_and_spans
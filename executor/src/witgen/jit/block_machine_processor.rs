@@ -360,7 +360,7 @@ impl<T: FieldElement> FixedEvaluator<T> for &BlockMachineProcessor<'_, T> {
             id: fixed_cell.id,
             ptype: PolynomialType::Constant,
         };
-        let values = self.fixed_data.fixed_cols[&poly_id].values_max_size();
+        let values = self.fixed_data.fixed_cols[&poly_id].compact_values_max_size();
 
         // By assumption of the block machine, all fixed columns are cyclic with a period of <block_size>.
         // An exception might be the first and last row.
@@ -376,7 +376,7 @@ impl<T: FieldElement> FixedEvaluator<T> for &BlockMachineProcessor<'_, T> {
         assert!(row > 0);
         assert!(row < values.len() - 1);
 
-        Some(values[row])
+        Some(values.get(row))
     }
 }
 
@@ -76,7 +76,9 @@ extern "C" fn get_fixed_value<T: FieldElement>(
         id: column,
         ptype: PolynomialType::Constant,
     };
-    fixed_data.fixed_cols[&poly_id].values_max_size()[row as usize]
+    fixed_data.fixed_cols[&poly_id]
+        .compact_values_max_size()
+        .get(row as usize)
 }
 
 extern "C" fn call_machine<T: FieldElement, Q: QueryCallback<T>>(
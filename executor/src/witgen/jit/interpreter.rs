@@ -225,8 +225,9 @@ impl<T: FieldElement> EffectsInterpreter<T> {
                         id: c.id,
                         ptype: PolynomialType::Constant,
                     };
-                    vars[*idx] = fixed_data.fixed_cols[&poly_id].values_max_size()
-                        [usize::try_from(row_offset + c.row_offset as i64).unwrap()];
+                    vars[*idx] = fixed_data.fixed_cols[&poly_id]
+                        .compact_values_max_size()
+                        .get(usize::try_from(row_offset + c.row_offset as i64).unwrap());
                 }
                 InterpreterAction::ReadParam(idx, i) => {
                     vars[*idx] = get_param(params, *i);
@@ -12,6 +12,7 @@ use crate::witgen::block_processor::BlockProcessor;
 use crate::witgen::data_structures::caller_data::CallerData;
 use crate::witgen::data_structures::finalizable_data::FinalizableData;
 use crate::witgen::data_structures::mutable_state::MutableState;
+use crate::witgen::fallback_solver::FallbackSolver;
 use crate::witgen::global_constraints::RangeConstraintSet;
 use crate::witgen::jit::function_cache::FunctionCache;
 use crate::witgen::jit::witgen_inference::CanProcessCall;
@@ -82,6 +83,8 @@ pub struct BlockMachine<'a, T: FieldElement> {
     block_count_jit: usize,
     /// Counts the number of blocks created using the runtime solver.
     block_count_runtime: usize,
+    /// The solver for rows on which no more progress is made, if enabled.
+    fallback_solver: Option<FallbackSolver<'a, T>>,
 }
 
 impl<'a, T: FieldElement> BlockMachine<'a, T> {
@@ -151,6 +154,9 @@ impl<'a, T: FieldElement> BlockMachine<'a, T> {
             function_cache,
             block_count_jit: 0,
             block_count_runtime: 0,
+            fallback_solver: fixed_data
+                .solver_fallback
+                .then(|| FallbackSolver::new(fixed_data)),
         })
     }
 
@@ -266,6 +272,9 @@ impl<'a, T: FieldElement> Machine<'a, T> for BlockMachine<'a, T> {
                     .collect();
             }
         } else {
+            if let Some(fallback_solver) = &self.fallback_solver {
+                fallback_solver.report(&self.name);
+            }
             let total_block_count = self.block_count_jit + self.block_count_runtime;
             log::debug!(
                 "{}: {} / {total_block_count} blocks computed via JIT.",
@@ -554,7 +563,7 @@ impl<'a, T: FieldElement> BlockMachine<'a, T> {
     }
 
     fn process<'b, Q: QueryCallback<T>>(
-        &self,
+        &mut self,
         mutable_state: &MutableState<'a, T, Q>,
         sequence_iterator: &mut ProcessingSequenceIterator,
         outer_query: OuterQuery<'a, 'b, T>,
@@ -576,7 +585,8 @@ impl<'a, T: FieldElement> BlockMachine<'a, T> {
             &self.parts,
             self.degree,
         )
-        .with_outer_query(outer_query);
+        .with_outer_query(outer_query)
+        .with_fallback_solver(self.fallback_solver.as_mut());
 
         let outer_assignments = processor.solve(sequence_iterator)?;
         let updated_data = processor.finish();
@@ -7,6 +7,7 @@ use itertools::{Either, Itertools};
 use powdr_ast::analyzed::{AlgebraicExpression, PolyID, PolynomialType};
 use powdr_number::FieldElement;
 
+use crate::constant_evaluator::FixedColumnValues;
 use crate::witgen::affine_expression::{AffineExpression, AlgebraicVariable};
 use crate::witgen::data_structures::caller_data::CallerData;
 use crate::witgen::data_structures::identity::BusReceive;
@@ -70,31 +71,31 @@ impl<T: Clone> TryFrom<&AlgebraicExpression<T>> for FixedColOrConstant<T, PolyID
 }
 
 impl<T: FieldElement> FixedColOrConstant<T, PolyID> {
-    fn into_values<'a>(self, fixed_data: &'a FixedData<T>) -> FixedColOrConstant<T, &'a [T]> {
+    fn into_values<'a>(
+        self,
+        fixed_data: &'a FixedData<T>,
+    ) -> FixedColOrConstant<T, &'a FixedColumnValues<T>> {
         match self {
-            FixedColOrConstant::FixedCol(poly_id) => {
-                FixedColOrConstant::FixedCol(fixed_data.fixed_cols[&poly_id].values_max_size())
-            }
+            FixedColOrConstant::FixedCol(poly_id) => FixedColOrConstant::FixedCol(
+                fixed_data.fixed_cols[&poly_id].compact_values_max_size(),
+            ),
             FixedColOrConstant::Constant(c) => FixedColOrConstant::Constant(c),
         }
     }
 }
 
-impl<T: FieldElement> FixedColOrConstant<T, &[T]> {
+impl<T: FieldElement> FixedColOrConstant<T, &FixedColumnValues<T>> {
     fn degree(&self) -> Option<usize> {
         match self {
             FixedColOrConstant::FixedCol(values) => Some(values.len()),
             FixedColOrConstant::Constant(_) => None,
         }
     }
-}
 
-impl<T: FieldElement> std::ops::Index<usize> for FixedColOrConstant<T, &[T]> {
-    type Output = T;
-    fn index(&self, row: usize) -> &Self::Output {
+    fn get(&self, row: usize) -> T {
         match self {
-            FixedColOrConstant::FixedCol(values) => &values[row],
-            FixedColOrConstant::Constant(c) => c,
+            FixedColOrConstant::FixedCol(values) => values.get(row),
+            FixedColOrConstant::Constant(c) => *c,
         }
     }
 }
@@ -163,12 +164,12 @@ fn create_index<T: FieldElement>(
             |(mut acc, mut set), row| {
                 let input: Vec<_> = input_column_values
                     .iter()
-                    .map(|column| column[row])
+                    .map(|column| column.get(row))
                     .collect();
 
                 let output: Vec<_> = output_column_values
                     .iter()
-                    .map(|column| column[row])
+                    .map(|column| column.get(row))
                     .collect();
 
                 let input_output = (input, output);
@@ -226,6 +227,30 @@ impl<'a, T: FieldElement> FixedLookup<'a, T> {
             && !selected_payload.expressions.is_empty()
     }
 
+    /// Returns all tuples of the lookup table of a receive this machine is responsible for.
+    pub fn table(fixed_data: &FixedData<T>, receive: &BusReceive<T>) -> HashSet<Vec<T>> {
+        assert!(Self::is_responsible(receive));
+        let columns = receive
+            .selected_payload
+            .expressions
+            .iter()
+            .map(|e| {
+                FixedColOrConstant::try_from(e)
+                    .unwrap()
+                    .into_values(fixed_data)
+            })
+            .collect::<Vec<_>>();
+        let degree = columns
+            .iter()
+            .filter_map(|column| column.degree())
+            .unique()
+            .exactly_one()
+            .expect("all columns in a given lookup are expected to have the same degree");
+        (0..degree)
+            .map(|row| columns.iter().map(|column| column.get(row)).collect())
+            .collect()
+    }
+
     pub fn new(
         global_constraints: GlobalConstraints<T>,
         fixed_data: &'a FixedData<'a, T>,
@@ -363,7 +388,7 @@ impl<'a, T: FieldElement> Machine<'a, T> for FixedLookup<'a, T> {
                     .expect("all columns in a given lookup are expected to have the same degree");
 
                 (0..degree)
-                    .map(|row| columns.iter().map(|col| col[row]).collect::<Vec<_>>())
+                    .map(|row| columns.iter().map(|col| col.get(row)).collect::<Vec<_>>())
                     .filter(|values| matches_range_constraint(values, &range_constraints))
                     .map(|values| {
                         values
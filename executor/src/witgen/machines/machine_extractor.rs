@@ -355,7 +355,7 @@ fn log_extracted_machine<T: FieldElement>(name: &str, parts: &MachineParts<'_, T
     }
 }
 
-fn suggest_machine_name<T: FieldElement>(parts: &MachineParts<'_, T>) -> String {
+pub(crate) fn suggest_machine_name<T: FieldElement>(parts: &MachineParts<'_, T>) -> String {
     let first_witness = parts.witnesses.iter().next().unwrap();
     let first_witness_name = parts.column_name(first_witness);
     let namespace = first_witness_name
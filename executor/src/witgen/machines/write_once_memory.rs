@@ -111,7 +111,7 @@ impl<'a, T: FieldElement> WriteOnceMemory<'a, T> {
         for row in 0..degree {
             let key = key_polys
                 .iter()
-                .map(|k| fixed_data.fixed_cols[k].values(degree)[row as usize])
+                .map(|k| fixed_data.fixed_cols[k].value(degree, row as usize))
                 .collect::<Vec<_>>();
             if key_to_index.insert(key, row).is_some() {
                 // Duplicate keys, can't be a write-once memory
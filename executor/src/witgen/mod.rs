@@ -13,10 +13,11 @@ use powdr_ast::analyzed::{
 };
 use powdr_ast::parsed::visitor::{AllChildren, ExpressionVisitable};
 use powdr_ast::parsed::{FunctionKind, LambdaExpression};
-use powdr_number::{DegreeType, FieldElement, KnownField};
+use powdr_executor_utils::expression_evaluator::{ExpressionEvaluator, OwnedTerminalValues};
+use powdr_number::{DegreeType, FieldElement};
 use std::iter::once;
 
-use crate::constant_evaluator::VariablySizedColumn;
+use crate::constant_evaluator::{FixedColumnValues, VariablySizedColumn};
 use crate::witgen::data_structures::mutable_state::MutableState;
 
 use self::data_structures::column_map::{FixedColumnMap, WitnessColumnMap};
@@ -32,8 +33,10 @@ pub(crate) mod analysis;
 mod block_processor;
 mod bus_accumulator;
 mod data_structures;
+mod diagnostics;
 mod eval_result;
 pub mod evaluators;
+mod fallback_solver;
 mod global_constraints;
 mod identity_processor;
 mod jit;
@@ -48,6 +51,9 @@ mod util;
 mod vm_processor;
 
 pub use affine_expression::{AffineExpression, AffineResult, AlgebraicVariable};
+pub use diagnostics::{
+    DiagnosticIdentity, DiagnosticVariable, FailureKind, WitgenDiagnostic, WITGEN_DIAGNOSTICS_ENV,
+};
 pub use evaluators::partial_expression_evaluator::{PartialExpressionEvaluator, SymbolicVariables};
 
 static OUTER_CODE_NAME: &str = "witgen (outer code)";
@@ -95,7 +101,12 @@ impl<T: FieldElement> WitgenCallbackContext<T> {
         self.fixed_col_values
             .iter()
             .filter(|(n, _)| fixed_column_names.contains(n))
-            .map(|(n, v)| (n.clone(), v.get_by_size(size).unwrap().to_vec().into()))
+            .map(|(n, v)| {
+                (
+                    n.clone(),
+                    vec![v.get_values_by_size(size).unwrap().clone()].into(),
+                )
+            })
             .collect()
     }
 
@@ -114,34 +125,30 @@ impl<T: FieldElement> WitgenCallbackContext<T> {
             .iter()
             .any(|identity| matches!(identity, AnalyzedIdentity::PhantomBusInteraction(_)));
 
-        let supports_field = match T::known_field().unwrap() {
-            KnownField::GoldilocksField
-            | KnownField::BabyBearField
-            | KnownField::KoalaBearField
-            | KnownField::Mersenne31Field => true,
-            KnownField::Bn254Field => false,
-        };
-
-        if has_phantom_bus_sends && supports_field {
-            log::debug!("Using hand-written bus witgen.");
-            assert_eq!(stage, 1);
-            let bus_columns = generate_bus_accumulator_columns(
+        if has_phantom_bus_sends {
+            match generate_bus_accumulator_columns(
                 pil,
                 current_witness,
                 &self.fixed_col_values,
-                challenges,
-            );
-
-            current_witness.iter().cloned().chain(bus_columns).collect()
-        } else {
-            log::debug!("Using automatic stage-1 witgen.");
-            let size = current_witness.iter().next().unwrap().1.len() as DegreeType;
-            let fixed_col_values = self.select_fixed_columns(pil, size);
-            WitnessGenerator::new(pil, &fixed_col_values, &*self.query_callback)
-                .with_external_witness_values(current_witness)
-                .with_challenges(stage, challenges)
-                .generate()
+                challenges.clone(),
+            ) {
+                Ok(bus_columns) => {
+                    log::debug!("Using hand-written bus witgen.");
+                    assert_eq!(stage, 1);
+                    return current_witness.iter().cloned().chain(bus_columns).collect();
+                }
+                Err(e) => log::debug!("{e}"),
+            }
         }
+
+        log::debug!("Using automatic stage-1 witgen.");
+        let size = current_witness.iter().next().unwrap().1.len() as DegreeType;
+        let fixed_col_values = self.select_fixed_columns(pil, size);
+        WitnessGenerator::new(pil, &fixed_col_values, &*self.query_callback)
+            .with_external_witness_values(current_witness)
+            .with_challenges(stage, challenges)
+            .generate()
+            .unwrap_or_else(|e| panic!("{e}"))
     }
 }
 
@@ -164,6 +171,7 @@ pub struct WitnessGenerator<'a, 'b, T: FieldElement> {
     external_witness_values: &'b [(String, Vec<T>)],
     stage: u8,
     challenges: BTreeMap<u64, T>,
+    solver_fallback: bool,
 }
 
 impl<'a, 'b, T: FieldElement> WitnessGenerator<'a, 'b, T> {
@@ -179,6 +187,7 @@ impl<'a, 'b, T: FieldElement> WitnessGenerator<'a, 'b, T> {
             external_witness_values: &[],
             stage: 0,
             challenges: BTreeMap::new(),
+            solver_fallback: false,
         }
     }
 
@@ -200,9 +209,21 @@ impl<'a, 'b, T: FieldElement> WitnessGenerator<'a, 'b, T> {
         }
     }
 
+    /// If enabled, rows of VM and block machines on which the regular solving gets stuck are
+    /// handed to a search-based solver, which sets their unknown cells if it finds a unique
+    /// solution.
+    pub fn with_solver_fallback(self, solver_fallback: bool) -> Self {
+        WitnessGenerator {
+            solver_fallback,
+            ..self
+        }
+    }
+
     /// Generates the committed polynomial values
     /// @returns the values (in source order) and the degree of the polynomials.
-    pub fn generate(self) -> Vec<(String, Vec<T>)> {
+    /// Fails if external values provided for an intermediate column do not match its
+    /// definition (see [check_external_intermediate_values]).
+    pub fn generate(self) -> Result<Vec<(String, Vec<T>)>, String> {
         record_start(OUTER_CODE_NAME);
         let fixed = FixedData::new(
             self.analyzed,
@@ -210,7 +231,8 @@ impl<'a, 'b, T: FieldElement> WitnessGenerator<'a, 'b, T> {
             self.external_witness_values,
             self.challenges,
             self.stage,
-        );
+        )
+        .with_solver_fallback(self.solver_fallback);
         let fixed = fixed.filter_identities(|fixed, identity| {
             let references_later_stage_challenge = identity.expr_any(|expr| {
                 if let AlgebraicExpression::Challenge(challenge) = expr {
@@ -279,10 +301,74 @@ impl<'a, 'b, T: FieldElement> WitnessGenerator<'a, 'b, T> {
                 (name, column)
             })
             .collect::<Vec<_>>();
-        witness_cols
+        check_external_intermediate_values(
+            self.analyzed,
+            self.fixed_col_values,
+            self.external_witness_values,
+            &witness_cols,
+        )?;
+        Ok(witness_cols)
     }
 }
 
+/// Checks that the external values provided for intermediate columns (usually witness
+/// columns the optimizer turned into intermediate columns) match their definitions,
+/// evaluated on `witness`. External values for other columns are ignored.
+pub fn check_external_intermediate_values<T: FieldElement>(
+    analyzed: &Analyzed<T>,
+    fixed_col_values: &[(String, VariablySizedColumn<T>)],
+    external_witness_values: &[(String, Vec<T>)],
+    witness: &[(String, Vec<T>)],
+) -> Result<(), String> {
+    let external_witness_values = external_witness_values
+        .iter()
+        .map(|(name, values)| (name.as_str(), values))
+        .collect::<BTreeMap<_, _>>();
+    let intermediate_definitions = analyzed.intermediate_definitions();
+    // The trace values by column size, only built for sizes that are needed.
+    let mut values_by_size = BTreeMap::new();
+    for (symbol, definitions) in analyzed.intermediate_polys_in_source_order() {
+        for ((name, _), definition) in symbol.array_elements().zip_eq(definitions) {
+            let Some(expected) = external_witness_values.get(name.as_str()) else {
+                continue;
+            };
+            log::warn!(
+                "External witness values for {name} are not used, because it is an intermediate column. \
+                Checking them against its definition instead."
+            );
+            let size = expected.len();
+            let values = values_by_size.entry(size).or_insert_with(|| {
+                OwnedTerminalValues::new(
+                    analyzed,
+                    witness
+                        .iter()
+                        .filter(|(_, column)| column.len() == size)
+                        .cloned()
+                        .collect(),
+                    fixed_col_values
+                        .iter()
+                        .filter_map(|(name, column)| {
+                            let column = column.get_values_by_size(size as DegreeType)?;
+                            Some((name.clone(), column.clone()))
+                        })
+                        .collect(),
+                )
+            });
+            for (row, expected) in expected.iter().enumerate() {
+                let value = ExpressionEvaluator::new(values.row(row), &intermediate_definitions)
+                    .evaluate(definition);
+                if value != *expected {
+                    return Err(format!(
+                        "External witness value for intermediate column {name} in row {row} is {expected}, \
+                        but its definition {definition} evaluates to {value}."
+                    ));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
 pub fn extract_publics<'a, T, I>(witness: I, pil: &Analyzed<T>) -> BTreeMap<String, Option<T>>
 where
     T: FieldElement,
@@ -319,6 +405,8 @@ pub struct FixedData<'a, T: FieldElement> {
     global_range_constraints: GlobalConstraints<T>,
     intermediate_definitions: BTreeMap<AlgebraicReferenceThin, AlgebraicExpression<T>>,
     stage: u8,
+    /// Whether to run the fallback solver on rows of VM and block machines that get stuck.
+    solver_fallback: bool,
 }
 
 impl<'a, T: FieldElement> FixedData<'a, T> {
@@ -363,6 +451,15 @@ impl<'a, T: FieldElement> FixedData<'a, T> {
                 },
             ));
 
+        // Columns that were turned into intermediate columns by the optimizer
+        // are determined by their definitions. Values provided for them are not
+        // used by witgen, see [check_external_intermediate_values].
+        for (symbol, _) in analyzed.intermediate_polys_in_source_order() {
+            for (name, _) in symbol.array_elements() {
+                external_witness_values.remove(name.as_str());
+            }
+        }
+
         if !external_witness_values.is_empty() {
             let available_columns = witness_cols
                 .iter()
@@ -410,6 +507,7 @@ impl<'a, T: FieldElement> FixedData<'a, T> {
             global_range_constraints,
             intermediate_definitions,
             stage,
+            solver_fallback: false,
         }
     }
 
@@ -451,6 +549,15 @@ impl<'a, T: FieldElement> FixedData<'a, T> {
         }
     }
 
+    /// Enables the search-based fallback solver for rows on which witness generation
+    /// cannot make progress otherwise.
+    pub fn with_solver_fallback(self, solver_fallback: bool) -> Self {
+        Self {
+            solver_fallback,
+            ..self
+        }
+    }
+
     fn all_poly_symbols(&self) -> impl Iterator<Item = &Symbol> {
         self.analyzed
             .definitions
@@ -605,26 +712,51 @@ pub struct FixedColumn<'a, T> {
     values: &'a VariablySizedColumn<T>,
 }
 
-impl<'a, T: Copy> FixedColumn<'a, T> {
+impl<'a, T: PartialEq + Copy> FixedColumn<'a, T> {
     pub fn new(name: &'a str, values: &'a VariablySizedColumn<T>) -> FixedColumn<'a, T> {
         let name = name.to_string();
         FixedColumn { name, values }
     }
 
+    /// Returns the values of the column with the given size, materializing them if the
+    /// column is stored compactly. Prefer [FixedColumn::value] for accessing single rows.
     pub fn values(&self, size: DegreeType) -> &[T] {
-        self.values.get_by_size(size).unwrap_or_else(|| {
-            panic!(
-                "Fixed column {} does not have a value for size {}. Available sizes: {:?}",
-                self.name,
-                size,
-                self.values.available_sizes()
-            )
-        })
+        self.values
+            .get_by_size(size)
+            .unwrap_or_else(|| self.missing_size(size))
     }
 
     pub fn values_max_size(&self) -> &[T] {
-        let max_size = self.values.available_sizes().into_iter().max().unwrap() as DegreeType;
-        self.values(max_size)
+        self.values(self.max_size())
+    }
+
+    /// Returns the (possibly compact) values of the column with the given size.
+    pub fn compact_values(&self, size: DegreeType) -> &FixedColumnValues<T> {
+        self.values
+            .get_values_by_size(size)
+            .unwrap_or_else(|| self.missing_size(size))
+    }
+
+    pub fn compact_values_max_size(&self) -> &FixedColumnValues<T> {
+        self.compact_values(self.max_size())
+    }
+
+    /// Returns the value in the given row of the column with the given size.
+    pub fn value(&self, size: DegreeType, row: usize) -> T {
+        self.compact_values(size).get(row)
+    }
+
+    fn max_size(&self) -> DegreeType {
+        self.values.available_sizes().into_iter().max().unwrap() as DegreeType
+    }
+
+    fn missing_size(&self, size: DegreeType) -> ! {
+        panic!(
+            "Fixed column {} does not have a value for size {}. Available sizes: {:?}",
+            self.name,
+            size,
+            self.values.available_sizes()
+        )
     }
 
     pub fn has_constant_inner_value(&self) -> Option<T> {
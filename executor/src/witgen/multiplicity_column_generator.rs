@@ -40,23 +40,24 @@ impl<'a, T: FieldElement> MultiplicityColumnGenerator<'a, T> {
 
         let (identities, _) = convert_identities(self.fixed.analyzed);
 
-        let all_columns = witness_columns
+        let witness_columns = witness_columns
             .into_iter()
             .map(|(name, col)| {
                 let poly_id = self.fixed.try_column_by_name(&name).unwrap();
                 (poly_id, col)
             })
-            .chain(
-                self.fixed
-                    .fixed_cols
-                    .iter()
-                    // TODO: Avoid clone
-                    // TODO: Find the actual size
-                    .map(|(poly_id, fixed_col)| (poly_id, fixed_col.values_max_size().to_vec())),
-            )
+            .collect::<BTreeMap<_, _>>();
+        let fixed_columns = self
+            .fixed
+            .fixed_cols
+            .iter()
+            // TODO: Avoid clone of dense columns
+            // TODO: Find the actual size
+            .map(|(poly_id, fixed_col)| (poly_id, fixed_col.compact_values_max_size().clone()))
             .collect::<BTreeMap<_, _>>();
         let terminal_values = OwnedTerminalValues {
-            trace: all_columns,
+            trace: witness_columns,
+            fixed: fixed_columns,
             public_values: publics
                 .into_iter()
                 // Publics might be unavailable if they are later-stage publics.
@@ -2,7 +2,7 @@ use std::collections::BTreeMap;
 
 use num_traits::One;
 use powdr_ast::analyzed::{
-    AlgebraicExpression as Expression, AlgebraicReference, ContainsNextRef, PolyID, PolynomialType,
+    AlgebraicExpression as Expression, AlgebraicReference, PolyID, PolynomialType,
 };
 
 use powdr_number::{DegreeType, FieldElement};
@@ -12,6 +12,7 @@ use crate::witgen::data_structures::mutable_state::MutableState;
 use crate::witgen::{query_processor::QueryProcessor, util::try_to_simple_poly, Constraint};
 
 use super::data_structures::identity::{BusReceive, Identity};
+use super::diagnostics::{FailedRow, Failure, FailureKind, WitgenDiagnostic};
 use super::global_constraints::RangeConstraintSet;
 use super::machines::MachineParts;
 use super::FixedData;
@@ -22,7 +23,7 @@ use super::{
         finalizable_data::FinalizableData,
     },
     identity_processor::IdentityProcessor,
-    rows::{Row, RowIndex, RowPair, RowUpdater, UnknownStrategy},
+    rows::{Cell, Row, RowIndex, RowPair, RowUpdater, ScratchRows, UnknownStrategy},
     Constraints, EvalError, EvalValue, IncompleteCause, QueryCallback,
 };
 
@@ -223,6 +224,7 @@ impl<'a, 'c, T: FieldElement, Q: QueryCallback<T>> Processor<'a, 'c, T, Q> {
         let mut query_processor = QueryProcessor::new(
             self.fixed_data,
             self.mutable_state.query_callback(),
+            self.mutable_state.code_cache(),
             self.size,
         );
 
@@ -278,28 +280,7 @@ impl<'a, 'c, T: FieldElement, Q: QueryCallback<T>> Processor<'a, 'c, T, Q> {
 
         // Compute updates
         let mut identity_processor = IdentityProcessor::new(self.mutable_state);
-        let updates = identity_processor
-            .process_identity(identity, &row_pair)
-            .map_err(|e| -> EvalError<T> {
-                let mut error = format!(
-                    r"Error in identity: {identity}
-Known values in current row (local: {row_index}, global {global_row_index}):
-{}
-",
-                    self.data[row_index].render_values(false, self.parts)
-                );
-                let intermediate_definitions = self.fixed_data.analyzed.intermediate_definitions();
-                if identity.contains_next_ref(&intermediate_definitions) {
-                    error += &format!(
-                        "Known values in next row (local: {}, global {}):\n{}\n",
-                        row_index + 1,
-                        global_row_index + 1,
-                        self.data[row_index + 1].render_values(false, self.parts)
-                    );
-                }
-                error += &format!("   => Error: {e}");
-                error.into()
-            })?;
+        let updates = identity_processor.process_identity(identity, &row_pair)?;
 
         if unknown_strategy == UnknownStrategy::Zero {
             assert!(updates.constraints.is_empty());
@@ -551,6 +532,45 @@ Known values in current row (local: {row_index}, global {global_row_index}):
         &self.data[i]
     }
 
+    /// Returns a copy of the given row and the next row, to try out values on.
+    pub fn scratch_rows(&self, row_index: usize) -> ScratchRows<'_, 'a, T> {
+        ScratchRows::new(
+            &self.data[row_index],
+            &self.data[row_index + 1],
+            self.row_offset + row_index as u64,
+            &self.publics,
+            self.fixed_data,
+            &self.parts.witnesses,
+            self.size,
+        )
+    }
+
+    /// Sets the values of cells of the row pair starting at the given row, e.g. as
+    /// determined on [ScratchRows].
+    pub fn set_cells(&mut self, row_index: usize, cells: &[(Cell, T)]) {
+        let (current, next) = self.data.mutable_row_pair(row_index);
+        for ((poly_id, is_next), value) in cells {
+            let row = if *is_next { &mut *next } else { &mut *current };
+            row.set_cell_known(poly_id, *value);
+        }
+    }
+
+    /// Builds a structured diagnostic for failures on the given row, see [FailedRow::diagnose].
+    pub fn diagnose(
+        &self,
+        row_index: usize,
+        kind: FailureKind,
+        identities: &[&'a Identity<T>],
+        failures: &[Failure<'a, T>],
+    ) -> WitgenDiagnostic {
+        FailedRow {
+            rows: self.scratch_rows(row_index),
+            fixed_data: self.fixed_data,
+            parts: self.parts,
+        }
+        .diagnose(kind, identities, failures)
+    }
+
     pub fn has_outer_query(&self) -> bool {
         self.outer_query.is_some()
     }
@@ -5,7 +5,7 @@ use powdr_ast::analyzed::{AlgebraicReference, Expression, PolyID, PolynomialType
 use powdr_ast::parsed::types::Type;
 
 use powdr_number::{BigInt, DegreeType, FieldElement};
-use powdr_pil_analyzer::evaluator::{self, Definitions, EvalError, SymbolLookup, Value};
+use powdr_pil_analyzer::evaluator::{self, CodeCache, Definitions, EvalError, SymbolLookup, Value};
 
 use super::affine_expression::AlgebraicVariable;
 use super::Constraints;
@@ -15,6 +15,7 @@ use super::{rows::RowPair, Constraint, EvalResult, EvalValue, FixedData, Incompl
 pub struct QueryProcessor<'a, 'b, T: FieldElement, QueryCallback: Send + Sync> {
     fixed_data: &'a FixedData<'a, T>,
     query_callback: &'b QueryCallback,
+    code_cache: &'b CodeCache<'a, T>,
     size: DegreeType,
 }
 
@@ -24,11 +25,13 @@ impl<'a, 'b, T: FieldElement, QueryCallback: super::QueryCallback<T>>
     pub fn new(
         fixed_data: &'a FixedData<'a, T>,
         query_callback: &'b QueryCallback,
+        code_cache: &'b CodeCache<'a, T>,
         size: DegreeType,
     ) -> Self {
         Self {
             fixed_data,
             query_callback,
+            code_cache,
             size,
         }
     }
@@ -48,6 +51,7 @@ impl<'a, 'b, T: FieldElement, QueryCallback: super::QueryCallback<T>>
             size: self.size,
             updates: Constraints::new(),
             query_callback: self.query_callback,
+            code_cache: self.code_cache,
         };
         let res = evaluator::evaluate(fun, &mut symbols)
             .and_then(|fun| evaluator::evaluate_function_call(fun, arguments, &mut symbols));
@@ -142,6 +146,7 @@ impl<'a, 'b, T: FieldElement, QueryCallback: super::QueryCallback<T>>
             size: self.size,
             updates: Constraints::new(),
             query_callback: self.query_callback,
+            code_cache: self.code_cache,
         };
         let fun = evaluator::evaluate(query, &mut symbols)?;
         let res =
@@ -156,6 +161,7 @@ struct Symbols<'a, 'b, 'c, T: FieldElement, QueryCallback: Send + Sync> {
     size: DegreeType,
     updates: Constraints<AlgebraicVariable<'a>, T>,
     query_callback: &'c QueryCallback,
+    code_cache: &'c CodeCache<'a, T>,
 }
 
 impl<'a, T: FieldElement, QueryCallback: super::QueryCallback<T>> SymbolLookup<'a, T>
@@ -218,9 +224,8 @@ impl<'a, T: FieldElement, QueryCallback: super::QueryCallback<T>> SymbolLookup<'
                 }
             }
             PolynomialType::Constant => {
-                let values = self.fixed_data.fixed_cols[&poly_ref.poly_id].values(self.size);
                 let row = self.rows.current_row_index + if poly_ref.next { 1 } else { 0 };
-                values[usize::from(row)]
+                self.fixed_data.fixed_cols[&poly_ref.poly_id].value(self.size, usize::from(row))
             }
         })
         .into())
@@ -322,6 +327,10 @@ impl<'a, T: FieldElement, QueryCallback: super::QueryCallback<T>> SymbolLookup<'
             Err(EvalError::DataNotAvailable)
         }
     }
+
+    fn code_cache(&self) -> Option<&CodeCache<'a, T>> {
+        Some(self.code_cache)
+    }
 }
 
 impl<'a, T: FieldElement, QueryCallback: Send + Sync> Symbols<'a, '_, '_, T, QueryCallback> {
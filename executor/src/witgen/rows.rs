@@ -1,18 +1,24 @@
 use std::{
-    collections::BTreeMap,
+    collections::{BTreeMap, BTreeSet, HashSet},
     fmt::Display,
     ops::{Add, Sub},
 };
 
 use itertools::Itertools;
-use powdr_ast::analyzed::{AlgebraicExpression as Expression, AlgebraicReference, PolyID};
+use powdr_ast::{
+    analyzed::{
+        AlgebraicExpression as Expression, AlgebraicReference, AlgebraicReferenceThin, PolyID,
+        PolynomialType,
+    },
+    parsed::visitor::AllChildren,
+};
 use powdr_number::{DegreeType, FieldElement};
 
-use crate::witgen::Constraint;
+use crate::witgen::{Constraint, EvalError};
 
 use super::{
     affine_expression::{AffineExpression, AffineResult, AlgebraicVariable},
-    data_structures::column_map::WitnessColumnMap,
+    data_structures::{column_map::WitnessColumnMap, identity::Identity},
     evaluators::symbolic_witness_evaluator::{SymbolicWitnessEvaluator, WitnessColumnEvaluator},
     global_constraints::RangeConstraintSet,
     machines::MachineParts,
@@ -530,3 +536,211 @@ impl<'a, T: FieldElement> RangeConstraintSet<AlgebraicVariable<'a>, T> for RowPa
         }
     }
 }
+
+/// A cell of a row pair: the column and whether it is in the next row.
+pub type Cell = (PolyID, bool);
+
+/// An owned copy of a row pair, on which values can be tried out and polynomial identities
+/// be propagated without affecting the trace or any other machine.
+#[derive(Clone)]
+pub struct ScratchRows<'b, 'a, T: FieldElement> {
+    current: Row<T>,
+    next: Row<T>,
+    row_index: RowIndex,
+    publics: &'b BTreeMap<&'a str, T>,
+    fixed_data: &'a FixedData<'a, T>,
+    /// The witness columns of the machine, only these are updated.
+    witnesses: &'b HashSet<PolyID>,
+    size: DegreeType,
+}
+
+impl<'b, 'a, T: FieldElement> ScratchRows<'b, 'a, T> {
+    pub fn new(
+        current: &Row<T>,
+        next: &Row<T>,
+        row_index: RowIndex,
+        publics: &'b BTreeMap<&'a str, T>,
+        fixed_data: &'a FixedData<'a, T>,
+        witnesses: &'b HashSet<PolyID>,
+        size: DegreeType,
+    ) -> Self {
+        Self {
+            current: current.clone(),
+            next: next.clone(),
+            row_index,
+            publics,
+            fixed_data,
+            witnesses,
+            size,
+        }
+    }
+
+    pub fn row_index(&self) -> RowIndex {
+        self.row_index
+    }
+
+    pub fn row(&self, is_next: bool) -> &Row<T> {
+        if is_next {
+            &self.next
+        } else {
+            &self.current
+        }
+    }
+
+    fn row_mut(&mut self, is_next: bool) -> &mut Row<T> {
+        if is_next {
+            &mut self.next
+        } else {
+            &mut self.current
+        }
+    }
+
+    pub fn value(&self, (poly_id, is_next): &Cell) -> Option<T> {
+        self.row(*is_next).value(poly_id)
+    }
+
+    pub fn range_constraint(&self, (poly_id, is_next): &Cell) -> Option<RangeConstraint<T>> {
+        self.row(*is_next).range_constraint(poly_id)
+    }
+
+    pub fn set_value(&mut self, (poly_id, is_next): &Cell, value: T) {
+        self.row_mut(*is_next).set_cell_known(poly_id, value);
+    }
+
+    /// Forgets the value of a cell, only keeping its global range constraint.
+    pub fn reset(&mut self, (poly_id, is_next): &Cell) {
+        let range_constraint = &self
+            .fixed_data
+            .global_range_constraints()
+            .witness_constraints[poly_id];
+        let row = self.row_mut(*is_next);
+        row.set_cell_unknown(poly_id);
+        if let Some(range_constraint) = range_constraint {
+            row.apply_update(
+                poly_id,
+                &Constraint::RangeConstraint(range_constraint.clone()),
+            );
+        }
+    }
+
+    pub fn row_pair(&self) -> RowPair<'_, 'a, T> {
+        RowPair::new(
+            &self.current,
+            &self.next,
+            self.row_index,
+            self.publics,
+            self.fixed_data,
+            UnknownStrategy::Unknown,
+            self.size,
+        )
+    }
+
+    /// Repeatedly solves the polynomial identities among `identities` for their unknown
+    /// cells, until no more progress is made. Other identities are ignored.
+    /// Returns the first error, i.e. the first violated identity.
+    pub fn propagate(&mut self, identities: &[&'a Identity<T>]) -> Result<(), EvalError<T>> {
+        loop {
+            let mut progress = false;
+            for &identity in identities {
+                let Identity::Polynomial(identity) = identity else {
+                    continue;
+                };
+                let rows = self.row_pair();
+                let updates = match rows.evaluate(&identity.expression) {
+                    Ok(evaluated) => evaluated.solve_with_range_constraints(&rows)?,
+                    Err(_) => continue,
+                };
+                for (variable, constraint) in &updates.constraints {
+                    let AlgebraicVariable::Column(poly) = variable else {
+                        continue;
+                    };
+                    if self.witnesses.contains(&poly.poly_id) {
+                        progress |= self.apply(&(poly.poly_id, poly.next), constraint);
+                    }
+                }
+            }
+            if !progress {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Applies an update, returning whether anything changed.
+    fn apply(&mut self, (poly_id, is_next): &Cell, constraint: &Constraint<T>) -> bool {
+        let row = self.row_mut(*is_next);
+        if row.value_is_known(poly_id) {
+            return false;
+        }
+        match constraint {
+            Constraint::Assignment(value) => row.set_cell_known(poly_id, *value),
+            Constraint::RangeConstraint(range_constraint) => {
+                let current = row.range_constraint(poly_id);
+                let new = match &current {
+                    Some(current) => range_constraint.conjunction(current),
+                    None => range_constraint.clone(),
+                };
+                if current.as_ref() == Some(&new) {
+                    return false;
+                }
+                row.set_cell_unknown(poly_id);
+                row.apply_update(poly_id, &Constraint::RangeConstraint(new));
+            }
+        }
+        true
+    }
+
+    /// Returns the witness cells of the machine referenced by the identities, including
+    /// those referenced via intermediate columns.
+    pub fn referenced_cells<'c>(
+        &self,
+        identities: impl IntoIterator<Item = &'c Identity<T>>,
+    ) -> BTreeSet<Cell>
+    where
+        T: 'c,
+    {
+        let mut cells = BTreeSet::new();
+        let mut visited = BTreeSet::new();
+        for identity in identities {
+            self.collect_cells(identity, false, &mut cells, &mut visited);
+        }
+        cells
+    }
+
+    fn collect_cells(
+        &self,
+        expression: &impl AllChildren<Expression<T>>,
+        is_next: bool,
+        cells: &mut BTreeSet<Cell>,
+        visited: &mut BTreeSet<(AlgebraicReferenceThin, bool)>,
+    ) {
+        for reference in expression.all_children().filter_map(|e| match e {
+            Expression::Reference(reference) => Some(reference),
+            _ => None,
+        }) {
+            let is_next = is_next || reference.next;
+            match reference.poly_id.ptype {
+                PolynomialType::Committed if self.witnesses.contains(&reference.poly_id) => {
+                    cells.insert((reference.poly_id, is_next));
+                }
+                PolynomialType::Intermediate => {
+                    let reference = reference.to_thin();
+                    if visited.insert((reference.clone(), is_next)) {
+                        let definition = &self.fixed_data.intermediate_definitions[&reference];
+                        self.collect_cells(definition, is_next, cells, visited);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Returns the name of the column of a cell, with a `'` suffix for cells in the next row.
+    pub fn cell_name(&self, (poly_id, is_next): &Cell) -> String {
+        let name = self.fixed_data.column_name(poly_id);
+        if *is_next {
+            format!("{name}'")
+        } else {
+            name.to_string()
+        }
+    }
+}
@@ -1,7 +1,5 @@
 use indicatif::{ProgressBar, ProgressStyle};
-use itertools::Itertools;
 use powdr_ast::analyzed::{ContainsNextRef, DegreeRange};
-use powdr_ast::indent;
 use powdr_number::{DegreeType, FieldElement};
 use std::cmp::max;
 
@@ -14,6 +12,8 @@ use crate::witgen::IncompleteCause;
 
 use super::affine_expression::AlgebraicVariable;
 use super::data_structures::identity::Identity;
+use super::diagnostics::{Failure, FailureKind};
+use super::fallback_solver::FallbackSolver;
 use super::machines::MachineParts;
 use super::processor::{OuterQuery, Processor, SolverState};
 
@@ -71,6 +71,8 @@ pub struct VmProcessor<'a, 'c, T: FieldElement, Q: QueryCallback<T>> {
     /// If true, we'll periodically check if we are in a loop. If yes, we'll add new rows by
     /// copying the old ones and check the constraints.
     loop_detection: bool,
+    /// The solver for rows on which no more progress is made, if enabled.
+    fallback_solver: Option<FallbackSolver<'a, T>>,
 }
 
 impl<'a, 'c, T: FieldElement, Q: QueryCallback<T>> VmProcessor<'a, 'c, T, Q> {
@@ -122,6 +124,9 @@ impl<'a, 'c, T: FieldElement, Q: QueryCallback<T>> VmProcessor<'a, 'c, T, Q> {
             processor,
             progress_bar,
             loop_detection,
+            fallback_solver: fixed_data
+                .solver_fallback
+                .then(|| FallbackSolver::new(fixed_data)),
         }
     }
 
@@ -132,6 +137,9 @@ impl<'a, 'c, T: FieldElement, Q: QueryCallback<T>> VmProcessor<'a, 'c, T, Q> {
 
     /// Returns the updated data, values for publics, and the length of the block.
     pub fn finish(self) -> (SolverState<'a, T>, DegreeType) {
+        if let Some(fallback_solver) = &self.fallback_solver {
+            fallback_solver.report(&self.machine_name);
+        }
         (self.processor.finish(), self.degree)
     }
 
@@ -297,16 +305,28 @@ impl<'a, 'c, T: FieldElement, Q: QueryCallback<T>> VmProcessor<'a, 'c, T, Q> {
             CompletableIdentities::new(self.identities_without_next_ref.iter().cloned());
         let mut identities_with_next_ref =
             CompletableIdentities::new(self.identities_with_next_ref.iter().cloned());
-        let outer_assignments = self
-            .loop_until_no_progress(row_index, &mut identities_without_next_ref)
-            .and_then(|outer_assignments| {
-                Ok(outer_assignments
-                    .into_iter()
-                    .chain(self.loop_until_no_progress(row_index, &mut identities_with_next_ref)?)
-                    .collect::<Vec<_>>())
-            })
+        let mut outer_assignments = self
+            .solve_row(
+                row_index,
+                &mut identities_without_next_ref,
+                &mut identities_with_next_ref,
+            )
             .map_err(|e| self.report_failure_and_panic_unsatisfiable(row_index, e))
             .unwrap();
+        if self.try_fallback_solver(
+            row_index,
+            [&identities_without_next_ref, &identities_with_next_ref],
+        ) {
+            outer_assignments.extend(
+                self.solve_row(
+                    row_index,
+                    &mut identities_without_next_ref,
+                    &mut identities_with_next_ref,
+                )
+                .map_err(|e| self.report_failure_and_panic_unsatisfiable(row_index, e))
+                .unwrap(),
+            );
+        }
 
         // Check that the computed row is "final" by asserting that all unknown values can
         // be set to 0.
@@ -347,13 +367,57 @@ impl<'a, 'c, T: FieldElement, Q: QueryCallback<T>> VmProcessor<'a, 'c, T, Q> {
         outer_assignments
     }
 
+    /// Processes the identities without and then those with next references, each until
+    /// no further progress is made.
+    fn solve_row(
+        &mut self,
+        row_index: DegreeType,
+        identities_without_next_ref: &mut CompletableIdentities<'a, T>,
+        identities_with_next_ref: &mut CompletableIdentities<'a, T>,
+    ) -> Result<Constraints<AlgebraicVariable<'a>, T>, Vec<Failure<'a, T>>> {
+        let mut outer_assignments =
+            self.loop_until_no_progress(row_index, identities_without_next_ref)?;
+        outer_assignments.extend(self.loop_until_no_progress(row_index, identities_with_next_ref)?);
+        Ok(outer_assignments)
+    }
+
+    /// If the fallback solver is enabled and some identities are still incomplete, tries to
+    /// determine their unknown cells with it.
+    /// Returns true if cells were set.
+    fn try_fallback_solver(
+        &mut self,
+        row_index: DegreeType,
+        identities: [&CompletableIdentities<'a, T>; 2],
+    ) -> bool {
+        let Some(fallback_solver) = &mut self.fallback_solver else {
+            return false;
+        };
+        let incomplete = identities
+            .iter()
+            .flat_map(|identities| &identities.identities_with_complete)
+            .filter(|(_, complete)| !complete)
+            .map(|(identity, _)| *identity)
+            .collect::<Vec<_>>();
+        if incomplete.is_empty() {
+            return false;
+        }
+        let row_index = row_index as usize;
+        match fallback_solver.solve(&self.processor.scratch_rows(row_index), &incomplete) {
+            Some(cells) => {
+                self.processor.set_cells(row_index, &cells);
+                true
+            }
+            None => false,
+        }
+    }
+
     /// Loops over all identities and queries, until no further progress is made.
     /// @returns the "incomplete" identities, i.e. identities that contain unknown values.
     fn loop_until_no_progress(
         &mut self,
         row_index: DegreeType,
         identities: &mut CompletableIdentities<'a, T>,
-    ) -> Result<Constraints<AlgebraicVariable<'a>, T>, Vec<EvalError<T>>> {
+    ) -> Result<Constraints<AlgebraicVariable<'a>, T>, Vec<Failure<'a, T>>> {
         let mut outer_assignments = vec![];
 
         // The PC lookup fills most of the columns and enables hints thus it should be run first.
@@ -378,7 +442,7 @@ impl<'a, 'c, T: FieldElement, Q: QueryCallback<T>> VmProcessor<'a, 'c, T, Q> {
                     &mut identities.identities_with_complete[pc_lookup_index];
                 let result = self
                     .process_identity(row_index, identity, is_complete, UnknownStrategy::Unknown)
-                    .map_err(|e| vec![e])?;
+                    .map_err(|e| vec![(Some(*identity), e)])?;
                 if result == Some(true) {
                     progress |= true;
                 }
@@ -390,7 +454,7 @@ impl<'a, 'c, T: FieldElement, Q: QueryCallback<T>> VmProcessor<'a, 'c, T, Q> {
                 let (outer_query_progress, new_outer_assignments) = self
                     .processor
                     .process_outer_query(row_index)
-                    .map_err(|e| vec![e])?;
+                    .map_err(|e| vec![(None, e)])?;
                 progress |= outer_query_progress;
                 outer_assignments.extend(new_outer_assignments);
             }
@@ -399,7 +463,7 @@ impl<'a, 'c, T: FieldElement, Q: QueryCallback<T>> VmProcessor<'a, 'c, T, Q> {
             progress |= self
                 .processor
                 .process_queries(row_index)
-                .map_err(|e| vec![e])?;
+                .map_err(|e| vec![(None, e)])?;
 
             if !progress {
                 break;
@@ -422,7 +486,7 @@ impl<'a, 'c, T: FieldElement, Q: QueryCallback<T>> VmProcessor<'a, 'c, T, Q> {
         row_index: DegreeType,
         identities: &mut CompletableIdentities<'a, T>,
         unknown_strategy: UnknownStrategy,
-    ) -> Result<bool, Vec<EvalError<T>>> {
+    ) -> Result<bool, Vec<Failure<'a, T>>> {
         let mut progress = false;
         let mut errors = vec![];
 
@@ -430,7 +494,7 @@ impl<'a, 'c, T: FieldElement, Q: QueryCallback<T>> VmProcessor<'a, 'c, T, Q> {
             match self.process_identity(row_index, identity, is_complete, unknown_strategy) {
                 Ok(Some(result)) => progress |= result,
                 Ok(None) => (),
-                Err(e) => errors.push(e),
+                Err(e) => errors.push((Some(identity), e)),
             }
         }
 
@@ -477,51 +541,52 @@ impl<'a, 'c, T: FieldElement, Q: QueryCallback<T>> VmProcessor<'a, 'c, T, Q> {
     fn report_failure_and_panic_unsatisfiable(
         &self,
         row_index: DegreeType,
-        failures: Vec<EvalError<T>>,
+        failures: Vec<Failure<'a, T>>,
     ) -> ! {
-        log::error!(
-            "\nError: Row {} failed. Set RUST_LOG=debug for more information.\n",
-            row_index + self.row_offset
-        );
         let row_index = row_index as usize;
-        log::debug!("Some identities where not satisfiable after the following values were uniquely determined (known nonzero first, then zero, unknown omitted):");
-        log::debug!(
+        let diagnostic = self.processor.diagnose(
+            row_index,
+            FailureKind::Unsatisfiable,
+            &self.parts.identities,
+            &failures,
+        );
+        log::error!("\nError: {diagnostic}");
+        log::trace!(
             "{}",
             self.processor.row(row_index).render(
                 &format!("Current row ({row_index})"),
-                false,
+                true,
                 self.parts
             )
         );
-        log::debug!(
+        log::trace!(
             "{}",
             self.processor.row(row_index + 1).render(
                 &format!("Next row ({})", row_index + 1),
-                false,
+                true,
                 self.parts
             )
         );
-        log::debug!("Set RUST_LOG=trace to understand why these values were chosen.");
-        log::error!(
-            "Errors:\n{}\n",
-            failures.iter().map(|r| indent(r.to_string(), 1)).join("\n")
-        );
+        log::error!("Set RUST_LOG=trace to understand why these values were chosen.");
+        diagnostic.write_if_requested();
         panic!("Witness generation failed.");
     }
 
     fn report_failure_and_panic_under_constrained(
         &self,
         row_index: DegreeType,
-        failures: Vec<EvalError<T>>,
+        failures: Vec<Failure<'a, T>>,
     ) -> ! {
-        log::error!(
-            "\nError: Row {} failed. Set RUST_LOG=debug for more information.\n",
-            row_index + self.row_offset
-        );
         let row_index = row_index as usize;
-
-        log::debug!("Some columns could not be determined, but setting them to zero does not satisfy the constraints. This typically means that the system is under-constrained!");
-        log::debug!(
+        let diagnostic = self.processor.diagnose(
+            row_index,
+            FailureKind::UnderConstrained,
+            &self.parts.identities,
+            &failures,
+        );
+        log::error!("\nError: {diagnostic}");
+        log::error!("This typically means that the system is under-constrained!");
+        log::trace!(
             "{}",
             self.processor.row(row_index).render(
                 &format!("Current row ({row_index})"),
@@ -529,7 +594,7 @@ impl<'a, 'c, T: FieldElement, Q: QueryCallback<T>> VmProcessor<'a, 'c, T, Q> {
                 self.parts
             )
         );
-        log::debug!(
+        log::trace!(
             "{}",
             self.processor.row(row_index + 1).render(
                 &format!("Next row ({})", row_index + 1),
@@ -537,11 +602,8 @@ impl<'a, 'c, T: FieldElement, Q: QueryCallback<T>> VmProcessor<'a, 'c, T, Q> {
                 self.parts
             )
         );
-        log::debug!("\nSet RUST_LOG=trace to understand why these values were (not) chosen.");
-        log::debug!(
-            "Assuming zero for unknown values, the following identities fail:\n{}\n",
-            failures.iter().map(|r| indent(r.to_string(), 1)).join("\n")
-        );
+        log::error!("Set RUST_LOG=trace to understand why these values were (not) chosen.");
+        diagnostic.write_if_requested();
         panic!("Witness generation failed.");
     }
 
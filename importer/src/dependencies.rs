@@ -0,0 +1,314 @@
+//! Loads the path dependencies declared in a `powdr.toml` manifest.
+//!
+//! A manifest looks like this:
+//! ```toml
+//! [package]
+//! name = "my_machines"
+//! version = "0.1.0"
+//!
+//! [dependencies]
+//! arith = { path = "../arith", version = "0.2" }
+//! ```
+//! The manifest of a program is found in the directory of its main file or in one of the
+//! parent directories. Each dependency is a directory with its own manifest and a `mod.asm` file.
+//! Like `std`, all dependencies (including the dependencies of dependencies) are added as modules
+//! at the root of the program, and every module can refer to the dependencies of its package by name.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
+};
+
+use itertools::Itertools;
+use powdr_ast::parsed::asm::{
+    ASMModule, ASMProgram, Import, Module, ModuleStatement, Part, SymbolDefinition, SymbolPath,
+    SymbolValue,
+};
+use powdr_parser::parse_asm;
+use powdr_parser_util::SourceRef;
+use semver::{Version, VersionReq};
+use serde::Deserialize;
+
+use crate::load_module_files;
+
+static MANIFEST_FILE: &str = "powdr.toml";
+static MOD_FILE: &str = "mod.asm";
+static STD_NAME: &str = "std";
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Manifest {
+    package: Package,
+    #[serde(default)]
+    dependencies: BTreeMap<String, Dependency>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Package {
+    name: String,
+    version: Version,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Dependency {
+    /// The directory of the dependency, relative to the directory of the manifest.
+    path: PathBuf,
+    /// The versions of the dependency that are accepted.
+    version: Option<VersionReq>,
+}
+
+impl Manifest {
+    fn read(path: &Path) -> Result<Self, String> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| format!("Cannot read manifest `{}`: {e}", path.display()))?;
+        toml::from_str(&contents)
+            .map_err(|e| format!("Error parsing manifest `{}`: {e}", path.display()))
+    }
+}
+
+/// A loaded dependency.
+struct LoadedPackage {
+    /// The canonical directory of the package.
+    dir: PathBuf,
+    version: Version,
+    module: ASMModule,
+}
+
+/// Adds the dependencies declared in the manifest of the program at `path` as modules
+/// at the root of the program. Does nothing if there is no path or no manifest.
+pub fn add_dependencies(path: Option<&Path>, program: ASMProgram) -> Result<ASMProgram, String> {
+    let Some(manifest_path) = path.and_then(find_manifest) else {
+        return Ok(program);
+    };
+    let manifest = Manifest::read(&manifest_path)?;
+    let root_dir = manifest_path.parent().unwrap().to_path_buf();
+
+    let mut packages = BTreeMap::new();
+    let mut chain = vec![root_dir.clone()];
+    for (name, dependency) in &manifest.dependencies {
+        load_dependency(name, dependency, &root_dir, &mut chain, &mut packages)?;
+    }
+
+    let defined_names = program
+        .main
+        .statements
+        .iter()
+        .flat_map(|s| s.defined_names())
+        .collect::<BTreeSet<_>>();
+    if let Some(name) = packages.keys().find(|name| defined_names.contains(name)) {
+        return Err(format!(
+            "Dependency `{name}` clashes with a symbol of the same name in `{}`.",
+            path.unwrap().display()
+        ));
+    }
+
+    let names = manifest.dependencies.keys().cloned().collect_vec();
+    let mut main = with_imports_in_submodules(program.main, &names);
+    main.statements
+        .extend(packages.into_iter().map(|(name, package)| {
+            ModuleStatement::SymbolDefinition(SymbolDefinition {
+                name,
+                value: SymbolValue::Module(Module::Local(package.module)),
+            })
+        }));
+    Ok(ASMProgram { main })
+}
+
+/// Returns the path of the manifest in the directory of the file or in the closest parent directory.
+fn find_manifest(path: &Path) -> Option<PathBuf> {
+    path.canonicalize()
+        .ok()?
+        .ancestors()
+        .skip(1)
+        .map(|dir| dir.join(MANIFEST_FILE))
+        .find(|manifest| manifest.is_file())
+}
+
+/// Loads the dependency and, recursively, its own dependencies into `packages`.
+/// `chain` is the list of the directories of the packages that depend on this one.
+fn load_dependency(
+    name: &str,
+    dependency: &Dependency,
+    base_dir: &Path,
+    chain: &mut Vec<PathBuf>,
+    packages: &mut BTreeMap<String, LoadedPackage>,
+) -> Result<(), String> {
+    let dir = base_dir.join(&dependency.path);
+    let dir = dir.canonicalize().map_err(|e| {
+        format!(
+            "Cannot find dependency `{name}` at `{}`: {e}",
+            dir.display()
+        )
+    })?;
+
+    if name == STD_NAME {
+        return Err(format!(
+            "Dependency `{name}` at `{}` clashes with the standard library.",
+            dir.display()
+        ));
+    }
+    if chain.contains(&dir) {
+        return Err(format!(
+            "Cyclic dependency: {}",
+            chain
+                .iter()
+                .chain([&dir])
+                .map(|d| d.display())
+                .format(" -> ")
+        ));
+    }
+    if let Some(package) = packages.get(name) {
+        if package.dir != dir {
+            return Err(format!(
+                "Dependency `{name}` refers to two different packages: `{}` and `{}`.",
+                package.dir.display(),
+                dir.display()
+            ));
+        }
+        return check_version(name, &package.version, dependency);
+    }
+
+    let manifest = Manifest::read(&dir.join(MANIFEST_FILE))?;
+    if manifest.package.name != name {
+        return Err(format!(
+            "Dependency `{name}` at `{}` is the package `{}`.",
+            dir.display(),
+            manifest.package.name
+        ));
+    }
+    check_version(name, &manifest.package.version, dependency)?;
+
+    chain.push(dir.clone());
+    for (dependency_name, dependency) in &manifest.dependencies {
+        load_dependency(dependency_name, dependency, &dir, chain, packages)?;
+    }
+    chain.pop();
+
+    let mod_path = dir.join(MOD_FILE);
+    let source = std::fs::read_to_string(&mod_path)
+        .map_err(|e| format!("Cannot read `{}`: {e}", mod_path.display()))?;
+    let program = parse_asm(Some(mod_path.to_str().unwrap()), &source).map_err(|err| {
+        err.output_to_stderr();
+        format!("Error parsing `{}`", mod_path.display())
+    })?;
+    let program = load_module_files(Some(mod_path), program)?;
+
+    let names = manifest.dependencies.keys().cloned().collect_vec();
+    packages.insert(
+        name.to_string(),
+        LoadedPackage {
+            dir,
+            version: manifest.package.version,
+            module: with_imports(program.main, &names),
+        },
+    );
+    Ok(())
+}
+
+fn check_version(name: &str, version: &Version, dependency: &Dependency) -> Result<(), String> {
+    match &dependency.version {
+        Some(requirement) if !requirement.matches(version) => Err(format!(
+            "Dependency `{name}` has version {version}, which does not match the requirement {requirement}."
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Adds `use super::<name>;` for each of the names to the module and all of its submodules,
+/// except for modules that define the name themselves.
+fn with_imports(module: ASMModule, names: &[String]) -> ASMModule {
+    let mut module = with_imports_in_submodules(module, names);
+    let defined_names = module
+        .statements
+        .iter()
+        .flat_map(|s| s.defined_names())
+        .cloned()
+        .collect::<BTreeSet<_>>();
+    let imports = names
+        .iter()
+        .filter(|name| !defined_names.contains(*name))
+        .map(|name| {
+            ModuleStatement::SymbolDefinition(SymbolDefinition {
+                name: name.clone(),
+                value: SymbolValue::Import(Import {
+                    source: SourceRef::unknown(),
+                    path: SymbolPath::from_parts([Part::Super, Part::Named(name.clone())]),
+                }),
+            })
+        })
+        .collect_vec();
+    module.statements.extend(imports);
+    module
+}
+
+fn with_imports_in_submodules(module: ASMModule, names: &[String]) -> ASMModule {
+    ASMModule {
+        statements: module
+            .statements
+            .into_iter()
+            .map(|s| match s {
+                ModuleStatement::SymbolDefinition(SymbolDefinition {
+                    name,
+                    value: SymbolValue::Module(Module::Local(m)),
+                }) => ModuleStatement::SymbolDefinition(SymbolDefinition {
+                    name,
+                    value: SymbolValue::Module(Module::Local(with_imports(m, names))),
+                }),
+                s => s,
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load(dir: &str) -> Result<ASMProgram, String> {
+        let main_path = Path::new("test_data/dependencies")
+            .join(dir)
+            .join("main.asm");
+        let main_str = std::fs::read_to_string(&main_path).unwrap();
+        let main = parse_asm(None, &main_str).unwrap();
+        load_module_files(Some(main_path.clone()), main)
+            .and_then(|main| add_dependencies(Some(&main_path), main))
+    }
+
+    #[test]
+    fn dependencies() {
+        let program = load("app").unwrap();
+        let printed = program.to_string();
+        // Transitive dependencies are added at the root as well.
+        assert!(printed.contains("mod arith {"));
+        assert!(printed.contains("mod util {"));
+        assert!(printed.contains("use super::arith;"));
+        crate::path_canonicalizer::canonicalize_paths(program)
+            .map_err(|e| e.message().to_string())
+            .unwrap();
+    }
+
+    #[test]
+    fn version_mismatch() {
+        assert_eq!(
+            load("version_mismatch").err().unwrap(),
+            "Dependency `util` has version 0.1.0, which does not match the requirement ^0.2."
+        );
+    }
+
+    #[test]
+    fn name_clash() {
+        assert_eq!(
+            load("name_clash").err().unwrap(),
+            "Dependency `arith` clashes with a symbol of the same name in `test_data/dependencies/name_clash/main.asm`."
+        );
+    }
+
+    #[test]
+    fn cycle() {
+        let error = load("cycle").err().unwrap();
+        assert!(error.starts_with("Cyclic dependency: "), "{error}");
+        assert!(error.ends_with("cycle/a"), "{error}");
+    }
+}
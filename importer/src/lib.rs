@@ -1,9 +1,13 @@
+mod dependencies;
+mod lints;
 mod module_loader;
 mod path_canonicalizer;
 mod powdr_std;
 
 use std::path::PathBuf;
 
+use dependencies::add_dependencies;
+pub use lints::lint;
 pub use module_loader::load_module_files;
 use path_canonicalizer::canonicalize_paths;
 use powdr_ast::parsed::asm::ASMProgram;
@@ -15,7 +19,8 @@ pub fn load_dependencies_and_resolve(
     path: Option<PathBuf>,
     module: ASMProgram,
 ) -> Result<ASMProgram, Error> {
-    load_module_files(path, module)
+    load_module_files(path.clone(), module)
+        .and_then(|module| add_dependencies(path.as_deref(), module))
         .and_then(add_std)
         .map_err(|e| SourceRef::default().with_error(e))
         .and_then(canonicalize_paths)
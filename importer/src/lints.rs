@@ -0,0 +1,107 @@
+use std::path::PathBuf;
+
+use powdr_ast::parsed::asm::{
+    ASMModule, ASMProgram, AbsoluteSymbolPath, Import, Module, ModuleStatement, SymbolDefinition,
+    SymbolValue,
+};
+use powdr_parser_util::{Error, Lint, SourceRef, Warning};
+
+use crate::{
+    dependencies::add_dependencies,
+    load_module_files,
+    path_canonicalizer::{generate_path_map, PathMap},
+    powdr_std::add_std,
+};
+
+/// Returns warnings about imports that are never used, except for the ones suppressed by a comment.
+/// Imports that are not part of a source file, like the automatically added import of the
+/// standard library, are not linted.
+pub fn lint(path: Option<PathBuf>, program: ASMProgram) -> Result<Vec<Warning>, Error> {
+    let program = load_module_files(path.clone(), program)
+        .and_then(|program| add_dependencies(path.as_deref(), program))
+        .and_then(add_std)
+        .map_err(|e| SourceRef::default().with_error(e))?;
+    let paths = generate_path_map(&program)?;
+
+    let mut warnings = vec![];
+    unused_imports(
+        &AbsoluteSymbolPath::default(),
+        &program.main,
+        &paths,
+        &mut warnings,
+    );
+    Ok(warnings
+        .into_iter()
+        .filter(|warning| !warning.is_suppressed())
+        .collect())
+}
+
+/// An import of name `name` at `location` is used if any path other than the import itself
+/// was resolved through `location::name`.
+fn unused_imports(
+    location: &AbsoluteSymbolPath,
+    module: &ASMModule,
+    paths: &PathMap,
+    warnings: &mut Vec<Warning>,
+) {
+    for statement in &module.statements {
+        let ModuleStatement::SymbolDefinition(SymbolDefinition { name, value }) = statement else {
+            continue;
+        };
+        match value {
+            SymbolValue::Module(Module::Local(m)) => {
+                unused_imports(&location.clone().with_part(name), m, paths, warnings)
+            }
+            SymbolValue::Import(Import { source, path }) if source.file_contents.is_some() => {
+                let import = location.clone().join(path.clone());
+                let imported_name = location.clone().with_part(name);
+                let used = paths.keys().any(|p| {
+                    *p != import
+                        && p.len() >= imported_name.len()
+                        && p.parts().zip(imported_name.parts()).all(|(a, b)| a == b)
+                });
+                if !used {
+                    warnings.push(source.with_warning(
+                        Lint::UnusedImport,
+                        format!("Import `{name}` is never used."),
+                    ));
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unused(input: &str) -> Vec<String> {
+        lint(None, powdr_parser::parse_asm(None, input).unwrap())
+            .unwrap()
+            .into_iter()
+            .map(|w| w.message().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn unused_imports() {
+        let input = r#"
+            use submodule::Foo;
+            use submodule::Bar;
+            use submodule as alias;
+            use submodule::x; // lint: allow(unused-import)
+            mod submodule {
+                machine Foo {}
+                machine Bar {}
+                machine Baz {}
+                let x = 1;
+            }
+            machine Main {
+                Foo foo;
+                alias::Baz baz;
+            }
+        "#;
+        assert_eq!(unused(input), vec!["Import `Bar` is never used."]);
+    }
+}
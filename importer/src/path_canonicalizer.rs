@@ -598,7 +598,7 @@ fn check_import(
     check_path(location.join(imported.path), state)
 }
 
-fn generate_path_map(program: &ASMProgram) -> Result<PathMap, Error> {
+pub(crate) fn generate_path_map(program: &ASMProgram) -> Result<PathMap, Error> {
     // an empty state starting from this module
     let mut state = State {
         root: &program.main,
@@ -11,6 +11,7 @@ use powdr_ast::parsed::{
     folder::Folder,
 };
 use powdr_parser::parse_asm;
+use powdr_parser_util::SourceRef;
 
 use crate::load_module_files;
 
@@ -111,6 +112,7 @@ impl Folder for StdAdder {
             statements.push(ModuleStatement::SymbolDefinition(SymbolDefinition {
                 name: "std".to_string(),
                 value: SymbolValue::Import(Import {
+                    source: SourceRef::unknown(),
                     path: std_import_path,
                 }),
             }));
@@ -0,0 +1,9 @@
+machine Main {
+    arith::Add add;
+}
+
+mod submodule {
+    machine Wrapper {
+        arith::Add add;
+    }
+}
@@ -0,0 +1,3 @@
+machine Add {
+    util::Helper helper;
+}
@@ -0,0 +1,69 @@
+use ark_bls12_381::Fr;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+powdr_field!(Bls12_381Field, Fr);
+
+#[cfg(test)]
+mod tests {
+    use super::Bls12_381Field;
+    use crate::{traits::int_from_hex_str, FieldElement};
+    use test_log::test;
+
+    #[test]
+    fn minus_one() {
+        let minus_one = Bls12_381Field::from(0) - Bls12_381Field::from(1);
+        assert_eq!(
+            minus_one.to_arbitrary_integer(),
+            crate::BigUint::from_str_radix(
+                "52435875175126190479447740508185965837690552500527637822603658699938581184512",
+                10
+            )
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn format() {
+        let one = Bls12_381Field::from(1);
+        assert_eq!(format!("{one:x}"), "1");
+        let minus_one = Bls12_381Field::from(0) - Bls12_381Field::from(1);
+        assert_eq!(
+            format!("{minus_one:x}"),
+            "73eda753299d7d483339d80809a1d80553bda402fffe5bfeffffffff00000000"
+        );
+    }
+
+    #[test]
+    fn bitwise() {
+        let n = int_from_hex_str::<Bls12_381Field>(
+            "00ff00ff00ff00ff00ff00ff00ff00ff00ff00ff00ff00ff00ff00ff00ff00ff",
+        );
+        let p = int_from_hex_str::<Bls12_381Field>(
+            "000ff00ff00ff00ff00ff00ff00ff00ff00ff00ff00ff00ff00ff00ff00ff00f",
+        );
+        let n_and_p = int_from_hex_str::<Bls12_381Field>(
+            "000f000f000f000f000f000f000f000f000f000f000f000f000f000f000f000f",
+        );
+        let n_shr_4 = int_from_hex_str::<Bls12_381Field>(
+            "000ff00ff00ff00ff00ff00ff00ff00ff00ff00ff00ff00ff00ff00ff00ff00f",
+        );
+
+        assert_eq!(n >> 4, n_shr_4);
+        assert_eq!(n & p, n_and_p);
+    }
+
+    #[test]
+    #[should_panic]
+    fn div_by_zero() {
+        let _ = Bls12_381Field::from(1) / Bls12_381Field::from(0);
+    }
+
+    #[test]
+    fn to_signed_integer() {
+        for value in [i32::MIN as i64, -3456, -1, 0, 1, 3456, i32::MAX as i64] {
+            let field_value = Bls12_381Field::from(value);
+            assert_eq!(field_value.to_signed_integer(), value.into());
+        }
+    }
+}
@@ -3,6 +3,7 @@
 #[macro_use]
 mod macros;
 mod baby_bear;
+mod bls12_381;
 mod bn254;
 mod goldilocks;
 mod koala_bear;
@@ -12,10 +13,12 @@ mod plonky3_macros;
 mod serialize;
 mod traits;
 pub use serialize::{
-    buffered_write_file, read_polys_csv_file, write_polys_csv_file, CsvRenderMode, ReadWrite,
+    buffered_write_file, read_polys_csv_file, write_polys_csv_file, write_polys_csv_file_from_row,
+    CsvRenderMode, ReadWrite,
 };
 
 pub use baby_bear::BabyBearField;
+pub use bls12_381::Bls12_381Field;
 pub use bn254::Bn254Field;
 pub use goldilocks::GoldilocksField;
 pub use koala_bear::KoalaBearField;
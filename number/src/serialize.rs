@@ -24,12 +24,34 @@ impl Default for CsvRenderMode {
     }
 }
 
+impl CsvRenderMode {
+    /// Renders a field element in this mode.
+    pub fn render<T: FieldElement>(&self, value: &T) -> String {
+        match self {
+            CsvRenderMode::SignedBase10 => format!("{value}"),
+            CsvRenderMode::UnsignedBase10 => format!("{}", value.to_integer()),
+            CsvRenderMode::Hex => format!("0x{:x}", value.to_integer()),
+        }
+    }
+}
+
 const ROW_NAME: &str = "Row";
 
 pub fn write_polys_csv_file<T: FieldElement>(
     file: impl Write,
     render_mode: CsvRenderMode,
     polys: &[(&String, &[T])],
+) {
+    write_polys_csv_file_from_row(file, render_mode, polys, 0)
+}
+
+/// Like [write_polys_csv_file], but for a slice of the columns starting at `first_row`,
+/// which is used to number the rows.
+pub fn write_polys_csv_file_from_row<T: FieldElement>(
+    file: impl Write,
+    render_mode: CsvRenderMode,
+    polys: &[(&String, &[T])],
+    first_row: usize,
 ) {
     let mut writer = Writer::from_writer(file);
 
@@ -44,15 +66,11 @@ pub fn write_polys_csv_file<T: FieldElement>(
     let max_len = polys.iter().map(|p| p.1.len()).max().unwrap();
     for row_index in 0..max_len {
         let mut row = Vec::new();
-        row.push(format!("{row_index}"));
+        row.push(format!("{}", first_row + row_index));
         for (_, values) in polys {
             let value = values
                 .get(row_index)
-                .map(|v| match render_mode {
-                    CsvRenderMode::SignedBase10 => format!("{v}"),
-                    CsvRenderMode::UnsignedBase10 => format!("{}", v.to_integer()),
-                    CsvRenderMode::Hex => format!("0x{:x}", v.to_integer()),
-                })
+                .map(|v| render_mode.render(v))
                 .unwrap_or_default();
             row.push(value);
         }
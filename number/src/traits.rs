@@ -83,6 +83,7 @@ pub enum KnownField {
     Mersenne31Field,
     GoldilocksField,
     Bn254Field,
+    Bls12_381Field,
 }
 
 impl KnownField {
@@ -91,7 +92,9 @@ impl KnownField {
             KnownField::BabyBearField
             | KnownField::KoalaBearField
             | KnownField::Mersenne31Field => FieldSize::Small,
-            KnownField::GoldilocksField | KnownField::Bn254Field => FieldSize::Large,
+            KnownField::GoldilocksField | KnownField::Bn254Field | KnownField::Bls12_381Field => {
+                FieldSize::Large
+            }
         }
     }
 }
@@ -104,6 +107,7 @@ impl Display for KnownField {
             KnownField::Mersenne31Field => write!(f, "Mersenne31"),
             KnownField::GoldilocksField => write!(f, "Goldilocks"),
             KnownField::Bn254Field => write!(f, "Bn254"),
+            KnownField::Bls12_381Field => write!(f, "Bls12_381"),
         }
     }
 }
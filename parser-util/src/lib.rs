@@ -1,14 +1,19 @@
 //! Utils used with different lalrpop parsers
 
+mod lint;
+
 use std::{
     fmt::{self, Debug, Formatter},
     hash::Hash,
     sync::Arc,
 };
 
+use codespan_reporting::diagnostic::{Diagnostic, Label, Severity};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
+pub use lint::{Lint, Warning};
+
 #[derive(Clone, Default, Serialize, Deserialize, JsonSchema)]
 /// A reference to a location in a source file.
 ///
@@ -57,6 +62,29 @@ impl SourceRef {
             message,
         }
     }
+
+    /// Returns a new Warning for this source reference.
+    pub fn with_warning(&self, lint: Lint, message: String) -> Warning {
+        Warning::new(lint, self.clone(), message)
+    }
+
+    /// Outputs a message about this source reference to stderr.
+    fn output_to_stderr(&self, severity: Severity, message: &str) {
+        use codespan_reporting::files::SimpleFiles;
+        use codespan_reporting::term;
+        use codespan_reporting::term::termcolor::{ColorChoice, StandardStream};
+
+        let config = term::Config::default();
+        let mut files = SimpleFiles::new();
+        let file_name = self.file_name.as_deref().unwrap_or("input");
+        let contents = self.file_contents.as_deref().unwrap_or_default();
+        let file_id = files.add(file_name, contents);
+        let diagnostic = Diagnostic::new(severity)
+            .with_message(message)
+            .with_labels(vec![Label::primary(file_id, self.start..self.end)]);
+        let mut writer = StandardStream::stderr(ColorChoice::Always);
+        term::emit(&mut writer, &config, &files, &diagnostic).unwrap()
+    }
 }
 
 impl Debug for SourceRef {
@@ -85,24 +113,8 @@ impl std::fmt::Display for Error {
 
 impl Error {
     pub fn output_to_stderr(&self) {
-        use codespan_reporting::diagnostic::{Diagnostic, Label};
-        use codespan_reporting::files::SimpleFiles;
-        use codespan_reporting::term;
-        use codespan_reporting::term::termcolor::{ColorChoice, StandardStream};
-
-        let config = term::Config::default();
-        let mut files = SimpleFiles::new();
-        let file_name = self.source_ref.file_name.as_deref().unwrap_or("input");
-        let contents = self.source_ref.file_contents.as_deref().unwrap_or_default();
-        let file_id = files.add(file_name, contents);
-        let diagnostic = Diagnostic::error()
-            .with_message(&self.message)
-            .with_labels(vec![Label::primary(
-                file_id,
-                self.source_ref.start..self.source_ref.end,
-            )]);
-        let mut writer = StandardStream::stderr(ColorChoice::Always);
-        term::emit(&mut writer, &config, &files, &diagnostic).unwrap()
+        self.source_ref
+            .output_to_stderr(Severity::Error, &self.message)
     }
 
     pub fn message(&self) -> &str {
@@ -0,0 +1,164 @@
+//! Warnings about code that is valid but likely not what was intended.
+
+use std::fmt::{self, Display, Formatter};
+
+use codespan_reporting::diagnostic::Severity;
+
+use crate::SourceRef;
+
+/// The prefix of a comment that suppresses lints, as in `// lint: allow(unused-let, shadowed-name)`.
+/// The comment applies to the line it is on and to the line after it.
+const ALLOW_COMMENT: &str = "lint: allow(";
+
+/// A kind of warning that can be suppressed individually.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Lint {
+    /// A `let` binding that is never referenced.
+    UnusedLet,
+    /// A `use` statement whose symbol is never referenced.
+    UnusedImport,
+    /// A witness column that is never referenced.
+    UnusedWitnessColumn,
+    /// A register that is not used by any function or instruction.
+    UnusedRegister,
+    /// A local variable with the same name as a symbol it hides.
+    ShadowedName,
+    /// An operation that is not the target of any link.
+    UnlinkedOperation,
+    /// A call to a query function whose result is ignored.
+    UnusedQueryResult,
+    /// An instruction that is not used by any function.
+    UnusedInstruction,
+    /// A match arm that can never be reached.
+    UnreachablePattern,
+}
+
+impl Lint {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Lint::UnusedLet => "unused-let",
+            Lint::UnusedImport => "unused-import",
+            Lint::UnusedWitnessColumn => "unused-witness-column",
+            Lint::UnusedRegister => "unused-register",
+            Lint::ShadowedName => "shadowed-name",
+            Lint::UnlinkedOperation => "unlinked-operation",
+            Lint::UnusedQueryResult => "unused-query-result",
+            Lint::UnusedInstruction => "unused-instruction",
+            Lint::UnreachablePattern => "unreachable-pattern",
+        }
+    }
+}
+
+impl Display for Lint {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Warning {
+    lint: Lint,
+    source_ref: SourceRef,
+    message: String,
+}
+
+impl Display for Warning {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} [{}] at {:?}",
+            self.message, self.lint, self.source_ref
+        )
+    }
+}
+
+impl Warning {
+    pub fn new(lint: Lint, source_ref: SourceRef, message: String) -> Self {
+        Self {
+            lint,
+            source_ref,
+            message,
+        }
+    }
+
+    pub fn output_to_stderr(&self) {
+        self.source_ref.output_to_stderr(
+            Severity::Warning,
+            &format!("{} [{}]", self.message, self.lint),
+        )
+    }
+
+    pub fn lint(&self) -> Lint {
+        self.lint
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    pub fn source_ref(&self) -> &SourceRef {
+        &self.source_ref
+    }
+
+    /// Returns true if the lint is allowed by a comment on the line the warning
+    /// starts on or on the line before.
+    pub fn is_suppressed(&self) -> bool {
+        let Some(contents) = self.source_ref.file_contents.as_deref() else {
+            return false;
+        };
+        let start = self.source_ref.start.min(contents.len());
+        let line_start = contents[..start].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = contents[start..]
+            .find('\n')
+            .map_or(contents.len(), |i| start + i);
+        let previous_line_start = contents[..line_start.saturating_sub(1)]
+            .rfind('\n')
+            .map_or(0, |i| i + 1);
+        contents[previous_line_start..line_end]
+            .lines()
+            .any(|line| allowed_lints(line).any(|name| name == self.lint.name()))
+    }
+}
+
+/// Returns the names of the lints allowed by a comment in the line.
+fn allowed_lints(line: &str) -> impl Iterator<Item = &str> {
+    line.split_once("//")
+        .and_then(|(_, comment)| comment.split_once(ALLOW_COMMENT))
+        .and_then(|(_, allowed)| allowed.split_once(')'))
+        .into_iter()
+        .flat_map(|(names, _)| names.split(','))
+        .map(str::trim)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn warning_at(contents: &str, pattern: &str, lint: Lint) -> Warning {
+        let start = contents.find(pattern).unwrap();
+        SourceRef {
+            file_name: None,
+            file_contents: Some(contents.into()),
+            start,
+            end: start + pattern.len(),
+        }
+        .with_warning(lint, String::new())
+    }
+
+    #[test]
+    fn suppression() {
+        let contents = r#"
+    let a = 1; // lint: allow(unused-let)
+    // lint: allow(shadowed-name, unused-let)
+    let b = 2;
+    let c = 3;
+    let d = 4; // lint: allow(shadowed-name)
+"#;
+        assert!(warning_at(contents, "a = 1", Lint::UnusedLet).is_suppressed());
+        assert!(warning_at(contents, "b = 2", Lint::UnusedLet).is_suppressed());
+        assert!(warning_at(contents, "b = 2", Lint::ShadowedName).is_suppressed());
+        assert!(!warning_at(contents, "c = 3", Lint::UnusedLet).is_suppressed());
+        assert!(!warning_at(contents, "d = 4", Lint::UnusedLet).is_suppressed());
+        assert!(warning_at(contents, "d = 4", Lint::ShadowedName).is_suppressed());
+    }
+}
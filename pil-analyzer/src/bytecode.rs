@@ -0,0 +1,525 @@
+//! Compiles expressions to a compact bytecode and runs it on a small stack-based VM.
+//!
+//! Each expression and closure body is compiled the first time it is evaluated or called.
+//! The code is stored in the [CodeCache] of the symbol lookup, which is shared by all
+//! evaluations using that lookup. If the symbol lookup does not provide a cache, the code
+//! is only re-used within a single evaluation. Local variables are resolved to slots
+//! in the current frame, builtin functions and constant literals are resolved at compile time
+//! and the values of symbols (which includes the dispatch of trait functions) are cached
+//! within an evaluation after the first lookup, as long as their type arguments do not depend
+//! on type variables.
+//!
+//! Like the tree-walking interpreter, the VM does not use the native stack for function calls,
+//! which allows arbitrarily deep recursion in PIL.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+
+use powdr_ast::{
+    analyzed::{Expression, PolynomialReference, Reference},
+    parsed::{
+        types::Type, ArrayLiteral, BinaryOperation, BinaryOperator, BlockExpression, FunctionCall,
+        IfExpression, IndexAccess, LambdaExpression, LetStatementInsideBlock, MatchArm,
+        MatchExpression, Number, StatementInsideBlock, UnaryOperation, UnaryOperator,
+    },
+};
+use powdr_number::{BigUint, FieldElement};
+use powdr_parser_util::SourceRef;
+
+use crate::evaluator::{
+    builtin_function, evaluate_binary_operation, evaluate_builtin_function, evaluate_condition,
+    evaluate_index_access, evaluate_let_statement, evaluate_literal, evaluate_unary_operation,
+    Closure, EvalError, SymbolLookup, Value,
+};
+
+/// A single instruction of the VM. Unless noted otherwise, instructions pop their
+/// operands from the value stack and push their result.
+enum Instr<'a, T> {
+    /// Pushes a value computed at compile time.
+    Const(Arc<Value<'a, T>>),
+    /// Pushes a number literal whose type is only known at runtime.
+    Literal(&'a BigUint, &'a Option<Type<u64>>),
+    /// Pushes the local variable with the given index.
+    Local(usize),
+    /// Pushes the value of the symbol.
+    Symbol(&'a PolynomialReference),
+    /// Creates a tuple from the given number of values.
+    Tuple(usize),
+    /// Creates an array from the given number of values.
+    Array(usize),
+    Binary(BinaryOperator),
+    Unary(UnaryOperator),
+    /// Accesses an array, the expression is used for error reporting.
+    Index(&'a Expression),
+    /// Calls a function on the given number of arguments, which are on top of the function.
+    Call(usize),
+    /// Creates a closure capturing the current local variables.
+    Closure(&'a LambdaExpression<Expression>),
+    Jump(usize),
+    /// Pops a boolean and jumps if it is false.
+    JumpIfFalse(usize),
+    /// Pops a value, binds the variables of the first matching arm and jumps to its code.
+    Match(&'a [MatchArm<Expression>], Vec<usize>),
+    /// Pops the value of the let statement (if it has one) and binds its variables.
+    Let(&'a LetStatementInsideBlock<Expression>),
+    /// Pops a value and adds it as proof item (unless it is the empty tuple).
+    AddProofItem,
+    /// Remembers the current number of local variables.
+    SaveLocals,
+    /// Truncates the local variables to the most recently remembered number.
+    RestoreLocals,
+    /// Fails for an expression that cannot be evaluated.
+    Unsupported(&'a Expression),
+}
+
+struct Code<'a, T>(Vec<Instr<'a, T>>);
+
+impl<'a, T: FieldElement> Code<'a, T> {
+    fn compile(expr: &'a Expression) -> Self {
+        let mut code = Code(vec![]);
+        code.expression(expr);
+        code
+    }
+
+    fn emit(&mut self, instr: Instr<'a, T>) -> usize {
+        self.0.push(instr);
+        self.0.len() - 1
+    }
+
+    /// Sets the target of the jump instruction at `index` to the next instruction.
+    fn patch_jump(&mut self, index: usize) {
+        let target = self.0.len();
+        match &mut self.0[index] {
+            Instr::Jump(t) | Instr::JumpIfFalse(t) => *t = target,
+            _ => unreachable!(),
+        }
+    }
+
+    fn expression(&mut self, expr: &'a Expression) {
+        match expr {
+            Expression::Reference(_, Reference::LocalVar(i, _)) => {
+                self.emit(Instr::Local(*i as usize));
+            }
+            Expression::Reference(_, Reference::Poly(poly)) => {
+                self.emit(match builtin_function(&poly.name) {
+                    Some(b) => Instr::Const(Value::BuiltinFunction(b).into()),
+                    None => Instr::Symbol(poly),
+                });
+            }
+            Expression::Number(_, Number { value: n, type_ }) => {
+                // Literals of generic type and literals that fail to evaluate
+                // (which is reported when they are reached) are evaluated at runtime.
+                let value = match type_ {
+                    Some(Type::TypeVar(_)) => None,
+                    _ => evaluate_literal(n.clone(), type_, &Default::default()).ok(),
+                };
+                self.emit(match value {
+                    Some(value) => Instr::Const(value),
+                    None => Instr::Literal(n, type_),
+                });
+            }
+            Expression::String(_, s) => {
+                self.emit(Instr::Const(Value::String(s.clone()).into()));
+            }
+            Expression::Tuple(_, items) => {
+                items.iter().for_each(|item| self.expression(item));
+                self.emit(Instr::Tuple(items.len()));
+            }
+            Expression::ArrayLiteral(_, ArrayLiteral { items }) => {
+                items.iter().for_each(|item| self.expression(item));
+                self.emit(Instr::Array(items.len()));
+            }
+            Expression::BinaryOperation(_, BinaryOperation { left, op, right }) => {
+                self.expression(left);
+                self.expression(right);
+                self.emit(Instr::Binary(*op));
+            }
+            Expression::UnaryOperation(_, UnaryOperation { op, expr: inner }) => {
+                self.expression(inner);
+                self.emit(Instr::Unary(*op));
+            }
+            Expression::LambdaExpression(_, lambda) => {
+                self.emit(Instr::Closure(lambda));
+            }
+            Expression::IndexAccess(_, IndexAccess { array, index }) => {
+                self.expression(array);
+                self.expression(index);
+                self.emit(Instr::Index(expr));
+            }
+            Expression::FunctionCall(
+                _,
+                FunctionCall {
+                    function,
+                    arguments,
+                },
+            ) => {
+                self.expression(function);
+                arguments.iter().for_each(|arg| self.expression(arg));
+                self.emit(Instr::Call(arguments.len()));
+            }
+            Expression::MatchExpression(_, MatchExpression { scrutinee, arms }) => {
+                self.expression(scrutinee);
+                let match_instr = self.emit(Instr::Match(arms, vec![]));
+                let mut targets = vec![];
+                let mut jumps_to_end = vec![];
+                for MatchArm { value, .. } in arms {
+                    targets.push(self.0.len());
+                    self.expression(value);
+                    self.emit(Instr::RestoreLocals);
+                    jumps_to_end.push(self.emit(Instr::Jump(0)));
+                }
+                jumps_to_end.into_iter().for_each(|j| self.patch_jump(j));
+                let Instr::Match(_, arm_targets) = &mut self.0[match_instr] else {
+                    unreachable!()
+                };
+                *arm_targets = targets;
+            }
+            Expression::IfExpression(
+                _,
+                IfExpression {
+                    condition,
+                    body,
+                    else_body,
+                },
+            ) => {
+                self.expression(condition);
+                let jump_to_else = self.emit(Instr::JumpIfFalse(0));
+                self.expression(body);
+                let jump_to_end = self.emit(Instr::Jump(0));
+                self.patch_jump(jump_to_else);
+                self.expression(else_body);
+                self.patch_jump(jump_to_end);
+            }
+            Expression::BlockExpression(_, BlockExpression { statements, expr }) => {
+                self.emit(Instr::SaveLocals);
+                for statement in statements {
+                    match statement {
+                        StatementInsideBlock::LetStatement(s) => {
+                            if let Some(value) = &s.value {
+                                self.expression(value);
+                            }
+                            self.emit(Instr::Let(s));
+                        }
+                        StatementInsideBlock::Expression(expr) => {
+                            self.expression(expr);
+                            self.emit(Instr::AddProofItem);
+                        }
+                    }
+                }
+                match expr {
+                    Some(expr) => self.expression(expr),
+                    None => {
+                        self.emit(Instr::Const(Value::Tuple(vec![]).into()));
+                    }
+                }
+                self.emit(Instr::RestoreLocals);
+            }
+            Expression::FreeInput(_, _) | Expression::StructExpression(_, _) => {
+                self.emit(Instr::Unsupported(expr));
+            }
+        }
+    }
+}
+
+/// Code compiled from expressions, shared by all evaluations that use the same symbol lookup.
+///
+/// Expressions are identified by their address, which is stable because
+/// they are borrowed for the lifetime of the cache.
+pub struct CodeCache<'a, T> {
+    code: RwLock<HashMap<usize, Arc<Code<'a, T>>>>,
+}
+
+impl<T> Default for CodeCache<'_, T> {
+    fn default() -> Self {
+        Self {
+            code: Default::default(),
+        }
+    }
+}
+
+impl<'a, T: FieldElement> CodeCache<'a, T> {
+    /// Returns the number of compiled expressions.
+    pub fn len(&self) -> usize {
+        self.code.read().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the code of the expression, compiling it if it is not yet in the cache.
+    fn get_or_compile(&self, expr: &'a Expression) -> Arc<Code<'a, T>> {
+        let key = expr as *const Expression as usize;
+        if let Some(code) = self.code.read().unwrap().get(&key) {
+            return code.clone();
+        }
+        self.code
+            .write()
+            .unwrap()
+            .entry(key)
+            .or_insert_with(|| Arc::new(Code::compile(expr)))
+            .clone()
+    }
+}
+
+/// The state of a function call.
+struct Frame<'a, T> {
+    code: Arc<Code<'a, T>>,
+    /// The index of the next instruction.
+    pc: usize,
+    local_vars: Vec<Arc<Value<'a, T>>>,
+    type_args: HashMap<String, Type>,
+    /// The numbers of local variables remembered by `SaveLocals`.
+    saved_locals: Vec<usize>,
+}
+
+impl<'a, T> Frame<'a, T> {
+    fn new(
+        code: Arc<Code<'a, T>>,
+        local_vars: Vec<Arc<Value<'a, T>>>,
+        type_args: HashMap<String, Type>,
+    ) -> Self {
+        Self {
+            code,
+            pc: 0,
+            local_vars,
+            type_args,
+            saved_locals: vec![],
+        }
+    }
+}
+
+pub(crate) struct Vm<'a, 'b, T, S> {
+    symbols: &'b mut S,
+    /// Compiled code, used if the symbol lookup does not provide a cache.
+    code_cache: CodeCache<'a, T>,
+    /// Values of symbols with concrete type arguments, by the address of the reference.
+    symbol_cache: HashMap<*const PolynomialReference, Arc<Value<'a, T>>>,
+    frame: Frame<'a, T>,
+    call_stack: Vec<Frame<'a, T>>,
+    value_stack: Vec<Arc<Value<'a, T>>>,
+}
+
+impl<'a, 'b, T: FieldElement, S: SymbolLookup<'a, T>> Vm<'a, 'b, T, S> {
+    fn new(symbols: &'b mut S, type_args: HashMap<String, Type>) -> Self {
+        Self {
+            symbols,
+            code_cache: Default::default(),
+            symbol_cache: Default::default(),
+            frame: Frame::new(Arc::new(Code(vec![])), vec![], type_args),
+            call_stack: vec![],
+            value_stack: vec![],
+        }
+    }
+
+    pub(crate) fn evaluate_expression(
+        symbols: &'b mut S,
+        expr: &'a Expression,
+        type_args: HashMap<String, Type>,
+    ) -> Result<Arc<Value<'a, T>>, EvalError> {
+        let mut vm = Self::new(symbols, type_args);
+        vm.frame.code = vm.code(expr);
+        vm.run()
+    }
+
+    pub(crate) fn evaluate_function_call(
+        symbols: &'b mut S,
+        function: Arc<Value<'a, T>>,
+        arguments: Vec<Arc<Value<'a, T>>>,
+        type_args: HashMap<String, Type>,
+    ) -> Result<Arc<Value<'a, T>>, EvalError> {
+        let mut vm = Self::new(symbols, type_args);
+        vm.call(function, arguments)?;
+        vm.run()
+    }
+
+    /// Runs until the code of the outermost frame is finished and returns the result.
+    fn run(&mut self) -> Result<Arc<Value<'a, T>>, EvalError> {
+        'frames: loop {
+            let code = self.frame.code.clone();
+            while let Some(instr) = code.0.get(self.frame.pc) {
+                self.frame.pc += 1;
+                match instr {
+                    Instr::Const(value) => self.value_stack.push(value.clone()),
+                    Instr::Literal(n, ty) => self.value_stack.push(evaluate_literal(
+                        (*n).clone(),
+                        ty,
+                        &self.frame.type_args,
+                    )?),
+                    Instr::Local(i) => self.value_stack.push(self.frame.local_vars[*i].clone()),
+                    Instr::Symbol(poly) => {
+                        let value = self.symbol(*poly)?;
+                        self.value_stack.push(value)
+                    }
+                    Instr::Tuple(len) => {
+                        let items = self.pop_values(*len);
+                        self.value_stack.push(Value::Tuple(items).into())
+                    }
+                    Instr::Array(len) => {
+                        let items = self.pop_values(*len);
+                        self.value_stack.push(Value::Array(items).into())
+                    }
+                    Instr::Binary(op) => {
+                        let right = self.pop_value();
+                        let left = self.pop_value();
+                        self.value_stack
+                            .push(evaluate_binary_operation(left, *op, right)?)
+                    }
+                    Instr::Unary(op) => {
+                        let inner = self.pop_value();
+                        self.value_stack.push(evaluate_unary_operation(*op, inner)?)
+                    }
+                    Instr::Index(expr) => {
+                        let index = self.pop_value();
+                        let array = self.pop_value();
+                        self.value_stack
+                            .push(evaluate_index_access(&array, &index, expr)?)
+                    }
+                    Instr::Call(len) => {
+                        let arguments = self.pop_values(*len);
+                        let function = self.pop_value();
+                        if self.call(function, arguments)? {
+                            continue 'frames;
+                        }
+                    }
+                    Instr::Closure(lambda) => self.value_stack.push(
+                        Value::from(Closure {
+                            lambda: *lambda,
+                            environment: self.frame.local_vars.clone(),
+                            type_args: self.frame.type_args.clone(),
+                        })
+                        .into(),
+                    ),
+                    Instr::Jump(target) => self.frame.pc = *target,
+                    Instr::JumpIfFalse(target) => {
+                        if !evaluate_condition(&self.pop_value())? {
+                            self.frame.pc = *target;
+                        }
+                    }
+                    Instr::Match(arms, targets) => {
+                        let v = self.pop_value();
+                        let (target, vars) = arms
+                            .iter()
+                            .zip(targets)
+                            .find_map(|(MatchArm { pattern, .. }, target)| {
+                                Value::try_match_pattern(&v, pattern).map(|vars| (*target, vars))
+                            })
+                            .ok_or_else(EvalError::NoMatch)?;
+                        self.frame.saved_locals.push(self.frame.local_vars.len());
+                        self.frame.local_vars.extend(vars);
+                        self.frame.pc = target;
+                    }
+                    Instr::Let(s) => {
+                        let value = s.value.as_ref().map(|_| self.pop_value());
+                        let vars = evaluate_let_statement(*s, value, self.symbols)?;
+                        self.frame.local_vars.extend(vars);
+                    }
+                    Instr::AddProofItem => {
+                        let result = self.pop_value();
+                        match result.as_ref() {
+                            Value::Tuple(t) if t.is_empty() => {}
+                            _ => self.symbols.add_proof_items(result, SourceRef::unknown())?,
+                        }
+                    }
+                    Instr::SaveLocals => self.frame.saved_locals.push(self.frame.local_vars.len()),
+                    Instr::RestoreLocals => {
+                        let len = self.frame.saved_locals.pop().unwrap();
+                        self.frame.local_vars.truncate(len);
+                    }
+                    Instr::Unsupported(expr) => match expr {
+                        Expression::FreeInput(_, _) => Err(EvalError::Unsupported(
+                            "Cannot evaluate free input.".to_string(),
+                        ))?,
+                        _ => unimplemented!("Struct expressions are not yet supported."),
+                    },
+                }
+            }
+            // The current function is finished, its result is on top of the value stack.
+            match self.call_stack.pop() {
+                Some(caller) => self.frame = caller,
+                None => break,
+            }
+        }
+        assert_eq!(self.value_stack.len(), 1);
+        Ok(self.value_stack.pop().unwrap())
+    }
+
+    /// Calls a function. Returns true if a new frame was entered, otherwise the
+    /// result is already on the value stack.
+    fn call(
+        &mut self,
+        function: Arc<Value<'a, T>>,
+        arguments: Vec<Arc<Value<'a, T>>>,
+    ) -> Result<bool, EvalError> {
+        match function.as_ref() {
+            Value::BuiltinFunction(b) => {
+                let result = evaluate_builtin_function(*b, arguments, self.symbols)?;
+                self.value_stack.push(result);
+                Ok(false)
+            }
+            Value::TypeConstructor(type_constructor) => {
+                self.value_stack
+                    .push(Value::Enum(type_constructor.to_enum_value(arguments)).into());
+                Ok(false)
+            }
+            Value::Closure(closure) => {
+                let lambda: &'a LambdaExpression<Expression> = closure.lambda;
+                let code = self.code(&lambda.body);
+                let callee = Frame::new(
+                    code,
+                    closure.local_vars(&arguments),
+                    closure.type_args.clone(),
+                );
+                let caller = std::mem::replace(&mut self.frame, callee);
+                // A call at the very end of a function does not need to return to it.
+                if caller.pc < caller.code.0.len() {
+                    self.call_stack.push(caller);
+                }
+                Ok(true)
+            }
+            e => panic!("Expected function but got {e}"),
+        }
+    }
+
+    /// Returns the compiled code of the expression.
+    fn code(&self, expr: &'a Expression) -> Arc<Code<'a, T>> {
+        self.symbols
+            .code_cache()
+            .unwrap_or(&self.code_cache)
+            .get_or_compile(expr)
+    }
+
+    /// Returns the value of a symbol, using the cache if the type arguments are concrete.
+    fn symbol(&mut self, poly: &'a PolynomialReference) -> Result<Arc<Value<'a, T>>, EvalError> {
+        let key = poly as *const PolynomialReference;
+        if let Some(value) = self.symbol_cache.get(&key) {
+            return Ok(value.clone());
+        }
+        let is_concrete = poly
+            .type_args
+            .iter()
+            .flatten()
+            .all(|ty| ty.is_concrete_type());
+        let type_args = poly.type_args.clone().map(|mut type_args| {
+            for ty in &mut type_args {
+                ty.substitute_type_vars(&self.frame.type_args);
+            }
+            type_args
+        });
+        let value = self.symbols.lookup(&poly.name, &type_args)?;
+        if is_concrete {
+            self.symbol_cache.insert(key, value.clone());
+        }
+        Ok(value)
+    }
+
+    fn pop_value(&mut self) -> Arc<Value<'a, T>> {
+        self.value_stack.pop().unwrap()
+    }
+
+    fn pop_values(&mut self, len: usize) -> Vec<Arc<Value<'a, T>>> {
+        self.value_stack.split_off(self.value_stack.len() - len)
+    }
+}
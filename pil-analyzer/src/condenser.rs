@@ -27,7 +27,8 @@ use powdr_parser_util::SourceRef;
 
 use crate::{
     evaluator::{
-        self, evaluate_function_call, Definitions, EnumValue, EvalError, SymbolLookup, Value,
+        self, evaluate_function_call, CodeCache, Definitions, EnumValue, EvalError, SymbolLookup,
+        Value,
     },
     expressionizer::{try_to_function_value_definition, try_value_to_expression},
     statement_processor::Counters,
@@ -205,6 +206,8 @@ pub struct Condenser<'a, T> {
     solved_impls: &'a SolvedTraitImpls,
     /// Evaluation cache.
     symbol_values: SymbolCache<'a, T>,
+    /// Compiled code of the evaluated expressions.
+    code_cache: CodeCache<'a, T>,
     /// Mapping from polynomial ID to name (does not contain array elements),
     /// updated with new columns.
     poly_id_to_name: BTreeMap<(PolynomialType, u64), String>,
@@ -247,6 +250,7 @@ impl<'a, T: FieldElement> Condenser<'a, T> {
             symbols,
             solved_impls,
             symbol_values: Default::default(),
+            code_cache: Default::default(),
             poly_id_to_name,
             namespace: Default::default(),
             counters,
@@ -652,6 +656,10 @@ impl<'a, T: FieldElement> SymbolLookup<'a, T> for Condenser<'a, T> {
         }
         Ok(())
     }
+
+    fn code_cache(&self) -> Option<&CodeCache<'a, T>> {
+        Some(&self.code_cache)
+    }
 }
 
 impl<T: FieldElement> Condenser<'_, T> {
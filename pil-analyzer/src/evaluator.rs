@@ -25,6 +25,9 @@ use powdr_ast::{
 use powdr_number::{BigInt, BigUint, FieldElement, LargeInt};
 use powdr_parser_util::SourceRef;
 
+pub use crate::bytecode::CodeCache;
+use crate::bytecode::Vm;
+
 /// Evaluates an expression given a hash map of definitions.
 pub fn evaluate_expression<'a, T: FieldElement>(
     expr: &'a Expression,
@@ -55,7 +58,7 @@ pub fn evaluate_generic<'a, 'b, T: FieldElement>(
     type_args: &'b HashMap<String, Type>,
     symbols: &mut impl SymbolLookup<'a, T>,
 ) -> Result<Arc<Value<'a, T>>, EvalError> {
-    Evaluator::evaluate_expression(symbols, expr, type_args.clone())
+    Vm::evaluate_expression(symbols, expr, type_args.clone())
 }
 
 /// Evaluates a function call.
@@ -63,6 +66,26 @@ pub fn evaluate_function_call<'a, T: FieldElement>(
     function: Arc<Value<'a, T>>,
     arguments: Vec<Arc<Value<'a, T>>>,
     symbols: &mut impl SymbolLookup<'a, T>,
+) -> Result<Arc<Value<'a, T>>, EvalError> {
+    Vm::evaluate_function_call(symbols, function, arguments, Default::default())
+}
+
+/// Like [evaluate_generic], but uses the tree-walking interpreter instead of compiling
+/// to bytecode. Both give the same results; this is kept for comparison in tests and benchmarks.
+pub fn evaluate_generic_tree_walking<'a, 'b, T: FieldElement>(
+    expr: &'a Expression,
+    type_args: &'b HashMap<String, Type>,
+    symbols: &mut impl SymbolLookup<'a, T>,
+) -> Result<Arc<Value<'a, T>>, EvalError> {
+    Evaluator::evaluate_expression(symbols, expr, type_args.clone())
+}
+
+/// Like [evaluate_function_call], but uses the tree-walking interpreter instead of compiling
+/// to bytecode. Note that symbols are still evaluated through [evaluate_generic].
+pub fn evaluate_function_call_tree_walking<'a, T: FieldElement>(
+    function: Arc<Value<'a, T>>,
+    arguments: Vec<Arc<Value<'a, T>>>,
+    symbols: &mut impl SymbolLookup<'a, T>,
 ) -> Result<Arc<Value<'a, T>>, EvalError> {
     Evaluator::evaluate_function_call(symbols, function, arguments, Default::default())
 }
@@ -108,7 +131,8 @@ pub enum EvalError {
     Unsupported(String),
     /// Array index access out of bounds.
     OutOfBounds(String),
-    /// Unable to match pattern. TODO As soon as we have "Option", patterns should be exhaustive
+    /// Unable to match pattern. Should not happen for match expressions of
+    /// analyzed code, since they are checked to be exhaustive.
     /// This error occurs quite often and thus should not require allocation.
     NoMatch(),
     /// Reference to an undefined symbol
@@ -527,6 +551,28 @@ impl<T> Closure<'_, T> {
     }
 }
 
+impl<'a, T: FieldElement> Closure<'a, T> {
+    /// Returns the local variables for evaluating the body of the closure
+    /// on the given arguments: The environment followed by the matched parameters.
+    pub(crate) fn local_vars(&self, arguments: &[Arc<Value<'a, T>>]) -> Vec<Arc<Value<'a, T>>> {
+        assert_eq!(self.lambda.params.len(), arguments.len());
+        let matched_arguments =
+            arguments
+                .iter()
+                .zip(&self.lambda.params)
+                .flat_map(|(arg, pattern)| {
+                    Value::try_match_pattern(arg, pattern).unwrap_or_else(|| {
+                        panic!("Irrefutable pattern did not match: {pattern} = {arg}")
+                    })
+                });
+        self.environment
+            .iter()
+            .cloned()
+            .chain(matched_arguments)
+            .collect()
+    }
+}
+
 pub struct Definitions<'a> {
     pub definitions: &'a HashMap<String, (Symbol, Option<FunctionValueDefinition>)>,
     pub solved_impls: &'a SolvedTraitImpls,
@@ -778,6 +824,12 @@ pub trait SymbolLookup<'a, T: FieldElement> {
             "Tried to output to channel outside of prover function.".to_string(),
         ))
     }
+
+    /// Returns the cache for the code compiled from the evaluated expressions.
+    /// Without a cache, the code is compiled again in every evaluation.
+    fn code_cache(&self) -> Option<&CodeCache<'a, T>> {
+        None
+    }
 }
 
 /// Operations to be performed by the evaluator.
@@ -986,26 +1038,9 @@ impl<'a, 'b, T: FieldElement, S: SymbolLookup<'a, T>> Evaluator<'a, 'b, T, S> {
         &mut self,
         s: &'a LetStatementInsideBlock<Expression>,
     ) -> Result<(), EvalError> {
-        let value = if s.value.is_none()
-            || matches!(&s.ty, Some(Type::Col) | Some(Type::Inter))
-            || matches!(&s.ty, Some(Type::Array(ArrayType { base, .. })) if matches!(base.as_ref(), Type::Col | Type::Inter))
-        {
-            // Dynamic column creation
-            let Pattern::Variable(_, name) = &s.pattern else {
-                unreachable!()
-            };
-            let value = s.value.as_ref().map(|_| self.value_stack.pop().unwrap());
-            self.symbols
-                .new_column(name, s.ty.as_ref(), None, value, SourceRef::unknown())?
-        } else {
-            // Regular local variable declaration.
-            self.value_stack.pop().unwrap()
-        };
-        self.local_vars.extend(
-            Value::try_match_pattern(&value, &s.pattern).unwrap_or_else(|| {
-                panic!("Irrefutable pattern did not match: {} = {value}", s.pattern)
-            }),
-        );
+        let value = s.value.as_ref().map(|_| self.value_stack.pop().unwrap());
+        let vars = evaluate_let_statement(s, value, self.symbols)?;
+        self.local_vars.extend(vars);
         Ok(())
     }
 
@@ -1016,8 +1051,8 @@ impl<'a, 'b, T: FieldElement, S: SymbolLookup<'a, T>> Evaluator<'a, 'b, T, S> {
         Ok(match reference {
             Reference::LocalVar(i, _name) => self.local_vars[*i as usize].clone(),
             Reference::Poly(poly) => {
-                if let Some((_, b)) = BUILTINS.iter().find(|(n, _)| (n == &poly.name)) {
-                    Value::BuiltinFunction(*b).into()
+                if let Some(b) = builtin_function(&poly.name) {
+                    Value::BuiltinFunction(b).into()
                 } else {
                     let type_args = poly.type_args.clone().map(|mut ta| {
                         for ty in &mut ta {
@@ -1053,58 +1088,12 @@ impl<'a, 'b, T: FieldElement, S: SymbolLookup<'a, T>> Evaluator<'a, 'b, T, S> {
             }
             Expression::UnaryOperation(_, UnaryOperation { op, .. }) => {
                 let inner = self.value_stack.pop().unwrap();
-                match (op, inner.as_ref()) {
-                    (UnaryOperator::Minus, Value::FieldElement(e)) => {
-                        Value::FieldElement(-*e).into()
-                    }
-                    (UnaryOperator::LogicalNot, Value::Bool(b)) => Value::Bool(!b).into(),
-                    (UnaryOperator::Minus, Value::Integer(n)) => Value::Integer(-n).into(),
-                    (UnaryOperator::Next, Value::Expression(e)) => e
-                        .clone()
-                        .next()
-                        .map(|next| Value::from(next).into())
-                        // a reference already had its `next` flag on
-                        .map_err(|reference| {
-                            EvalError::TypeError(format!(
-                                "Double application of \"'\" on: {}",
-                                reference.name
-                            ))
-                        })?,
-                    (op, Value::Expression(e)) => Value::from(AlgebraicExpression::new_unary(
-                        (*op).try_into().unwrap(),
-                        e.clone(),
-                    ))
-                    .into(),
-                    (_, inner) => Err(EvalError::TypeError(format!(
-                        "Operator \"{op}\" not supported on types: {inner}: {}",
-                        inner.type_formatted()
-                    )))?,
-                }
+                evaluate_unary_operation(*op, inner)?
             }
             Expression::IndexAccess(_, _) => {
                 let index = self.value_stack.pop().unwrap();
                 let array = self.value_stack.pop().unwrap();
-                let Value::Array(elements) = array.as_ref() else {
-                    panic!()
-                };
-                match index.as_ref() {
-                    Value::Integer(index)
-                        if index.is_negative()
-                            || *index >= (elements.len() as u64).into() =>
-                    {
-                        Err(EvalError::OutOfBounds(format!(
-                            "Index access out of bounds: Tried to access element {index} of array of size {} in: {expr}.",
-                            elements.len()
-                        )))?
-                    }
-                    Value::Integer(index) => {
-                        elements[usize::try_from(index).unwrap()].clone()
-                    }
-                    index => Err(EvalError::TypeError(format!(
-                            "Expected integer for array index access but got {index}: {}",
-                            index.type_formatted()
-                    )))?,
-                }
+                evaluate_index_access(&array, &index, expr)?
             }
             Expression::FunctionCall(_, FunctionCall { arguments, .. }) => {
                 let arguments = self
@@ -1135,13 +1124,11 @@ impl<'a, 'b, T: FieldElement, S: SymbolLookup<'a, T>> Evaluator<'a, 'b, T, S> {
                 },
             ) => {
                 let v = self.value_stack.pop().unwrap();
-                let condition = match v.as_ref() {
-                    Value::Bool(b) => Ok(b),
-                    x => Err(EvalError::TypeError(format!(
-                        "Expected boolean value but got {x}"
-                    ))),
-                }?;
-                let body = if *condition { body } else { else_body };
+                let body = if evaluate_condition(&v)? {
+                    body
+                } else {
+                    else_body
+                };
                 return self.expand(body);
             }
 
@@ -1164,27 +1151,11 @@ impl<'a, 'b, T: FieldElement, S: SymbolLookup<'a, T>> Evaluator<'a, 'b, T, S> {
             Value::TypeConstructor(type_constructor) => self
                 .value_stack
                 .push(Value::Enum(type_constructor.to_enum_value(arguments)).into()),
-            Value::Closure(Closure {
-                lambda,
-                environment,
-                type_args,
-            }) => {
-                assert_eq!(lambda.params.len(), arguments.len());
-                let matched_arguments =
-                    arguments
-                        .iter()
-                        .zip(&lambda.params)
-                        .flat_map(|(arg, pattern)| {
-                            Value::try_match_pattern(arg, pattern).unwrap_or_else(|| {
-                                panic!("Irrefutable pattern did not match: {pattern} = {arg}")
-                            })
-                        });
-
-                let local_vars = environment
-                    .iter()
-                    .cloned()
-                    .chain(matched_arguments)
-                    .collect::<Vec<_>>();
+            Value::Closure(closure) => {
+                let Closure {
+                    lambda, type_args, ..
+                } = closure;
+                let local_vars = closure.local_vars(&arguments);
 
                 self.op_stack.push(Operation::SetEnvironment(
                     std::mem::take(&mut self.local_vars),
@@ -1200,7 +1171,101 @@ impl<'a, 'b, T: FieldElement, S: SymbolLookup<'a, T>> Evaluator<'a, 'b, T, S> {
     }
 }
 
-fn evaluate_literal<'a, T: FieldElement>(
+/// Evaluates a let statement given the value (if any) and returns the values of the
+/// variables in its pattern. Creates a new column if the statement declares one.
+pub(crate) fn evaluate_let_statement<'a, T: FieldElement>(
+    s: &'a LetStatementInsideBlock<Expression>,
+    value: Option<Arc<Value<'a, T>>>,
+    symbols: &mut impl SymbolLookup<'a, T>,
+) -> Result<Vec<Arc<Value<'a, T>>>, EvalError> {
+    let value = if value.is_none()
+        || matches!(&s.ty, Some(Type::Col) | Some(Type::Inter))
+        || matches!(&s.ty, Some(Type::Array(ArrayType { base, .. })) if matches!(base.as_ref(), Type::Col | Type::Inter))
+    {
+        // Dynamic column creation
+        let Pattern::Variable(_, name) = &s.pattern else {
+            unreachable!()
+        };
+        symbols.new_column(name, s.ty.as_ref(), None, value, SourceRef::unknown())?
+    } else {
+        // Regular local variable declaration.
+        value.unwrap()
+    };
+    Ok(Value::try_match_pattern(&value, &s.pattern)
+        .unwrap_or_else(|| panic!("Irrefutable pattern did not match: {} = {value}", s.pattern)))
+}
+
+/// Returns the builtin function of the given name, if there is one.
+pub(crate) fn builtin_function(name: &str) -> Option<BuiltinFunction> {
+    BUILTINS.iter().find(|(n, _)| *n == name).map(|(_, b)| *b)
+}
+
+/// Returns the value of the condition of an if expression.
+pub(crate) fn evaluate_condition<T: Display>(condition: &Value<'_, T>) -> Result<bool, EvalError> {
+    match condition {
+        Value::Bool(b) => Ok(*b),
+        x => Err(EvalError::TypeError(format!(
+            "Expected boolean value but got {x}"
+        ))),
+    }
+}
+
+pub(crate) fn evaluate_unary_operation<T: FieldElement>(
+    op: UnaryOperator,
+    inner: Arc<Value<'_, T>>,
+) -> Result<Arc<Value<'_, T>>, EvalError> {
+    Ok(match (op, inner.as_ref()) {
+        (UnaryOperator::Minus, Value::FieldElement(e)) => Value::FieldElement(-*e).into(),
+        (UnaryOperator::LogicalNot, Value::Bool(b)) => Value::Bool(!b).into(),
+        (UnaryOperator::Minus, Value::Integer(n)) => Value::Integer(-n).into(),
+        (UnaryOperator::Next, Value::Expression(e)) => e
+            .clone()
+            .next()
+            .map(|next| Value::from(next).into())
+            // a reference already had its `next` flag on
+            .map_err(|reference| {
+                EvalError::TypeError(format!(
+                    "Double application of \"'\" on: {}",
+                    reference.name
+                ))
+            })?,
+        (op, Value::Expression(e)) => Value::from(AlgebraicExpression::new_unary(
+            op.try_into().unwrap(),
+            e.clone(),
+        ))
+        .into(),
+        (_, inner) => Err(EvalError::TypeError(format!(
+            "Operator \"{op}\" not supported on types: {inner}: {}",
+            inner.type_formatted()
+        )))?,
+    })
+}
+
+/// Evaluates the index access expression `expr` given the values of the array and the index.
+pub(crate) fn evaluate_index_access<'a, T: FieldElement>(
+    array: &Value<'a, T>,
+    index: &Value<'a, T>,
+    expr: &Expression,
+) -> Result<Arc<Value<'a, T>>, EvalError> {
+    let Value::Array(elements) = array else {
+        panic!()
+    };
+    match index {
+        Value::Integer(index) if index.is_negative() || *index >= (elements.len() as u64).into() => {
+            Err(EvalError::OutOfBounds(format!(
+                "Index access out of bounds: Tried to access element {index} of array of size {} in: {expr}.",
+                elements.len()
+            )))
+        }
+        Value::Integer(index) => Ok(elements[usize::try_from(index).unwrap()].clone()),
+        index => Err(EvalError::TypeError(format!(
+            "Expected integer for array index access but got {index}: {}",
+            index.type_formatted()
+        ))),
+    }
+}
+
+pub(crate) fn evaluate_literal<'a, T: FieldElement>(
     n: BigUint,
     ty: &Option<Type<u64>>,
     type_args: &HashMap<String, Type>,
@@ -1235,7 +1300,7 @@ fn evaluate_literal<'a, T: FieldElement>(
     .into())
 }
 
-fn evaluate_binary_operation<'a, T: FieldElement>(
+pub(crate) fn evaluate_binary_operation<'a, T: FieldElement>(
     left: Arc<Value<'a, T>>,
     op: BinaryOperator,
     right: Arc<Value<'a, T>>,
@@ -1403,7 +1468,7 @@ fn to_selected_exprs_expanded<'a, 'b, T>(
 }
 
 #[allow(clippy::print_stdout)]
-fn evaluate_builtin_function<'a, T: FieldElement>(
+pub(crate) fn evaluate_builtin_function<'a, T: FieldElement>(
     b: BuiltinFunction,
     mut arguments: Vec<Arc<Value<'a, T>>>,
     symbols: &mut impl SymbolLookup<'a, T>,
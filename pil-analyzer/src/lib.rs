@@ -1,8 +1,11 @@
+mod bytecode;
 mod call_graph;
 mod condenser;
 pub mod evaluator;
 pub mod expression_processor;
 pub(crate) mod expressionizer;
+mod lints;
+mod match_checker;
 mod pil_analyzer;
 mod side_effect_checker;
 mod statement_processor;
@@ -24,7 +27,9 @@ use powdr_ast::{
     },
 };
 
-pub use pil_analyzer::{analyze_ast, analyze_file, analyze_string};
+pub use pil_analyzer::{
+    analyze_ast, analyze_file, analyze_string, lint_ast, lint_file, lint_string,
+};
 
 pub trait AnalysisDriver: Clone + Copy {
     /// Turns a declaration into an absolute name.
@@ -0,0 +1,254 @@
+use std::collections::{HashMap, HashSet};
+use std::iter::once;
+
+use itertools::Itertools;
+
+use powdr_ast::{
+    analyzed::{
+        Expression, FunctionValueDefinition, PolynomialType, Reference, Symbol, SymbolKind,
+        TypedExpression,
+    },
+    parsed::{
+        asm::{parse_absolute_path, AbsoluteSymbolPath, SymbolPath},
+        types::{FunctionType, Type, TypeScheme},
+        visitor::{AllChildren, Children},
+        BlockExpression, FunctionCall, FunctionKind, LambdaExpression, LetStatementInsideBlock,
+        MatchArm, MatchExpression, Pattern, SourceReference, StatementInsideBlock,
+        TraitImplementation,
+    },
+};
+use powdr_parser_util::{Lint, SourceRef, Warning};
+
+use crate::side_effect_checker::function_kind_of_symbol;
+
+/// Returns warnings about unused symbols and local variables, local variables
+/// that shadow symbols and ignored results of query functions.
+/// Symbols from the standard library, automatically added symbols and symbols whose
+/// name starts with an underscore are not linted.
+pub fn lint(
+    definitions: &HashMap<String, (Symbol, Option<FunctionValueDefinition>)>,
+    proof_items: &[Expression],
+    trait_impls: &[TraitImplementation<Expression>],
+    ignored_symbols: &HashSet<String>,
+) -> Vec<Warning> {
+    let is_linted = |name: &str, source: &SourceRef| {
+        !name.starts_with("std::")
+            && !name.rsplit("::").next().unwrap().starts_with('_')
+            && !ignored_symbols.contains(name)
+            && source.file_contents.is_some()
+    };
+
+    let mut referenced = HashSet::new();
+    #[allow(clippy::iter_over_hash_type)]
+    for (name, (_, value)) in definitions {
+        referenced.extend(
+            value
+                .iter()
+                .flat_map(|v| v.children())
+                .flat_map(referenced_symbols)
+                .filter(|r| r != name),
+        );
+    }
+    referenced.extend(
+        proof_items
+            .iter()
+            .chain(trait_impls.iter().flat_map(|i| i.children()))
+            .flat_map(referenced_symbols),
+    );
+
+    let mut warnings = vec![];
+    let mut linted_expressions = vec![];
+    for (name, (symbol, value)) in definitions.iter().sorted_by_key(|(name, _)| *name) {
+        if !is_linted(name, &symbol.source) {
+            continue;
+        }
+        let unused = !referenced.contains(name);
+        match (&symbol.kind, value) {
+            (SymbolKind::Other(), Some(FunctionValueDefinition::Expression(_))) if unused => {
+                warnings.push(
+                    symbol
+                        .source
+                        .with_warning(Lint::UnusedLet, format!("Symbol `{name}` is never used.")),
+                );
+            }
+            (SymbolKind::Poly(PolynomialType::Committed), _) if unused => {
+                warnings.push(symbol.source.with_warning(
+                    Lint::UnusedWitnessColumn,
+                    format!("Witness column `{name}` is never used."),
+                ));
+            }
+            _ => {}
+        }
+        if let Some(FunctionValueDefinition::Expression(TypedExpression { e, .. })) = value {
+            let namespace = parse_absolute_path(&format!("::{name}")).parent();
+            warnings.extend(shadowed_names(e, &namespace, definitions));
+            linted_expressions.push(e);
+        }
+    }
+    linted_expressions.extend(proof_items);
+    linted_expressions.extend(trait_impls.iter().flat_map(|i| i.children()));
+
+    for e in linted_expressions
+        .into_iter()
+        .flat_map(|e| e.all_children())
+    {
+        if let Expression::BlockExpression(_, block) = e {
+            warnings.extend(unused_local_variables(block));
+            warnings.extend(ignored_query_results(block, definitions));
+        }
+    }
+    warnings.sort_by_key(|w| (w.source_ref().file_name.clone(), w.source_ref().start));
+    warnings
+}
+
+fn referenced_symbols(e: &Expression) -> impl Iterator<Item = String> + '_ {
+    e.all_children().filter_map(|e| match e {
+        Expression::Reference(_, Reference::Poly(r)) => Some(r.name.clone()),
+        _ => None,
+    })
+}
+
+fn referenced_local_variables(e: &Expression) -> impl Iterator<Item = &str> + '_ {
+    e.all_children().filter_map(|e| match e {
+        Expression::Reference(_, Reference::LocalVar(_, name)) => Some(name.as_str()),
+        _ => None,
+    })
+}
+
+/// Returns the variables bound by the pattern, with their source references.
+fn variables(pattern: &Pattern) -> Box<dyn Iterator<Item = (&SourceRef, &String)> + '_> {
+    match pattern {
+        Pattern::Variable(source, name) => Box::new(once((source, name))),
+        _ => Box::new(pattern.children().flat_map(variables)),
+    }
+}
+
+/// Returns warnings for variables bound in `let` statements of the block that are never used.
+/// Since local variables cannot shadow each other, it is sufficient to compare names.
+fn unused_local_variables(block: &BlockExpression<Expression>) -> Vec<Warning> {
+    let BlockExpression { statements, expr } = block;
+    statements
+        .iter()
+        .enumerate()
+        .filter_map(|(i, statement)| match statement {
+            StatementInsideBlock::LetStatement(LetStatementInsideBlock { pattern, .. }) => {
+                Some((i, pattern))
+            }
+            StatementInsideBlock::Expression(_) => None,
+        })
+        .flat_map(|(i, pattern)| {
+            let used = statements[i + 1..]
+                .iter()
+                .flat_map(|s| s.children())
+                .chain(expr.iter().map(|e| e.as_ref()))
+                .flat_map(referenced_local_variables)
+                .collect::<HashSet<_>>();
+            variables(pattern)
+                .filter(move |(_, name)| !name.starts_with('_') && !used.contains(name.as_str()))
+                .map(|(source, name)| {
+                    source.with_warning(
+                        Lint::UnusedLet,
+                        format!("Local variable `{name}` is never used."),
+                    )
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Returns warnings for local variables in the expression that have the same name as a symbol
+/// that would be found when resolving the name in the given namespace.
+fn shadowed_names<'a>(
+    e: &'a Expression,
+    namespace: &'a AbsoluteSymbolPath,
+    definitions: &'a HashMap<String, (Symbol, Option<FunctionValueDefinition>)>,
+) -> impl Iterator<Item = Warning> + 'a {
+    e.all_children()
+        .flat_map(|e| -> Box<dyn Iterator<Item = &'a Pattern> + 'a> {
+            match e {
+                Expression::LambdaExpression(_, LambdaExpression { params, .. }) => {
+                    Box::new(params.iter())
+                }
+                Expression::BlockExpression(_, BlockExpression { statements, .. }) => {
+                    Box::new(statements.iter().filter_map(|s| match s {
+                        StatementInsideBlock::LetStatement(LetStatementInsideBlock {
+                            pattern,
+                            ..
+                        }) => Some(pattern),
+                        StatementInsideBlock::Expression(_) => None,
+                    }))
+                }
+                Expression::MatchExpression(_, MatchExpression { arms, .. }) => {
+                    Box::new(arms.iter().map(|MatchArm { pattern, .. }| pattern))
+                }
+                _ => Box::new(std::iter::empty()),
+            }
+        })
+        .flat_map(variables)
+        .filter_map(move |(source, name)| {
+            let path = SymbolPath::from_identifier(name.clone());
+            let shadowed = namespace
+                .iter_to_root()
+                .chain(once(parse_absolute_path("::std::prelude")))
+                .map(|prefix| {
+                    prefix
+                        .join(path.clone())
+                        .relative_to(&Default::default())
+                        .to_string()
+                })
+                .find(|candidate| definitions.contains_key(candidate))?;
+            Some(source.with_warning(
+                Lint::ShadowedName,
+                format!("Local variable `{name}` shadows the symbol `{shadowed}`."),
+            ))
+        })
+}
+
+/// Returns warnings for `let _ = f(...);` statements in the block where `f` is a
+/// query function that does not return `()`.
+fn ignored_query_results(
+    block: &BlockExpression<Expression>,
+    definitions: &HashMap<String, (Symbol, Option<FunctionValueDefinition>)>,
+) -> Vec<Warning> {
+    block
+        .statements
+        .iter()
+        .filter_map(|statement| {
+            let StatementInsideBlock::LetStatement(LetStatementInsideBlock {
+                pattern: Pattern::CatchAll(_),
+                value: Some(call @ Expression::FunctionCall(_, FunctionCall { function, .. })),
+                ..
+            }) = statement
+            else {
+                return None;
+            };
+            let Expression::Reference(_, Reference::Poly(reference)) = function.as_ref() else {
+                return None;
+            };
+            let name = &reference.name;
+            if function_kind_of_symbol(definitions, name) != FunctionKind::Query {
+                return None;
+            }
+            let Some((
+                _,
+                Some(FunctionValueDefinition::Expression(TypedExpression {
+                    type_scheme:
+                        Some(TypeScheme {
+                            ty: Type::Function(FunctionType { value, .. }),
+                            ..
+                        }),
+                    ..
+                })),
+            )) = definitions.get(name)
+            else {
+                return None;
+            };
+            (**value != Type::empty_tuple() && **value != Type::Bottom).then(|| {
+                call.source_reference().with_warning(
+                    Lint::UnusedQueryResult,
+                    format!("The result of the query function `{name}` is ignored."),
+                )
+            })
+        })
+        .collect()
+}
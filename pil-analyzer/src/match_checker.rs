@@ -0,0 +1,333 @@
+use std::collections::HashMap;
+
+use itertools::Itertools;
+use powdr_ast::{
+    analyzed::{Expression, FunctionValueDefinition, Symbol},
+    parsed::{MatchArm, MatchExpression, Pattern, SourceReference},
+};
+use powdr_number::BigInt;
+use powdr_parser_util::{Error, Lint, Warning};
+
+/// The maximum number of uncovered patterns listed in an error message.
+const MAX_EXAMPLES: usize = 3;
+
+/// Checks that the patterns of all match expressions cover all possible values
+/// of the scrutinee and that each arm can be reached.
+/// Returns the errors for non-exhaustive match expressions and the warnings
+/// for unreachable arms.
+pub fn check_match_expressions<'a>(
+    match_exprs: impl Iterator<Item = &'a Expression>,
+    definitions: &HashMap<String, (Symbol, Option<FunctionValueDefinition>)>,
+) -> (Vec<Error>, Vec<Warning>) {
+    let checker = MatchChecker { definitions };
+    let mut errors = vec![];
+    let mut warnings = vec![];
+    for expr in match_exprs {
+        let Expression::MatchExpression(source_ref, MatchExpression { arms, .. }) = expr else {
+            unreachable!()
+        };
+        let mut rows: Vec<Vec<Pat>> = vec![];
+        for MatchArm { pattern, .. } in arms {
+            let pat = Pat::from(pattern);
+            if !checker.is_useful(&rows, &[pat.clone()]) {
+                warnings.push(pattern.source_reference().with_warning(
+                    Lint::UnreachablePattern,
+                    format!("Unreachable pattern: {pattern}"),
+                ));
+            }
+            rows.push(vec![pat]);
+        }
+        let missing = checker.missing(&rows, 1);
+        if !missing.is_empty() {
+            let mut examples = missing
+                .iter()
+                .take(MAX_EXAMPLES)
+                .map(|w| format!("`{}`", w[0]))
+                .join(", ");
+            if missing.len() > MAX_EXAMPLES {
+                examples += " and more";
+            }
+            errors.push(source_ref.with_error(format!(
+                "Match expression is not exhaustive. Patterns not covered: {examples}"
+            )));
+        }
+    }
+    (errors, warnings)
+}
+
+/// A pattern, simplified for the exhaustiveness check.
+#[derive(Clone)]
+enum Pat<'a> {
+    /// Matches any value.
+    Wildcard,
+    Constructor(Constructor<'a>, Vec<Pat<'a>>),
+    /// An array pattern containing `..`, with the patterns before and after it.
+    Slice(Vec<Pat<'a>>, Vec<Pat<'a>>),
+}
+
+impl<'a> From<&'a Pattern> for Pat<'a> {
+    fn from(pattern: &'a Pattern) -> Self {
+        let all = |items: &'a [Pattern]| items.iter().map(Pat::from).collect_vec();
+        match pattern {
+            Pattern::CatchAll(_) | Pattern::Variable(_, _) => Pat::Wildcard,
+            Pattern::Ellipsis(_) => unreachable!("Should be handled by the array pattern"),
+            Pattern::Number(_, n) => Pat::Constructor(Constructor::Number(n), vec![]),
+            Pattern::String(_, s) => Pat::Constructor(Constructor::String(s), vec![]),
+            Pattern::Tuple(_, items) => {
+                Pat::Constructor(Constructor::Tuple(items.len()), all(items))
+            }
+            Pattern::Array(_, items) => {
+                match items.iter().position(|p| matches!(p, Pattern::Ellipsis(_))) {
+                    Some(pos) => Pat::Slice(all(&items[..pos]), all(&items[pos + 1..])),
+                    None => Pat::Constructor(Constructor::Array(items.len()), all(items)),
+                }
+            }
+            Pattern::Enum(_, name, fields) => Pat::Constructor(
+                Constructor::Variant(name.to_string()),
+                fields.as_deref().map(all).unwrap_or_default(),
+            ),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq)]
+enum Constructor<'a> {
+    Tuple(usize),
+    /// An array of exactly this length.
+    Array(usize),
+    /// An array of this length or longer. Used for the lengths that cannot
+    /// be distinguished by the patterns of a column.
+    ArrayFrom(usize),
+    /// An enum variant, by its absolute name.
+    Variant(String),
+    Number(&'a BigInt),
+    String(&'a str),
+}
+
+/// The constructors used by the first patterns of a set of rows.
+enum Signature<'a> {
+    /// All patterns are wildcards.
+    Wildcards,
+    /// All constructors of the type, each of them has to be covered.
+    Complete(Vec<Constructor<'a>>),
+    /// The type has infinitely many constructors, so it can only be covered
+    /// by a wildcard. Contains a value not used by any of the patterns.
+    Incomplete(String),
+}
+
+struct MatchChecker<'a> {
+    definitions: &'a HashMap<String, (Symbol, Option<FunctionValueDefinition>)>,
+}
+
+impl<'a> MatchChecker<'a> {
+    /// Returns true if there is a value matched by `row` but by none of the `rows`.
+    fn is_useful(&self, rows: &[Vec<Pat<'a>>], row: &[Pat<'a>]) -> bool {
+        let Some((head, rest)) = row.split_first() else {
+            return rows.is_empty();
+        };
+        if let Pat::Constructor(constructor, args) = head {
+            let specialized = self.specialize(rows, constructor);
+            return self.is_useful(&specialized, &[args.as_slice(), rest].concat());
+        }
+        let heads = rows.iter().map(|r| &r[0]).chain([head]);
+        match self.signature(heads) {
+            Signature::Complete(constructors) => constructors.iter().any(|constructor| {
+                self.specialize_row(row, constructor)
+                    .is_some_and(|row| self.is_useful(&self.specialize(rows, constructor), &row))
+            }),
+            Signature::Wildcards | Signature::Incomplete(_) => {
+                self.is_useful(&default_rows(rows), rest)
+            }
+        }
+    }
+
+    /// Returns examples of values of `width` items that are not matched by any of the rows.
+    fn missing(&self, rows: &[Vec<Pat<'a>>], width: usize) -> Vec<Vec<String>> {
+        if width == 0 {
+            return if rows.is_empty() {
+                vec![vec![]]
+            } else {
+                vec![]
+            };
+        }
+        let example = match self.signature(rows.iter().map(|r| &r[0])) {
+            Signature::Complete(constructors) => {
+                let mut missing = vec![];
+                for constructor in constructors {
+                    let arity = self.arity(&constructor);
+                    let specialized = self.specialize(rows, &constructor);
+                    missing.extend(
+                        self.missing(&specialized, arity + width - 1)
+                            .into_iter()
+                            .map(|items| self.apply(&constructor, items)),
+                    );
+                    if missing.len() > MAX_EXAMPLES {
+                        break;
+                    }
+                }
+                return missing;
+            }
+            Signature::Wildcards => "_".to_string(),
+            Signature::Incomplete(example) => example,
+        };
+        self.missing(&default_rows(rows), width - 1)
+            .into_iter()
+            .map(|items| [vec![example.clone()], items].concat())
+            .collect()
+    }
+
+    fn signature<'b>(&self, heads: impl Iterator<Item = &'b Pat<'a>>) -> Signature<'a>
+    where
+        'a: 'b,
+    {
+        let heads = heads.filter(|p| !matches!(p, Pat::Wildcard)).collect_vec();
+        let Some(first) = heads.first() else {
+            return Signature::Wildcards;
+        };
+        match first {
+            Pat::Wildcard => unreachable!(),
+            Pat::Constructor(Constructor::Tuple(n), _) => {
+                Signature::Complete(vec![Constructor::Tuple(*n)])
+            }
+            Pat::Constructor(Constructor::Array(_) | Constructor::ArrayFrom(_), _)
+            | Pat::Slice(_, _) => {
+                // Arrays longer than all fixed-length patterns and at least as long
+                // as all patterns with `..` behave the same.
+                let max_len = heads
+                    .iter()
+                    .map(|p| match p {
+                        Pat::Constructor(_, items) => items.len() + 1,
+                        Pat::Slice(prefix, suffix) => prefix.len() + suffix.len(),
+                        Pat::Wildcard => unreachable!(),
+                    })
+                    .max()
+                    .unwrap();
+                Signature::Complete(
+                    (0..max_len)
+                        .map(Constructor::Array)
+                        .chain([Constructor::ArrayFrom(max_len)])
+                        .collect(),
+                )
+            }
+            Pat::Constructor(Constructor::Variant(name), _) => {
+                let Some((_, Some(FunctionValueDefinition::TypeConstructor(enum_decl, _)))) =
+                    self.definitions.get(name)
+                else {
+                    panic!("Enum variant {name} not found.");
+                };
+                let enum_name = name.rsplit_once("::").unwrap().0;
+                Signature::Complete(
+                    enum_decl
+                        .variants
+                        .iter()
+                        .map(|v| Constructor::Variant(format!("{enum_name}::{}", v.name)))
+                        .collect(),
+                )
+            }
+            Pat::Constructor(Constructor::Number(_), _) => {
+                let used = heads
+                    .iter()
+                    .filter_map(|p| match p {
+                        Pat::Constructor(Constructor::Number(n), _) => Some(*n),
+                        _ => None,
+                    })
+                    .collect_vec();
+                let unused = (0..)
+                    .map(BigInt::from)
+                    .find(|n| !used.contains(&n))
+                    .unwrap();
+                Signature::Incomplete(unused.to_string())
+            }
+            Pat::Constructor(Constructor::String(_), _) => Signature::Incomplete("_".to_string()),
+        }
+    }
+
+    fn arity(&self, constructor: &Constructor) -> usize {
+        match constructor {
+            Constructor::Tuple(n) | Constructor::Array(n) | Constructor::ArrayFrom(n) => *n,
+            Constructor::Variant(name) => match self.definitions.get(name) {
+                Some((_, Some(FunctionValueDefinition::TypeConstructor(_, variant)))) => {
+                    variant.fields.as_ref().map(|f| f.len()).unwrap_or_default()
+                }
+                _ => panic!("Enum variant {name} not found."),
+            },
+            Constructor::Number(_) | Constructor::String(_) => 0,
+        }
+    }
+
+    /// Returns the rows that match the constructor, with the first pattern replaced
+    /// by the patterns for the constructor's items.
+    fn specialize(
+        &self,
+        rows: &[Vec<Pat<'a>>],
+        constructor: &Constructor<'a>,
+    ) -> Vec<Vec<Pat<'a>>> {
+        rows.iter()
+            .filter_map(|row| self.specialize_row(row, constructor))
+            .collect()
+    }
+
+    fn specialize_row(
+        &self,
+        row: &[Pat<'a>],
+        constructor: &Constructor<'a>,
+    ) -> Option<Vec<Pat<'a>>> {
+        let (head, rest) = row.split_first().unwrap();
+        let items = match head {
+            Pat::Wildcard => vec![Pat::Wildcard; self.arity(constructor)],
+            Pat::Constructor(c, items) => (c == constructor).then(|| items.clone())?,
+            Pat::Slice(prefix, suffix) => {
+                let (Constructor::Array(len) | Constructor::ArrayFrom(len)) = constructor else {
+                    unreachable!()
+                };
+                let wildcards = len.checked_sub(prefix.len() + suffix.len())?;
+                [
+                    prefix.clone(),
+                    vec![Pat::Wildcard; wildcards],
+                    suffix.clone(),
+                ]
+                .concat()
+            }
+        };
+        Some([items, rest.to_vec()].concat())
+    }
+
+    /// Formats the constructor applied to the first items, followed by the remaining items.
+    fn apply(&self, constructor: &Constructor, mut items: Vec<String>) -> Vec<String> {
+        let rest = items.split_off(self.arity(constructor));
+        let formatted = match constructor {
+            Constructor::Tuple(_) => format!("({})", items.join(", ")),
+            Constructor::Array(_) => format!("[{}]", items.join(", ")),
+            Constructor::ArrayFrom(_) => {
+                format!(
+                    "[{}]",
+                    items.into_iter().chain(["..".to_string()]).join(", ")
+                )
+            }
+            Constructor::Variant(name) => {
+                // Only print the enum name and the variant name.
+                let short_name = name.split("::").collect_vec();
+                let short_name = short_name[short_name.len().saturating_sub(2)..].join("::");
+                match self.definitions.get(name) {
+                    Some((_, Some(FunctionValueDefinition::TypeConstructor(_, variant))))
+                        if variant.fields.is_some() =>
+                    {
+                        format!("{short_name}({})", items.join(", "))
+                    }
+                    _ => short_name,
+                }
+            }
+            Constructor::Number(n) => n.to_string(),
+            Constructor::String(s) => format!("{s:?}"),
+        };
+        [vec![formatted], rest].concat()
+    }
+}
+
+/// Returns the rows starting with a wildcard, without their first pattern.
+fn default_rows<'a>(rows: &[Vec<Pat<'a>>]) -> Vec<Vec<Pat<'a>>> {
+    rows.iter()
+        .filter(|row| matches!(row[0], Pat::Wildcard))
+        .map(|row| row[1..].to_vec())
+        .collect()
+}
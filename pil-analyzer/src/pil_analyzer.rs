@@ -6,6 +6,7 @@ use std::iter::once;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
+use crate::match_checker::check_match_expressions;
 use crate::structural_checks::check_structs_fields;
 use itertools::Itertools;
 use powdr_ast::parsed::asm::{
@@ -24,7 +25,7 @@ use powdr_ast::analyzed::{
     PolynomialType, Reference, SolvedTraitImpls, StatementIdentifier, Symbol, SymbolKind,
 };
 use powdr_parser::{parse, parse_module, parse_type};
-use powdr_parser_util::Error;
+use powdr_parser_util::{Error, Warning};
 
 use crate::traits_resolver::TraitsResolver;
 use crate::type_builtins::constr_function_statement_type;
@@ -32,7 +33,7 @@ use crate::type_inference::infer_types;
 use crate::{side_effect_checker, AnalysisDriver};
 
 use crate::statement_processor::{Counters, PILItem, StatementProcessor};
-use crate::{condenser, evaluator, expression_processor::ExpressionProcessor};
+use crate::{condenser, evaluator, expression_processor::ExpressionProcessor, lints};
 
 pub fn analyze_file<T: FieldElement>(path: &Path) -> Result<Analyzed<T>, Vec<Error>> {
     let files = import_all_dependencies(path);
@@ -54,10 +55,43 @@ fn analyze<T: FieldElement>(files: Vec<PILFile>) -> Result<Analyzed<T>, Vec<Erro
     analyzer.side_effect_check()?;
     analyzer.validate_structs()?;
     analyzer.type_check()?;
+    for warning in analyzer.check_match_expressions()? {
+        log::warn!("{warning}");
+    }
     let solved_impls = analyzer.resolve_trait_impls()?;
     analyzer.condense(solved_impls)
 }
 
+/// Analyzes the file and its imports and returns warnings about unused or suspicious code,
+/// except for the ones suppressed by a comment.
+pub fn lint_file(path: &Path) -> Result<Vec<Warning>, Vec<Error>> {
+    let files = import_all_dependencies(path);
+    lint(files)
+}
+
+pub fn lint_ast(pil_file: PILFile) -> Result<Vec<Warning>, Vec<Error>> {
+    lint(vec![pil_file])
+}
+
+pub fn lint_string(contents: &str) -> Result<Vec<Warning>, Vec<Error>> {
+    let pil_file = powdr_parser::parse(Some("input"), contents).map_err(|e| vec![e])?;
+    lint(vec![pil_file])
+}
+
+fn lint(files: Vec<PILFile>) -> Result<Vec<Warning>, Vec<Error>> {
+    let mut analyzer = PILAnalyzer::new();
+    analyzer.process(files)?;
+    analyzer.side_effect_check()?;
+    analyzer.validate_structs()?;
+    analyzer.type_check()?;
+    let mut warnings = analyzer.check_match_expressions()?;
+    warnings.extend(analyzer.lint());
+    Ok(warnings
+        .into_iter()
+        .filter(|warning| !warning.is_suppressed())
+        .collect())
+}
+
 #[derive(Default)]
 struct PILAnalyzer {
     /// Known symbols by name and category, determined in the first step.
@@ -266,6 +300,31 @@ impl PILAnalyzer {
         check_structs_fields(structs_exprs, &self.definitions)
     }
 
+    /// Checks that all match expressions are exhaustive and returns warnings
+    /// about unreachable arms. Has to run after type checking.
+    pub fn check_match_expressions(&self) -> Result<Vec<Warning>, Vec<Error>> {
+        let match_exprs = self
+            .all_children()
+            .filter(|expr| matches!(expr, Expression::MatchExpression(_, _)));
+
+        let (errors, warnings) = check_match_expressions(match_exprs, &self.definitions);
+        if errors.is_empty() {
+            Ok(warnings)
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Returns warnings about unused or suspicious code. Has to run after type checking.
+    pub fn lint(&self) -> Vec<Warning> {
+        lints::lint(
+            &self.definitions,
+            &self.proof_items,
+            &self.trait_impls,
+            &self.auto_added_symbols,
+        )
+    }
+
     pub fn type_check(&mut self) -> Result<(), Vec<Error>> {
         let query_type: Type = parse_type("int -> std::prelude::Query").unwrap().into();
         let mut expressions = vec![];
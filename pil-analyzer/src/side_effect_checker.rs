@@ -122,25 +122,32 @@ impl SideEffectChecker<'_> {
         }
     }
 
-    /// Returns the function kind of a referenced symbol.
     fn function_kind_of_symbol(&self, name: &str) -> FunctionKind {
-        if let Some(kind) = BUILTIN_KINDS.get(name) {
-            return *kind;
-        }
-        let (symbol, value) = self.definitions.get(name).unwrap();
-        if symbol.kind != SymbolKind::Other() {
-            // If referenced, columns are `expr`, so they are not functions and thus pure.
-            return FunctionKind::Pure;
-        }
-        if let Some(FunctionValueDefinition::Expression(TypedExpression {
-            type_scheme: _,
-            e: Expression::LambdaExpression(_, LambdaExpression { kind, .. }),
-        })) = value
-        {
-            *kind
-        } else {
-            FunctionKind::Pure
-        }
+        function_kind_of_symbol(self.definitions, name)
+    }
+}
+
+/// Returns the function kind of a referenced symbol.
+pub fn function_kind_of_symbol(
+    definitions: &HashMap<String, (Symbol, Option<FunctionValueDefinition>)>,
+    name: &str,
+) -> FunctionKind {
+    if let Some(kind) = BUILTIN_KINDS.get(name) {
+        return *kind;
+    }
+    let (symbol, value) = definitions.get(name).unwrap();
+    if symbol.kind != SymbolKind::Other() {
+        // If referenced, columns are `expr`, so they are not functions and thus pure.
+        return FunctionKind::Pure;
+    }
+    if let Some(FunctionValueDefinition::Expression(TypedExpression {
+        type_scheme: _,
+        e: Expression::LambdaExpression(_, LambdaExpression { kind, .. }),
+    })) = value
+    {
+        *kind
+    } else {
+        FunctionKind::Pure
     }
 }
 
@@ -1,8 +1,13 @@
-use powdr_ast::analyzed::{FunctionValueDefinition, TypedExpression};
+use std::sync::Arc;
+
+use powdr_ast::{
+    analyzed::{FunctionValueDefinition, TypedExpression},
+    parsed::types::Type,
+};
 use powdr_number::{FieldElement, GoldilocksField};
 use powdr_pil_analyzer::{
     analyze_string,
-    evaluator::{self, evaluate, Definitions, SymbolLookup, Value},
+    evaluator::{self, evaluate, CodeCache, Definitions, EvalError, SymbolLookup, Value},
 };
 use test_log::test;
 
@@ -101,7 +106,7 @@ fn fibonacci() {
 #[test]
 fn capturing() {
     let src = r#"namespace Main(16);
-        let f: int, (int -> int) -> (int -> int) = |n, g| match n { 99 => |i| n, 1 => g };
+        let f: int, (int -> int) -> (int -> int) = |n, g| match n { 99 => |i| n, _ => g };
         let result = f(1, f(99, |x| x + 3000))(0);
     "#;
     // If the lambda function returned by the expression f(99, ...) does not
@@ -260,7 +265,8 @@ fn match_pattern_complex() {
             ((_, 2), [y, z]) => 3 + y + z,
             ((x, 3), _) => x,
             ((x, -1), _) => x,
-            (t, [_, r]) => r
+            (t, [_, r]) => r,
+            _ => 0
         };
         let res = [
             f(((1, 9), [20, 4])),
@@ -647,3 +653,107 @@ fn test_trait_function_call_cross_impl() {
 
     assert_eq!(parse_and_evaluate_symbol(input, "F::r"), "6".to_string());
 }
+
+#[test]
+fn bytecode_matches_tree_walking() {
+    let input = r#"
+    namespace std::array(4);
+        let len = 123;
+    namespace F(4);
+        enum Op { Add(int, int), Neg(int), Nop }
+        trait Eval<T> {
+            eval: T -> int,
+        }
+        impl Eval<Op> {
+            eval: |op| match op {
+                Op::Add(a, b) => a + b,
+                Op::Neg(a) => -a,
+                _ => 0,
+            },
+        }
+        let<T: Add> twice: T -> T = |x| x + x;
+        let first_or: int[], int -> int = |arr, default| match arr {
+            [x, ..] => x,
+            [] => default,
+        };
+        let fold: int, int, (int, int -> int) -> int = |i, acc, f| if i == 0 { acc } else { fold(i - 1, f(acc, i), f) };
+        let f: int -> (int, fe, int[], string) = |n| {
+            let (a, b) = (n * 2, n + 1);
+            let ops = [Op::Add(a, b), Op::Neg(a), Op::Nop];
+            let total = fold(n, 0, |acc, i| acc + Eval::eval(ops[i % std::array::len(ops)]) * i);
+            let x: fe = 7;
+            (twice(total), twice(x), [first_or([a, b], 1), first_or([], b)], "done")
+        };
+    "#;
+    let analyzed = analyze_string::<GoldilocksField>(input).unwrap();
+    let mut symbols = evaluator::Definitions {
+        definitions: &analyzed.definitions,
+        solved_impls: &analyzed.solved_impls,
+    };
+    let function = symbols.lookup("F::f", &None).unwrap();
+    for n in [0, 1, 7, 100] {
+        let arguments = vec![Value::Integer(n.into()).into()];
+        let bytecode =
+            evaluator::evaluate_function_call(function.clone(), arguments.clone(), &mut symbols)
+                .unwrap();
+        let tree_walking = evaluator::evaluate_function_call_tree_walking(
+            function.clone(),
+            arguments,
+            &mut symbols,
+        )
+        .unwrap();
+        assert_eq!(bytecode.to_string(), tree_walking.to_string());
+    }
+}
+
+/// Looks up symbols like [Definitions], but keeps the compiled code between evaluations.
+struct CachingDefinitions<'a, T> {
+    definitions: Definitions<'a>,
+    code_cache: CodeCache<'a, T>,
+}
+
+impl<'a, T: FieldElement> SymbolLookup<'a, T> for CachingDefinitions<'a, T> {
+    fn lookup(
+        &mut self,
+        name: &'a str,
+        type_args: &Option<Vec<Type>>,
+    ) -> Result<Arc<Value<'a, T>>, EvalError> {
+        Definitions::lookup_with_symbols(
+            self.definitions.definitions,
+            self.definitions.solved_impls,
+            name,
+            type_args,
+            self,
+        )
+    }
+
+    fn code_cache(&self) -> Option<&CodeCache<'a, T>> {
+        Some(&self.code_cache)
+    }
+}
+
+#[test]
+fn code_is_compiled_once() {
+    let input = r#"
+    namespace F(4);
+        let square: int -> int = |x| x * x;
+        let f: int -> int = |n| if n == 0 { 0 } else { square(n) + f(n - 1) };
+    "#;
+    let analyzed = analyze_string::<GoldilocksField>(input).unwrap();
+    let mut symbols = CachingDefinitions::<GoldilocksField> {
+        definitions: Definitions {
+            definitions: &analyzed.definitions,
+            solved_impls: &analyzed.solved_impls,
+        },
+        code_cache: Default::default(),
+    };
+    let function = symbols.lookup("F::f", &None).unwrap();
+    for (n, expected) in [(3, "14"), (4, "30"), (10, "385")] {
+        let arguments = vec![Value::Integer(n.into()).into()];
+        let result =
+            evaluator::evaluate_function_call(function.clone(), arguments, &mut symbols).unwrap();
+        assert_eq!(result.to_string(), expected);
+        // The definitions and the bodies of `F::f` and `F::square`.
+        assert_eq!(symbols.code_cache.len(), 4);
+    }
+}
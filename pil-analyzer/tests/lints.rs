@@ -0,0 +1,116 @@
+use powdr_parser_util::Lint;
+
+fn lint_string(input: &str) -> Vec<(Lint, String)> {
+    powdr_pil_analyzer::lint_string(input)
+        .unwrap()
+        .into_iter()
+        .map(|w| (w.lint(), w.message().to_string()))
+        .collect()
+}
+
+#[test]
+fn unused_symbols() {
+    let input = r#"namespace N(16);
+    let used: int = 1;
+    let unused: int = used + 1;
+    let _ignored: int = 2;
+    col witness w, x;
+    w = w * w;
+    "#;
+    assert_eq!(
+        lint_string(input),
+        vec![
+            (
+                Lint::UnusedLet,
+                "Symbol `N::unused` is never used.".to_string()
+            ),
+            (
+                Lint::UnusedWitnessColumn,
+                "Witness column `N::x` is never used.".to_string()
+            ),
+        ]
+    );
+}
+
+#[test]
+fn unused_local_variables() {
+    let input = r#"namespace N(16);
+    let f: int -> int = |i| { let a = i + 1; let (b, _c) = (i, a); i };
+    col fixed c(i) { f(i) };
+    "#;
+    assert_eq!(
+        lint_string(input),
+        vec![(
+            Lint::UnusedLet,
+            "Local variable `b` is never used.".to_string()
+        )]
+    );
+}
+
+#[test]
+fn shadowed_names() {
+    let input = r#"let y: int = 2;
+    namespace N(16);
+    let x: int = 1;
+    let f: int -> int = |x| x + 1;
+    let g: int -> int = |i| match i { y => y };
+    col fixed c(i) { f(x) + g(i) + y };
+    "#;
+    assert_eq!(
+        lint_string(input),
+        vec![
+            (
+                Lint::ShadowedName,
+                "Local variable `x` shadows the symbol `N::x`.".to_string()
+            ),
+            (
+                Lint::ShadowedName,
+                "Local variable `y` shadows the symbol `y`.".to_string()
+            ),
+        ]
+    );
+}
+
+#[test]
+fn ignored_query_result() {
+    let input = r#"namespace N(16);
+    let q: int -> int = query |i| i;
+    let p: int -> () = query |i| ();
+    // lint: allow(unused-let)
+    let f: int -> () = query |i| { let _ = q(i); let _ = p(i); () };
+    "#;
+    assert_eq!(
+        lint_string(input),
+        vec![(
+            Lint::UnusedQueryResult,
+            "The result of the query function `N::q` is ignored.".to_string()
+        )]
+    );
+}
+
+#[test]
+fn unreachable_pattern() {
+    let input = r#"namespace N(16);
+    let f: int -> int = |i| match i { _ => 1, 0 => 2 };
+    col fixed c(i) { f(i) };
+    "#;
+    assert_eq!(
+        lint_string(input),
+        vec![(
+            Lint::UnreachablePattern,
+            "Unreachable pattern: 0".to_string()
+        )]
+    );
+}
+
+#[test]
+fn suppression() {
+    let input = r#"namespace N(16);
+    // lint: allow(unused-let)
+    let unused: int = 1;
+    let x: int = 1;
+    let f: int -> int = |x| x + 1; // lint: allow(shadowed-name)
+    col fixed c(i) { f(x) };
+    "#;
+    assert_eq!(lint_string(input), vec![]);
+}
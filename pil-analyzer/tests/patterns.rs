@@ -0,0 +1,135 @@
+use itertools::Itertools;
+use powdr_ast::analyzed::Analyzed;
+use powdr_number::GoldilocksField;
+use powdr_parser_util::Lint;
+use test_log::test;
+
+fn analyze_string(input: &str) -> Analyzed<GoldilocksField> {
+    powdr_pil_analyzer::analyze_string(input)
+        .map_err(|errors| {
+            errors
+                .into_iter()
+                .map(|e| {
+                    e.output_to_stderr();
+                    e.to_string()
+                })
+                .format("\n")
+        })
+        .unwrap()
+}
+
+#[test]
+#[should_panic = "Match expression is not exhaustive. Patterns not covered: `O::B`"]
+fn missing_enum_variant() {
+    let input = r#"namespace N(16);
+    enum O { A(int), B }
+    let f: O -> int = |o| match o { O::A(x) => x };
+    "#;
+    analyze_string(input);
+}
+
+#[test]
+#[should_panic = "Match expression is not exhaustive. Patterns not covered: `O::A(1)`, `O::B`"]
+fn missing_nested_patterns() {
+    let input = r#"namespace N(16);
+    enum O { A(int), B }
+    let f: O -> int = |o| match o { O::A(0) => 1 };
+    "#;
+    analyze_string(input);
+}
+
+#[test]
+#[should_panic = "Match expression is not exhaustive. Patterns not covered: `2`"]
+fn missing_number() {
+    let input = r#"namespace N(16);
+    let f: int -> int = |i| match i { 0 => 1, 1 => 2 };
+    "#;
+    analyze_string(input);
+}
+
+#[test]
+#[should_panic = "Match expression is not exhaustive. Patterns not covered: `_`"]
+fn missing_string() {
+    let input = r#"namespace N(16);
+    let f: string -> int = |s| match s { "a" => 1, "b" => 2 };
+    "#;
+    analyze_string(input);
+}
+
+#[test]
+#[should_panic = "Match expression is not exhaustive. Patterns not covered: `(1, 1)`"]
+fn missing_tuple() {
+    let input = r#"namespace N(16);
+    let f: (int, int) -> int = |t| match t { (0, _) => 1, (_, 0) => 2 };
+    "#;
+    analyze_string(input);
+}
+
+#[test]
+#[should_panic = "Match expression is not exhaustive. Patterns not covered: `[_, _, ..]`"]
+fn missing_array_length() {
+    let input = r#"namespace N(16);
+    let f: int[] -> int = |a| match a { [] => 0, [x] => x };
+    "#;
+    analyze_string(input);
+}
+
+#[test]
+#[should_panic = "Match expression is not exhaustive. Patterns not covered: `[_]`"]
+fn missing_array_with_ellipsis() {
+    let input = r#"namespace N(16);
+    let f: int[] -> int = |a| match a { [] => 0, [x, .., y] => x + y };
+    "#;
+    analyze_string(input);
+}
+
+#[test]
+fn exhaustive() {
+    let input = r#"namespace N(16);
+    enum O { A(int), B }
+    let f: O -> int = |o| match o { O::A(0) => 1, O::A(x) => x, O::B => 0 };
+    let g: int[] -> int = |a| match a { [] => 0, [x] => x, [x, .., y] => x + y };
+    let h: (O, int) -> int = |t| match t { (O::B, _) => 0, (_, 0) => 1, (O::A(x), _) => x };
+    let k: int -> int = |i| match i { 0 => 1, n => n };
+    "#;
+    analyze_string(input);
+}
+
+#[test]
+fn unreachable_arm() {
+    // Unreachable arms are only reported as warnings.
+    let input = r#"namespace N(16);
+    let f: int -> int = |i| match i { _ => 1, 0 => 2 };
+    let g: int[] -> int = |a| match a { [x, ..] => x, [x, y] => y, [] => 0 };
+    "#;
+    analyze_string(input);
+
+    let warnings = powdr_pil_analyzer::lint_string(input)
+        .unwrap()
+        .into_iter()
+        .filter(|w| w.lint() == Lint::UnreachablePattern)
+        .map(|w| {
+            let source_ref = w.source_ref();
+            (
+                w.message().to_string(),
+                &input[source_ref.start..source_ref.end],
+                source_ref.start,
+            )
+        })
+        .collect_vec();
+    assert_eq!(
+        warnings,
+        [
+            (
+                "Unreachable pattern: 0".to_string(),
+                "0",
+                input.find("0 => 2").unwrap()
+            ),
+            (
+                "Unreachable pattern: [x, y]".to_string(),
+                "[x, y]",
+                input.find("[x, y]").unwrap()
+            ),
+        ]
+    );
+}
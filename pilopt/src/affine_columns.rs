@@ -0,0 +1,344 @@
+//! Elimination of witness columns that are affine combinations of other columns.
+
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+use itertools::Itertools;
+use powdr_ast::analyzed::{
+    AlgebraicBinaryOperation, AlgebraicBinaryOperator, AlgebraicExpression, AlgebraicReference,
+    AlgebraicUnaryOperation, AlgebraicUnaryOperator, Analyzed, Expression, Identity, PolyID,
+    PolynomialIdentity, PolynomialType, Reference, Symbol, SymbolKind,
+};
+use powdr_ast::parsed::visitor::{AllChildren, Children};
+use powdr_number::FieldElement;
+
+use crate::{Substitution, SubstitutionRecord};
+
+/// A variable of a linear constraint: the constant one or a column on the current row.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+enum Variable {
+    One,
+    Column(PolyID),
+}
+
+/// A linear combination of variables, without zero coefficients.
+type LinearForm<T> = BTreeMap<Variable, T>;
+
+/// Collects all polynomial identities that are linear and only reference the current row,
+/// solves them per namespace using Gaussian elimination and turns each solved witness
+/// column into an intermediate column defined by the solution.
+///
+/// The identities that are implied by these definitions are removed.
+/// Columns that are referenced by name (e.g. in hints, prover functions or public
+/// declarations), array elements and columns of later stages are never removed.
+///
+/// Returns the number of removed witness columns.
+pub fn remove_affine_witness_columns<T: FieldElement>(pil_file: &mut Analyzed<T>) -> usize {
+    substitute_affine_witness_columns(pil_file, &mut SubstitutionRecord::default())
+}
+
+/// Like [remove_affine_witness_columns], but also records the solved columns.
+pub(crate) fn substitute_affine_witness_columns<T: FieldElement>(
+    pil_file: &mut Analyzed<T>,
+    substitutions: &mut SubstitutionRecord<T>,
+) -> usize {
+    let intermediates = pil_file
+        .intermediate_columns
+        .values()
+        .flat_map(|(symbol, definitions)| {
+            symbol
+                .array_elements()
+                .zip(definitions)
+                .map(|((_, poly_id), definition)| (poly_id, definition))
+        })
+        .collect::<BTreeMap<_, _>>();
+    let eliminable = eliminable_columns(pil_file);
+
+    // Linear identities, grouped by the namespace of the witness columns they reference.
+    let mut systems: BTreeMap<String, Vec<(usize, LinearForm<T>)>> = BTreeMap::new();
+    let mut references = BTreeMap::new();
+    for (index, identity) in pil_file.identities.iter().enumerate() {
+        let Identity::Polynomial(PolynomialIdentity { expression, .. }) = identity else {
+            continue;
+        };
+        let Some(form) = linearize(expression, &intermediates, &mut references) else {
+            continue;
+        };
+        let namespaces = form
+            .keys()
+            .filter_map(|v| match v {
+                Variable::Column(id) if id.ptype == PolynomialType::Committed => {
+                    Some(namespace_of(&references[id].name).to_string())
+                }
+                _ => None,
+            })
+            .collect::<BTreeSet<_>>();
+        if let Ok(namespace) = namespaces.into_iter().exactly_one() {
+            systems.entry(namespace).or_default().push((index, form));
+        }
+    }
+
+    let mut definitions = BTreeMap::new();
+    let mut identities_to_remove = BTreeSet::new();
+    for rows in systems.into_values() {
+        let (solved, implied) = solve(rows, &eliminable);
+        identities_to_remove.extend(implied);
+        definitions.extend(solved);
+    }
+    let removed = definitions.len();
+    if removed == 0 {
+        return 0;
+    }
+    log::info!("Removed {removed} witness columns that are affine combinations of other columns.");
+
+    pil_file.remove_identities(&identities_to_remove);
+
+    // Turn the solved columns into intermediate columns, with temporary IDs
+    // that are re-assigned below.
+    let mut next_intermediate_id = pil_file.intermediate_count() as u64;
+    let mut replacements = BTreeMap::new();
+    for (poly_id, form) in definitions {
+        let name = references[&poly_id].name.clone();
+        let (symbol, _) = pil_file.definitions.remove(&name).unwrap();
+        log::debug!("Turning witness column {name} into an intermediate column.");
+        let symbol = Symbol {
+            id: next_intermediate_id,
+            kind: SymbolKind::Poly(PolynomialType::Intermediate),
+            stage: None,
+            ..symbol
+        };
+        next_intermediate_id += 1;
+        replacements.insert(poly_id, PolyID::from(&symbol));
+        let definition = to_expression(&form, &references);
+        substitutions.record(name.clone(), Substitution::Expression(definition.clone()));
+        pil_file
+            .intermediate_columns
+            .insert(name, (symbol, vec![definition]));
+    }
+    pil_file.post_visit_expressions_in_identities_mut(&mut |e: &mut AlgebraicExpression<T>| {
+        if let AlgebraicExpression::Reference(reference) = e {
+            if let Some(replacement) = replacements.get(&reference.poly_id) {
+                reference.poly_id = *replacement;
+            }
+        }
+    });
+    // Removing no definitions re-assigns all IDs to be contiguous and in source order.
+    pil_file.remove_definitions(&Default::default());
+    removed
+}
+
+/// Returns the witness columns that can be turned into intermediate columns.
+fn eliminable_columns<T: FieldElement>(pil_file: &Analyzed<T>) -> HashSet<PolyID> {
+    let referenced_by_name = Children::<Expression>::children(pil_file)
+        .flat_map(|e| e.all_children())
+        .filter_map(|e| match e {
+            Expression::Reference(_, Reference::Poly(reference)) => Some(reference.name.as_str()),
+            _ => None,
+        })
+        .collect::<HashSet<_>>();
+    let in_connect_identities = pil_file
+        .identities
+        .iter()
+        .filter(|identity| matches!(identity, Identity::Connect(_)))
+        .flat_map(AllChildren::<AlgebraicExpression<T>>::all_children)
+        .filter_map(|e| match e {
+            AlgebraicExpression::Reference(reference) => Some(reference.poly_id),
+            _ => None,
+        })
+        .collect::<HashSet<_>>();
+
+    pil_file
+        .committed_polys_in_source_order()
+        .filter(|(symbol, value)| {
+            !symbol.is_array()
+                && symbol.stage.unwrap_or(0) == 0
+                && value.is_none()
+                && !referenced_by_name.contains(symbol.absolute_name.as_str())
+        })
+        .map(|(symbol, _)| PolyID::from(symbol))
+        .filter(|poly_id| !in_connect_identities.contains(poly_id))
+        .collect()
+}
+
+/// Brings the rows into reduced row echelon form, using the eliminable columns as pivots.
+/// Returns the definition of each pivot column in terms of the other variables
+/// and the indices of the identities that are implied by these definitions.
+fn solve<T: FieldElement>(
+    rows: Vec<(usize, LinearForm<T>)>,
+    eliminable: &HashSet<PolyID>,
+) -> (BTreeMap<PolyID, LinearForm<T>>, Vec<usize>) {
+    let mut pivots: Vec<(PolyID, LinearForm<T>)> = vec![];
+    let mut implied = vec![];
+    for (index, mut row) in rows {
+        for (pivot, pivot_row) in &pivots {
+            if let Some(factor) = row.get(&Variable::Column(*pivot)).copied() {
+                add_multiple(&mut row, pivot_row, -factor);
+            }
+        }
+        // Prefer to eliminate columns declared later.
+        let pivot = row
+            .keys()
+            .rev()
+            .find_map(|v| match v {
+                Variable::Column(id) if eliminable.contains(id) => Some(*id),
+                _ => None,
+            })
+            .filter(|_| row.len() > 1);
+        match pivot {
+            Some(pivot) => {
+                let factor = T::one() / row[&Variable::Column(pivot)];
+                row.values_mut().for_each(|c| *c = *c * factor);
+                for (_, other) in &mut pivots {
+                    if let Some(factor) = other.get(&Variable::Column(pivot)).copied() {
+                        add_multiple(other, &row, -factor);
+                    }
+                }
+                pivots.push((pivot, row));
+                implied.push(index);
+            }
+            None if row.is_empty() => implied.push(index),
+            None => {}
+        }
+    }
+
+    let definitions = pivots
+        .into_iter()
+        .map(|(pivot, mut row)| {
+            // The row reads `pivot + rest = 0`, so `pivot = -rest`.
+            row.remove(&Variable::Column(pivot));
+            row.values_mut().for_each(|c| *c = -*c);
+            (pivot, row)
+        })
+        .collect();
+    (definitions, implied)
+}
+
+/// Adds `factor * other` to `row`.
+fn add_multiple<T: FieldElement>(row: &mut LinearForm<T>, other: &LinearForm<T>, factor: T) {
+    for (v, c) in other {
+        let entry = row.entry(*v).or_insert(T::zero());
+        *entry += *c * factor;
+        if *entry == T::zero() {
+            row.remove(v);
+        }
+    }
+}
+
+/// Returns the expression as a linear combination of columns on the current row,
+/// if possible. Intermediate columns are inlined.
+fn linearize<T: FieldElement>(
+    e: &AlgebraicExpression<T>,
+    intermediates: &BTreeMap<PolyID, &AlgebraicExpression<T>>,
+    references: &mut BTreeMap<PolyID, AlgebraicReference>,
+) -> Option<LinearForm<T>> {
+    Some(match e {
+        AlgebraicExpression::Number(n) => constant(*n),
+        AlgebraicExpression::Reference(reference) if !reference.next => {
+            match reference.poly_id.ptype {
+                PolynomialType::Intermediate => {
+                    linearize(intermediates[&reference.poly_id], intermediates, references)?
+                }
+                PolynomialType::Committed | PolynomialType::Constant => {
+                    references
+                        .entry(reference.poly_id)
+                        .or_insert_with(|| reference.clone());
+                    [(Variable::Column(reference.poly_id), T::one())].into()
+                }
+            }
+        }
+        AlgebraicExpression::BinaryOperation(AlgebraicBinaryOperation { left, op, right }) => {
+            let mut left = linearize(left, intermediates, references)?;
+            let right = linearize(right, intermediates, references)?;
+            match op {
+                AlgebraicBinaryOperator::Add => {
+                    add_multiple(&mut left, &right, T::one());
+                    left
+                }
+                AlgebraicBinaryOperator::Sub => {
+                    add_multiple(&mut left, &right, -T::one());
+                    left
+                }
+                AlgebraicBinaryOperator::Mul => {
+                    let (factor, mut form) = match (as_constant(&left), as_constant(&right)) {
+                        (Some(factor), _) => (factor, right),
+                        (_, Some(factor)) => (factor, left),
+                        _ => return None,
+                    };
+                    form.values_mut().for_each(|c| *c = *c * factor);
+                    form.retain(|_, c| *c != T::zero());
+                    form
+                }
+                AlgebraicBinaryOperator::Pow => return None,
+            }
+        }
+        AlgebraicExpression::UnaryOperation(AlgebraicUnaryOperation {
+            op: AlgebraicUnaryOperator::Minus,
+            expr,
+        }) => {
+            let mut form = linearize(expr, intermediates, references)?;
+            form.values_mut().for_each(|c| *c = -*c);
+            form
+        }
+        _ => return None,
+    })
+}
+
+fn constant<T: FieldElement>(value: T) -> LinearForm<T> {
+    if value == T::zero() {
+        Default::default()
+    } else {
+        [(Variable::One, value)].into()
+    }
+}
+
+fn as_constant<T: FieldElement>(form: &LinearForm<T>) -> Option<T> {
+    match form.iter().next() {
+        None => Some(T::zero()),
+        Some((Variable::One, value)) if form.len() == 1 => Some(*value),
+        _ => None,
+    }
+}
+
+/// Turns the linear form into an expression of the form `a + 2 * b - c - 7`.
+fn to_expression<T: FieldElement>(
+    form: &LinearForm<T>,
+    references: &BTreeMap<PolyID, AlgebraicReference>,
+) -> AlgebraicExpression<T> {
+    // Constants go last.
+    let terms = form
+        .iter()
+        .filter(|(v, _)| matches!(v, Variable::Column(_)))
+        .chain(form.iter().filter(|(v, _)| matches!(v, Variable::One)))
+        .map(|(v, c)| {
+            let (negative, c) = if c.is_in_lower_half() {
+                (false, *c)
+            } else {
+                (true, -*c)
+            };
+            let term = match v {
+                Variable::One => AlgebraicExpression::Number(c),
+                Variable::Column(id) => {
+                    let reference = AlgebraicExpression::Reference(references[id].clone());
+                    if c == T::one() {
+                        reference
+                    } else {
+                        AlgebraicExpression::Number(c) * reference
+                    }
+                }
+            };
+            (negative, term)
+        });
+    terms
+        .fold(None, |acc, (negative, term)| {
+            Some(match (acc, negative) {
+                (None, false) => term,
+                (None, true) => AlgebraicExpression::new_unary(AlgebraicUnaryOperator::Minus, term),
+                (Some(acc), false) => acc + term,
+                (Some(acc), true) => acc - term,
+            })
+        })
+        .unwrap_or(AlgebraicExpression::Number(T::zero()))
+}
+
+fn namespace_of(name: &str) -> &str {
+    name.rsplit_once("::").map(|(ns, _)| ns).unwrap_or_default()
+}
@@ -0,0 +1,590 @@
+//! Reduction of the degree of constraints to a given bound.
+//!
+//! Sub-expressions of constraints that exceed the bound are moved into new witness
+//! columns, constrained to be equal to the sub-expression. Each new column is declared
+//! in the namespace and at the stage of the columns it depends on, and if the
+//! standard library is available, it gets a hint that lets witness generation fill
+//! it from the sub-expression. Sub-expressions that only refer to the next row are
+//! shifted to the current row and the new column is referenced at the next row instead,
+//! so that the hint only needs the values of a single row.
+
+use std::collections::{BTreeMap, HashSet};
+use std::iter::once;
+
+use powdr_ast::analyzed::{
+    AlgebraicBinaryOperation, AlgebraicBinaryOperator, AlgebraicExpression, AlgebraicReference,
+    AlgebraicUnaryOperation, Analyzed, BusInteractionIdentity, DegreeRange, Expression,
+    FunctionValueDefinition, Identity, LookupIdentity, PermutationIdentity,
+    PhantomBusInteractionIdentity, PhantomLookupIdentity, PhantomPermutationIdentity, PolyID,
+    PolynomialReference, PolynomialType, Reference, StatementIdentifier, Symbol, SymbolKind,
+    TypedExpression,
+};
+use powdr_ast::parsed::types::Type;
+use powdr_ast::parsed::visitor::{AllChildren, Children, ExpressionVisitable};
+use powdr_ast::parsed::{
+    BinaryOperation, FunctionCall, FunctionKind, IndexAccess, LambdaExpression, Number, Pattern,
+    SourceReference, UnaryOperation, UnaryOperator,
+};
+use powdr_number::{BigUint, FieldElement};
+use powdr_parser_util::SourceRef;
+
+/// The symbols the hints of the new witness columns refer to.
+const HINT_SYMBOLS: [&str; 3] = [
+    "std::prelude::set_hint",
+    "std::prelude::Query::Hint",
+    "std::prover::eval",
+];
+
+/// Rewrites all polynomial identities, the expressions of lookups, permutations and
+/// bus interactions and the definitions of intermediate columns such that none of them
+/// has a degree larger than `max_degree`. The selector of a lookup or permutation and the
+/// multiplicity of a bus interaction are reduced together with the tuple they multiply.
+/// Powers with a non-constant exponent are left unreduced.
+///
+/// Panics if `max_degree` is smaller than 2, see [crate::PassManager::validate].
+pub fn reduce_degree<T: FieldElement>(mut pil_file: Analyzed<T>, max_degree: usize) -> Analyzed<T> {
+    assert!(
+        max_degree >= 2,
+        "Cannot reduce the constraint degree below 2, but {max_degree} was requested."
+    );
+    let mut reducer = DegreeReducer::new(&pil_file, max_degree);
+
+    let intermediates = reducer.intermediates.keys().copied().collect::<Vec<_>>();
+    for poly_id in intermediates {
+        reducer.reduce_intermediate(poly_id);
+    }
+    for (symbol, definitions) in pil_file.intermediate_columns.values_mut() {
+        for ((_, poly_id), definition) in symbol.array_elements().zip(definitions) {
+            *definition = reducer.intermediates[&poly_id].clone();
+        }
+    }
+
+    for identity in &mut pil_file.identities {
+        reducer.source = identity.source_reference().clone();
+        reducer.reduce_identity(identity);
+    }
+
+    let new_columns = reducer.new_columns.len();
+    let with_hints = HINT_SYMBOLS
+        .iter()
+        .all(|name| pil_file.definitions.contains_key(*name));
+    let mut new_identities = vec![];
+    for (symbol, definition, source) in std::mem::take(&mut reducer.new_columns) {
+        let reference = AlgebraicReference {
+            name: symbol.absolute_name.clone(),
+            poly_id: PolyID::from(&symbol),
+            next: false,
+        };
+        let hint = with_hints.then(|| reducer.hint(&definition));
+        pil_file.source_order.push(StatementIdentifier::Definition(
+            symbol.absolute_name.clone(),
+        ));
+        pil_file
+            .definitions
+            .insert(symbol.absolute_name.clone(), (symbol, hint));
+        new_identities.push((
+            AlgebraicExpression::Reference(reference) - definition,
+            source,
+        ));
+    }
+    for (identity, source) in new_identities {
+        pil_file.append_polynomial_identity(identity, source);
+    }
+    if new_columns > 0 {
+        log::info!(
+            "Introduced {new_columns} witness columns to reduce the constraint degree to {max_degree}."
+        );
+    }
+    pil_file
+}
+
+struct DegreeReducer<T> {
+    max_degree: usize,
+    /// The name of the symbol defining each column, with the index for array elements.
+    column_names: BTreeMap<PolyID, (String, Option<usize>)>,
+    /// The stage of each witness column.
+    stages: BTreeMap<PolyID, u32>,
+    /// The degree range of each namespace.
+    namespace_degrees: BTreeMap<String, DegreeRange>,
+    /// The definitions of all intermediate columns and array elements.
+    intermediates: BTreeMap<PolyID, AlgebraicExpression<T>>,
+    /// The degrees of the intermediate columns whose definitions have already been reduced.
+    intermediate_degrees: BTreeMap<PolyID, usize>,
+    /// The new witness columns, with the expression they are constrained to
+    /// and the source reference of the constraint they were extracted from.
+    new_columns: Vec<(Symbol, AlgebraicExpression<T>, SourceRef)>,
+    /// Expressions that have already been moved to new witness columns.
+    extracted: BTreeMap<AlgebraicExpression<T>, AlgebraicReference>,
+    /// All symbol names, used to find unique names for the new columns.
+    names: HashSet<String>,
+    next_witness_id: u64,
+    /// The source reference of the constraint currently processed.
+    source: SourceRef,
+}
+
+impl<T: FieldElement> DegreeReducer<T> {
+    fn new(pil_file: &Analyzed<T>, max_degree: usize) -> Self {
+        let symbols = pil_file
+            .definitions
+            .values()
+            .map(|(symbol, _)| symbol)
+            .chain(
+                pil_file
+                    .intermediate_columns
+                    .values()
+                    .map(|(symbol, _)| symbol),
+            )
+            .filter(|symbol| matches!(symbol.kind, SymbolKind::Poly(_)))
+            .collect::<Vec<_>>();
+
+        let column_names = symbols
+            .iter()
+            .flat_map(|symbol| {
+                symbol
+                    .array_elements()
+                    .enumerate()
+                    .map(move |(index, (_, poly_id))| {
+                        let index = symbol.is_array().then_some(index);
+                        (poly_id, (symbol.absolute_name.clone(), index))
+                    })
+            })
+            .collect();
+        let stages = symbols
+            .iter()
+            .filter(|symbol| symbol.kind == SymbolKind::Poly(PolynomialType::Committed))
+            .flat_map(|symbol| {
+                symbol
+                    .array_elements()
+                    .map(|(_, poly_id)| (poly_id, symbol.stage.unwrap_or(0)))
+            })
+            .collect();
+        let mut namespace_degrees = BTreeMap::new();
+        for symbol in &symbols {
+            if let Some(degree) = symbol.degree {
+                namespace_degrees
+                    .entry(namespace_of(&symbol.absolute_name).to_string())
+                    .or_insert(degree);
+            }
+        }
+        let intermediates = pil_file
+            .intermediate_columns
+            .values()
+            .flat_map(|(symbol, definitions)| {
+                symbol
+                    .array_elements()
+                    .zip(definitions)
+                    .map(|((_, poly_id), definition)| (poly_id, definition.clone()))
+            })
+            .collect();
+
+        Self {
+            max_degree,
+            column_names,
+            stages,
+            namespace_degrees,
+            intermediates,
+            intermediate_degrees: Default::default(),
+            new_columns: vec![],
+            extracted: Default::default(),
+            names: pil_file
+                .definitions
+                .keys()
+                .chain(pil_file.intermediate_columns.keys())
+                .cloned()
+                .collect(),
+            next_witness_id: pil_file.commitment_count() as u64,
+            source: Default::default(),
+        }
+    }
+
+    /// Reduces the definition of the given intermediate column, after reducing the
+    /// definitions of all intermediate columns it references.
+    fn reduce_intermediate(&mut self, poly_id: PolyID) {
+        if self.intermediate_degrees.contains_key(&poly_id) {
+            return;
+        }
+        let mut definition = self.intermediates[&poly_id].clone();
+        let referenced = definition
+            .all_children()
+            .filter_map(|e| match e {
+                AlgebraicExpression::Reference(r)
+                    if r.poly_id.ptype == PolynomialType::Intermediate =>
+                {
+                    Some(r.poly_id)
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        for r in referenced {
+            self.reduce_intermediate(r);
+        }
+        self.reduce(&mut definition);
+        self.intermediate_degrees
+            .insert(poly_id, self.degree(&definition));
+        self.intermediates.insert(poly_id, definition);
+    }
+
+    /// Reduces all expressions of the identity. Connect identities are left unchanged.
+    fn reduce_identity(&mut self, identity: &mut Identity<T>) {
+        match identity {
+            Identity::Connect(_) => return,
+            Identity::Lookup(LookupIdentity { left, right, .. })
+            | Identity::PhantomLookup(PhantomLookupIdentity { left, right, .. })
+            | Identity::Permutation(PermutationIdentity { left, right, .. })
+            | Identity::PhantomPermutation(PhantomPermutationIdentity { left, right, .. }) => {
+                for side in [left, right] {
+                    self.reduce_tuple(&mut side.selector, side.expressions.iter_mut().collect());
+                }
+            }
+            Identity::BusInteraction(BusInteractionIdentity {
+                multiplicity,
+                bus_id,
+                payload,
+                ..
+            })
+            | Identity::PhantomBusInteraction(PhantomBusInteractionIdentity {
+                multiplicity,
+                bus_id,
+                payload,
+                ..
+            }) => {
+                self.reduce_tuple(multiplicity, once(bus_id).chain(&mut payload.0).collect());
+            }
+            Identity::Polynomial(_) => {}
+        }
+        // Reducing an expression again does not change it, so this only reduces
+        // the expressions not handled above.
+        for expression in identity.children_mut() {
+            self.reduce(expression);
+        }
+    }
+
+    /// Reduces a factor and a tuple of expressions that are each multiplied with it, such
+    /// that the degree of none of these products exceeds the maximum degree.
+    fn reduce_tuple(
+        &mut self,
+        factor: &mut AlgebraicExpression<T>,
+        mut tuple: Vec<&mut AlgebraicExpression<T>>,
+    ) {
+        self.reduce(factor);
+        for e in &mut tuple {
+            self.reduce(e);
+        }
+        loop {
+            let factor_degree = self.degree(factor);
+            let Some((e, degree)) = tuple
+                .iter_mut()
+                .map(|e| {
+                    let degree = self.degree(e);
+                    (e, degree)
+                })
+                .max_by_key(|(_, degree)| *degree)
+            else {
+                return;
+            };
+            if factor_degree + degree <= self.max_degree {
+                return;
+            }
+            if degree >= factor_degree {
+                self.extract(e);
+            } else {
+                self.extract(factor);
+            }
+        }
+    }
+
+    /// Rewrites the expression such that its degree does not exceed the maximum degree.
+    /// All intermediate columns it references need to be reduced already.
+    fn reduce(&mut self, e: &mut AlgebraicExpression<T>) {
+        match e {
+            AlgebraicExpression::BinaryOperation(AlgebraicBinaryOperation {
+                left,
+                op: AlgebraicBinaryOperator::Mul,
+                right,
+            }) => {
+                self.reduce(left);
+                self.reduce(right);
+                loop {
+                    let (left_degree, right_degree) = (self.degree(left), self.degree(right));
+                    if left_degree + right_degree <= self.max_degree {
+                        break;
+                    }
+                    if left_degree >= right_degree {
+                        self.extract(left);
+                    } else {
+                        self.extract(right);
+                    }
+                }
+            }
+            AlgebraicExpression::BinaryOperation(AlgebraicBinaryOperation {
+                left,
+                op: AlgebraicBinaryOperator::Pow,
+                right,
+            }) => match exponent(right) {
+                Some(exponent) if self.degree(left) * exponent > self.max_degree => {
+                    // Turn the power into a product and reduce that.
+                    *e = power_as_product(left, exponent);
+                    self.reduce(e);
+                }
+                _ => self.reduce(left),
+            },
+            AlgebraicExpression::BinaryOperation(AlgebraicBinaryOperation {
+                left, right, ..
+            }) => {
+                self.reduce(left);
+                self.reduce(right);
+            }
+            AlgebraicExpression::UnaryOperation(AlgebraicUnaryOperation { expr, .. }) => {
+                self.reduce(expr);
+            }
+            AlgebraicExpression::Reference(_)
+            | AlgebraicExpression::PublicReference(_)
+            | AlgebraicExpression::Challenge(_)
+            | AlgebraicExpression::Number(_) => {}
+        }
+    }
+
+    fn degree(&self, e: &AlgebraicExpression<T>) -> usize {
+        match e {
+            AlgebraicExpression::Reference(reference) => match reference.poly_id.ptype {
+                PolynomialType::Committed | PolynomialType::Constant => 1,
+                PolynomialType::Intermediate => self.intermediate_degrees[&reference.poly_id],
+            },
+            AlgebraicExpression::BinaryOperation(AlgebraicBinaryOperation {
+                left,
+                op: AlgebraicBinaryOperator::Mul,
+                right,
+            }) => self.degree(left) + self.degree(right),
+            AlgebraicExpression::BinaryOperation(AlgebraicBinaryOperation {
+                left,
+                op: AlgebraicBinaryOperator::Pow,
+                right,
+            }) => match exponent(right) {
+                Some(exponent) => self.degree(left) * exponent,
+                // Like `AlgebraicExpression::degree`, which does not know the exponent either.
+                None => self.degree(left).max(self.degree(right)),
+            },
+            AlgebraicExpression::BinaryOperation(AlgebraicBinaryOperation {
+                left, right, ..
+            }) => self.degree(left).max(self.degree(right)),
+            AlgebraicExpression::UnaryOperation(AlgebraicUnaryOperation { expr, .. }) => {
+                self.degree(expr)
+            }
+            AlgebraicExpression::PublicReference(_)
+            | AlgebraicExpression::Challenge(_)
+            | AlgebraicExpression::Number(_) => 0,
+        }
+    }
+
+    /// Replaces the expression by a reference to a witness column constrained to be
+    /// equal to it, creating the column if the expression has not been extracted before.
+    fn extract(&mut self, e: &mut AlgebraicExpression<T>) {
+        assert!(self.degree(e) > 1);
+        let mut definition = std::mem::replace(e, AlgebraicExpression::Number(T::zero()));
+        let next = refers_only_to_next_row(&definition);
+        if next {
+            definition.post_visit_expressions_mut(&mut |e| {
+                if let AlgebraicExpression::Reference(r) = e {
+                    r.next = false;
+                }
+            });
+        }
+        let mut reference = match self.extracted.get(&definition) {
+            Some(reference) => reference.clone(),
+            None => self.new_column(definition),
+        };
+        reference.next = next;
+        *e = AlgebraicExpression::Reference(reference);
+    }
+
+    fn new_column(&mut self, definition: AlgebraicExpression<T>) -> AlgebraicReference {
+        let namespace = definition
+            .all_children()
+            .find_map(|e| match e {
+                AlgebraicExpression::Reference(r) => {
+                    Some(namespace_of(&self.column_names[&r.poly_id].0).to_string())
+                }
+                _ => None,
+            })
+            .unwrap();
+        let name = (0..)
+            .map(|i| format!("{namespace}::degree_reduction_{i}"))
+            .find(|name| !self.names.contains(name))
+            .unwrap();
+        let stage = self.stage(&definition);
+        let symbol = Symbol {
+            id: self.next_witness_id,
+            source: self.source.clone(),
+            absolute_name: name.clone(),
+            stage: (stage > 0).then_some(stage),
+            kind: SymbolKind::Poly(PolynomialType::Committed),
+            length: None,
+            degree: self.namespace_degrees.get(&namespace).copied(),
+        };
+        self.next_witness_id += 1;
+        let reference = AlgebraicReference {
+            name: name.clone(),
+            poly_id: PolyID::from(&symbol),
+            next: false,
+        };
+
+        self.names.insert(name.clone());
+        self.column_names
+            .insert(reference.poly_id, (name.clone(), None));
+        self.stages.insert(reference.poly_id, stage);
+        self.extracted.insert(definition.clone(), reference.clone());
+        self.new_columns
+            .push((symbol, definition, self.source.clone()));
+        reference
+    }
+
+    /// Returns the earliest stage at which the expression can be evaluated.
+    fn stage(&self, e: &AlgebraicExpression<T>) -> u32 {
+        e.all_children()
+            .map(|e| match e {
+                AlgebraicExpression::Reference(r) => match r.poly_id.ptype {
+                    PolynomialType::Committed => self.stages[&r.poly_id],
+                    PolynomialType::Constant => 0,
+                    PolynomialType::Intermediate => self.stage(&self.intermediates[&r.poly_id]),
+                },
+                AlgebraicExpression::Challenge(challenge) => challenge.stage + 1,
+                _ => 0,
+            })
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Returns the hint `query |_| std::prelude::Query::Hint(std::prover::eval(<definition>))`.
+    fn hint(&self, definition: &AlgebraicExpression<T>) -> FunctionValueDefinition {
+        let body = FunctionCall {
+            function: Box::new(symbol_reference("std::prelude::Query::Hint")),
+            arguments: vec![FunctionCall {
+                function: Box::new(symbol_reference("std::prover::eval")),
+                arguments: vec![self.to_expression(definition)],
+            }
+            .into()],
+        };
+        FunctionValueDefinition::Expression(TypedExpression {
+            e: LambdaExpression {
+                kind: FunctionKind::Query,
+                params: vec![Pattern::CatchAll(SourceRef::unknown())],
+                body: Box::new(body.into()),
+                param_types: vec![Type::Int],
+            }
+            .into(),
+            type_scheme: None,
+        })
+    }
+
+    /// Converts an algebraic expression to an expression that evaluates to it.
+    fn to_expression(&self, e: &AlgebraicExpression<T>) -> Expression {
+        match e {
+            AlgebraicExpression::Reference(r) => {
+                let (name, index) = &self.column_names[&r.poly_id];
+                let mut e = symbol_reference(name);
+                if let Some(index) = index {
+                    e = IndexAccess {
+                        array: Box::new(e),
+                        index: Box::new(
+                            Number {
+                                value: BigUint::from(*index),
+                                type_: Some(Type::Int),
+                            }
+                            .into(),
+                        ),
+                    }
+                    .into();
+                }
+                if r.next {
+                    e = UnaryOperation {
+                        op: UnaryOperator::Next,
+                        expr: Box::new(e),
+                    }
+                    .into();
+                }
+                e
+            }
+            AlgebraicExpression::PublicReference(name) => symbol_reference(name),
+            AlgebraicExpression::Challenge(challenge) => FunctionCall {
+                function: Box::new(symbol_reference("std::prelude::challenge")),
+                arguments: [challenge.stage as u64, challenge.id]
+                    .into_iter()
+                    .map(|x| BigUint::from(x).into())
+                    .collect(),
+            }
+            .into(),
+            AlgebraicExpression::Number(n) => Number {
+                value: n.to_arbitrary_integer(),
+                type_: Some(Type::Expr),
+            }
+            .into(),
+            AlgebraicExpression::BinaryOperation(AlgebraicBinaryOperation { left, op, right }) => {
+                BinaryOperation {
+                    left: Box::new(self.to_expression(left)),
+                    op: (*op).into(),
+                    right: Box::new(self.to_expression(right)),
+                }
+                .into()
+            }
+            AlgebraicExpression::UnaryOperation(operation) => UnaryOperation {
+                op: operation.op.into(),
+                expr: Box::new(self.to_expression(&operation.expr)),
+            }
+            .into(),
+        }
+    }
+}
+
+fn symbol_reference(name: &str) -> Expression {
+    Expression::Reference(
+        SourceRef::unknown(),
+        Reference::Poly(PolynomialReference {
+            name: name.to_string(),
+            type_args: None,
+        }),
+    )
+}
+
+/// Returns true if the expression references columns, but only at the next row.
+fn refers_only_to_next_row<T>(e: &AlgebraicExpression<T>) -> bool {
+    let mut references = e
+        .all_children()
+        .filter_map(|e| match e {
+            AlgebraicExpression::Reference(r) => Some(r.next),
+            _ => None,
+        })
+        .peekable();
+    references.peek().is_some() && references.all(|next| next)
+}
+
+fn namespace_of(name: &str) -> &str {
+    name.rsplit_once("::").map(|(ns, _)| ns).unwrap_or_default()
+}
+
+/// Returns the exponent of a power, if it is a constant.
+fn exponent<T: FieldElement>(e: &AlgebraicExpression<T>) -> Option<usize> {
+    match e {
+        AlgebraicExpression::Number(n) => Some(n.to_degree() as usize),
+        _ => None,
+    }
+}
+
+/// Returns `base**exponent` as a balanced product.
+fn power_as_product<T: FieldElement>(
+    base: &AlgebraicExpression<T>,
+    exponent: usize,
+) -> AlgebraicExpression<T> {
+    match exponent {
+        0 => AlgebraicExpression::Number(T::one()),
+        1 => base.clone(),
+        _ => {
+            let half = power_as_product(base, exponent / 2);
+            let square = half.clone() * half;
+            if exponent % 2 == 1 {
+                square * base.clone()
+            } else {
+                square
+            }
+        }
+    }
+}
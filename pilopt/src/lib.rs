@@ -17,32 +17,36 @@ use powdr_ast::parsed::visitor::{AllChildren, Children, ExpressionVisitable};
 use powdr_ast::parsed::Number;
 use powdr_number::{BigUint, FieldElement};
 
+mod affine_columns;
+pub mod degree_reduction;
+mod pass_manager;
 pub mod referenced_symbols;
+mod substitutions;
 
+pub use affine_columns::remove_affine_witness_columns;
+pub use degree_reduction::reduce_degree;
+pub use pass_manager::{OptimizerOutput, OptimizerStatistics, Pass, PassManager, PassStatistics};
 use referenced_symbols::{ReferencedSymbols, SymbolReference};
+pub use substitutions::{Substitution, SubstitutionRecord};
 
-pub fn optimize<T: FieldElement>(mut pil_file: Analyzed<T>) -> Analyzed<T> {
+/// Runs the default optimizer passes until the PIL file does not change anymore.
+pub fn optimize<T: FieldElement>(pil_file: Analyzed<T>) -> Analyzed<T> {
+    optimize_with(pil_file, &PassManager::default())
+        .expect("The default passes do not write any files")
+        .pil_file
+}
+
+/// Runs the passes configured in the pass manager and returns the optimized
+/// PIL file together with statistics about the effect of each pass and the
+/// substitutions of witness columns.
+/// Fails if the pass manager cannot write to its dump directory.
+pub fn optimize_with<T: FieldElement>(
+    pil_file: Analyzed<T>,
+    pass_manager: &PassManager,
+) -> Result<OptimizerOutput<T>, String> {
     let col_count_pre = (pil_file.commitment_count(), pil_file.constant_count());
-    let mut pil_hash = hash_pil_state(&pil_file);
-    loop {
-        remove_unreferenced_definitions(&mut pil_file);
-        remove_constant_fixed_columns(&mut pil_file);
-        deduplicate_fixed_columns(&mut pil_file);
-        simplify_identities(&mut pil_file);
-        extract_constant_lookups(&mut pil_file);
-        remove_constant_witness_columns(&mut pil_file);
-        remove_constant_intermediate_columns(&mut pil_file);
-        simplify_identities(&mut pil_file);
-        remove_equal_constrained_witness_columns(&mut pil_file);
-        remove_trivial_identities(&mut pil_file);
-        remove_duplicate_identities(&mut pil_file);
-
-        let new_hash = hash_pil_state(&pil_file);
-        if pil_hash == new_hash {
-            break;
-        }
-        pil_hash = new_hash;
-    }
+    let output = pass_manager.run(pil_file)?;
+    let pil_file = &output.pil_file;
     let col_count_post = (pil_file.commitment_count(), pil_file.constant_count());
     log::info!(
         "Removed {} witness and {} fixed columns. Total count now: {} witness and {} fixed columns.",
@@ -51,7 +55,7 @@ pub fn optimize<T: FieldElement>(mut pil_file: Analyzed<T>) -> Analyzed<T> {
         col_count_post.0,
         col_count_post.1
     );
-    pil_file
+    Ok(output)
 }
 
 fn hash_pil_state<T: Hash>(pil_file: &Analyzed<T>) -> u64 {
@@ -89,7 +93,10 @@ fn hash_pil_state<T: Hash>(pil_file: &Analyzed<T>) -> u64 {
 
 /// Removes all definitions that are not referenced by an identity, public declaration
 /// or witness column hint.
-fn remove_unreferenced_definitions<T: FieldElement>(pil_file: &mut Analyzed<T>) {
+fn remove_unreferenced_definitions<T: FieldElement>(
+    pil_file: &mut Analyzed<T>,
+    substitutions: &mut SubstitutionRecord<T>,
+) {
     let poly_id_to_definition_name = build_poly_id_to_definition_name_lookup(pil_file);
     let mut symbols_seen = collect_required_symbols(pil_file, &poly_id_to_definition_name);
     let mut impls_to_retain = HashSet::new();
@@ -157,6 +164,15 @@ fn remove_unreferenced_definitions<T: FieldElement>(pil_file: &mut Analyzed<T>)
         .filter(|name| !required_names.contains(name.as_str()))
         .cloned()
         .collect();
+    for name in &definitions_to_remove {
+        if let Some((symbol, _)) = pil_file.definitions.get(name) {
+            if symbol.kind == SymbolKind::Poly(PolynomialType::Committed) {
+                for (element, _) in symbol.array_elements() {
+                    substitutions.record(element, Substitution::Unconstrained);
+                }
+            }
+        }
+    }
     pil_file.remove_definitions(&definitions_to_remove);
     let impls_to_remove = (0..pil_file.trait_impls.len())
         .filter(|i| !impls_to_retain.contains(i))
@@ -556,7 +572,10 @@ fn extract_constant_lookups<T: FieldElement>(pil_file: &mut Analyzed<T>) {
 
 /// Identifies witness columns that are constrained to a single value, replaces every
 /// reference to this column by the value and deletes the column.
-fn remove_constant_witness_columns<T: FieldElement>(pil_file: &mut Analyzed<T>) {
+fn remove_constant_witness_columns<T: FieldElement>(
+    pil_file: &mut Analyzed<T>,
+    substitutions: &mut SubstitutionRecord<T>,
+) {
     let mut constant_polys = pil_file
         .identities
         .iter()
@@ -583,6 +602,13 @@ fn remove_constant_witness_columns<T: FieldElement>(pil_file: &mut Analyzed<T>)
         .collect::<HashSet<PolyID>>();
     constant_polys.retain(|((_, id), _)| columns.contains(id));
 
+    for ((name, _), value) in &constant_polys {
+        let value = T::checked_from(value.clone()).unwrap();
+        substitutions.record(
+            name.clone(),
+            Substitution::Expression(AlgebraicExpression::Number(value)),
+        );
+    }
     substitute_polynomial_references(pil_file, constant_polys);
 }
 
@@ -886,9 +912,12 @@ fn equal_constrained<T: FieldElement>(
     }
 }
 
-fn remove_equal_constrained_witness_columns<T: FieldElement>(pil_file: &mut Analyzed<T>) {
+fn remove_equal_constrained_witness_columns<T: FieldElement>(
+    pil_file: &mut Analyzed<T>,
+    substitutions: &mut SubstitutionRecord<T>,
+) {
     let poly_id_to_array_elem = build_poly_id_to_definition_name_lookup(pil_file);
-    let mut substitutions: BTreeMap<(String, PolyID), (String, PolyID)> = pil_file
+    let mut column_substitutions: BTreeMap<(String, PolyID), (String, PolyID)> = pil_file
         .identities
         .iter()
         .filter_map(|id| {
@@ -900,9 +929,18 @@ fn remove_equal_constrained_witness_columns<T: FieldElement>(pil_file: &mut Anal
         })
         .collect();
 
-    resolve_transitive_substitutions(&mut substitutions);
+    resolve_transitive_substitutions(&mut column_substitutions);
+
+    for ((name, _), (replacement_name, replacement_id)) in &column_substitutions {
+        let replacement = AlgebraicExpression::Reference(AlgebraicReference {
+            name: replacement_name.clone(),
+            poly_id: *replacement_id,
+            next: false,
+        });
+        substitutions.record(name.clone(), Substitution::Expression(replacement));
+    }
 
-    let (subs_by_id, subs_by_name): (HashMap<_, _>, HashMap<_, _>) = substitutions
+    let (subs_by_id, subs_by_name): (HashMap<_, _>, HashMap<_, _>) = column_substitutions
         .iter()
         .map(|(k, v)| ((k.1, v), (&k.0, v)))
         .unzip();
@@ -0,0 +1,398 @@
+//! Configuration of the optimizer passes and statistics about their effect.
+
+use std::fmt::{self, Display, Formatter};
+use std::fs;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use powdr_ast::analyzed::Analyzed;
+use powdr_number::FieldElement;
+use strum::{Display, EnumString, EnumVariantNames, VariantNames};
+
+use super::{
+    affine_columns::substitute_affine_witness_columns, deduplicate_fixed_columns,
+    degree_reduction::reduce_degree, extract_constant_lookups, hash_pil_state,
+    remove_constant_fixed_columns, remove_constant_intermediate_columns,
+    remove_constant_witness_columns, remove_duplicate_identities,
+    remove_equal_constrained_witness_columns, remove_trivial_identities,
+    remove_unreferenced_definitions, simplify_identities, SubstitutionRecord,
+};
+
+/// An optimization pass on a PIL file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, EnumString, EnumVariantNames, Display)]
+pub enum Pass {
+    #[strum(serialize = "remove-unreferenced-definitions")]
+    RemoveUnreferencedDefinitions,
+    #[strum(serialize = "remove-constant-fixed-columns")]
+    RemoveConstantFixedColumns,
+    #[strum(serialize = "deduplicate-fixed-columns")]
+    DeduplicateFixedColumns,
+    #[strum(serialize = "simplify-identities")]
+    SimplifyIdentities,
+    #[strum(serialize = "extract-constant-lookups")]
+    ExtractConstantLookups,
+    #[strum(serialize = "remove-constant-witness-columns")]
+    RemoveConstantWitnessColumns,
+    #[strum(serialize = "remove-constant-intermediate-columns")]
+    RemoveConstantIntermediateColumns,
+    #[strum(serialize = "remove-equal-constrained-witness-columns")]
+    RemoveEqualConstrainedWitnessColumns,
+    #[strum(serialize = "remove-trivial-identities")]
+    RemoveTrivialIdentities,
+    #[strum(serialize = "remove-duplicate-identities")]
+    RemoveDuplicateIdentities,
+    /// Not run by default, see [crate::remove_affine_witness_columns].
+    #[strum(serialize = "remove-affine-witness-columns")]
+    RemoveAffineWitnessColumns,
+    /// Not run by default, see [crate::reduce_degree]. Needs a maximum degree,
+    /// see [PassManager::with_max_degree].
+    #[strum(serialize = "reduce-degree")]
+    ReduceDegree,
+}
+
+impl Pass {
+    fn run<T: FieldElement>(
+        self,
+        pil_file: &mut Analyzed<T>,
+        substitutions: &mut SubstitutionRecord<T>,
+        max_degree: Option<usize>,
+    ) {
+        match self {
+            Pass::RemoveUnreferencedDefinitions => {
+                remove_unreferenced_definitions(pil_file, substitutions)
+            }
+            Pass::RemoveConstantFixedColumns => remove_constant_fixed_columns(pil_file),
+            Pass::DeduplicateFixedColumns => deduplicate_fixed_columns(pil_file),
+            Pass::SimplifyIdentities => simplify_identities(pil_file),
+            Pass::ExtractConstantLookups => extract_constant_lookups(pil_file),
+            Pass::RemoveConstantWitnessColumns => {
+                remove_constant_witness_columns(pil_file, substitutions)
+            }
+            Pass::RemoveConstantIntermediateColumns => {
+                remove_constant_intermediate_columns(pil_file)
+            }
+            Pass::RemoveEqualConstrainedWitnessColumns => {
+                remove_equal_constrained_witness_columns(pil_file, substitutions)
+            }
+            Pass::RemoveTrivialIdentities => remove_trivial_identities(pil_file),
+            Pass::RemoveDuplicateIdentities => remove_duplicate_identities(pil_file),
+            Pass::RemoveAffineWitnessColumns => {
+                substitute_affine_witness_columns(pil_file, substitutions);
+            }
+            Pass::ReduceDegree => {
+                *pil_file = reduce_degree(std::mem::take(pil_file), max_degree.unwrap());
+            }
+        }
+    }
+}
+
+/// Runs a sequence of passes repeatedly, until the PIL file does not change anymore.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PassManager {
+    passes: Vec<Pass>,
+    /// If set, the PIL file is written to this directory after each pass.
+    dump_directory: Option<PathBuf>,
+    /// The maximum constraint degree for [Pass::ReduceDegree].
+    max_degree: Option<usize>,
+}
+
+impl Default for PassManager {
+    fn default() -> Self {
+        Self::new(vec![
+            Pass::RemoveUnreferencedDefinitions,
+            Pass::RemoveConstantFixedColumns,
+            Pass::DeduplicateFixedColumns,
+            Pass::SimplifyIdentities,
+            Pass::ExtractConstantLookups,
+            Pass::RemoveConstantWitnessColumns,
+            Pass::RemoveConstantIntermediateColumns,
+            Pass::SimplifyIdentities,
+            Pass::RemoveEqualConstrainedWitnessColumns,
+            Pass::RemoveTrivialIdentities,
+            Pass::RemoveDuplicateIdentities,
+        ])
+    }
+}
+
+impl PassManager {
+    /// Creates a pass manager that runs exactly the given passes, in this order.
+    pub fn new(passes: Vec<Pass>) -> Self {
+        Self {
+            passes,
+            dump_directory: None,
+            max_degree: None,
+        }
+    }
+
+    pub fn passes(&self) -> &[Pass] {
+        &self.passes
+    }
+
+    /// Appends the pass to the passes run in each iteration.
+    pub fn with_pass(mut self, pass: Pass) -> Self {
+        self.passes.push(pass);
+        self
+    }
+
+    /// Adds [Pass::ReduceDegree] as the first pass, unless it is configured already.
+    /// It has to run first, see [PassManager::validate].
+    pub fn with_degree_reduction(mut self) -> Self {
+        if !self.passes.contains(&Pass::ReduceDegree) {
+            self.passes.insert(0, Pass::ReduceDegree);
+        }
+        self
+    }
+
+    /// Removes all occurrences of the pass.
+    pub fn without_pass(mut self, pass: Pass) -> Self {
+        self.passes.retain(|p| *p != pass);
+        self
+    }
+
+    /// Writes the PIL file to the given directory after each pass.
+    pub fn with_dump_directory(mut self, directory: PathBuf) -> Self {
+        self.dump_directory = Some(directory);
+        self
+    }
+
+    /// Sets the maximum degree [Pass::ReduceDegree] reduces the constraints to,
+    /// and adds the pass if it is not configured yet.
+    pub fn with_max_degree(mut self, max_degree: usize) -> Self {
+        self.max_degree = Some(max_degree);
+        self.with_degree_reduction()
+    }
+
+    pub fn max_degree(&self) -> Option<usize> {
+        self.max_degree
+    }
+
+    /// Checks that the configuration can be run: [Pass::ReduceDegree] needs a maximum
+    /// degree, and constraints cannot be reduced to a degree below 2. It also has to be
+    /// the first pass, so that the hints of the witness columns it introduces can refer
+    /// to definitions the other passes remove if they are unreferenced.
+    pub fn validate(&self) -> Result<(), String> {
+        if self
+            .passes
+            .iter()
+            .skip(1)
+            .any(|pass| *pass == Pass::ReduceDegree)
+        {
+            return Err(format!(
+                "The optimizer pass {} has to be the first pass.",
+                Pass::ReduceDegree
+            ));
+        }
+        match self.max_degree {
+            Some(max_degree) if max_degree < 2 => Err(format!(
+                "Cannot reduce the constraint degree below 2, but {max_degree} was requested."
+            )),
+            None if self.passes.contains(&Pass::ReduceDegree) => Err(format!(
+                "The optimizer pass {} needs a maximum constraint degree.",
+                Pass::ReduceDegree
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Runs the passes until the PIL file does not change anymore.
+    /// Fails if the PIL file cannot be written to the dump directory or if
+    /// the configuration is invalid, see [PassManager::validate].
+    pub fn run<T: FieldElement>(
+        &self,
+        mut pil_file: Analyzed<T>,
+    ) -> Result<OptimizerOutput<T>, String> {
+        self.validate()?;
+        if let Some(directory) = &self.dump_directory {
+            fs::create_dir_all(directory).map_err(|e| {
+                format!(
+                    "Could not create optimizer dump directory {}: {e}",
+                    directory.display()
+                )
+            })?;
+        }
+        let mut statistics = OptimizerStatistics {
+            iterations: 0,
+            passes: self
+                .passes
+                .iter()
+                .map(|&pass| PassStatistics::new(pass))
+                .collect(),
+        };
+        let mut substitutions = SubstitutionRecord::default();
+        let mut pil_hash = hash_pil_state(&pil_file);
+        loop {
+            for (index, (pass, stats)) in self.passes.iter().zip(&mut statistics.passes).enumerate()
+            {
+                let before = Counts::of(&pil_file);
+                let start = Instant::now();
+                pass.run(&mut pil_file, &mut substitutions, self.max_degree);
+                stats.record(before, Counts::of(&pil_file), start.elapsed());
+
+                if let Some(directory) = &self.dump_directory {
+                    let file = directory.join(format!(
+                        "{:02}_{index:02}_{pass}.pil",
+                        statistics.iterations
+                    ));
+                    fs::write(&file, pil_file.to_string()).map_err(|e| {
+                        format!("Could not write optimizer dump {}: {e}", file.display())
+                    })?;
+                }
+            }
+            statistics.iterations += 1;
+
+            let new_hash = hash_pil_state(&pil_file);
+            if pil_hash == new_hash {
+                break;
+            }
+            pil_hash = new_hash;
+        }
+        log::debug!("Optimizer statistics:\n{statistics}");
+        Ok(OptimizerOutput {
+            pil_file,
+            statistics,
+            substitutions,
+        })
+    }
+}
+
+/// The result of running the optimizer.
+pub struct OptimizerOutput<T> {
+    pub pil_file: Analyzed<T>,
+    pub statistics: OptimizerStatistics,
+    /// How the witness columns of the original PIL file that were substituted or
+    /// removed can be computed from the remaining columns.
+    pub substitutions: SubstitutionRecord<T>,
+}
+
+impl FromStr for PassManager {
+    type Err = String;
+
+    /// Parses a comma-separated list of passes. If all entries are prefixed by `+` or `-`,
+    /// the passes are added to or removed from the default passes, otherwise the list
+    /// is the exact sequence of passes to run. Added passes are appended, except for
+    /// `reduce-degree`, which is added as the first pass.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let entries = s
+            .split(',')
+            .map(|entry| entry.trim())
+            .filter(|entry| !entry.is_empty())
+            .collect::<Vec<_>>();
+        let parse = |name: &str| {
+            Pass::from_str(name).map_err(|_| {
+                format!(
+                    "Unknown optimizer pass: {name}. Available passes: {}",
+                    Pass::VARIANTS.join(", ")
+                )
+            })
+        };
+        if entries
+            .iter()
+            .all(|entry| entry.starts_with('+') || entry.starts_with('-'))
+        {
+            entries
+                .into_iter()
+                .try_fold(PassManager::default(), |manager, entry| {
+                    let (modifier, name) = entry.split_at(1);
+                    let pass = parse(name)?;
+                    Ok(if modifier == "-" {
+                        manager.without_pass(pass)
+                    } else if pass == Pass::ReduceDegree {
+                        manager.with_degree_reduction()
+                    } else {
+                        manager.with_pass(pass)
+                    })
+                })
+        } else {
+            Ok(PassManager::new(
+                entries.into_iter().map(parse).collect::<Result<_, _>>()?,
+            ))
+        }
+    }
+}
+
+/// The number of columns and identities in a PIL file.
+#[derive(Clone, Copy)]
+struct Counts {
+    witness_columns: usize,
+    fixed_columns: usize,
+    intermediate_columns: usize,
+    identities: usize,
+}
+
+impl Counts {
+    fn of<T>(pil_file: &Analyzed<T>) -> Self {
+        Self {
+            witness_columns: pil_file.commitment_count(),
+            fixed_columns: pil_file.constant_count(),
+            intermediate_columns: pil_file.intermediate_count(),
+            identities: pil_file.identities.len(),
+        }
+    }
+}
+
+/// The accumulated effect of a single pass over all iterations.
+/// Removed counts are negative if the pass added columns or identities.
+#[derive(Clone, Debug)]
+pub struct PassStatistics {
+    pub pass: Pass,
+    pub removed_witness_columns: i64,
+    pub removed_fixed_columns: i64,
+    pub removed_intermediate_columns: i64,
+    pub removed_identities: i64,
+    pub time: Duration,
+}
+
+impl PassStatistics {
+    fn new(pass: Pass) -> Self {
+        Self {
+            pass,
+            removed_witness_columns: 0,
+            removed_fixed_columns: 0,
+            removed_intermediate_columns: 0,
+            removed_identities: 0,
+            time: Duration::default(),
+        }
+    }
+
+    fn record(&mut self, before: Counts, after: Counts, time: Duration) {
+        let removed = |before: usize, after: usize| before as i64 - after as i64;
+        self.removed_witness_columns += removed(before.witness_columns, after.witness_columns);
+        self.removed_fixed_columns += removed(before.fixed_columns, after.fixed_columns);
+        self.removed_intermediate_columns +=
+            removed(before.intermediate_columns, after.intermediate_columns);
+        self.removed_identities += removed(before.identities, after.identities);
+        self.time += time;
+    }
+}
+
+/// Statistics about a run of the optimizer, with one entry per configured pass.
+#[derive(Clone, Debug)]
+pub struct OptimizerStatistics {
+    /// The number of times the sequence of passes was run.
+    pub iterations: usize,
+    pub passes: Vec<PassStatistics>,
+}
+
+impl Display for OptimizerStatistics {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{:<42} {:>8} {:>8} {:>8} {:>10} {:>10}",
+            "pass", "witness", "fixed", "inter.", "identities", "time"
+        )?;
+        for stats in &self.passes {
+            writeln!(
+                f,
+                "{:<42} {:>8} {:>8} {:>8} {:>10} {:>10.2?}",
+                stats.pass.to_string(),
+                stats.removed_witness_columns,
+                stats.removed_fixed_columns,
+                stats.removed_intermediate_columns,
+                stats.removed_identities,
+                stats.time
+            )?;
+        }
+        write!(f, "({} iterations)", self.iterations)
+    }
+}
@@ -0,0 +1,49 @@
+//! A record of the witness columns the optimizer substituted or removed.
+
+use powdr_ast::analyzed::AlgebraicExpression;
+
+/// How the values of a witness column that was substituted or removed by the optimizer
+/// are determined.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Substitution<T> {
+    /// The column is not referenced by any constraint anymore, so any value satisfies them.
+    Unconstrained,
+    /// The column is equal to the expression, evaluated on the same row.
+    /// The expression only references witness and fixed columns, which are identified
+    /// by their names, since poly IDs change while optimizing.
+    Expression(AlgebraicExpression<T>),
+}
+
+/// The substitutions of witness columns performed by the optimizer, in the order
+/// they were performed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SubstitutionRecord<T> {
+    substitutions: Vec<(String, Substitution<T>)>,
+}
+
+impl<T> Default for SubstitutionRecord<T> {
+    fn default() -> Self {
+        Self {
+            substitutions: vec![],
+        }
+    }
+}
+
+impl<T> SubstitutionRecord<T> {
+    pub(crate) fn record(&mut self, column: String, substitution: Substitution<T>) {
+        self.substitutions.push((column, substitution));
+    }
+
+    /// Returns the substitutions, latest first.
+    ///
+    /// The expression of a substitution references only columns that exist at the time
+    /// of the substitution, so each of them is a column of the optimized PIL file, a fixed
+    /// column of the original PIL file or a column that is substituted later.
+    /// In this order, all referenced columns can be computed before they are used.
+    pub fn latest_first(&self) -> impl Iterator<Item = (&str, &Substitution<T>)> {
+        self.substitutions
+            .iter()
+            .rev()
+            .map(|(column, substitution)| (column.as_str(), substitution))
+    }
+}
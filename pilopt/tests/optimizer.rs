@@ -1,7 +1,10 @@
 use powdr_number::GoldilocksField;
 use powdr_pil_analyzer::analyze_string;
 
-use powdr_pilopt::optimize;
+use powdr_pilopt::{
+    optimize, optimize_with, reduce_degree, remove_affine_witness_columns, OptimizerOutput, Pass,
+    PassManager, Substitution,
+};
 use pretty_assertions::assert_eq;
 
 #[test]
@@ -501,3 +504,393 @@ fn simplify_associative_operations() {
     let optimized = optimize(analyze_string::<GoldilocksField>(input).unwrap()).to_string();
     assert_eq!(optimized, expectation);
 }
+
+#[test]
+fn reduce_degree_of_product() {
+    let input = r#"namespace N(16);
+    col witness x;
+    col witness y;
+    x * x * x * y = y;
+"#;
+    let expectation = r#"namespace N(16);
+    col witness x;
+    col witness y;
+    N::degree_reduction_1 * N::y = N::y;
+    col witness degree_reduction_0;
+    col witness degree_reduction_1;
+    N::degree_reduction_0 = N::x * N::x;
+    N::degree_reduction_1 = N::degree_reduction_0 * N::x;
+"#;
+    let reduced = reduce_degree(analyze_string::<GoldilocksField>(input).unwrap(), 2).to_string();
+    assert_eq!(reduced, expectation);
+}
+
+#[test]
+fn reduce_degree_reuses_columns() {
+    let input = r#"namespace N(16);
+    col witness x;
+    col witness y;
+    col sq = x * x;
+    sq * sq = y;
+"#;
+    let expectation = r#"namespace N(16);
+    col witness x;
+    col witness y;
+    col sq = N::x * N::x;
+    N::degree_reduction_0 * N::degree_reduction_0 = N::y;
+    col witness degree_reduction_0;
+    N::degree_reduction_0 = N::sq;
+"#;
+    let reduced = reduce_degree(analyze_string::<GoldilocksField>(input).unwrap(), 2).to_string();
+    assert_eq!(reduced, expectation);
+}
+
+#[test]
+fn reduce_degree_next_references() {
+    let input = r#"namespace N(16);
+    col witness x;
+    col witness y;
+    x' * x' * x' = y;
+    x * y' * y' = x;
+"#;
+    // The first extracted expression only refers to the next row, so it is
+    // shifted to the current row and the new column is referenced at the next row.
+    let expectation = r#"namespace N(16);
+    col witness x;
+    col witness y;
+    N::degree_reduction_0' * N::x' = N::y;
+    N::degree_reduction_1 * N::y' = N::x;
+    col witness degree_reduction_0;
+    col witness degree_reduction_1;
+    N::degree_reduction_0 = N::x * N::x;
+    N::degree_reduction_1 = N::x * N::y';
+"#;
+    let reduced = reduce_degree(analyze_string::<GoldilocksField>(input).unwrap(), 2).to_string();
+    assert_eq!(reduced, expectation);
+}
+
+#[test]
+fn reduce_degree_lookup_selector() {
+    let input = r#"namespace N(16);
+    col fixed cnt = [0, 1]*;
+    col witness s;
+    col witness x;
+    col witness y;
+    s $ [x * y] in [cnt];
+    s * s $ [x] in [cnt];
+"#;
+    // The selector is multiplied with each expression of the tuple, so the degrees of
+    // the selector and of the tuple add up even if neither exceeds the bound on its own.
+    let expectation = r#"namespace N(16);
+    col fixed cnt = [0, 1]*;
+    col witness s;
+    col witness x;
+    col witness y;
+    N::s $ [N::degree_reduction_0] in [N::cnt];
+    N::degree_reduction_1 $ [N::x] in [N::cnt];
+    col witness degree_reduction_0;
+    col witness degree_reduction_1;
+    N::degree_reduction_0 = N::x * N::y;
+    N::degree_reduction_1 = N::s * N::s;
+"#;
+    let reduced = reduce_degree(analyze_string::<GoldilocksField>(input).unwrap(), 2).to_string();
+    assert_eq!(reduced, expectation);
+}
+
+#[test]
+fn reduce_degree_later_stage() {
+    let input = r#"namespace std::prover;
+    let eval: expr -> fe = [];
+namespace N(16);
+    col witness x;
+    col witness stage(1) z;
+    let alpha: expr = std::prelude::challenge(0, 1);
+    z' * z' * x = 1;
+    (x + alpha) * (x + alpha) * x = z;
+    x * x * x = 1;
+"#;
+    let reduced = reduce_degree(analyze_string::<GoldilocksField>(input).unwrap(), 2);
+    let stage = |name: &str| reduced.definitions[name].0.stage;
+    // Depends on a witness column of stage 1.
+    assert_eq!(stage("N::degree_reduction_0"), Some(1));
+    // Depends on a challenge of stage 0, which is available from stage 1.
+    assert_eq!(stage("N::degree_reduction_1"), Some(1));
+    assert_eq!(stage("N::degree_reduction_2"), None);
+    // All new columns get a hint that evaluates their definition on the current row.
+    let printed = reduced.to_string();
+    for hint in [
+        "std::prover::eval(N::z * N::z)",
+        "std::prover::eval((N::x + std::prelude::challenge(0, 1)) * (N::x + std::prelude::challenge(0, 1)))",
+        "std::prover::eval(N::x * N::x)",
+    ] {
+        assert!(printed.contains(hint), "Missing hint {hint} in:\n{printed}");
+    }
+}
+
+#[test]
+fn reduce_degree_pass() {
+    let input = r#"namespace N(16);
+    col witness x;
+    col witness y;
+    x * x * x * y = y;
+"#;
+    let passes: PassManager = "+reduce-degree".parse().unwrap();
+    assert_eq!(passes.passes().first(), Some(&Pass::ReduceDegree));
+    let error = optimize_with(analyze_string::<GoldilocksField>(input).unwrap(), &passes)
+        .err()
+        .unwrap();
+    assert_eq!(
+        error,
+        "The optimizer pass reduce-degree needs a maximum constraint degree."
+    );
+
+    let passes: PassManager = "simplify-identities,reduce-degree".parse().unwrap();
+    assert_eq!(
+        passes.passes(),
+        &[Pass::SimplifyIdentities, Pass::ReduceDegree]
+    );
+    assert_eq!(
+        passes.with_max_degree(2).validate().unwrap_err(),
+        "The optimizer pass reduce-degree has to be the first pass."
+    );
+
+    let passes = PassManager::default().with_max_degree(1);
+    assert_eq!(
+        passes.validate().unwrap_err(),
+        "Cannot reduce the constraint degree below 2, but 1 was requested."
+    );
+
+    let passes = PassManager::default().with_max_degree(2);
+    let OptimizerOutput {
+        pil_file: optimized,
+        statistics,
+        ..
+    } = optimize_with(analyze_string::<GoldilocksField>(input).unwrap(), &passes).unwrap();
+    let intermediates = optimized.intermediate_definitions();
+    assert!(optimized
+        .identities
+        .iter()
+        .all(|identity| identity.degree(&intermediates) <= 2));
+    let added_columns = statistics
+        .passes
+        .iter()
+        .filter(|stats| stats.pass == Pass::ReduceDegree)
+        .map(|stats| stats.removed_witness_columns)
+        .sum::<i64>();
+    assert_eq!(added_columns, -2);
+}
+
+#[test]
+fn affine_witness_column() {
+    let input = r#"namespace N(65536);
+    col witness x;
+    col witness y;
+    col witness z;
+    z = x + 2 * y + 1;
+    x * y = z;
+"#;
+    let expectation = r#"namespace N(65536);
+    col witness x;
+    col witness y;
+    col z = N::x + 2 * N::y + 1;
+    N::x * N::y = N::z;
+"#;
+    let mut pil = analyze_string::<GoldilocksField>(input).unwrap();
+    assert_eq!(remove_affine_witness_columns(&mut pil), 1);
+    assert_eq!(pil.commitment_count(), 2);
+    assert_eq!(pil.to_string(), expectation);
+}
+
+#[test]
+fn affine_witness_columns_linear_system() {
+    let input = r#"namespace N(65536);
+    col witness a;
+    col witness b;
+    col witness c;
+    col witness d;
+    a + b = c;
+    c + d = 2 * a;
+    a * b * c * d = 1;
+"#;
+    let expectation = r#"namespace N(65536);
+    col witness a;
+    col witness b;
+    col c = N::a + N::b;
+    col d = N::a - N::b;
+    N::a * N::b * N::c * N::d = 1;
+"#;
+    let mut pil = analyze_string::<GoldilocksField>(input).unwrap();
+    assert_eq!(remove_affine_witness_columns(&mut pil), 2);
+    assert_eq!(pil.to_string(), expectation);
+}
+
+#[test]
+fn affine_witness_columns_keep_referenced() {
+    let input = r#"namespace N(65536);
+    col witness x;
+    col witness y;
+    col witness z[1];
+    y = x + 1;
+    z[0] = x + 2;
+    query |i| {
+        let _ = y;
+    };
+"#;
+    let mut pil = analyze_string::<GoldilocksField>(input).unwrap();
+    assert_eq!(remove_affine_witness_columns(&mut pil), 1);
+    assert_eq!(pil.commitment_count(), 2);
+    assert!(pil.intermediate_columns.contains_key("N::x"));
+}
+
+#[test]
+fn pass_manager_from_str() {
+    let default = PassManager::default();
+    let without: PassManager = "-simplify-identities".parse().unwrap();
+    assert_eq!(without.passes().len(), default.passes().len() - 2);
+    assert!(!without.passes().contains(&Pass::SimplifyIdentities));
+
+    let with: PassManager = "+remove-affine-witness-columns, -remove-trivial-identities"
+        .parse()
+        .unwrap();
+    assert_eq!(
+        with.passes().last(),
+        Some(&Pass::RemoveAffineWitnessColumns)
+    );
+    assert!(!with.passes().contains(&Pass::RemoveTrivialIdentities));
+
+    let exact: PassManager = "remove-trivial-identities,simplify-identities"
+        .parse()
+        .unwrap();
+    assert_eq!(
+        exact.passes(),
+        [Pass::RemoveTrivialIdentities, Pass::SimplifyIdentities]
+    );
+
+    assert!("simplify-identities,+remove-trivial-identities"
+        .parse::<PassManager>()
+        .is_err());
+    assert!("no-such-pass".parse::<PassManager>().is_err());
+}
+
+#[test]
+fn pass_manager_disabled_pass() {
+    let input = r#"namespace N(65536);
+    col fixed one = [1]*;
+    col witness X;
+    col witness Y;
+    X' = X * one + Y;
+"#;
+    let pil = analyze_string::<GoldilocksField>(input).unwrap();
+    let passes = PassManager::default().without_pass(Pass::RemoveConstantFixedColumns);
+    let OptimizerOutput {
+        pil_file: optimized,
+        statistics,
+        ..
+    } = optimize_with(pil, &passes).unwrap();
+    assert_eq!(optimized.constant_count(), 1);
+    assert!(statistics
+        .passes
+        .iter()
+        .all(|stats| stats.pass != Pass::RemoveConstantFixedColumns));
+
+    let pil = analyze_string::<GoldilocksField>(input).unwrap();
+    let OptimizerOutput {
+        pil_file: optimized,
+        statistics,
+        ..
+    } = optimize_with(pil, &PassManager::default()).unwrap();
+    assert_eq!(optimized.constant_count(), 0);
+    let removed_fixed = statistics
+        .passes
+        .iter()
+        .filter(|stats| stats.pass == Pass::RemoveConstantFixedColumns)
+        .map(|stats| stats.removed_fixed_columns)
+        .sum::<i64>();
+    assert_eq!(removed_fixed, 1);
+}
+
+#[test]
+fn pass_manager_dump() {
+    let input = r#"namespace N(65536);
+    col witness X;
+    col witness Y;
+    X = Y;
+    X' = X * Y;
+"#;
+    let directory = mktemp::Temp::new_dir().unwrap();
+    let passes = PassManager::new(vec![
+        Pass::RemoveEqualConstrainedWitnessColumns,
+        Pass::RemoveTrivialIdentities,
+    ])
+    .with_dump_directory(directory.to_path_buf());
+    let pil = analyze_string::<GoldilocksField>(input).unwrap();
+    let OptimizerOutput {
+        pil_file: optimized,
+        statistics,
+        ..
+    } = optimize_with(pil, &passes).unwrap();
+
+    let dump = std::fs::read_to_string(
+        directory.join("00_00_remove-equal-constrained-witness-columns.pil"),
+    )
+    .unwrap();
+    assert!(dump.contains("N::X = N::X;"));
+    assert!(!optimized.to_string().contains("N::X = N::X;"));
+    // The second iteration does not change anything.
+    assert_eq!(statistics.iterations, 2);
+    assert_eq!(
+        std::fs::read_dir(&directory).unwrap().count(),
+        2 * passes.passes().len()
+    );
+    assert_eq!(
+        std::fs::read_to_string(directory.join("01_01_remove-trivial-identities.pil")).unwrap(),
+        optimized.to_string()
+    );
+}
+
+#[test]
+fn pass_manager_dump_error() {
+    let input = r#"namespace N(65536);
+    col witness X;
+    X' = X;
+"#;
+    let directory = mktemp::Temp::new_dir().unwrap();
+    // The dump directory cannot be created, because a file with the same name exists.
+    let file = directory.join("dump");
+    std::fs::write(&file, "").unwrap();
+    let passes = PassManager::default().with_dump_directory(file);
+    let pil = analyze_string::<GoldilocksField>(input).unwrap();
+    let error = optimize_with(pil, &passes).unwrap_err();
+    assert!(error.starts_with("Could not create optimizer dump directory"));
+}
+
+#[test]
+fn substitution_record() {
+    let input = r#"namespace N(65536);
+    col witness X;
+    col witness Y;
+    col witness Z;
+    X = 5;
+    Y = Z;
+    Z' = Z + X;
+"#;
+    let pil = analyze_string::<GoldilocksField>(input).unwrap();
+    let OptimizerOutput { substitutions, .. } =
+        optimize_with(pil, &PassManager::default()).unwrap();
+    let substitutions = substitutions
+        .latest_first()
+        .map(|(column, substitution)| match substitution {
+            Substitution::Unconstrained => format!("{column}: unconstrained"),
+            Substitution::Expression(e) => format!("{column} = {e}"),
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        substitutions,
+        [
+            "N::Z: unconstrained",
+            "N::X: unconstrained",
+            "N::Z = N::Y",
+            "N::X = 5"
+        ]
+    );
+}
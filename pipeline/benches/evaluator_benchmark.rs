@@ -3,9 +3,9 @@ use std::sync::Arc;
 use ::powdr_pipeline::Pipeline;
 use powdr_ast::analyzed::Analyzed;
 use powdr_number::{BigInt, GoldilocksField};
-use powdr_pil_analyzer::evaluator::Value;
+use powdr_pil_analyzer::evaluator::{self, SymbolLookup, Value};
 
-use powdr_pipeline::test_util::{evaluate_function, evaluate_integer_function, std_analyzed};
+use powdr_pipeline::test_util::std_analyzed;
 
 use criterion::{criterion_group, criterion_main, Criterion};
 
@@ -29,76 +29,69 @@ fn sqrt_inputs() -> Vec<(String, u64)> {
         .collect()
 }
 
+/// Calls a function, using either the bytecode VM or the tree-walking interpreter.
+fn call_function<'a>(
+    analyzed: &'a Analyzed<GoldilocksField>,
+    function: &'a str,
+    arguments: Vec<Arc<Value<'a, GoldilocksField>>>,
+    tree_walking: bool,
+) -> Arc<Value<'a, GoldilocksField>> {
+    let mut symbols = evaluator::Definitions {
+        definitions: &analyzed.definitions,
+        solved_impls: &analyzed.solved_impls,
+    };
+    let function = symbols.lookup(function, &None).unwrap();
+    if tree_walking {
+        evaluator::evaluate_function_call_tree_walking(function, arguments, &mut symbols)
+    } else {
+        evaluator::evaluate_function_call(function, arguments, &mut symbols)
+    }
+    .unwrap()
+}
+
+fn integers<'a>(values: &[BigInt]) -> Vec<Arc<Value<'a, GoldilocksField>>> {
+    values
+        .iter()
+        .map(|v| Arc::new(Value::Integer(v.clone())))
+        .collect()
+}
+
+/// Compares the bytecode VM with the tree-walking interpreter.
+/// The `std::math::ff` functions are used in the hints of the std arith machines.
 fn evaluator_benchmark(c: &mut Criterion) {
     let mut group = c.benchmark_group("evaluator-benchmark");
 
     let analyzed = std_analyzed::<GoldilocksField>();
 
-    group.bench_function("std::math::ff::inverse", |b| {
-        b.iter(|| {
-            let modulus = BigInt::from_str_radix(
-                "fffffffffffffffffffffffffffffffffffffffffffffffffffffffefffffc2f",
-                16,
-            )
-            .unwrap();
-            let x = modulus.clone() - BigInt::from(17);
-
-            evaluate_integer_function(
-                &analyzed,
-                "std::math::ff::inverse",
-                vec![x.clone(), modulus.clone()],
-            );
-        })
-    });
-
-    group.bench_function("std::math::ff::reduce", |b| {
-        b.iter(|| {
-            let modulus = BigInt::from_str_radix(
-                "fffffffffffffffffffffffffffffffffffffffffffffffffffffffefffffc2f",
-                16,
-            )
-            .unwrap();
-            let x = modulus.clone() + BigInt::from(17);
-
-            evaluate_integer_function(
-                &analyzed,
-                "std::math::ff::reduce",
-                vec![x.clone(), modulus.clone()],
-            );
-        })
-    });
-
-    group.bench_function("std::math::ff::mul", |b| {
-        b.iter(|| {
-            let modulus = BigInt::from_str_radix(
-                "fffffffffffffffffffffffffffffffffffffffffffffffffffffffefffffc2f",
-                16,
-            )
-            .unwrap();
-            let x = modulus.clone() - BigInt::from(17);
-            let y = modulus.clone() - BigInt::from(11);
-
-            evaluate_integer_function(
-                &analyzed,
-                "std::math::ff::mul",
-                vec![x.clone(), y.clone(), modulus.clone()],
-            );
-        })
-    });
+    let modulus = BigInt::from_str_radix(
+        "fffffffffffffffffffffffffffffffffffffffffffffffffffffffefffffc2f",
+        16,
+    )
+    .unwrap();
+    let ff_inputs = [
+        (
+            "std::math::ff::inverse",
+            vec![modulus.clone() - BigInt::from(17), modulus.clone()],
+        ),
+        (
+            "std::math::ff::reduce",
+            vec![modulus.clone() + BigInt::from(17), modulus.clone()],
+        ),
+        (
+            "std::math::ff::mul",
+            vec![
+                modulus.clone() - BigInt::from(17),
+                modulus.clone() - BigInt::from(11),
+                modulus.clone(),
+            ],
+        ),
+    ];
 
     let sqrt_analyzed: Analyzed<GoldilocksField> = {
         let mut pipeline = Pipeline::default().from_asm_string(SQRT_CODE.to_string(), None);
         pipeline.compute_analyzed_pil().unwrap().clone()
     };
 
-    for (name, val) in sqrt_inputs() {
-        group.bench_with_input(format!("sqrt_{name}"), &val, |b, val| {
-            b.iter(|| {
-                evaluate_integer_function(&sqrt_analyzed, "sqrt", vec![BigInt::from(*val)]);
-            });
-        });
-    }
-
     let sort_analyzed: Analyzed<GoldilocksField> = {
         let code =
             "let sort_int: int[] -> int[] = |x| std::array::sort(x, |a, b| a < b);".to_string();
@@ -106,18 +99,37 @@ fn evaluator_benchmark(c: &mut Criterion) {
         pipeline.compute_analyzed_pil().unwrap().clone()
     };
 
-    for l in &SORT_SIZES {
-        let input = Arc::new(Value::Array(
-            (0..*l)
-                .rev()
-                .map(|x| Arc::new(Value::Integer(x.into())))
-                .collect(),
-        ));
-        group.bench_with_input(format!("sort_{l}"), &input, |b, x| {
-            b.iter(|| {
-                evaluate_function(&sort_analyzed, "sort_int", vec![x.clone()]);
+    for (suffix, tree_walking) in [("", false), ("_tree_walking", true)] {
+        for (function, arguments) in &ff_inputs {
+            group.bench_function(format!("{function}{suffix}"), |b| {
+                b.iter(|| call_function(&analyzed, function, integers(arguments), tree_walking))
             });
-        });
+        }
+
+        for (name, val) in sqrt_inputs() {
+            group.bench_with_input(format!("sqrt_{name}{suffix}"), &val, |b, val| {
+                b.iter(|| {
+                    call_function(
+                        &sqrt_analyzed,
+                        "sqrt",
+                        integers(&[BigInt::from(*val)]),
+                        tree_walking,
+                    )
+                });
+            });
+        }
+
+        for l in &SORT_SIZES {
+            let input = Arc::new(Value::Array(
+                (0..*l)
+                    .rev()
+                    .map(|x| Arc::new(Value::Integer(x.into())))
+                    .collect(),
+            ));
+            group.bench_with_input(format!("sort_{l}{suffix}"), &input, |b, x| {
+                b.iter(|| call_function(&sort_analyzed, "sort_int", vec![x.clone()], tree_walking));
+            });
+        }
     }
 
     group.finish();
@@ -1,10 +1,13 @@
 //! The main powdr lib, used to compile from assembly to PIL
 
+pub mod optimizer_check;
 pub mod pipeline;
+pub mod replay;
 pub mod test_runner;
 pub mod test_util;
 pub mod util;
 pub mod verify;
+pub mod witness_export;
 
 use std::collections::BTreeMap;
 use std::sync::{Arc, Mutex};
@@ -0,0 +1,166 @@
+//! Differential checking of the PIL optimizer.
+//!
+//! A witness generated for the optimized PIL is mapped back to the witness columns of the
+//! unoptimized PIL, so that the identities of the unoptimized PIL can be checked against it.
+//! If the optimizer changed the statement, some of these identities are violated.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use powdr_ast::analyzed::{AlgebraicExpression, AlgebraicReference, Analyzed, PolynomialType};
+use powdr_ast::parsed::visitor::AllChildren;
+use powdr_executor::constant_evaluator::{FixedColumnValues, VariablySizedColumn};
+use powdr_executor_utils::expression_evaluator::{ExpressionEvaluator, TerminalAccess};
+use powdr_number::FieldElement;
+use powdr_pilopt::{Substitution, SubstitutionRecord};
+
+/// Maps a witness of the optimized PIL to the stage-0 witness columns of the unoptimized PIL.
+///
+/// Columns that still exist are copied. The values of all other columns are computed from
+/// the substitutions recorded by the optimizer. Columns that are not constrained by the
+/// optimized PIL anymore, and columns whose substitution references them, cannot be
+/// computed this way, because the identities of the unoptimized PIL might still constrain
+/// them. These are returned separately, to be computed by witgen on the unoptimized PIL.
+/// Fails if a column is neither part of the witness nor recorded.
+///
+/// Returns the computed columns in source order and the names of the columns that are left.
+pub fn map_witness_to_unoptimized<T: FieldElement>(
+    unoptimized: &Analyzed<T>,
+    unoptimized_fixed: &[(String, VariablySizedColumn<T>)],
+    substitutions: &SubstitutionRecord<T>,
+    witness: &[(String, Vec<T>)],
+) -> Result<(Vec<(String, Vec<T>)>, Vec<String>), String> {
+    let mut sizes = witness
+        .iter()
+        .map(|(name, values)| (namespace_of(name).to_string(), values.len()))
+        .collect::<BTreeMap<_, _>>();
+    for (symbol, _) in unoptimized.committed_polys_in_source_order() {
+        let namespace = namespace_of(&symbol.absolute_name);
+        if !sizes.contains_key(namespace) {
+            let degree = symbol.degree.unwrap();
+            if degree.min != degree.max {
+                return Err(format!(
+                    "Cannot determine the size of namespace {namespace}, \
+                    all its witness columns were removed."
+                ));
+            }
+            sizes.insert(namespace.to_string(), degree.max as usize);
+        }
+    }
+
+    let fixed = sized_fixed_columns(unoptimized_fixed, &sizes)
+        .into_iter()
+        .collect::<BTreeMap<_, _>>();
+    let mut known = witness.iter().cloned().collect::<BTreeMap<_, _>>();
+    let mut unknown = BTreeSet::new();
+    let no_intermediates = BTreeMap::new();
+    for (name, substitution) in substitutions.latest_first() {
+        let size = *sizes
+            .get(namespace_of(name))
+            .ok_or_else(|| format!("Cannot determine the size of removed column {name}."))?;
+        match substitution {
+            Substitution::Unconstrained => {
+                if !known.contains_key(name) {
+                    unknown.insert(name.to_string());
+                }
+            }
+            Substitution::Expression(expression) => {
+                if let Some(missing) = expression.all_children().find_map(|e| match e {
+                    AlgebraicExpression::Reference(reference) => {
+                        let available = match reference.poly_id.ptype {
+                            PolynomialType::Committed => known.contains_key(&reference.name),
+                            PolynomialType::Constant => fixed.contains_key(&reference.name),
+                            PolynomialType::Intermediate => false,
+                        };
+                        (!available).then_some(&reference.name)
+                    }
+                    _ => None,
+                }) {
+                    if unknown.contains(missing) {
+                        unknown.insert(name.to_string());
+                        continue;
+                    }
+                    return Err(format!(
+                        "Cannot compute removed column {name} = {expression}, \
+                        the values of {missing} are not known."
+                    ));
+                }
+                let column = (0..size)
+                    .map(|row| {
+                        let access = NamedRow {
+                            witness: &known,
+                            fixed: &fixed,
+                            row,
+                        };
+                        ExpressionEvaluator::new(access, &no_intermediates).evaluate(expression)
+                    })
+                    .collect();
+                unknown.remove(name);
+                known.insert(name.to_string(), column);
+            }
+        }
+    }
+
+    let columns = unoptimized
+        .committed_polys_in_source_order()
+        .filter(|(symbol, _)| symbol.stage.unwrap_or(0) == 0)
+        .flat_map(|(symbol, _)| symbol.array_elements())
+        .filter(|(name, _)| !unknown.contains(name))
+        .map(|(name, _)| {
+            let values = known.remove(&name).ok_or_else(|| {
+                format!(
+                    "Cannot reconstruct the values of witness column {name}, it was removed \
+                    by the optimizer without recording a substitution."
+                )
+            })?;
+            Ok((name, values))
+        })
+        .collect::<Result<_, String>>()?;
+    Ok((columns, unknown.into_iter().collect()))
+}
+
+/// Returns the fixed columns, each in the size of its namespace.
+fn sized_fixed_columns<T: FieldElement>(
+    fixed: &[(String, VariablySizedColumn<T>)],
+    sizes: &BTreeMap<String, usize>,
+) -> Vec<(String, FixedColumnValues<T>)> {
+    fixed
+        .iter()
+        .filter_map(|(name, column)| {
+            let values = match sizes.get(namespace_of(name)) {
+                Some(size) => column.get_values_by_size(*size as u64)?.clone(),
+                None => column.get_values_uniquely_sized().ok()?.clone(),
+            };
+            Some((name.clone(), values))
+        })
+        .collect()
+}
+
+/// A row of the witness and fixed columns, where columns are accessed by name.
+struct NamedRow<'a, T> {
+    witness: &'a BTreeMap<String, Vec<T>>,
+    fixed: &'a BTreeMap<String, FixedColumnValues<T>>,
+    row: usize,
+}
+
+impl<T: FieldElement> TerminalAccess<T> for NamedRow<'_, T> {
+    fn get(&self, reference: &AlgebraicReference) -> T {
+        let row = self.row + reference.next as usize;
+        match reference.poly_id.ptype {
+            PolynomialType::Committed => {
+                let values = &self.witness[&reference.name];
+                values[row % values.len()]
+            }
+            PolynomialType::Constant => {
+                let values = &self.fixed[&reference.name];
+                values.get(row % values.len())
+            }
+            PolynomialType::Intermediate => {
+                unreachable!("Substitutions do not reference intermediate columns")
+            }
+        }
+    }
+}
+
+fn namespace_of(name: &str) -> &str {
+    name.rsplit_once("::").map(|(ns, _)| ns).unwrap_or_default()
+}
@@ -1,9 +1,9 @@
 use std::{
     borrow::Borrow,
-    collections::HashMap,
     fmt::Display,
     fs,
     io::{self, BufReader, BufWriter, Write},
+    panic::{self, AssertUnwindSafe},
     path::{Path, PathBuf},
     rc::Rc,
     sync::Arc,
@@ -23,20 +23,26 @@ use powdr_backend::{Backend, BackendOptions, BackendType, Proof};
 use powdr_executor::{
     constant_evaluator::{self, VariablySizedColumn},
     witgen::{
-        chain_callbacks, extract_publics, unused_query_callback, QueryCallback, WitgenCallback,
-        WitgenCallbackContext, WitnessGenerator,
+        chain_callbacks, check_external_intermediate_values, extract_publics,
+        unused_query_callback, QueryCallback, WitgenCallback, WitgenCallbackContext,
+        WitnessGenerator,
     },
 };
 pub use powdr_linker::{DegreeMode, LinkerMode, LinkerParams};
 use powdr_number::{write_polys_csv_file, CsvRenderMode, FieldElement, ReadWrite};
+use powdr_parser_util::Warning;
+use powdr_pilopt::{OptimizerOutput, OptimizerStatistics, Pass, PassManager, SubstitutionRecord};
 use powdr_schemas::SerializedAnalyzed;
 
 use crate::{
     dict_data_to_query_callback, handle_simple_queries_callback, inputs_to_query_callback,
+    optimizer_check::map_witness_to_unoptimized,
+    replay::{read_replay_file, QueryRecorder},
     serde_data_to_query_callback,
     util::{FixedPolySet, WitnessPolySet},
+    witness_export::{fixed_columns_for_witness, WitnessExport},
 };
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 
 pub type Columns<T> = Vec<(String, Vec<T>)>;
 pub type VariablySizedColumns<T> = Vec<(String, VariablySizedColumn<T>)>;
@@ -70,8 +76,16 @@ pub struct Artifacts<T: FieldElement> {
     pil_string: Option<String>,
     /// An analyzed .pil file, with all dependencies imported, potentially from other files.
     analyzed_pil: Option<Analyzed<T>>,
+    /// A copy of the analyzed .pil file before optimization, only kept when
+    /// the optimizer is checked.
+    unoptimized_pil: Option<Arc<Analyzed<T>>>,
+    /// The substitutions of witness columns performed by the optimizer, only kept
+    /// when the optimizer is checked.
+    optimizer_substitutions: Option<SubstitutionRecord<T>>,
     /// An optimized .pil file.
     optimized_pil: Option<Arc<Analyzed<T>>>,
+    /// Statistics about the optimizer passes that produced the optimized .pil file.
+    optimizer_statistics: Option<OptimizerStatistics>,
     /// Fully evaluated fixed columns.
     fixed_cols: Option<Arc<VariablySizedColumns<T>>>,
     /// Generated witnesses.
@@ -107,12 +121,20 @@ struct Arguments<T: FieldElement> {
     backend_options: BackendOptions,
     /// Linker options
     linker_params: LinkerParams,
+    /// The passes the PIL optimizer runs.
+    optimizer_passes: PassManager,
+    /// Whether to check the witness of the optimized PIL against the unoptimized PIL.
+    check_optimizer: bool,
+    /// Whether witness generation falls back to a search-based solver on stuck rows.
+    witgen_solver_fallback: bool,
     /// CSV render mode for witness generation.
     csv_render_mode: CsvRenderMode,
     /// Whether to export the witness as a CSV file.
     export_witness_csv: bool,
     /// Whether to export all columns (witness and constants) to a CSV file.
     export_all_columns_csv: bool,
+    /// The parts of the witness to export to a file per machine, if any.
+    witness_export: Option<WitnessExport>,
     /// The optional setup file to use for proving.
     setup_file: Option<PathBuf>,
     /// The optional proving key file to use for proving.
@@ -123,6 +145,10 @@ struct Arguments<T: FieldElement> {
     vkey_app_file: Option<PathBuf>,
     /// The optional existing proof file to use for aggregation.
     existing_proof_file: Option<PathBuf>,
+    /// Records all queries, if set.
+    query_recorder: Option<QueryRecorder<T>>,
+    /// Whether all queries are answered from a replay file.
+    replaying_queries: bool,
 }
 
 #[derive(Clone)]
@@ -167,7 +193,10 @@ impl<T: FieldElement> Clone for Artifacts<T> {
             pil_file_path: self.pil_file_path.clone(),
             pil_string: self.pil_string.clone(),
             analyzed_pil: self.analyzed_pil.clone(),
+            unoptimized_pil: self.unoptimized_pil.clone(),
+            optimizer_substitutions: self.optimizer_substitutions.clone(),
             optimized_pil: self.optimized_pil.clone(),
+            optimizer_statistics: self.optimizer_statistics.clone(),
             fixed_cols: self.fixed_cols.clone(),
             witness: self.witness.clone(),
             proof: self.proof.clone(),
@@ -316,7 +345,22 @@ impl<T: FieldElement> Pipeline<T> {
         self
     }
 
+    /// Export selected namespaces, columns and rows of the witness, one file per machine.
+    pub fn with_witness_export(mut self, witness_export: WitnessExport) -> Self {
+        self.arguments.witness_export = Some(witness_export);
+        self
+    }
+
     pub fn add_query_callback(mut self, query_callback: Arc<dyn QueryCallback<T>>) -> Self {
+        if self.arguments.replaying_queries {
+            log::warn!("Ignoring query callback, all queries are answered from the replay file.");
+            return self;
+        }
+        if let Some(recorder) = &mut self.arguments.query_recorder {
+            recorder.chain(query_callback);
+            self.arguments.query_callback = Some(recorder.callback());
+            return self;
+        }
         let query_callback = match self.arguments.query_callback {
             Some(old_callback) => Arc::new(chain_callbacks(old_callback, query_callback)),
             None => query_callback,
@@ -325,10 +369,62 @@ impl<T: FieldElement> Pipeline<T> {
         self
     }
 
+    /// Records all queries and their responses, including those of query callbacks
+    /// added later. The recording is written to `path` before and after witness generation,
+    /// including when it fails, or by calling [Pipeline::write_query_recording].
+    pub fn with_query_recording(mut self, path: PathBuf) -> Self {
+        let inner = self
+            .arguments
+            .query_callback
+            .take()
+            .unwrap_or_else(|| Arc::new(unused_query_callback()));
+        let recorder = QueryRecorder::new(inner, path);
+        self.arguments.query_callback = Some(recorder.callback());
+        self.arguments.query_recorder = Some(recorder);
+        self
+    }
+
+    /// Writes the queries recorded so far to the replay file, if recording is enabled.
+    pub fn write_query_recording(&self) -> Result<(), Vec<String>> {
+        self.write_query_recording_with(&self.arguments.external_witness_values)
+    }
+
+    fn write_query_recording_with(
+        &self,
+        external_witness_values: &[(String, Vec<T>)],
+    ) -> Result<(), Vec<String>> {
+        match &self.arguments.query_recorder {
+            Some(recorder) => recorder.write(&self.initial_memory, external_witness_values),
+            None => Ok(()),
+        }
+    }
+
+    /// Takes all prover inputs from the replay file at `path`, as written by a pipeline
+    /// with [Pipeline::with_query_recording]: all queries are answered from the file
+    /// and the initial memory and external witness values are replaced by the recorded
+    /// ones. Outputs of the guest are still available through the host context.
+    /// Query callbacks added before or after are ignored.
+    pub fn with_query_replay(mut self, path: &Path) -> Result<Self, Vec<String>> {
+        let replay = read_replay_file(path)?;
+        self.arguments.query_callback = Some(Arc::new(chain_callbacks(
+            self.host_context.query_callback(),
+            Arc::new(replay.query_callback),
+        )));
+        self.arguments.query_recorder = None;
+        self.arguments.replaying_queries = true;
+        self.initial_memory = replay.initial_memory;
+        self.arguments.external_witness_values = replay.external_witness_values;
+        Ok(self)
+    }
+
     /// Adds data to the initial memory given by the prover.
     /// This is a more efficient method of passing bytes from the host
     /// to the guest.
     pub fn add_to_initial_memory(mut self, data: Vec<u8>) -> Self {
+        if self.arguments.replaying_queries {
+            log::warn!("Ignoring initial memory, it is taken from the replay file.");
+            return self;
+        }
         self.initial_memory.push(data);
         self
     }
@@ -355,6 +451,61 @@ impl<T: FieldElement> Pipeline<T> {
         self.add_query_callback(Arc::new(dict_data_to_query_callback(inputs)))
     }
 
+    /// Rewrites constraints of a degree larger than `max_degree` when the PIL is optimized.
+    pub fn with_max_constraint_degree(mut self, max_degree: usize) -> Self {
+        self.arguments.optimizer_passes = self
+            .arguments
+            .optimizer_passes
+            .clone()
+            .with_max_degree(max_degree);
+        self
+    }
+
+    /// Rewrites constraints of a degree larger than the degree bound of the backend
+    /// when the PIL is optimized. The backend needs to be set before the PIL is optimized.
+    pub fn with_degree_reduction(mut self) -> Self {
+        self.arguments.optimizer_passes = self
+            .arguments
+            .optimizer_passes
+            .clone()
+            .with_degree_reduction();
+        self
+    }
+
+    /// Turns witness columns that are affine combinations of other columns into
+    /// intermediate columns when optimizing the PIL.
+    pub fn with_affine_column_elimination(mut self) -> Self {
+        self.arguments.optimizer_passes = self
+            .arguments
+            .optimizer_passes
+            .with_pass(Pass::RemoveAffineWitnessColumns);
+        self
+    }
+
+    /// Checks the generated witness against the identities of the unoptimized PIL,
+    /// to detect optimizer passes that change the statement being proven.
+    /// The witness is mapped back to the unoptimized columns and checked using the
+    /// mock backend, which reports all violated identities.
+    pub fn with_optimizer_check(mut self) -> Self {
+        self.arguments.check_optimizer = true;
+        self
+    }
+
+    /// Lets witness generation hand rows on which it gets stuck (e.g. because of non-linear
+    /// constraints) to a solver that searches over the range-constrained unknown cells.
+    /// The cells are only set if the solution is unique.
+    pub fn with_witgen_solver_fallback(mut self) -> Self {
+        self.arguments.witgen_solver_fallback = true;
+        self
+    }
+
+    /// Sets the passes the PIL optimizer runs, e.g. to disable or reorder passes
+    /// or to dump the PIL after each pass.
+    pub fn with_optimizer_passes(mut self, passes: PassManager) -> Self {
+        self.arguments.optimizer_passes = passes;
+        self
+    }
+
     pub fn with_linker_params(mut self, linker_params: LinkerParams) -> Self {
         self.arguments.linker_params = linker_params;
         self
@@ -643,28 +794,8 @@ impl<T: FieldElement> Pipeline<T> {
             if let Some(path) =
                 self.path_if_should_write(|name| format!("{name}_all_columns.csv"))?
             {
-                // get the column size for each namespace. This assumes all witness columns of the same namespace have the same size.
-                let witness_sizes: HashMap<&str, u64> = witness
-                    .iter()
-                    .map(|(name, values)| {
-                        let namespace = name.split("::").next().unwrap();
-                        (namespace, values.len() as u64)
-                    })
-                    .collect();
-
-                // choose the fixed column of the correct size. This assumes any namespace with no witness columns has a unique size
-                let fixed_columns = fixed.iter().map(|(name, columns)| {
-                    let namespace = name.split("::").next().unwrap();
-                    let columns = witness_sizes
-                        .get(&namespace)
-                        // if we have witness columns, use their size
-                        .map(|size| columns.get_by_size(*size).unwrap())
-                        // otherwise, return the unique size
-                        .unwrap_or_else(|| columns.get_uniquely_sized().unwrap());
-                    (name, columns)
-                });
-
-                let columns = fixed_columns
+                let columns = fixed_columns_for_witness(fixed, witness)
+                    .into_iter()
                     .chain(witness.iter().map(|(name, values)| (name, values.as_ref())))
                     .collect::<Vec<_>>();
 
@@ -673,6 +804,30 @@ impl<T: FieldElement> Pipeline<T> {
             }
         }
 
+        if let Some(export) = &self.arguments.witness_export {
+            let fixed = if export.include_fixed {
+                fixed_columns_for_witness(fixed, witness)
+            } else {
+                vec![]
+            };
+            let witness = witness
+                .iter()
+                .map(|(name, values)| (name, values.as_ref()))
+                .collect::<Vec<_>>();
+            for (namespace, (columns, first_row)) in export.select(&fixed, &witness) {
+                let extension = export.format.extension();
+                if let Some(path) = self.path_if_should_write(|name| {
+                    format!("{name}_{namespace}_witness.{extension}")
+                })? {
+                    let file =
+                        BufWriter::new(fs::File::create(path).map_err(|e| vec![format!("{}", e)])?);
+                    export
+                        .write(file, self.arguments.csv_render_mode, &columns, first_row)
+                        .map_err(|e| vec![format!("{}", e)])?;
+                }
+            }
+        }
+
         Ok(())
     }
 
@@ -689,6 +844,52 @@ impl<T: FieldElement> Pipeline<T> {
         Ok(())
     }
 
+    /// Runs the lints of all stages up to the parsed PIL file on the input and returns the
+    /// warnings for the input file, sorted by position. Warnings about code in dependencies
+    /// are not reported. Computing the PIL file for asm inputs has the same side effects as
+    /// in `compute_parsed_pil_file`.
+    pub fn lint(&mut self) -> Result<Vec<Warning>, Vec<String>> {
+        let (file_name, warnings) =
+            if self.artifact.asm_string.is_some() || self.artifact.asm_file_path.is_some() {
+                let (path, parsed) = self.compute_parsed_asm_file()?.clone();
+                let file_name = path.as_ref().map(|p| p.to_str().unwrap().to_string());
+
+                self.log("Linting imports");
+                let mut warnings = powdr_importer::lint(path, parsed).map_err(|e| {
+                    e.output_to_stderr();
+                    vec![e.message().to_string()]
+                })?;
+
+                self.log("Linting machines");
+                warnings.extend(powdr_asmopt::lint(self.compute_analyzed_asm()?));
+
+                self.log("Linting PIL");
+                let pil_file = self.compute_parsed_pil_file()?.clone();
+                warnings.extend(
+                    powdr_pil_analyzer::lint_ast(pil_file).map_err(output_pil_analysis_errors)?,
+                );
+                (file_name, warnings)
+            } else if let Some(pil_string) = &self.artifact.pil_string {
+                let warnings = powdr_pil_analyzer::lint_string(pil_string)
+                    .map_err(output_pil_analysis_errors)?;
+                (Some("input".to_string()), warnings)
+            } else if let Some(path) = &self.artifact.pil_file_path {
+                let warnings =
+                    powdr_pil_analyzer::lint_file(path).map_err(output_pil_analysis_errors)?;
+                (Some(path.to_str().unwrap().to_string()), warnings)
+            } else {
+                panic!()
+            };
+
+        let mut warnings = warnings
+            .into_iter()
+            .filter(|w| w.source_ref().file_name.as_deref() == file_name.as_deref())
+            .collect::<Vec<_>>();
+        warnings.sort_by_key(|w| (w.source_ref().start, w.source_ref().end, w.lint()));
+        warnings.dedup_by_key(|w| (w.source_ref().start, w.source_ref().end, w.lint()));
+        Ok(warnings)
+    }
+
     // Removes artifacts related to witgen and proofs.
     // This is useful for when a single pipeline is used for several proofs.
     // In that case, we want to keep the fixed columns and backend setup unmodified,
@@ -965,21 +1166,64 @@ impl<T: FieldElement> Pipeline<T> {
 
         self.compute_analyzed_pil()?;
         let analyzed_pil = self.artifact.analyzed_pil.take().unwrap();
+        if self.arguments.check_optimizer {
+            self.artifact.unoptimized_pil = Some(Arc::new(analyzed_pil.clone()));
+        }
 
         self.log("Optimizing pil...");
-        let optimized = powdr_pilopt::optimize(analyzed_pil);
+        let OptimizerOutput {
+            pil_file: optimized,
+            statistics,
+            substitutions,
+        } = powdr_pilopt::optimize_with(analyzed_pil, &self.optimizer_passes()?)
+            .map_err(|e| vec![e])?;
+        self.log(&format!("Optimizer statistics:\n{statistics}"));
         self.maybe_write_pil(&optimized, "_opt")?;
         self.maybe_write_pil_object(&optimized, "_opt")?;
 
         self.artifact.optimized_pil = Some(Arc::new(optimized));
+        self.artifact.optimizer_statistics = Some(statistics);
+        if self.arguments.check_optimizer {
+            self.artifact.optimizer_substitutions = Some(substitutions);
+        }
 
         Ok(self.artifact.optimized_pil.as_ref().unwrap().clone())
     }
 
+    /// The configured optimizer passes. If the degree reduction pass is enabled without
+    /// a maximum degree, the degree bound of the backend is used.
+    fn optimizer_passes(&self) -> Result<PassManager, Vec<String>> {
+        let passes = &self.arguments.optimizer_passes;
+        if !passes.passes().contains(&Pass::ReduceDegree) || passes.max_degree().is_some() {
+            return Ok(passes.clone());
+        }
+        let max_degree = self
+            .arguments
+            .backend
+            .and_then(|backend| backend.factory::<T>().max_constraint_degree())
+            .ok_or_else(|| {
+                vec![format!(
+                    "The optimizer pass {} needs a maximum constraint degree, \
+                    but none was given and the backend does not bound the degree.",
+                    Pass::ReduceDegree
+                )]
+            })?;
+        self.log(&format!(
+            "Reducing constraint degree to the degree bound {max_degree} of the backend."
+        ));
+        Ok(passes.clone().with_max_degree(max_degree))
+    }
+
     pub fn optimized_pil(&self) -> Result<Arc<Analyzed<T>>, Vec<String>> {
         Ok(self.artifact.optimized_pil.as_ref().unwrap().clone())
     }
 
+    /// Statistics about the optimizer passes, if the pipeline ran the optimizer.
+    /// They are not available if the optimized .pil file was read from a file.
+    pub fn optimizer_statistics(&self) -> Option<&OptimizerStatistics> {
+        self.artifact.optimizer_statistics.as_ref()
+    }
+
     pub fn compute_fixed_cols(&mut self) -> Result<Arc<VariablySizedColumns<T>>, Vec<String>> {
         if let Some(ref fixed_cols) = self.artifact.fixed_cols {
             return Ok(fixed_cols.clone());
@@ -1022,15 +1266,26 @@ impl<T: FieldElement> Pipeline<T> {
             .flat_map(|(s, _)| s.array_elements().map(|(name, _)| name))
             .collect();
 
+        // Witness columns the optimizer turned into intermediate columns.
+        let intermediate_cols: BTreeSet<_> = pil
+            .intermediate_polys_in_source_order()
+            .flat_map(|(s, _)| s.array_elements().map(|(name, _)| name))
+            .collect();
+
         let mut external_witness_values =
             std::mem::take(&mut self.arguments.external_witness_values);
-        // witgen needs external witness columns sorted by source order
+        // witgen needs external witness columns sorted by source order,
+        // values for intermediate columns are only checked and go last.
         external_witness_values.sort_by_key(|(name, _)| {
             witness_cols
                 .iter()
                 .position(|n| n == name)
                 .unwrap_or_else(|| {
-                    panic!("external witness {name} does not exist in the optimized PIL")
+                    assert!(
+                        intermediate_cols.contains(name),
+                        "external witness {name} does not exist in the optimized PIL"
+                    );
+                    witness_cols.len()
                 })
         });
 
@@ -1039,6 +1294,14 @@ impl<T: FieldElement> Pipeline<T> {
             .all(|name| external_witness_values.iter().any(|(e, _)| e == name))
         {
             self.log("All witness columns externally provided, skipping witness generation.");
+            check_external_intermediate_values(
+                &pil,
+                &fixed_cols,
+                &external_witness_values,
+                &external_witness_values,
+            )
+            .map_err(|e| vec![e])?;
+            external_witness_values.retain(|(name, _)| !intermediate_cols.contains(name));
             self.artifact.witness = Some(Arc::new(external_witness_values));
         } else {
             self.log("Deducing witness columns...");
@@ -1049,9 +1312,24 @@ impl<T: FieldElement> Pipeline<T> {
                 .query_callback
                 .clone()
                 .unwrap_or_else(|| Arc::new(unused_query_callback()));
-            let witness = WitnessGenerator::new(&pil, &fixed_cols, query_callback.borrow())
-                .with_external_witness_values(&external_witness_values)
-                .generate();
+            // The inputs are written before witness generation and the queries again if it
+            // fails, so that failures can be reproduced from the replay file.
+            self.write_query_recording_with(&external_witness_values)?;
+            let witness = panic::catch_unwind(AssertUnwindSafe(|| {
+                WitnessGenerator::new(&pil, &fixed_cols, query_callback.borrow())
+                    .with_external_witness_values(&external_witness_values)
+                    .with_solver_fallback(self.arguments.witgen_solver_fallback)
+                    .generate()
+            }))
+            .unwrap_or_else(|payload| {
+                if let Err(errors) = self.write_query_recording_with(&external_witness_values) {
+                    for error in errors {
+                        log::error!("{error}");
+                    }
+                }
+                panic::resume_unwind(payload)
+            })
+            .map_err(|e| vec![e])?;
 
             self.log(&format!(
                 "Witness generation took {}s",
@@ -1059,14 +1337,101 @@ impl<T: FieldElement> Pipeline<T> {
             ));
 
             self.maybe_write_witness(&fixed_cols, &witness)?;
+            self.write_query_recording_with(&external_witness_values)?;
 
             self.artifact.witness = Some(Arc::new(witness));
         }
         self.artifact.proof = None;
 
+        if self.arguments.check_optimizer {
+            self.check_optimized_witness()?;
+        }
+
         Ok(self.artifact.witness.as_ref().unwrap().clone())
     }
 
+    /// Checks the witness of the optimized PIL against the identities of the unoptimized PIL
+    /// using the mock backend.
+    fn check_optimized_witness(&mut self) -> Result<(), Vec<String>> {
+        let (Some(unoptimized), Some(substitutions)) = (
+            self.artifact.unoptimized_pil.clone(),
+            self.artifact.optimizer_substitutions.as_ref(),
+        ) else {
+            return Err(vec![
+                "Cannot check the optimizer, because the pipeline did not run it.".to_string(),
+            ]);
+        };
+        let witness = self.witness()?;
+
+        self.log("Checking the witness against the unoptimized PIL...");
+        let unoptimized_fixed = Arc::new(constant_evaluator::generate(&unoptimized));
+        let (mut witness, unconstrained) =
+            map_witness_to_unoptimized(&unoptimized, &unoptimized_fixed, substitutions, &witness)
+                .map_err(|e| vec![e])?;
+        if !unconstrained.is_empty() {
+            // These columns are not constrained by the optimized PIL, but the identities
+            // the optimizer removed together with them might still constrain them.
+            self.log(&format!(
+                "Computing the removed columns {} with witgen on the unoptimized PIL...",
+                unconstrained.join(", ")
+            ));
+            let query_callback = self
+                .arguments
+                .query_callback
+                .clone()
+                .unwrap_or_else(|| Arc::new(unused_query_callback()));
+            witness = panic::catch_unwind(AssertUnwindSafe(|| {
+                WitnessGenerator::new(&unoptimized, &unoptimized_fixed, query_callback.borrow())
+                    .with_external_witness_values(&witness)
+                    .generate()
+            }))
+            .map_err(|_| {
+                vec![format!(
+                    "Witness generation on the unoptimized PIL failed to compute the removed columns {}.",
+                    unconstrained.join(", ")
+                )]
+            })?
+            .map_err(|e| vec![e])?;
+        }
+
+        let ctx = WitgenCallbackContext::new(
+            unoptimized_fixed.clone(),
+            self.arguments.query_callback.as_ref().cloned(),
+        );
+        let witgen_callback =
+            WitgenCallback::new(Arc::new(move |pil, current_witness, challenges, stage| {
+                ctx.next_stage_witness(pil, current_witness, challenges, stage)
+            }));
+        let backend = BackendType::Mock
+            .factory::<T>()
+            .create(
+                unoptimized,
+                unoptimized_fixed,
+                None,
+                None,
+                None,
+                None,
+                None,
+                Default::default(),
+            )
+            .map_err(|e| vec![e.to_string()])?;
+        backend
+            .prove(&witness, None, witgen_callback)
+            .map_err(|e| {
+                let reason = match e {
+                    powdr_backend::Error::BackendError(e) => e,
+                    e => e.to_string(),
+                };
+                vec![
+                    "The witness of the optimized PIL violates identities of the unoptimized PIL."
+                        .to_string(),
+                    reason,
+                ]
+            })?;
+        self.log("The witness satisfies the unoptimized PIL.");
+        Ok(())
+    }
+
     pub fn witness(&self) -> Result<Arc<Columns<T>>, Vec<String>> {
         Ok(self.artifact.witness.as_ref().unwrap().clone())
     }
@@ -0,0 +1,148 @@
+//! Recording and replaying of prover queries.
+//!
+//! While recording, every query sent to the query callback and its response are stored,
+//! so that they can be written to a replay file, together with the initial memory and
+//! the external witness values given by the prover. A pipeline replaying such a file takes
+//! all prover inputs from it, which reproduces an execution or a witness generation
+//! exactly, as long as it is given the same program.
+
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{BufReader, BufWriter},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use powdr_executor::witgen::{chain_callbacks, QueryCallback};
+use powdr_number::{FieldElement, KnownField};
+use serde::{Deserialize, Serialize};
+
+type QueryResponse<T> = Result<Option<T>, String>;
+
+/// The contents of a replay file.
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "T: FieldElement")]
+struct QueryLog<T: FieldElement> {
+    field: Option<KnownField>,
+    initial_memory: Vec<Vec<u8>>,
+    external_witness_values: Vec<(String, Vec<T>)>,
+    /// The response to each query. Queries are answered deterministically,
+    /// so repeated queries are only stored once.
+    queries: BTreeMap<String, QueryResponse<T>>,
+}
+
+/// Records the queries answered by a query callback.
+#[derive(Clone)]
+pub struct QueryRecorder<T: FieldElement> {
+    /// The callback answering the queries.
+    inner: Arc<dyn QueryCallback<T>>,
+    /// The file the recorded queries are written to.
+    path: PathBuf,
+    /// Shared between all copies of the pipeline, which all record into the same file.
+    queries: Arc<Mutex<BTreeMap<String, QueryResponse<T>>>>,
+}
+
+impl<T: FieldElement> QueryRecorder<T> {
+    pub fn new(inner: Arc<dyn QueryCallback<T>>, path: PathBuf) -> Self {
+        Self {
+            inner,
+            path,
+            queries: Default::default(),
+        }
+    }
+
+    /// Adds a callback that answers the queries the existing ones fail on.
+    pub fn chain(&mut self, query_callback: Arc<dyn QueryCallback<T>>) {
+        self.inner = Arc::new(chain_callbacks(self.inner.clone(), query_callback));
+    }
+
+    /// The query callback that forwards to the inner callback and records the responses.
+    pub fn callback(&self) -> Arc<dyn QueryCallback<T>> {
+        let inner = self.inner.clone();
+        let queries = self.queries.clone();
+        Arc::new(move |query: &str| {
+            let response = inner(query);
+            let mut queries = queries.lock().unwrap();
+            if let Some(previous) = queries.get(query) {
+                if previous != &response {
+                    log::warn!("Query {query} was answered differently before, only the first response is recorded.");
+                }
+            } else {
+                queries.insert(query.to_string(), response.clone());
+            }
+            response
+        })
+    }
+
+    /// Writes all queries recorded so far, the initial memory and the external witness
+    /// values to the replay file.
+    pub fn write(
+        &self,
+        initial_memory: &[Vec<u8>],
+        external_witness_values: &[(String, Vec<T>)],
+    ) -> Result<(), Vec<String>> {
+        let log = QueryLog {
+            field: T::known_field(),
+            initial_memory: initial_memory.to_vec(),
+            external_witness_values: external_witness_values.to_vec(),
+            queries: self.queries.lock().unwrap().clone(),
+        };
+        let file = File::create(&self.path).map_err(|e| {
+            vec![format!(
+                "Could not create replay file {}: {e}",
+                self.path.display()
+            )]
+        })?;
+        serde_cbor::to_writer(BufWriter::new(file), &log)
+            .map_err(|e| vec![format!("Could not write replay file: {e}")])?;
+        log::info!(
+            "Wrote {} recorded queries to {}",
+            log.queries.len(),
+            self.path.display()
+        );
+        Ok(())
+    }
+}
+
+/// The prover inputs read from a replay file.
+pub struct Replay<T, Q> {
+    /// Answers all queries from the file. Queries that were not recorded result in an error.
+    pub query_callback: Q,
+    pub initial_memory: Vec<Vec<u8>>,
+    pub external_witness_values: Vec<(String, Vec<T>)>,
+}
+
+/// Reads the replay file at `path`.
+pub fn read_replay_file<T: FieldElement>(
+    path: &Path,
+) -> Result<Replay<T, impl QueryCallback<T>>, Vec<String>> {
+    let file = File::open(path).map_err(|e| {
+        vec![format!(
+            "Could not open replay file {}: {e}",
+            path.display()
+        )]
+    })?;
+    let log: QueryLog<T> = serde_cbor::from_reader(BufReader::new(file))
+        .map_err(|e| vec![format!("Could not read replay file: {e}")])?;
+    if log.field != T::known_field() {
+        return Err(vec![format!(
+            "Replay file was recorded over {:?}, but the pipeline uses {:?}",
+            log.field,
+            T::known_field()
+        )]);
+    }
+
+    let queries = log.queries;
+    let callback = move |query: &str| -> QueryResponse<T> {
+        queries
+            .get(query)
+            .cloned()
+            .unwrap_or_else(|| Err(format!("Query {query} not found in the replay file")))
+    };
+    Ok(Replay {
+        query_callback: callback,
+        initial_memory: log.initial_memory,
+        external_witness_values: log.external_witness_values,
+    })
+}
@@ -1,34 +1,62 @@
-use std::{collections::BTreeSet, path::PathBuf, str::FromStr};
+use std::{
+    any::Any,
+    fmt::{self, Display, Formatter},
+    panic::{self, AssertUnwindSafe},
+    path::PathBuf,
+    str::FromStr,
+};
 
 use itertools::Itertools;
 
 use powdr_ast::{
     analyzed::{Analyzed, FunctionValueDefinition},
+    asm_analysis::{AnalysisASMFile, Machine},
     parsed::{
-        asm::SymbolPath,
+        asm::{
+            parse_absolute_path, ASMProgram, AbsoluteSymbolPath, ModuleStatement, SymbolDefinition,
+            SymbolPath, SymbolValue,
+        },
         types::{FunctionType, Type},
     },
 };
-use powdr_number::FieldElement;
-use powdr_pil_analyzer::evaluator::{self, SymbolLookup};
+use powdr_backend::BackendType;
+use powdr_number::{BigInt, FieldElement};
+use powdr_pil_analyzer::evaluator::{self, EnumValue, SymbolLookup, Value};
 
 use crate::Pipeline;
 
+/// The type of machine tests, see `std::testing::MachineTest`.
+const MACHINE_TEST_TYPE: &str = "std::testing::MachineTest";
+/// The type of expected rejections in machine tests, see `std::testing::Rejection`.
+const REJECTION_TYPE: &str = "std::testing::Rejection";
+/// The degree of the main machine generated for machine tests.
+const MACHINE_TEST_MAIN_DEGREE: u32 = 32;
+
 /// Executes all functions in the given file that start with `test_` and are
 /// inside a module called `test` (or a sub-module thereof).
+/// For asm files, this also runs the machine tests declared with
+/// `std::testing::MachineTest` in these modules.
 ///
 /// @param include_std_tests: Whether to run the tests inside the standard library.
 pub fn run_from_file<F: FieldElement>(
     input: &str,
     include_std_tests: bool,
 ) -> Result<usize, Vec<String>> {
-    let mut pipeline = Pipeline::<F>::default().from_file(PathBuf::from(&input));
+    let path = PathBuf::from(&input);
+    let mut pipeline = Pipeline::<F>::default().from_file(path.clone());
+
+    let machine_tests = if path.extension().is_some_and(|ext| ext == "asm") {
+        let (_, program) = pipeline.compute_parsed_asm_file()?.clone();
+        let asm = pipeline.compute_analyzed_asm()?.clone();
+        Some(MachineTestContext { path, program, asm })
+    } else {
+        None
+    };
 
     let analyzed = pipeline.compute_analyzed_pil()?;
-    run_tests::<F>(analyzed, include_std_tests)
+    run_tests_internal::<F>(analyzed, include_std_tests, machine_tests.as_ref())
 }
 
-#[allow(clippy::print_stdout)]
 /// Executes all functions in the given file that start with `test_` and are
 /// inside a module called `test` (or a sub-module thereof).
 ///
@@ -38,6 +66,23 @@ pub fn run_from_file<F: FieldElement>(
 pub fn run_tests<F: FieldElement>(
     analyzed: &Analyzed<F>,
     include_std_tests: bool,
+) -> Result<usize, Vec<String>> {
+    run_tests_internal(analyzed, include_std_tests, None)
+}
+
+/// The kind of a test, determined by the type of the test symbol.
+enum TestKind {
+    /// A function of type `-> ()` that is evaluated.
+    Function,
+    /// A value of type `std::testing::MachineTest`.
+    Machine,
+}
+
+#[allow(clippy::print_stdout)]
+fn run_tests_internal<F: FieldElement>(
+    analyzed: &Analyzed<F>,
+    include_std_tests: bool,
+    machine_tests: Option<&MachineTestContext>,
 ) -> Result<usize, Vec<String>> {
     let mut symbols = evaluator::Definitions {
         definitions: &analyzed.definitions,
@@ -45,7 +90,7 @@ pub fn run_tests<F: FieldElement>(
     };
 
     let mut errors = vec![];
-    let tests: BTreeSet<&String> = analyzed
+    let tests: Vec<(&String, TestKind)> = analyzed
         .definitions
         .iter()
         .filter(|(n, _)| {
@@ -58,14 +103,21 @@ pub fn run_tests<F: FieldElement>(
             let Some(FunctionValueDefinition::Expression(f)) = val else {
                 return None;
             };
-            // Require a plain `->()` type.
-            (f.type_scheme.as_ref().unwrap().ty
+            let ty = &f.type_scheme.as_ref().unwrap().ty;
+            // Require a plain `->()` type for functions.
+            if *ty
                 == (FunctionType {
                     params: vec![],
                     value: Box::new(Type::empty_tuple()),
                 })
-                .into())
-            .then_some(n)
+                .into()
+            {
+                Some((n, TestKind::Function))
+            } else if ty.to_string() == MACHINE_TEST_TYPE {
+                Some((n, TestKind::Machine))
+            } else {
+                None
+            }
         })
         .collect();
     let field_name = F::known_field().map_or_else(
@@ -74,7 +126,7 @@ pub fn run_tests<F: FieldElement>(
     );
     println!("Running {} tests using field {field_name}...", tests.len());
     println!("{}", "-".repeat(85));
-    for name in &tests {
+    for (name, kind) in &tests {
         let name_len = name.len();
         let padding = if name_len >= 75 {
             " ".to_string()
@@ -82,14 +134,25 @@ pub fn run_tests<F: FieldElement>(
             " ".repeat(76 - name_len)
         };
         print!("{name}...");
-        let function = symbols.lookup(name, &None).unwrap();
-        match evaluator::evaluate_function_call::<F>(function, vec![], &mut symbols) {
-            Err(e) => {
-                let msg = e.to_string();
+        let value = symbols.lookup(name, &None).unwrap();
+        let result = match kind {
+            TestKind::Function => {
+                evaluator::evaluate_function_call::<F>(value, vec![], &mut symbols)
+                    .map(|_| ())
+                    .map_err(|e| e.to_string())
+            }
+            TestKind::Machine => MachineTest::try_from_value(&value).and_then(|test| {
+                machine_tests
+                    .ok_or_else(|| "Machine tests are only supported in asm files.".to_string())?
+                    .run::<F>(&test)
+            }),
+        };
+        match result {
+            Err(msg) => {
                 println!("{padding}failed\n  {msg}");
                 errors.push((name, msg));
             }
-            Ok(_) => println!("{padding}ok"),
+            Ok(()) => println!("{padding}ok"),
         }
     }
 
@@ -107,3 +170,278 @@ pub fn run_tests<F: FieldElement>(
         Err(vec![format!("{} test(s) failed.", errors.len())])
     }
 }
+
+/// A call of an operation of a machine, see `std::testing::MachineTest`.
+struct MachineTest {
+    machine: AbsoluteSymbolPath,
+    operation: String,
+    inputs: Vec<BigInt>,
+    outputs: Vec<BigInt>,
+    /// If set, the call is expected to be rejected in this way.
+    rejection: Option<Rejection>,
+}
+
+/// The way a call is rejected, see `std::testing::Rejection`.
+#[derive(Debug, PartialEq)]
+enum Rejection {
+    /// Witness generation fails with a message that contains the string.
+    WitnessGeneration(String),
+    /// The witness does not satisfy the constraints.
+    Constraints,
+}
+
+impl Rejection {
+    fn try_from_value<T>(value: &Value<'_, T>) -> Result<Self, String> {
+        match value {
+            Value::Enum(EnumValue {
+                variant: "WitnessGeneration",
+                data: Some(fields),
+                ..
+            }) => match &fields[..] {
+                [message] => match message.as_ref() {
+                    Value::String(message) => Ok(Rejection::WitnessGeneration(message.clone())),
+                    _ => Err("Expected the message of the rejection to be a string.".to_string()),
+                },
+                _ => Err("Expected a single field in Rejection::WitnessGeneration.".to_string()),
+            },
+            Value::Enum(EnumValue {
+                variant: "Constraints",
+                data: None,
+                ..
+            }) => Ok(Rejection::Constraints),
+            _ => Err(format!("Expected a value of type {REJECTION_TYPE}.")),
+        }
+    }
+
+    /// Returns true if the actual rejection is of this kind and, for witness
+    /// generation, its message contains the expected one.
+    fn matches(&self, actual: &Rejection) -> bool {
+        match (self, actual) {
+            (Rejection::WitnessGeneration(expected), Rejection::WitnessGeneration(actual)) => {
+                actual.contains(expected.as_str())
+            }
+            (Rejection::Constraints, Rejection::Constraints) => true,
+            _ => false,
+        }
+    }
+}
+
+impl Display for Rejection {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Rejection::WitnessGeneration(message) => {
+                write!(f, "rejected by witness generation: {message}")
+            }
+            Rejection::Constraints => write!(f, "rejected by the constraints"),
+        }
+    }
+}
+
+impl MachineTest {
+    fn try_from_value<T>(value: &Value<'_, T>) -> Result<Self, String> {
+        let Value::Enum(EnumValue {
+            variant,
+            data: Some(fields),
+            ..
+        }) = value
+        else {
+            return Err(format!("Expected a value of type {MACHINE_TEST_TYPE}."));
+        };
+        let (machine, operation, inputs, outputs, rejection) = match (*variant, &fields[..]) {
+            ("Returns", [machine, operation, inputs, outputs]) => {
+                (machine, operation, inputs, outputs, None)
+            }
+            ("Rejects", [machine, operation, inputs, outputs, rejection]) => (
+                machine,
+                operation,
+                inputs,
+                outputs,
+                Some(Rejection::try_from_value(rejection.as_ref())?),
+            ),
+            _ => {
+                return Err(format!(
+                    "Unexpected fields in {MACHINE_TEST_TYPE}::{variant}."
+                ))
+            }
+        };
+        let (Value::String(machine), Value::String(operation)) =
+            (machine.as_ref(), operation.as_ref())
+        else {
+            return Err("Expected the machine and the operation to be strings.".to_string());
+        };
+        let integers = |array: &Value<'_, T>| match array {
+            Value::Array(items) => items
+                .iter()
+                .map(|item| match item.as_ref() {
+                    Value::Integer(n) => Ok(n.clone()),
+                    _ => Err("Expected the inputs and outputs to be integers.".to_string()),
+                })
+                .collect::<Result<Vec<_>, _>>(),
+            _ => Err("Expected the inputs and outputs to be arrays.".to_string()),
+        };
+        Ok(Self {
+            machine: parse_absolute_path(&format!("::{}", machine.trim_start_matches("::"))),
+            operation: operation.clone(),
+            inputs: integers(inputs.as_ref())?,
+            outputs: integers(outputs.as_ref())?,
+            rejection,
+        })
+    }
+}
+
+/// The parts of an asm file needed to run its machine tests.
+struct MachineTestContext {
+    /// The path of the file, used to resolve its modules.
+    path: PathBuf,
+    /// The parsed file, to which the main machine of each test is added.
+    program: ASMProgram,
+    /// The analyzed file, used to look up machines and operations.
+    asm: AnalysisASMFile,
+}
+
+impl MachineTestContext {
+    /// Runs the test through witness generation and the mock backend.
+    fn run<F: FieldElement>(&self, test: &MachineTest) -> Result<(), String> {
+        let program = self.program_for_test(test)?;
+        let mut pipeline = Pipeline::<F>::default()
+            .with_tmp_output()
+            .from_asm_string(program, Some(self.path.clone()))
+            .with_backend(BackendType::Mock, None);
+        pipeline
+            .compute_fixed_cols()
+            .map_err(|e| format!("Failed to compile the test: {}", e.join("\n")))?;
+
+        // Witness generation reports most failures by panicking. The panic is caught
+        // without replacing the panic hook, which is global to all threads.
+        let rejection = match panic::catch_unwind(AssertUnwindSafe(|| pipeline.compute_witness())) {
+            Ok(witness) => {
+                witness.map_err(|e| format!("Witness generation failed: {}", e.join("\n")))?;
+                // The mock backend only fails if the constraints are not satisfied.
+                panic::catch_unwind(AssertUnwindSafe(|| pipeline.compute_proof()))
+                    .map_err(|payload| {
+                        format!("Proof generation failed: {}", panic_message(payload))
+                    })?
+                    .err()
+                    .map(|_| Rejection::Constraints)
+            }
+            Err(payload) => Some(Rejection::WitnessGeneration(panic_message(payload))),
+        };
+
+        match (rejection, &test.rejection) {
+            (None, None) => Ok(()),
+            (Some(actual), Some(expected)) if expected.matches(&actual) => Ok(()),
+            (Some(actual), Some(expected)) => Err(format!(
+                "Expected the call to be {expected}, but it was {actual}"
+            )),
+            (None, Some(expected)) => Err(format!(
+                "Expected the call to be {expected}, but it was accepted."
+            )),
+            (Some(actual), None) => Err(format!("The call was {actual}")),
+        }
+    }
+
+    /// Returns the source of the file with its `Main` machine replaced by a machine
+    /// that instantiates the machine under test and calls the operation once.
+    fn program_for_test(&self, test: &MachineTest) -> Result<String, String> {
+        let machine = self.machine(&test.machine)?;
+        let operation = machine
+            .operation_definitions()
+            .find(|op| op.name == test.operation)
+            .ok_or_else(|| {
+                format!(
+                    "Machine `{}` has no operation `{}`.",
+                    test.machine, test.operation
+                )
+            })?;
+        let params = &operation.operation.params;
+        if params.inputs.len() != test.inputs.len() || params.outputs.len() != test.outputs.len() {
+            return Err(format!(
+                "Operation `{}` expects {} inputs and {} outputs, but the test has {} and {}.",
+                test.operation,
+                params.inputs.len(),
+                params.outputs.len(),
+                test.inputs.len(),
+                test.outputs.len()
+            ));
+        }
+
+        let mut declarations = vec![];
+        self.instantiate(&test.machine, "machine_under_test", &mut declarations)?;
+
+        // All arguments, including the expected outputs, are passed to a single instruction
+        // so that the link constrains the outputs of the operation to the expected values.
+        let registers = (0..test.inputs.len() + test.outputs.len())
+            .map(|i| format!("X{i}"))
+            .collect::<Vec<_>>();
+        let (input_registers, output_registers) = registers.split_at(test.inputs.len());
+        let outputs = match output_registers {
+            [] => String::new(),
+            [output] => format!("{output} = "),
+            outputs => format!("({}) = ", outputs.join(", ")),
+        };
+        let main = format!(
+            "machine Main with degree: {MACHINE_TEST_MAIN_DEGREE} {{\n{}\nreg pc[@pc];\n{}\n\
+            instr call_operation {} link => {outputs}machine_under_test.{}({});\n\
+            function main {{\ncall_operation {};\nreturn;\n}}\n}}",
+            declarations.join("\n"),
+            registers.iter().map(|r| format!("reg {r}[<=];")).join("\n"),
+            registers.join(", "),
+            test.operation,
+            input_registers.join(", "),
+            test.inputs.iter().chain(&test.outputs).join(", "),
+        );
+        let main = powdr_parser::parse_module(None, &main)
+            .map_err(|e| format!("Failed to generate the main machine: {}", e.message()))?;
+
+        let mut program = self.program.clone();
+        program.main.statements.retain(|s| {
+            !matches!(s, ModuleStatement::SymbolDefinition(SymbolDefinition {
+                name,
+                value: SymbolValue::Machine(_),
+            }) if name == "Main")
+        });
+        program.main.statements.extend(main.statements);
+        Ok(program.to_string())
+    }
+
+    /// Adds declarations for an instance of the machine at `path` called `name`, preceded by
+    /// the declarations of instances for its machine parameters.
+    fn instantiate(
+        &self,
+        path: &AbsoluteSymbolPath,
+        name: &str,
+        declarations: &mut Vec<String>,
+    ) -> Result<(), String> {
+        let machine = self.machine(path)?;
+        let mut args = vec![];
+        for param in &machine.params.0 {
+            let ty = AbsoluteSymbolPath::default().join(param.ty.clone().unwrap());
+            let dependency = format!("{name}_{}", param.name);
+            self.instantiate(&ty, &dependency, declarations)?;
+            args.push(dependency);
+        }
+        let args = if args.is_empty() {
+            String::new()
+        } else {
+            format!("({})", args.join(", "))
+        };
+        declarations.push(format!("{path} {name}{args};"));
+        Ok(())
+    }
+
+    fn machine(&self, path: &AbsoluteSymbolPath) -> Result<&Machine, String> {
+        self.asm
+            .machines()
+            .find(|(p, _)| p == path)
+            .map(|(_, machine)| machine)
+            .ok_or_else(|| format!("Machine `{path}` not found."))
+    }
+}
+
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "Unknown panic".to_string())
+}
@@ -0,0 +1,311 @@
+//! Export of selected parts of the witness, one file per machine.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::{self, Write},
+    ops::Range,
+    str::FromStr,
+};
+
+use powdr_executor::constant_evaluator::VariablySizedColumn;
+use powdr_number::{write_polys_csv_file_from_row, CsvRenderMode, FieldElement};
+
+/// The file format of an exported witness slice.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum WitnessExportFormat {
+    /// A CSV file with a column per polynomial, like `--export-witness-csv`.
+    #[default]
+    Csv,
+    /// A JSON object per row, mapping the column names to the values.
+    JsonLines,
+}
+
+impl WitnessExportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            WitnessExportFormat::Csv => "csv",
+            WitnessExportFormat::JsonLines => "jsonl",
+        }
+    }
+}
+
+/// Selects the parts of the witness that are exported. Each namespace (i.e. machine) is
+/// written to a separate file, so that a single machine can be inspected without loading
+/// the whole trace.
+#[derive(Clone, Debug, Default)]
+pub struct WitnessExport {
+    /// The namespaces to export. If empty, all namespaces are exported.
+    pub namespaces: Vec<String>,
+    /// A glob pattern (supporting `*` and `?`) the full column names have to match.
+    pub columns: Option<String>,
+    /// The rows to export. If None, all rows are exported.
+    pub rows: Option<Range<usize>>,
+    /// Whether to export the fixed columns of the selected namespaces as well.
+    pub include_fixed: bool,
+    pub format: WitnessExportFormat,
+}
+
+impl WitnessExport {
+    fn selects(&self, column: &str) -> bool {
+        let namespace = namespace(column);
+        (self.namespaces.is_empty() || self.namespaces.iter().any(|n| n == namespace))
+            && self
+                .columns
+                .as_ref()
+                .map_or(true, |pattern| glob_match(pattern, column))
+    }
+
+    /// Returns the selected columns, sliced to the selected rows, grouped by namespace.
+    /// The second element of each entry is the index of the first exported row.
+    pub fn select<'a, T>(
+        &self,
+        fixed: &[(&'a String, &'a [T])],
+        witness: &[(&'a String, &'a [T])],
+    ) -> BTreeMap<&'a str, (Vec<(&'a String, &'a [T])>, usize)> {
+        let fixed = if self.include_fixed { fixed } else { &[] };
+        let mut by_namespace: BTreeMap<_, (Vec<_>, _)> = BTreeMap::new();
+        for (name, values) in fixed.iter().chain(witness) {
+            if !self.selects(name) {
+                continue;
+            }
+            let rows = self.rows.clone().unwrap_or(0..values.len());
+            let start = rows.start.min(values.len());
+            let end = rows.end.clamp(start, values.len());
+            let entry = by_namespace.entry(namespace(name)).or_default();
+            entry.0.push((*name, &values[start..end]));
+            entry.1 = start;
+        }
+        for namespace in &self.namespaces {
+            if !by_namespace.contains_key(namespace.as_str()) {
+                log::warn!("No columns selected for export in namespace {namespace}.");
+            }
+        }
+        by_namespace
+    }
+
+    /// Writes the columns of one namespace, as returned by [WitnessExport::select].
+    pub fn write<T: FieldElement>(
+        &self,
+        file: impl Write,
+        render_mode: CsvRenderMode,
+        columns: &[(&String, &[T])],
+        first_row: usize,
+    ) -> io::Result<()> {
+        match self.format {
+            WitnessExportFormat::Csv => {
+                write_polys_csv_file_from_row(file, render_mode, columns, first_row);
+                Ok(())
+            }
+            WitnessExportFormat::JsonLines => {
+                write_json_lines(file, render_mode, columns, first_row)
+            }
+        }
+    }
+}
+
+/// Parses a row range of the form `start..end`, `start..` or `..end`.
+pub fn parse_row_range(range: &str) -> Result<Range<usize>, String> {
+    let (start, end) = range
+        .split_once("..")
+        .ok_or_else(|| format!("Invalid row range {range}, expected start..end"))?;
+    let parse = |bound: &str, default| {
+        if bound.is_empty() {
+            Ok(default)
+        } else {
+            usize::from_str(bound).map_err(|e| format!("Invalid row range {range}: {e}"))
+        }
+    };
+    Ok(parse(start, 0)?..parse(end, usize::MAX)?)
+}
+
+/// Returns the namespace of a column, i.e. the first segment of its name.
+fn namespace(column: &str) -> &str {
+    column.split("::").next().unwrap()
+}
+
+/// Matches a name against a glob pattern, where `*` matches any sequence of characters
+/// and `?` matches any single character.
+fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let name = name.chars().collect::<Vec<_>>();
+    // The positions after the last `*` in the pattern and the corresponding position
+    // in the name, to backtrack to if the rest does not match.
+    let mut backtrack = None;
+    let (mut p, mut n) = (0, 0);
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                p += 1;
+                backtrack = Some((p, n));
+            }
+            Some(c) if *c == '?' || *c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match backtrack {
+                Some((star_p, star_n)) => {
+                    p = star_p;
+                    n = star_n + 1;
+                    backtrack = Some((star_p, star_n + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// Writes a JSON object for each row, mapping `Row` to the row index and the column
+/// names to the rendered values.
+fn write_json_lines<T: FieldElement>(
+    mut file: impl Write,
+    render_mode: CsvRenderMode,
+    columns: &[(&String, &[T])],
+    first_row: usize,
+) -> io::Result<()> {
+    let names = columns
+        .iter()
+        .map(|(name, _)| serde_json::to_string(name).unwrap())
+        .collect::<Vec<_>>();
+    let len = columns.iter().map(|(_, values)| values.len()).max();
+    for row in 0..len.unwrap_or(0) {
+        write!(file, "{{\"Row\":{}", first_row + row)?;
+        for (name, (_, values)) in names.iter().zip(columns) {
+            if let Some(value) = values.get(row) {
+                write!(file, ",{name}:\"{}\"", render_mode.render(value))?;
+            }
+        }
+        writeln!(file, "}}")?;
+    }
+    file.flush()
+}
+
+/// Chooses the fixed columns of the size of the witness columns of the same namespace.
+/// This assumes all witness columns of the same namespace have the same size.
+/// Fixed columns of namespaces without witness columns are only included if they have
+/// a unique size, and columns that do not have a fitting size are skipped.
+pub(crate) fn fixed_columns_for_witness<'a, T: PartialEq + Copy>(
+    fixed: &'a [(String, VariablySizedColumn<T>)],
+    witness: &[(String, Vec<T>)],
+) -> Vec<(&'a String, &'a [T])> {
+    let witness_sizes: HashMap<&str, u64> = witness
+        .iter()
+        .map(|(name, values)| (namespace(name), values.len() as u64))
+        .collect();
+
+    fixed
+        .iter()
+        .filter_map(|(name, column)| {
+            let values = match witness_sizes.get(namespace(name)) {
+                Some(size) => column.get_by_size(*size),
+                None => column
+                    .get_uniquely_sized()
+                    .ok()
+                    .map(|values| values.as_slice()),
+            };
+            if values.is_none() {
+                log::debug!("Skipping fixed column {name}, none of its sizes matches the witness.");
+            }
+            Some((name, values?))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use powdr_number::GoldilocksField;
+
+    use super::*;
+
+    #[test]
+    fn glob() {
+        assert!(glob_match("main::*", "main::pc"));
+        assert!(glob_match("*::pc", "main::pc"));
+        assert!(glob_match("main::?c", "main::pc"));
+        assert!(glob_match("*", ""));
+        assert!(glob_match("*a*b*", "xxaxxbxx"));
+        assert!(!glob_match("main::*", "main_vm::pc"));
+        assert!(!glob_match("main::?", "main::pc"));
+        assert!(!glob_match("*a*b", "xxaxxbxx"));
+    }
+
+    #[test]
+    fn row_ranges() {
+        assert_eq!(parse_row_range("2..5"), Ok(2..5));
+        assert_eq!(parse_row_range("2.."), Ok(2..usize::MAX));
+        assert_eq!(parse_row_range("..5"), Ok(0..5));
+        assert!(parse_row_range("5").is_err());
+        assert!(parse_row_range("a..5").is_err());
+    }
+
+    #[test]
+    fn fixed_columns_of_witness_size() {
+        let name = |n: &str| n.to_string();
+        let column = |sizes: &[u64]| {
+            VariablySizedColumn::from(
+                sizes
+                    .iter()
+                    .map(|size| (0..*size).map(GoldilocksField::from).collect::<Vec<_>>())
+                    .collect::<Vec<_>>(),
+            )
+        };
+        let fixed = vec![
+            (name("main::f"), column(&[4, 8])),
+            (name("other::g"), column(&[4, 8])),
+            (name("single::h"), column(&[4])),
+        ];
+        let witness = vec![(name("main::a"), vec![GoldilocksField::from(0); 8])];
+
+        let selected = fixed_columns_for_witness(&fixed, &witness)
+            .into_iter()
+            .map(|(name, values)| (name.as_str(), values.len()))
+            .collect::<Vec<_>>();
+        // `other` has no witness columns and multiple sizes, so it is skipped.
+        assert_eq!(selected, vec![("main::f", 8), ("single::h", 4)]);
+    }
+
+    #[test]
+    fn select_and_write() {
+        let name = |n: &str| n.to_string();
+        let (a, b, c, f) = (
+            name("main::a"),
+            name("main::b"),
+            name("other::c"),
+            name("main::f"),
+        );
+        let values = (0..8u64).map(GoldilocksField::from).collect::<Vec<_>>();
+        let witness = [(&a, &values[..]), (&b, &values[..]), (&c, &values[..])];
+        let fixed = [(&f, &values[..])];
+
+        let export = WitnessExport {
+            namespaces: vec!["main".to_string()],
+            columns: Some("main::?".to_string()),
+            rows: Some(2..4),
+            include_fixed: true,
+            format: WitnessExportFormat::JsonLines,
+        };
+        let selected = export.select(&fixed, &witness);
+        assert_eq!(selected.keys().collect::<Vec<_>>(), vec![&"main"]);
+        let (columns, first_row) = &selected["main"];
+        assert_eq!(*first_row, 2);
+        let names = columns
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["main::f", "main::a", "main::b"]);
+
+        let mut out = vec![];
+        export
+            .write(
+                &mut out,
+                CsvRenderMode::UnsignedBase10,
+                &columns[1..2],
+                *first_row,
+            )
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "{\"Row\":2,\"main::a\":\"2\"}\n{\"Row\":3,\"main::a\":\"3\"}\n"
+        );
+    }
+}
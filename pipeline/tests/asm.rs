@@ -1,9 +1,10 @@
-use std::collections::BTreeMap;
+use std::{collections::BTreeMap, panic::AssertUnwindSafe};
 
 use powdr_executor::constant_evaluator;
 use powdr_linker::{LinkerMode, LinkerParams};
 use powdr_number::{BabyBearField, FieldElement, GoldilocksField, Mersenne31Field};
 use powdr_pipeline::{
+    test_runner,
     test_util::{
         asm_string_to_pil, make_prepared_pipeline, make_simple_prepared_pipeline,
         regular_test_all_fields, regular_test_gl, resolve_test_file, test_mock_backend,
@@ -52,6 +53,51 @@ fn simple_sum_asm() {
     regular_test_all_fields(f, &i);
 }
 
+#[test]
+fn simple_sum_asm_reduced_degree() {
+    let f = "asm/simple_sum.asm";
+    let i = [16, 4, 1, 2, 8, 5];
+    let mut pipeline = Pipeline::<GoldilocksField>::default()
+        .with_tmp_output()
+        .from_file(resolve_test_file(f))
+        .with_prover_inputs(slice_to_vec(&i))
+        .with_max_constraint_degree(2);
+    pipeline.compute_witness().unwrap();
+
+    let pil = pipeline.optimized_pil().unwrap();
+    let intermediates = pil.intermediate_definitions();
+    assert!(pil
+        .identities
+        .iter()
+        .all(|identity| identity.degree(&intermediates) <= 2));
+    test_mock_backend(pipeline);
+}
+
+#[test]
+fn simple_sum_asm_affine_column_elimination() {
+    let f = "asm/simple_sum.asm";
+    let i = [16, 4, 1, 2, 8, 5];
+    let mut pipeline = Pipeline::<GoldilocksField>::default()
+        .with_tmp_output()
+        .from_file(resolve_test_file(f))
+        .with_prover_inputs(slice_to_vec(&i))
+        .with_affine_column_elimination();
+    pipeline.compute_witness().unwrap();
+    test_mock_backend(pipeline);
+}
+
+#[test]
+fn simple_sum_asm_optimizer_check() {
+    let f = "asm/simple_sum.asm";
+    let i = [16, 4, 1, 2, 8, 5];
+    let mut pipeline = Pipeline::<GoldilocksField>::default()
+        .from_file(resolve_test_file(f))
+        .with_prover_inputs(slice_to_vec(&i))
+        .with_affine_column_elimination()
+        .with_optimizer_check();
+    pipeline.compute_witness().unwrap();
+}
+
 #[test]
 #[should_panic = "Witness generation failed."]
 fn secondary_machine_plonk() {
@@ -119,6 +165,58 @@ fn palindrome() {
     test_mock_backend(pipeline);
 }
 
+#[test]
+fn palindrome_record_and_replay() {
+    let f = "asm/palindrome.asm";
+    let i = [7, 1, 7, 3, 9, 3, 7, 1];
+    let tmp_dir = mktemp::Temp::new_dir().unwrap();
+    let replay_file = tmp_dir.to_path_buf().join("palindrome.replay");
+
+    let mut recording = Pipeline::<GoldilocksField>::default()
+        .from_file(resolve_test_file(f))
+        .with_query_recording(replay_file.clone())
+        .with_prover_inputs(slice_to_vec(&i));
+    let witness = recording.compute_witness().unwrap();
+
+    // No prover inputs are given, all queries are answered from the recording.
+    let mut replaying = Pipeline::<GoldilocksField>::default()
+        .from_file(resolve_test_file(f))
+        .with_query_replay(&replay_file)
+        .unwrap();
+    assert_eq!(replaying.compute_witness().unwrap(), witness);
+
+    // Queries that were not recorded fail.
+    let replay = Pipeline::<GoldilocksField>::default()
+        .with_query_replay(&replay_file)
+        .unwrap();
+    assert!(replay.data_callback().unwrap()("Input(0, 100)").is_err());
+}
+
+#[test]
+fn record_failing_witgen_and_replay() {
+    let f = "asm/palindrome.asm";
+    // Not a palindrome, so witness generation fails.
+    let i = [7, 1, 7, 3, 9, 3, 7, 2];
+    let tmp_dir = mktemp::Temp::new_dir().unwrap();
+    let replay_file = tmp_dir.to_path_buf().join("palindrome.replay");
+
+    let mut recording = Pipeline::<GoldilocksField>::default()
+        .from_file(resolve_test_file(f))
+        .with_query_recording(replay_file.clone())
+        .with_prover_inputs(slice_to_vec(&i));
+    let result = std::panic::catch_unwind(AssertUnwindSafe(|| recording.compute_witness()));
+    assert!(result.is_err());
+    assert!(replay_file.exists());
+
+    // The replay fails in the same way, without any prover inputs.
+    let mut replaying = Pipeline::<GoldilocksField>::default()
+        .from_file(resolve_test_file(f))
+        .with_query_replay(&replay_file)
+        .unwrap();
+    let result = std::panic::catch_unwind(AssertUnwindSafe(|| replaying.compute_witness()));
+    assert!(result.is_err());
+}
+
 #[test]
 fn single_function_vm() {
     let f = "asm/single_function_vm.asm";
@@ -317,6 +415,37 @@ fn dynamic_vadcop() {
     test_plonky3_pipeline(pipeline_gl);
 }
 
+#[cfg(feature = "halo2")]
+#[test]
+fn vadcop_merged_machines() {
+    use powdr_backend::BackendType;
+    use powdr_number::Bn254Field;
+
+    let f = "asm/vadcop_merged_machines.asm";
+    // Add and Mul have different sizes and are proven as one machine, stacked on top of each other.
+    for additions in [1, 4] {
+        // Native linker mode, because bus constraints are exponential in Halo2
+        let mut pipeline = make_prepared_pipeline::<Bn254Field>(
+            f,
+            vec![additions.into()],
+            vec![],
+            LinkerMode::Native,
+        );
+        let witness = pipeline.compute_witness().unwrap();
+        let size = |column: &str| witness.iter().find(|(k, _)| k == column).unwrap().1.len();
+        assert_eq!(size("main_add::z"), 4);
+        assert_eq!(size("main_mul::z"), 8);
+
+        pipeline
+            .with_backend(
+                BackendType::Halo2MockComposite,
+                Some("merge_machines".to_string()),
+            )
+            .compute_proof()
+            .unwrap();
+    }
+}
+
 #[test]
 fn vm_to_vm_to_vm() {
     let f = "asm/vm_to_vm_to_vm.asm";
@@ -850,6 +979,7 @@ fn types_in_expressions() {
             let h: expr -> O<expr> = |i| O::A::<expr>(i);
             match h(g[1]) {
                 O::A(x) => x,
+                O::B => 0,
             } = 0
         };
         machine Main with degree: 64 {
@@ -886,3 +1016,18 @@ fn expand_fixed_jit() {
         .collect::<Vec<_>>();
     assert_eq!(fixed_col_names, vec!["main::LAST"]);
 }
+
+#[test]
+fn machine_tests() {
+    let f = resolve_test_file("asm/machine_tests.asm");
+    let count = test_runner::run_from_file::<GoldilocksField>(f.to_str().unwrap(), false).unwrap();
+    assert_eq!(count, 4);
+}
+
+#[test]
+fn machine_tests_wrong_rejection() {
+    let f = resolve_test_file("asm/machine_tests_wrong_rejection.asm");
+    let errors =
+        test_runner::run_from_file::<GoldilocksField>(f.to_str().unwrap(), false).unwrap_err();
+    assert_eq!(errors, vec!["1 test(s) failed.".to_string()]);
+}
@@ -1,5 +1,5 @@
 use powdr_linker::LinkerMode;
-use powdr_number::{GoldilocksField, Mersenne31Field};
+use powdr_number::{Bls12_381Field, GoldilocksField, Mersenne31Field};
 use powdr_pipeline::{
     test_util::{
         assert_proofs_fail_for_invalid_witnesses, assert_proofs_fail_for_invalid_witnesses_estark,
@@ -367,6 +367,14 @@ fn naive_byte_decomposition_bn254() {
     test_halo2_with_backend_variant(pipeline, BackendVariant::Composite);
 }
 
+#[test]
+fn naive_byte_decomposition_bls12_381() {
+    // This should pass, because BLS12-381 is a field that can fit all 64-Bit integers.
+    let f = "pil/naive_byte_decomposition.pil";
+    let pipeline = make_simple_prepared_pipeline::<Bls12_381Field>(f, LinkerMode::Native);
+    test_mock_backend(pipeline);
+}
+
 #[test]
 #[should_panic = "Witness generation failed."]
 fn naive_byte_decomposition_gl() {
@@ -414,6 +422,13 @@ fn serialize_deserialize_optimized_pil() {
     assert_eq!(input_pil_file, output_pil_file);
 }
 
+#[test]
+fn fibonacci_bls12_381() {
+    let f = "pil/fibonacci.pil";
+    let pipeline = make_simple_prepared_pipeline::<Bls12_381Field>(f, LinkerMode::Bus);
+    test_mock_backend(pipeline);
+}
+
 mod reparse {
     use powdr_pipeline::test_util::run_reparse_test;
     use test_log::test;
@@ -431,3 +446,144 @@ mod book {
 
     include!(concat!(env!("OUT_DIR"), "/pil_book_tests.rs"));
 }
+
+const OPTIMIZER_CHECK_PIL: &str = r"
+namespace main(8);
+    col fixed STEP(i) { i };
+    col witness x, y, z, c;
+    c = 3;
+    x = STEP;
+    y = x;
+    z = 2 * y + c;
+";
+
+#[test]
+fn optimizer_check() {
+    let mut pipeline = Pipeline::<GoldilocksField>::default()
+        .from_pil_string(OPTIMIZER_CHECK_PIL.to_string())
+        .with_optimizer_check();
+    pipeline.compute_witness().unwrap();
+}
+
+#[test]
+fn optimizer_check_detects_violated_identity() {
+    let mut pipeline = Pipeline::<GoldilocksField>::default()
+        .from_pil_string(OPTIMIZER_CHECK_PIL.to_string())
+        .with_optimizer_check();
+    let optimized = pipeline.compute_optimized_pil().unwrap();
+    // Providing all witness columns skips witness generation. The values
+    // satisfy `x = STEP`, but not `z = 2 * y + c`.
+    let external_witness = optimized
+        .committed_polys_in_source_order()
+        .map(|(symbol, _)| {
+            let values = (0..8).map(GoldilocksField::from).collect::<Vec<_>>();
+            (symbol.absolute_name.clone(), values)
+        })
+        .collect();
+    let errors = pipeline
+        .add_external_witness_values(external_witness)
+        .compute_witness()
+        .unwrap_err();
+    assert!(errors[0].contains("violates identities of the unoptimized PIL"));
+}
+
+const AFFINE_COLUMN_PIL: &str = r"
+namespace main(8);
+    col fixed STEP(i) { i };
+    col witness x, z;
+    [x] in [STEP];
+    z = x + 1;
+    x * z = STEP * STEP + STEP;
+";
+
+fn affine_column_pipeline(z_offset: u64) -> Pipeline<GoldilocksField> {
+    // `main::z` is turned into an intermediate column, the values for it are only checked.
+    let external_witness = [("main::x", 0), ("main::z", z_offset)]
+        .into_iter()
+        .map(|(name, offset)| {
+            let values = (0..8).map(|i| GoldilocksField::from(i + offset)).collect();
+            (name.to_string(), values)
+        })
+        .collect();
+    Pipeline::<GoldilocksField>::default()
+        .from_pil_string(AFFINE_COLUMN_PIL.to_string())
+        .with_affine_column_elimination()
+        .add_external_witness_values(external_witness)
+}
+
+#[test]
+fn external_values_for_affine_column() {
+    let witness = affine_column_pipeline(1).compute_witness().unwrap();
+    assert_eq!(
+        witness
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>(),
+        vec!["main::x"]
+    );
+}
+
+#[test]
+fn wrong_external_values_for_affine_column() {
+    let errors = affine_column_pipeline(0).compute_witness().unwrap_err();
+    assert!(errors[0].contains("intermediate column main::z in row 0"));
+}
+
+const NON_LINEAR_PIL: &str = r"
+namespace main(16);
+    col fixed NIBBLE(i) { i };
+    col fixed SQUARE(i) { i * i };
+    col witness x;
+    [x] in [NIBBLE];
+    x * x = SQUARE;
+";
+
+#[test]
+#[should_panic = "Witness generation failed."]
+fn non_linear_without_solver_fallback() {
+    let mut pipeline =
+        Pipeline::<GoldilocksField>::default().from_pil_string(NON_LINEAR_PIL.to_string());
+    pipeline.compute_witness().unwrap();
+}
+
+#[test]
+fn non_linear_with_solver_fallback() {
+    let mut pipeline = Pipeline::<GoldilocksField>::default()
+        .from_pil_string(NON_LINEAR_PIL.to_string())
+        .with_witgen_solver_fallback();
+    let witness = pipeline.compute_witness().unwrap();
+    let x = witness.iter().find(|(name, _)| name == "main::x").unwrap();
+    assert_eq!(x.1, (0..16).map(GoldilocksField::from).collect::<Vec<_>>());
+}
+
+const NON_LINEAR_BLOCK_MACHINE_PIL: &str = r"
+let N: int = 8;
+
+// a block machine which computes square roots of squares of nibbles
+namespace Sqrt(N);
+    col fixed NIBBLE(i) { i & 0xf };
+    col witness A, C;
+    [A] in [NIBBLE];
+    A * A = C;
+
+namespace main(N);
+    col fixed c(i) { (i & 0x7) * (i & 0x7) };
+    col witness a;
+    col fixed CALL = [1, 0]*;
+    (1 - CALL) * a = 0;
+    CALL $ [a, c] in [Sqrt::A, Sqrt::C];
+";
+
+#[test]
+fn non_linear_block_machine_with_solver_fallback() {
+    let mut pipeline = Pipeline::<GoldilocksField>::default()
+        .from_pil_string(NON_LINEAR_BLOCK_MACHINE_PIL.to_string())
+        .with_witgen_solver_fallback();
+    let witness = pipeline.compute_witness().unwrap();
+    let a = witness.iter().find(|(name, _)| name == "main::a").unwrap();
+    assert_eq!(
+        a.1,
+        [0, 0, 2, 0, 4, 0, 6, 0].map(GoldilocksField::from).to_vec()
+    );
+    test_mock_backend(pipeline);
+}
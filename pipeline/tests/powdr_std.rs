@@ -1,7 +1,7 @@
 use std::sync::Arc;
 
-use powdr_linker::LinkerMode;
-use powdr_number::{BabyBearField, BigInt, Bn254Field, GoldilocksField};
+use powdr_linker::{DegreeMode, LinkerMode, LinkerParams};
+use powdr_number::{BabyBearField, BigInt, Bls12_381Field, Bn254Field, GoldilocksField};
 
 use powdr_pil_analyzer::evaluator::Value;
 use powdr_pipeline::{
@@ -9,8 +9,8 @@ use powdr_pipeline::{
     test_util::{
         evaluate_function, evaluate_integer_function, gen_estark_proof_with_backend_variant,
         gen_halo2_proof, make_simple_prepared_pipeline, regular_test_bb, regular_test_gl,
-        regular_test_small_field, std_analyzed, test_halo2_with_backend_variant, test_mock_backend,
-        test_plonky3_pipeline, BackendVariant,
+        regular_test_small_field, resolve_test_file, std_analyzed, test_halo2_with_backend_variant,
+        test_mock_backend, test_plonky3_pipeline, BackendVariant,
     },
     Pipeline,
 };
@@ -108,6 +108,14 @@ fn split_bn254_test() {
     test_halo2_with_backend_variant(pipeline, BackendVariant::Composite);
 }
 
+#[test]
+#[ignore = "Too slow"]
+fn split_bls12_381_test() {
+    let f = "std/split_bls12_381_test.asm";
+    let pipeline = make_simple_prepared_pipeline::<Bls12_381Field>(f, LinkerMode::Native);
+    test_mock_backend(pipeline);
+}
+
 #[test]
 #[ignore = "Too slow"]
 fn split_gl_test() {
@@ -211,6 +219,35 @@ fn lookup_via_challenges() {
     test_plonky3_pipeline(pipeline);
 }
 
+#[test]
+fn permutation_via_challenges_reduced_degree() {
+    let f = "std/permutation_via_challenges.asm";
+    let mut pipeline = Pipeline::<GoldilocksField>::default()
+        .with_tmp_output()
+        .with_linker_params(LinkerParams {
+            mode: LinkerMode::Bus,
+            degree_mode: DegreeMode::Vadcop,
+        })
+        .from_file(resolve_test_file(f))
+        .with_max_constraint_degree(2);
+    pipeline.compute_witness().unwrap();
+
+    let pil = pipeline.optimized_pil().unwrap();
+    let intermediates = pil.intermediate_definitions();
+    assert!(pil
+        .identities
+        .iter()
+        .all(|identity| identity.degree(&intermediates) <= 2));
+    // The update of the accumulator is reduced using columns of stage 1,
+    // which witness generation computes from their hints.
+    assert!(pil.definitions.values().any(|(symbol, hint)| {
+        symbol.absolute_name.contains("degree_reduction")
+            && symbol.stage == Some(1)
+            && hint.is_some()
+    }));
+    test_mock_backend(pipeline);
+}
+
 #[test]
 fn lookup_via_challenges_range_constraint() {
     let f = "std/lookup_via_challenges_range_constraint.asm";
@@ -443,8 +480,10 @@ fn std_tests() {
     let count1 = run_tests(&std_analyzed::<GoldilocksField>(), true).unwrap();
     let count2 = run_tests(&std_analyzed::<Bn254Field>(), true).unwrap();
     let count3 = run_tests(&std_analyzed::<BabyBearField>(), true).unwrap();
+    let count4 = run_tests(&std_analyzed::<Bls12_381Field>(), true).unwrap();
     assert_eq!(count1, count2);
     assert_eq!(count2, count3);
+    assert_eq!(count3, count4);
     assert!(count1 >= 9);
 }
 
@@ -502,7 +541,11 @@ mod reparse {
     /// but these tests panic if the field is too small. This is *probably*
     /// fine, because all of these tests have a similar variant that does
     /// run on Goldilocks.
-    const BLACKLIST: [&str; 2] = ["std/poseidon_bn254_test.asm", "std/split_bn254_test.asm"];
+    const BLACKLIST: [&str; 3] = [
+        "std/poseidon_bn254_test.asm",
+        "std/split_bn254_test.asm",
+        "std/split_bls12_381_test.asm",
+    ];
 
     fn run_reparse_test(file: &str) {
         run_reparse_test_with_blacklist(file, &BLACKLIST);
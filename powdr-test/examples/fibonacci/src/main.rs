@@ -1,4 +1,4 @@
-use powdr::Session;
+use powdr::{FieldElement, LargeInt, Session};
 
 fn main() {
     env_logger::init();
@@ -8,22 +8,34 @@ fn main() {
         .guest_path("./guest")
         .out_path("powdr-target")
         .build()
+        .unwrap()
         .write(&n);
 
     // Fast dry run to test execution.
-    session.run();
+    session.run().unwrap();
 
-    let r: u32 = session.stdout();
+    let r: u32 = session.stdout().unwrap();
     assert_eq!(r, 89);
 
-    session.prove();
+    let receipt = session.prove().unwrap();
 
-    let r: u32 = session.stdout();
+    let r: u32 = receipt.stdout().unwrap();
     assert_eq!(r, 89);
 
-    let publics = session.publics();
+    // The guest commits 8 words. The other publics are the registers and memory roots
+    // that link the chunks.
+    let publics: Vec<u32> = receipt
+        .publics()
+        .iter()
+        .filter(|(name, _)| name.rsplit("::").next().unwrap().starts_with("hash_"))
+        .map(|(_, v)| v.to_integer().try_into_u32().unwrap())
+        .collect();
     assert_eq!(
         publics,
         [555233681, 1854640251, 3298928347, 2857173302, 2660189392, 1608424695, 543896544, 3870154745]
     );
+
+    // Receipts can be verified without a pipeline.
+    let vkey = session.verification_key().unwrap();
+    receipt.verify(&vkey).unwrap();
 }
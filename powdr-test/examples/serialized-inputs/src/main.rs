@@ -21,11 +21,12 @@ fn main() {
         .out_path("powdr-target")
         .chunk_size_log2(18)
         .build()
+        .unwrap()
         .write(&some_data)
         .write(&s);
 
     // Fast dry run to test execution.
-    session.run();
+    session.run().unwrap();
 
     // Uncomment to compute the proof.
     //session.prove();
@@ -10,31 +10,45 @@ pub use powdr_riscv as riscv;
 pub use powdr_riscv_executor as riscv_executor;
 use powdr_riscv_executor::hash_map_to_memory_state;
 
+mod receipt;
+
+pub use receipt::{ChunkProof, Receipt, VerificationKey};
+
 pub use powdr_pipeline::Pipeline;
 
+pub use powdr_number::Bls12_381Field;
 pub use powdr_number::Bn254Field;
 pub use powdr_number::GoldilocksField;
-pub use powdr_number::{FieldElement, LargeInt};
+pub use powdr_number::{BabyBearField, KoalaBearField, Mersenne31Field};
+pub use powdr_number::{FieldElement, KnownField, LargeInt};
 
+use powdr_number::FieldSize;
 use riscv::{CompilerOptions, RuntimeLibs};
 
 use std::fs::{self, File};
+use std::marker::PhantomData;
 use std::path::Path;
 use std::path::PathBuf;
 use std::time::Instant;
 
-#[derive(Default)]
-pub struct SessionBuilder {
+pub struct SessionBuilder<F: FieldElement = GoldilocksField> {
     guest_path: String,
     out_path: String,
     asm_file: Option<String>,
     chunk_size_log2: Option<u8>,
     precompiles: RuntimeLibs,
+    _marker: PhantomData<F>,
 }
 
-pub struct Session {
-    pipeline: Pipeline<GoldilocksField>,
+/// A session compiling, running and proving a guest program over the field `F`.
+///
+/// Over Goldilocks, the execution is split into chunks using continuations.
+/// Over the small fields (BabyBear, KoalaBear, Mersenne31), continuations and
+/// precompiles are not available and the execution is proven as a whole.
+pub struct Session<F: FieldElement = GoldilocksField> {
+    pipeline: Pipeline<F>,
     out_path: String,
+    backend: backend::BackendType,
 }
 
 const DEFAULT_PKEY: &str = "pkey.bin";
@@ -46,11 +60,30 @@ const DEFAULT_MAX_DEGREE_LOG: u8 = 20;
 // Minimum acceptable max degree.
 const DEFAULT_MIN_MAX_DEGREE_LOG: u8 = 18;
 
-impl SessionBuilder {
+impl<F: FieldElement> Default for SessionBuilder<F> {
+    fn default() -> Self {
+        Self {
+            guest_path: Default::default(),
+            out_path: Default::default(),
+            asm_file: None,
+            chunk_size_log2: None,
+            precompiles: RuntimeLibs::new(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<F: FieldElement> SessionBuilder<F> {
+    /// Creates a builder for a session over the field `F`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
     /// Builds a session with the given parameters.
-    pub fn build(self) -> Session {
+    pub fn build(self) -> Result<Session<F>, Vec<String>> {
+        check_field_support::<F>(&self.precompiles)?;
         let pipeline = match self.asm_file {
-            Some(asm_file) => Pipeline::<GoldilocksField>::default()
+            Some(asm_file) => Pipeline::<F>::default()
                 .from_asm_file(asm_file.into())
                 .with_output(Path::new(&self.out_path).to_path_buf(), true),
             None => pipeline_from_guest(
@@ -59,13 +92,16 @@ impl SessionBuilder {
                 DEFAULT_MIN_DEGREE_LOG,
                 self.chunk_size_log2.unwrap_or(DEFAULT_MAX_DEGREE_LOG),
                 self.precompiles,
-            ),
+            )?,
         };
+        // Plonky3 supports all fields a guest can be compiled for.
+        let backend = powdr_backend::BackendType::Plonky3;
         Session {
             pipeline,
             out_path: self.out_path,
+            backend,
         }
-        .with_backend(powdr_backend::BackendType::Plonky3)
+        .with_backend(backend)
     }
 
     /// Sets the path to the guest program.
@@ -91,6 +127,7 @@ impl SessionBuilder {
     /// If the execution trace is longer than the 2^chunk_size_log2,
     /// the execution will be split into multiple chunks of length `2^chunk_size_log2`.
     /// Each chunk will be proven separately.
+    /// Over fields without continuations, this is the maximum length of the execution.
     pub fn chunk_size_log2(mut self, chunk_size_log2: u8) -> Self {
         assert!(chunk_size_log2 >= DEFAULT_MIN_MAX_DEGREE_LOG);
         self.chunk_size_log2 = Some(chunk_size_log2);
@@ -104,26 +141,35 @@ impl SessionBuilder {
 }
 
 impl Session {
+    /// Creates a builder for a session over Goldilocks.
+    /// Use [`SessionBuilder::new`] for sessions over other fields.
     pub fn builder() -> SessionBuilder {
-        SessionBuilder {
-            precompiles: RuntimeLibs::new(),
-            ..SessionBuilder::default()
-        }
+        SessionBuilder::new()
     }
+}
 
-    pub fn into_pipeline(self) -> Pipeline<GoldilocksField> {
+impl<F: FieldElement> Session<F> {
+    pub fn into_pipeline(self) -> Pipeline<F> {
         self.pipeline
     }
 
-    pub fn pipeline(&self) -> &Pipeline<GoldilocksField> {
+    pub fn pipeline(&self) -> &Pipeline<F> {
         &self.pipeline
     }
 
-    pub fn with_backend(self, backend: backend::BackendType) -> Self {
-        Session {
+    /// Sets the backend used for proving. Fails if the backend does not support the field.
+    pub fn with_backend(self, backend: backend::BackendType) -> Result<Self, Vec<String>> {
+        let field = known_field::<F>()?;
+        if !backend.supports_field(field) {
+            return Err(vec![format!(
+                "Backend {backend} does not support the field {field}"
+            )]);
+        }
+        Ok(Session {
             pipeline: self.pipeline.with_backend(backend, None),
+            backend,
             ..self
-        }
+        })
     }
 
     pub fn write<S: serde::Serialize>(self, data: &S) -> Self {
@@ -141,26 +187,35 @@ impl Session {
         }
     }
 
-    pub fn run(&mut self) {
-        run(&mut self.pipeline);
+    pub fn run(&mut self) -> Result<(), Vec<String>> {
+        run(&mut self.pipeline)
     }
 
-    pub fn run_with_profiler(&mut self) {
+    pub fn run_with_profiler(&mut self) -> Result<(), Vec<String>> {
         let profiler = riscv_executor::ProfilerOptions {
             output_directory: ".".to_string(),
             file_stem: None,
             flamegraph: true,
             callgrind: true,
+            instruction_trace: false,
         };
         run_with_profiler(&mut self.pipeline, profiler)
     }
 
-    pub fn prove(&mut self) {
-        let asm_name = self.pipeline.asm_string().unwrap().0.clone().unwrap();
-        let pil_file = pil_file_path(&asm_name);
+    /// Proves the execution of the guest and returns a receipt containing the proofs
+    /// of all chunks, their public values and the outputs of the guest.
+    /// The outputs are not authenticated by the proofs, see [`Receipt`].
+    pub fn prove(&mut self) -> Result<Receipt<F>, Vec<String>> {
+        let asm_name = self
+            .pipeline
+            .compute_asm_string()?
+            .0
+            .clone()
+            .ok_or_else(|| vec!["The guest program has no path".to_string()])?;
+        let pil_file = pil_file_path(&asm_name)?;
 
         let generate_artifacts = if let Ok(existing_pil) = fs::read_to_string(&pil_file) {
-            let computed_pil = self.pipeline.compute_optimized_pil().unwrap().to_string();
+            let computed_pil = self.pipeline.compute_optimized_pil()?.to_string();
             if existing_pil != computed_pil {
                 log::info!("Compiled PIL changed, invalidating artifacts...");
                 true
@@ -178,9 +233,9 @@ impl Session {
 
         if generate_artifacts {
             println!("Creating program ZK setup. This has to be done only once per program.");
-            self.pipeline.compute_fixed_cols().unwrap();
-            self.pipeline.setup_backend().unwrap();
-            self.export_setup();
+            self.pipeline.compute_fixed_cols()?;
+            self.pipeline.setup_backend()?;
+            self.export_setup()?;
             self.pipeline.set_pkey_file(pkey.clone());
             self.pipeline.set_vkey_file(vkey.clone());
         } else {
@@ -192,131 +247,214 @@ impl Session {
             {
                 log::info!("Read constants from file...");
             } else {
-                self.pipeline.compute_fixed_cols().unwrap();
+                self.pipeline.compute_fixed_cols()?;
             }
 
             if pkey.exists() && vkey.exists() {
                 log::info!("Re-using proving and verification keys...");
                 self.pipeline.set_pkey_file(pkey.clone());
                 self.pipeline.set_vkey_file(vkey.clone());
-                self.pipeline.setup_backend().unwrap();
+                self.pipeline.setup_backend()?;
             } else {
-                self.export_setup();
+                self.export_setup()?;
                 self.pipeline.set_pkey_file(pkey.clone());
                 self.pipeline.set_vkey_file(vkey.clone());
             }
         }
 
-        prove(&mut self.pipeline);
+        if supports_continuations(known_field::<F>()?) {
+            prove(&mut self.pipeline)
+        } else {
+            prove_without_continuations(&mut self.pipeline)
+        }
     }
 
-    pub fn export_setup(&mut self) {
+    pub fn export_setup(&mut self) -> Result<(), Vec<String>> {
         let mut path = PathBuf::from(self.out_path.clone());
         path.push(DEFAULT_PKEY);
-        let file = File::create(path).unwrap();
+        let file = create_file(&path)?;
 
-        self.pipeline.export_proving_key(file).unwrap();
+        self.pipeline.export_proving_key(file)?;
 
         let mut path = PathBuf::from(self.out_path.clone());
         path.push(DEFAULT_VKEY);
-        let file = File::create(path).unwrap();
+        let file = create_file(&path)?;
 
-        self.pipeline.export_verification_key(file).unwrap();
+        self.pipeline.export_verification_key(file)
     }
 
-    pub fn publics(&self) -> [u32; 8] {
-        let pubs: Vec<u32> = self
-            .pipeline
-            .publics()
-            .unwrap()
-            .iter()
-            .map(|(_, v)| v.unwrap().to_integer().try_into_u32().unwrap())
-            .collect();
-        pubs.try_into().expect("There should be exactly 8 publics")
+    /// Returns the key needed to verify the receipts produced by this session.
+    pub fn verification_key(&mut self) -> Result<VerificationKey<F>, Vec<String>> {
+        let mut vkey = vec![];
+        self.pipeline.export_verification_key(&mut vkey)?;
+        let execution_bounds = if supports_continuations(known_field::<F>()?) {
+            Some(riscv::continuations::execution_bounds(&mut self.pipeline)?)
+        } else {
+            None
+        };
+        Ok(VerificationKey {
+            backend: self.backend.to_string(),
+            backend_options: Default::default(),
+            pil: self.pipeline.compute_optimized_pil()?.as_ref().clone(),
+            vkey,
+            execution_bounds,
+        })
+    }
+
+    /// The public values of the last proven chunk.
+    pub fn publics(&self) -> Result<Vec<(String, F)>, Vec<String>> {
+        known_publics(&self.pipeline)
     }
 
-    pub fn stdout<S: serde::de::DeserializeOwned>(&self) -> S {
+    pub fn stdout<S: serde::de::DeserializeOwned>(&self) -> Result<S, Vec<String>> {
         let host = self.pipeline.host_context();
-        host.read(1).unwrap()
+        host.read(1).map_err(|e| vec![e])
     }
 
-    pub fn stderr<S: serde::de::DeserializeOwned>(&self) -> S {
+    pub fn stderr<S: serde::de::DeserializeOwned>(&self) -> Result<S, Vec<String>> {
         let host = self.pipeline.host_context();
-        host.read(2).unwrap()
+        host.read(2).map_err(|e| vec![e])
+    }
+}
+
+fn known_field<F: FieldElement>() -> Result<KnownField, Vec<String>> {
+    F::known_field()
+        .ok_or_else(|| vec!["Sessions are only supported over known fields".to_string()])
+}
+
+/// Continuations rely on the Goldilocks-specific bootloader.
+fn supports_continuations(field: KnownField) -> bool {
+    field == KnownField::GoldilocksField
+}
+
+/// Checks that guests can be compiled for the field `F` with the given precompiles.
+fn check_field_support<F: FieldElement>(precompiles: &RuntimeLibs) -> Result<(), Vec<String>> {
+    let field = known_field::<F>()?;
+    match field.field_size() {
+        FieldSize::Large if field == KnownField::GoldilocksField => Ok(()),
+        FieldSize::Large => Err(vec![format!(
+            "RISC-V guests are not supported over {field}"
+        )]),
+        FieldSize::Small => {
+            let unsupported = [
+                ("arith", precompiles.arith),
+                ("keccak", precompiles.keccak),
+                ("poseidon2", precompiles.poseidon2),
+            ]
+            .into_iter()
+            .filter_map(|(name, enabled)| enabled.then_some(name))
+            .collect::<Vec<_>>();
+            if unsupported.is_empty() {
+                Ok(())
+            } else {
+                Err(vec![format!(
+                    "Precompiles not supported over {field}: {}",
+                    unsupported.join(", ")
+                )])
+            }
+        }
     }
 }
 
-fn pil_file_path(asm_name: &Path) -> PathBuf {
-    let file_stem = asm_name.file_stem().unwrap().to_str().unwrap();
+/// The public values of the current witness, failing if any of them is unknown.
+fn known_publics<F: FieldElement>(pipeline: &Pipeline<F>) -> Result<Vec<(String, F)>, Vec<String>> {
+    pipeline
+        .publics()?
+        .into_iter()
+        .map(|(name, v)| {
+            v.map(|v| (name.clone(), v))
+                .ok_or_else(|| vec![format!("Public {name} is not known")])
+        })
+        .collect()
+}
+
+fn create_file(path: &Path) -> Result<File, Vec<String>> {
+    File::create(path).map_err(|e| vec![format!("Error creating {}: {e}", path.display())])
+}
+
+fn pil_file_path(asm_name: &Path) -> Result<PathBuf, Vec<String>> {
+    let file_stem = asm_name
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .ok_or_else(|| vec![format!("Invalid guest path: {}", asm_name.display())])?;
     let opt_file_stem = format!("{file_stem}_opt");
-    asm_name.with_file_name(opt_file_stem).with_extension("pil")
+    Ok(asm_name.with_file_name(opt_file_stem).with_extension("pil"))
 }
 
-pub fn build_guest(
+/// Compiles the guest for the field `F`, with continuations if the field supports them.
+pub fn build_guest<F: FieldElement>(
     guest_path: &str,
     out_path: &Path,
     min_degree_log: u8,
     max_degree_log: u8,
     precompiles: RuntimeLibs,
-) -> (PathBuf, String) {
-    let options = CompilerOptions::new_gl()
-        .with_runtime_libs(precompiles)
-        .with_continuations()
+) -> Result<(PathBuf, String), Vec<String>> {
+    check_field_support::<F>(&precompiles)?;
+    let field = known_field::<F>()?;
+    let options = CompilerOptions::new(field, precompiles, supports_continuations(field))
         .with_min_degree_log(min_degree_log)
         .with_max_degree_log(max_degree_log);
     riscv::compile_rust(guest_path, options, out_path, true, None)
         .ok_or_else(|| vec!["could not compile rust".to_string()])
-        .unwrap()
 }
 
-pub fn pipeline_from_guest(
+pub fn pipeline_from_guest<F: FieldElement>(
     guest_path: &str,
     out_path: &Path,
     min_degree_log: u8,
     max_degree_log: u8,
     precompiles: RuntimeLibs,
-) -> Pipeline<GoldilocksField> {
+) -> Result<Pipeline<F>, Vec<String>> {
     println!("Compiling guest program...");
 
-    let (asm_file_path, asm_contents) = build_guest(
+    let (asm_file_path, asm_contents) = build_guest::<F>(
         guest_path,
         out_path,
         min_degree_log,
         max_degree_log,
         precompiles,
-    );
+    )?;
 
     // Create a pipeline from the asm program
-    Pipeline::<GoldilocksField>::default()
+    Ok(Pipeline::<F>::default()
         .from_asm_string(asm_contents.clone(), Some(asm_file_path.clone()))
-        .with_output(out_path.into(), true)
+        .with_output(out_path.into(), true))
 }
 
-pub fn run(pipeline: &mut Pipeline<GoldilocksField>) {
+pub fn run<F: FieldElement>(pipeline: &mut Pipeline<F>) -> Result<(), Vec<String>> {
     run_internal(pipeline, None)
 }
 
-pub fn run_with_profiler(
-    pipeline: &mut Pipeline<GoldilocksField>,
+pub fn run_with_profiler<F: FieldElement>(
+    pipeline: &mut Pipeline<F>,
     profiler: riscv_executor::ProfilerOptions,
-) {
+) -> Result<(), Vec<String>> {
     run_internal(pipeline, Some(profiler))
 }
 
-fn run_internal(
-    pipeline: &mut Pipeline<GoldilocksField>,
+fn run_internal<F: FieldElement>(
+    pipeline: &mut Pipeline<F>,
     profiler: Option<riscv_executor::ProfilerOptions>,
-) {
+) -> Result<(), Vec<String>> {
+    let field = known_field::<F>()?;
+    if field != KnownField::GoldilocksField {
+        return Err(vec![format!(
+            "The RISC-V executor does not support {field} yet, only Goldilocks"
+        )]);
+    }
+
     println!("Running powdr-riscv executor in fast mode...");
     let start = Instant::now();
 
-    let asm = pipeline.compute_analyzed_asm().unwrap().clone();
+    let asm = pipeline.compute_analyzed_asm()?.clone();
     let initial_memory = riscv::continuations::load_initial_memory(&asm, pipeline.initial_memory());
 
     let trace_len = riscv_executor::execute(
         &asm,
         hash_map_to_memory_state(initial_memory),
-        pipeline.data_callback().unwrap(),
+        pipeline
+            .data_callback()
+            .ok_or_else(|| vec!["No query callback set".to_string()])?,
         &riscv::continuations::bootloader::default_input(&[]),
         profiler,
     );
@@ -324,22 +462,39 @@ fn run_internal(
     let duration = start.elapsed();
     println!("Fast executor took: {duration:?}");
     println!("Trace length: {trace_len}");
+
+    Ok(())
 }
 
-pub fn prove(pipeline: &mut Pipeline<GoldilocksField>) {
+/// Proves all chunks of the execution and collects the proofs, their public values
+/// and the outputs of the guest into a [`Receipt`].
+pub fn prove<F: FieldElement>(pipeline: &mut Pipeline<F>) -> Result<Receipt<F>, Vec<String>> {
+    let field = known_field::<F>()?;
+    if !supports_continuations(field) {
+        return Err(vec![format!(
+            "Continuations are not supported over {field}"
+        )]);
+    }
+
     log::info!("Running powdr-riscv executor in trace mode for continuations...");
     let start = Instant::now();
 
     let bootloader_inputs =
-        riscv::continuations::rust_continuations_dry_run(&mut pipeline.clone(), None);
+        riscv::continuations::rust_continuations_dry_run(&mut pipeline.clone(), None)?;
 
     let duration = start.elapsed();
     log::info!("Trace executor took: {:?}", duration);
 
+    // The dry run executes the whole program, so this is everything the guest wrote.
+    // Witness generation only sees one chunk at a time and clears the host context.
+    let outputs = pipeline.host_context().file_data.lock().unwrap().clone();
+
+    let mut chunks = vec![];
+
     // TODO how do we skip PIL compilation and fixed column generation if not needed?
     // We can check whether they exist and not generate it, but what if the asm changed?
     // Maybe one solution is to at least compile asm to PIL and see if that changed.
-    let generate_proof = |pipeline: &mut Pipeline<GoldilocksField>| -> Result<(), Vec<String>> {
+    let generate_proof = |pipeline: &mut Pipeline<F>| -> Result<(), Vec<String>> {
         let start = Instant::now();
         log::info!("Generating witness...");
         pipeline.compute_witness()?;
@@ -349,11 +504,14 @@ pub fn prove(pipeline: &mut Pipeline<GoldilocksField>) {
         println!("Generating proof...");
         let start = Instant::now();
 
-        pipeline.compute_proof().unwrap();
+        let proof = pipeline.compute_proof()?.clone();
 
         let duration = start.elapsed();
         println!("Proof generation took: {duration:?}");
 
+        let publics = known_publics(pipeline)?;
+        chunks.push(ChunkProof { proof, publics });
+
         Ok(())
     };
 
@@ -364,7 +522,79 @@ pub fn prove(pipeline: &mut Pipeline<GoldilocksField>) {
         bootloader_inputs.bootloader_inputs.len()
     );
     let start = Instant::now();
-    riscv::continuations::rust_continuations(pipeline, generate_proof, bootloader_inputs).unwrap();
+    riscv::continuations::rust_continuations(pipeline, generate_proof, bootloader_inputs)?;
     let duration = start.elapsed();
     log::info!("Proof generation for all chunks took: {:?}", duration);
+
+    // Restore the outputs of the full execution, so that they can be read from the session.
+    *pipeline.host_context().file_data.lock().unwrap() = outputs.clone();
+
+    Ok(Receipt { chunks, outputs })
+}
+
+/// Proves the whole execution at once, for fields that do not support continuations.
+pub fn prove_without_continuations<F: FieldElement>(
+    pipeline: &mut Pipeline<F>,
+) -> Result<Receipt<F>, Vec<String>> {
+    if !pipeline.initial_memory().is_empty() {
+        return Err(vec![
+            "Writing to the initial memory requires continuations, which are not supported over this field"
+                .to_string(),
+        ]);
+    }
+
+    let start = Instant::now();
+    log::info!("Generating witness...");
+    pipeline.compute_witness()?;
+    let duration = start.elapsed();
+    log::info!("Generating witness took: {duration:?}");
+
+    println!("Generating proof...");
+    let start = Instant::now();
+    let proof = pipeline.compute_proof()?.clone();
+    let duration = start.elapsed();
+    println!("Proof generation took: {duration:?}");
+
+    let publics = known_publics(pipeline)?;
+    let outputs = pipeline.host_context().file_data.lock().unwrap().clone();
+
+    Ok(Receipt {
+        chunks: vec![ChunkProof { proof, publics }],
+        outputs,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn unsupported_field() {
+        let errors = SessionBuilder::<Bn254Field>::new()
+            .asm_file("test.asm")
+            .build()
+            .err()
+            .unwrap();
+        assert_eq!(errors, vec!["RISC-V guests are not supported over Bn254"]);
+    }
+
+    #[cfg(feature = "halo2")]
+    #[test]
+    fn unsupported_backend() {
+        for backend in [
+            backend::BackendType::Halo2,
+            backend::BackendType::Halo2Mock,
+            backend::BackendType::Halo2MockComposite,
+        ] {
+            let session = Session::builder().asm_file("test.asm").build().unwrap();
+            let errors = session.with_backend(backend).err().unwrap();
+            assert_eq!(
+                errors,
+                vec![format!(
+                    "Backend {backend} does not support the field Goldilocks"
+                )]
+            );
+            assert!(backend.supports_field(KnownField::Bn254Field));
+        }
+    }
 }
@@ -0,0 +1,302 @@
+use std::{collections::BTreeMap, str::FromStr, sync::Arc};
+
+use powdr_ast::analyzed::Analyzed;
+use powdr_backend::{BackendOptions, BackendType, Proof};
+use powdr_number::FieldElement;
+use powdr_riscv::continuations::manifest::{ChunkManifest, ContinuationsManifest, ExecutionBounds};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+/// The proof of a single continuation chunk.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(bound = "F: FieldElement")]
+pub struct ChunkProof<F: FieldElement> {
+    /// The backend-specific proof.
+    pub proof: Proof,
+    /// The public values the chunk was proven against, in declaration order.
+    pub publics: Vec<(String, F)>,
+}
+
+/// Everything needed to verify the proofs of a [`Receipt`] without building a pipeline.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(bound = "F: FieldElement")]
+pub struct VerificationKey<F: FieldElement> {
+    /// The name of the backend the proofs were generated with, e.g. `plonky3`.
+    pub backend: String,
+    /// The options the backend was instantiated with.
+    pub backend_options: BackendOptions,
+    /// The optimized PIL the proofs were generated for.
+    pub pil: Analyzed<F>,
+    /// The backend-specific verification key.
+    pub vkey: Vec<u8>,
+    /// The initial memory root and final PC of the execution, if it is proven with
+    /// continuations.
+    pub execution_bounds: Option<ExecutionBounds<F>>,
+}
+
+/// The result of proving a guest program: the proofs of all chunks,
+/// their public values and the data the guest wrote to the host.
+///
+/// Only the chunk proofs and their public values are checked by [`Receipt::verify`].
+/// The outputs are not committed to by the proofs, so they are unauthenticated: anyone
+/// holding the receipt can change them without invalidating it. Guests whose results
+/// need to be trusted by a verifier have to expose them as public values instead.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(bound = "F: FieldElement")]
+pub struct Receipt<F: FieldElement> {
+    /// The proofs of all chunks, in execution order.
+    pub chunks: Vec<ChunkProof<F>>,
+    /// The data written by the guest, by file descriptor. Not authenticated by the proofs.
+    pub outputs: BTreeMap<u32, Vec<u8>>,
+}
+
+impl<F: FieldElement> VerificationKey<F> {
+    pub fn to_bytes(&self) -> Result<Vec<u8>, String> {
+        serde_cbor::to_vec(self).map_err(|e| format!("Failed to serialize verification key: {e}"))
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        serde_cbor::from_slice(bytes)
+            .map_err(|e| format!("Failed to deserialize verification key: {e}"))
+    }
+}
+
+impl<F: FieldElement> Receipt<F> {
+    /// The public values of the last chunk, i.e. those describing the final state of the execution.
+    pub fn publics(&self) -> &[(String, F)] {
+        self.chunks
+            .last()
+            .map(|chunk| chunk.publics.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Deserializes what the guest wrote to the file descriptor `fd`.
+    /// The data is not authenticated by the proofs, even if [`Receipt::verify`] succeeds.
+    pub fn read<S: DeserializeOwned>(&self, fd: u32) -> Result<S, String> {
+        let data = self
+            .outputs
+            .get(&fd)
+            .ok_or_else(|| format!("File descriptor {fd} not found"))?;
+        serde_cbor::from_slice(data).map_err(|e| format!("Error deserializing data: {e}"))
+    }
+
+    /// Deserializes what the guest wrote to stdout, see [`Receipt::read`].
+    pub fn stdout<S: DeserializeOwned>(&self) -> Result<S, String> {
+        self.read(1)
+    }
+
+    /// Deserializes what the guest wrote to stderr, see [`Receipt::read`].
+    pub fn stderr<S: DeserializeOwned>(&self) -> Result<S, String> {
+        self.read(2)
+    }
+
+    /// Verifies the proofs of all chunks against `vkey`, and that the chunks are linked
+    /// through their public registers and memory roots, starting with the initial memory
+    /// and ending with the program terminated.
+    /// The outputs of the guest are not part of the proofs and are not checked.
+    pub fn verify(&self, vkey: &VerificationKey<F>) -> Result<(), Vec<String>> {
+        if self.chunks.is_empty() {
+            return Err(vec!["Receipt does not contain any proof".to_string()]);
+        }
+
+        let backend_type = BackendType::from_str(&vkey.backend)
+            .map_err(|e| vec![format!("Unknown backend {}: {e}", vkey.backend)])?;
+        let mut key = vkey.vkey.as_slice();
+        // The verifier only needs the verification key, no fixed columns are passed.
+        let backend = backend_type
+            .factory::<F>()
+            .create(
+                Arc::new(vkey.pil.clone()),
+                Arc::new(vec![]),
+                None,
+                None,
+                None,
+                Some(&mut key),
+                None,
+                vkey.backend_options.clone(),
+            )
+            .map_err(backend_error)?;
+
+        for (i, chunk) in self.chunks.iter().enumerate() {
+            let instances = chunk.publics.iter().map(|(_, v)| *v).collect();
+            backend
+                .verify(&chunk.proof, &[instances])
+                .map_err(backend_error)
+                .map_err(|mut e| {
+                    e.insert(0, format!("Proof of chunk {i} is invalid"));
+                    e
+                })?;
+        }
+
+        self.verify_chaining(vkey.execution_bounds.as_ref())
+    }
+
+    /// Checks that each chunk starts in the state the previous one ended in, and that the
+    /// chunks cover the whole execution.
+    /// Receipts proven without continuations consist of a single chunk that does not expose
+    /// its state transition, so there is nothing to check apart from the number of chunks.
+    fn verify_chaining(&self, bounds: Option<&ExecutionBounds<F>>) -> Result<(), Vec<String>> {
+        let Some(bounds) = bounds else {
+            return match self.chunks.len() {
+                1 => Ok(()),
+                n => Err(vec![format!(
+                    "Receipts proven without continuations contain a single proof, found {n}"
+                )]),
+            };
+        };
+        let chunks = self
+            .chunks
+            .iter()
+            .enumerate()
+            .map(|(i, chunk)| {
+                ChunkManifest::from_publics(&chunk.publics)
+                    .map_err(|e| vec![format!("Chunk {i}: {e}")])
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        ContinuationsManifest { chunks }.verify_chaining(bounds)
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>, String> {
+        serde_cbor::to_vec(self).map_err(|e| format!("Failed to serialize receipt: {e}"))
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        serde_cbor::from_slice(bytes).map_err(|e| format!("Failed to deserialize receipt: {e}"))
+    }
+}
+
+fn backend_error(e: powdr_backend::Error) -> Vec<String> {
+    match e {
+        powdr_backend::Error::BackendError(e) => vec![e],
+        e => vec![e.to_string()],
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use powdr_number::BabyBearField;
+
+    use super::*;
+
+    #[test]
+    fn empty_receipt_is_rejected() {
+        let receipt = Receipt::<BabyBearField> {
+            chunks: vec![],
+            outputs: Default::default(),
+        };
+        let vkey = VerificationKey {
+            backend: "plonky3".to_string(),
+            backend_options: Default::default(),
+            pil: powdr_pil_analyzer::analyze_string("namespace main(4);").unwrap(),
+            vkey: vec![],
+            execution_bounds: None,
+        };
+        assert_eq!(
+            receipt.verify(&vkey).unwrap_err(),
+            vec!["Receipt does not contain any proof".to_string()]
+        );
+    }
+
+    #[test]
+    fn outputs() {
+        let receipt = Receipt::<BabyBearField> {
+            chunks: vec![],
+            outputs: [(1, serde_cbor::to_vec(&"hello").unwrap())]
+                .into_iter()
+                .collect(),
+        };
+        let receipt = Receipt::<BabyBearField>::from_bytes(&receipt.to_bytes().unwrap()).unwrap();
+        assert_eq!(receipt.stdout::<String>().unwrap(), "hello");
+        assert_eq!(
+            receipt.stderr::<String>().unwrap_err(),
+            "File descriptor 2 not found"
+        );
+    }
+
+    #[cfg(feature = "plonky3")]
+    mod plonky3 {
+        use powdr_backend::BackendType;
+        use powdr_number::BabyBearField;
+        use powdr_pipeline::Pipeline;
+
+        use super::super::*;
+
+        const PIL: &str = r"
+            namespace main(8);
+                col fixed LAST = [0]* + [1];
+                col witness x;
+                x' = (1 - LAST) * (x + 1);
+                public out = x(7);
+        ";
+
+        /// Proves the PIL and returns the receipt together with its verification key.
+        fn prove() -> (Receipt<BabyBearField>, VerificationKey<BabyBearField>) {
+            let backend = BackendType::Plonky3;
+            let mut pipeline = Pipeline::<BabyBearField>::default()
+                .from_pil_string(PIL.to_string())
+                .with_backend(backend, None);
+            pipeline.compute_witness().unwrap();
+            let proof = pipeline.compute_proof().unwrap().clone();
+            let publics = pipeline
+                .publics()
+                .unwrap()
+                .into_iter()
+                .map(|(name, value)| (name, value.unwrap()))
+                .collect();
+            let mut vkey = vec![];
+            pipeline.export_verification_key(&mut vkey).unwrap();
+            let vkey = VerificationKey {
+                backend: backend.to_string(),
+                backend_options: Default::default(),
+                pil: pipeline.compute_optimized_pil().unwrap().as_ref().clone(),
+                vkey,
+                execution_bounds: None,
+            };
+            let receipt = Receipt {
+                chunks: vec![ChunkProof { proof, publics }],
+                outputs: Default::default(),
+            };
+            (receipt, vkey)
+        }
+
+        #[test]
+        fn verify_after_round_trip() {
+            let (receipt, vkey) = prove();
+            let bytes = receipt.to_bytes().unwrap();
+            let receipt = Receipt::<BabyBearField>::from_bytes(&bytes).unwrap();
+            assert_eq!(receipt.to_bytes().unwrap(), bytes);
+            let vkey_bytes = vkey.to_bytes().unwrap();
+            let vkey = VerificationKey::<BabyBearField>::from_bytes(&vkey_bytes).unwrap();
+            assert_eq!(vkey.to_bytes().unwrap(), vkey_bytes);
+
+            assert_eq!(receipt.publics(), &[("main::out".to_string(), 7.into())]);
+            receipt.verify(&vkey).unwrap();
+        }
+
+        #[test]
+        fn outputs_are_not_authenticated() {
+            let (mut receipt, vkey) = prove();
+            receipt
+                .outputs
+                .insert(1, serde_cbor::to_vec(&"forged").unwrap());
+            receipt.verify(&vkey).unwrap();
+        }
+
+        #[test]
+        fn tampered_proof_is_rejected() {
+            let (mut receipt, vkey) = prove();
+            let proof = &mut receipt.chunks[0].proof;
+            let middle = proof.len() / 2;
+            proof[middle] ^= 1;
+            let errors = receipt.verify(&vkey).unwrap_err();
+            assert_eq!(errors[0], "Proof of chunk 0 is invalid");
+        }
+
+        #[test]
+        fn tampered_publics_are_rejected() {
+            let (mut receipt, vkey) = prove();
+            receipt.chunks[0].publics[0].1 = 8.into();
+            let errors = receipt.verify(&vkey).unwrap_err();
+            assert_eq!(errors[0], "Proof of chunk 0 is invalid");
+        }
+    }
+}
@@ -0,0 +1,326 @@
+//! Decoding of the main machine into a dense list of typed statements.
+//!
+//! The statements of the main function are decoded once, before execution:
+//! instruction names become [`Instruction`]s, registers become indices into
+//! the register bank and labels become their batch numbers. This way, the
+//! execution loop neither compares strings nor looks up names in hash maps.
+//!
+//! Statements the executor does not support are decoded into
+//! [`DecodedStatement::Unsupported`], which only fails if it is executed.
+
+use std::collections::HashMap;
+
+use itertools::Itertools;
+use powdr_ast::{
+    asm_analysis::FunctionStatement,
+    parsed::{
+        asm::{AssignmentRegister, DebugDirective},
+        BinaryOperation, BinaryOperator, Expression, FunctionCall, Number, UnaryOperation,
+        UnaryOperator,
+    },
+};
+use powdr_number::FieldElement;
+
+use crate::{Elem, Instruction};
+
+/// The maximum number of arguments of an instruction.
+pub(crate) const MAX_ARGS: usize = 8;
+
+/// An instruction argument or the right hand side of an assignment.
+#[derive(Debug)]
+pub(crate) enum Operand<F: FieldElement> {
+    /// A number or a label.
+    Const(Elem<F>),
+    /// The index of a register.
+    Reg(u16),
+    Binary(BinaryOperator, Box<Operand<F>>, Box<Operand<F>>),
+    Neg(Box<Operand<F>>),
+    /// A call to an instruction that returns a value.
+    Call(Instruction, Vec<Operand<F>>),
+    /// A prover query, with the name of the `std::prelude::Query` variant.
+    Query(String, Vec<Operand<F>>),
+}
+
+/// A statement of the main function, ready to be executed.
+#[derive(Debug)]
+pub(crate) enum DecodedStatement<'a, F: FieldElement> {
+    /// Assignment of `rhs` to the register with index `dest`, through the assignment register X.
+    Assignment {
+        dest: u16,
+        rhs: Operand<F>,
+        /// Whether `rhs` is a free input.
+        free_input: bool,
+    },
+    Instruction(Instruction, Vec<Operand<F>>),
+    Return,
+    DebugDirective(&'a DebugDirective),
+    /// A statement that cannot be executed, with the reason.
+    Unsupported(String),
+}
+
+/// Decodes the statements of the main function (with batches expanded).
+/// Labels share the identifier space with registers, and take precedence over them.
+pub(crate) fn decode_statements<'a, F: FieldElement>(
+    statements: &[&'a FunctionStatement],
+    label_map: &HashMap<&str, Elem<F>>,
+    reg_map: &HashMap<String, u16>,
+) -> Vec<DecodedStatement<'a, F>> {
+    let decoder = Decoder { label_map, reg_map };
+    statements
+        .iter()
+        .map(|statement| decoder.decode_statement(statement))
+        .collect()
+}
+
+struct Decoder<'b, F: FieldElement> {
+    label_map: &'b HashMap<&'b str, Elem<F>>,
+    reg_map: &'b HashMap<String, u16>,
+}
+
+impl<F: FieldElement> Decoder<'_, F> {
+    fn decode_statement<'a>(&self, statement: &'a FunctionStatement) -> DecodedStatement<'a, F> {
+        self.try_decode_statement(statement)
+            .unwrap_or_else(DecodedStatement::Unsupported)
+    }
+
+    fn try_decode_statement<'a>(
+        &self,
+        statement: &'a FunctionStatement,
+    ) -> Result<DecodedStatement<'a, F>, String> {
+        Ok(match statement {
+            FunctionStatement::Assignment(a) => {
+                let [(dest, asgn_reg)] = &a.lhs_with_reg[..] else {
+                    return Err("Only assignments to a single register are supported.".into());
+                };
+                // we currently only assign through X
+                if !matches!(asgn_reg, AssignmentRegister::Register(x) if x == "X") {
+                    return Err("Only assignments through X are supported.".into());
+                }
+                DecodedStatement::Assignment {
+                    dest: self.register(dest)?,
+                    rhs: self.decode_expression(&a.rhs)?,
+                    free_input: matches!(a.rhs.as_ref(), Expression::FreeInput(_, _)),
+                }
+            }
+            FunctionStatement::Instruction(i) => DecodedStatement::Instruction(
+                instruction(&i.instruction)?,
+                self.arguments(&i.inputs)?,
+            ),
+            FunctionStatement::Return(_) => DecodedStatement::Return,
+            FunctionStatement::DebugDirective(dd) => {
+                DecodedStatement::DebugDirective(&dd.directive)
+            }
+            FunctionStatement::Label(_) => unreachable!(),
+        })
+    }
+
+    fn arguments(&self, arguments: &[Expression]) -> Result<Vec<Operand<F>>, String> {
+        if arguments.len() > MAX_ARGS {
+            return Err(format!(
+                "Instructions with more than {MAX_ARGS} arguments are not supported."
+            ));
+        }
+        arguments
+            .iter()
+            .map(|arg| self.decode_expression(arg))
+            .collect()
+    }
+
+    fn register(&self, name: &str) -> Result<u16, String> {
+        self.reg_map
+            .get(name)
+            .copied()
+            .ok_or_else(|| format!("Unknown register or label: {name}"))
+    }
+
+    fn decode_expression(&self, expression: &Expression) -> Result<Operand<F>, String> {
+        Ok(match expression {
+            Expression::Reference(_, r) => {
+                let name = r
+                    .try_to_identifier()
+                    .ok_or_else(|| format!("Unsupported reference: {r}"))?;
+                match self.label_map.get(name.as_str()) {
+                    Some(label) => Operand::Const(*label),
+                    None => Operand::Reg(self.register(name)?),
+                }
+            }
+            Expression::Number(_, Number { value: n, .. }) => {
+                let unsigned: u32 = n
+                    .try_into()
+                    .map_err(|_| format!("Value does not fit in 32 bits: {n}"))?;
+
+                Operand::Const(unsigned.into())
+            }
+            Expression::BinaryOperation(
+                _,
+                BinaryOperation {
+                    left: l,
+                    op,
+                    right: r,
+                },
+            ) => Operand::Binary(
+                *op,
+                Box::new(self.decode_expression(l)?),
+                Box::new(self.decode_expression(r)?),
+            ),
+            Expression::UnaryOperation(_, UnaryOperation { op, expr: arg }) => match op {
+                UnaryOperator::Minus => Operand::Neg(Box::new(self.decode_expression(arg)?)),
+                UnaryOperator::LogicalNot | UnaryOperator::Next => {
+                    return Err(format!("Unsupported operator: {expression}"));
+                }
+            },
+            Expression::FunctionCall(
+                _,
+                FunctionCall {
+                    function,
+                    arguments,
+                },
+            ) => match function.as_ref() {
+                // whatever. we don't need to convert anything
+                Expression::Reference(_, f)
+                    if ["std::prover::eval", "std::convert::int"].contains(&&f.to_string()[..]) =>
+                {
+                    let argument = arguments
+                        .first()
+                        .ok_or_else(|| format!("Missing argument: {expression}"))?;
+                    self.decode_expression(argument)?
+                }
+                Expression::Reference(_, f) => {
+                    let name = f
+                        .try_to_identifier()
+                        .ok_or_else(|| format!("Unsupported function: {f}"))?;
+                    Operand::Call(instruction(name)?, self.arguments(arguments)?)
+                }
+                _ => {
+                    return Err(format!(
+                        "Function call not implemented: {function}({})",
+                        arguments.iter().format(", ")
+                    ));
+                }
+            },
+            Expression::FreeInput(_, expr) => {
+                let pattern_error = || format!("Free input does not match pattern: {expr}");
+                let Expression::FunctionCall(
+                    _,
+                    FunctionCall {
+                        function,
+                        arguments,
+                    },
+                ) = expr.as_ref()
+                else {
+                    return Err(pattern_error());
+                };
+                let Expression::Reference(_, f) = function.as_ref() else {
+                    return Err(pattern_error());
+                };
+                let variant = f
+                    .to_string()
+                    .strip_prefix("std::prelude::Query::")
+                    .ok_or_else(pattern_error)?
+                    .to_string();
+                Operand::Query(
+                    variant,
+                    arguments
+                        .iter()
+                        .map(|arg| self.decode_expression(arg))
+                        .collect::<Result<_, _>>()?,
+                )
+            }
+            _ => return Err(format!("Unsupported expression: {expression}")),
+        })
+    }
+}
+
+fn instruction(name: &str) -> Result<Instruction, String> {
+    Instruction::from_name(name).ok_or_else(|| format!("Unknown instruction: {name}"))
+}
+
+#[cfg(test)]
+mod test {
+    use powdr_ast::{
+        asm_analysis::{AssignmentStatement, InstructionStatement, Return},
+        parsed::NamespacedPolynomialReference,
+    };
+    use powdr_number::GoldilocksField;
+
+    use super::*;
+
+    fn reg(name: &str) -> Expression {
+        NamespacedPolynomialReference::from_identifier(name.to_string()).into()
+    }
+
+    #[test]
+    fn unsupported_statements_are_decoded() {
+        let statements = [
+            FunctionStatement::Instruction(InstructionStatement {
+                source: Default::default(),
+                instruction: "unknown_instr".to_string(),
+                inputs: vec![reg("x1")],
+            }),
+            FunctionStatement::Assignment(AssignmentStatement {
+                source: Default::default(),
+                lhs_with_reg: vec![(
+                    "x1".to_string(),
+                    AssignmentRegister::Register("X".to_string()),
+                )],
+                rhs: Box::new(
+                    UnaryOperation {
+                        op: UnaryOperator::LogicalNot,
+                        expr: Box::new(reg("x2")),
+                    }
+                    .into(),
+                ),
+            }),
+            FunctionStatement::Instruction(InstructionStatement {
+                source: Default::default(),
+                instruction: "jump".to_string(),
+                inputs: vec![reg("end"), reg("x3")],
+            }),
+            FunctionStatement::Return(Return {
+                source: Default::default(),
+                values: vec![],
+            }),
+            FunctionStatement::Assignment(AssignmentStatement {
+                source: Default::default(),
+                lhs_with_reg: vec![(
+                    "x1".to_string(),
+                    AssignmentRegister::Register("X".to_string()),
+                )],
+                rhs: Box::new(
+                    FunctionCall {
+                        function: Box::new(reg("std::prover::eval")),
+                        arguments: vec![],
+                    }
+                    .into(),
+                ),
+            }),
+        ];
+        let statements = statements.iter().collect::<Vec<_>>();
+        let label_map = [("end", Elem::<GoldilocksField>::from(3u32))].into();
+        let reg_map = [("x1", 0), ("x2", 1), ("x3", 2)]
+            .map(|(name, index)| (name.to_string(), index))
+            .into();
+
+        let program = decode_statements(&statements, &label_map, &reg_map);
+
+        assert!(matches!(
+            &program[0],
+            DecodedStatement::Unsupported(message) if message == "Unknown instruction: unknown_instr"
+        ));
+        assert!(matches!(
+            &program[1],
+            DecodedStatement::Unsupported(message) if message == "Unsupported operator: !x2"
+        ));
+        assert!(matches!(
+            &program[2],
+            DecodedStatement::Instruction(Instruction::jump, args)
+                if matches!(args[..], [Operand::Const(_), Operand::Reg(2)])
+        ));
+        assert!(matches!(program[3], DecodedStatement::Return));
+        assert!(matches!(
+            &program[4],
+            DecodedStatement::Unsupported(message)
+                if message == "Missing argument: std::prover::eval()"
+        ));
+    }
+}
@@ -0,0 +1,433 @@
+//! Instruction-level execution traces.
+//!
+//! A trace records, for each executed RISC-V instruction, the PC, the source location
+//! of the last `.debug loc` directive, the original instruction (from `.debug insn`),
+//! and the register writes and memory accesses it performed. Statements that are not
+//! preceded by a `.debug insn` directive (e.g. the bootloader) are recorded one by one,
+//! using the powdr asm statement as the instruction text. The RISC-V compiler only emits
+//! `.debug insn` directives if `CompilerOptions::with_debug_instructions` is set.
+//!
+//! The binary format starts with the magic bytes `POWDRTRC` and a format version,
+//! followed by the list of source files and one record per instruction.
+//! Integers are LEB128-encoded and strings are length-prefixed UTF-8.
+//! Instruction texts are interned: the first occurrence of a text is written after its id.
+
+use std::{
+    collections::HashMap,
+    fmt::{self, Display, Formatter},
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+use crate::MemOperationKind;
+
+const MAGIC: &[u8; 8] = b"POWDRTRC";
+const VERSION: u64 = 1;
+
+/// A memory access performed by an instruction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MemAccess {
+    pub kind: MemOperationKind,
+    pub address: u32,
+    /// The lower 64 bits of the value read or written.
+    pub value: u64,
+}
+
+/// A single executed instruction.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TracedInstruction {
+    pub pc: u32,
+    /// File index (starting at 1) and line of the last `.debug loc` directive, if any.
+    pub loc: Option<(u32, u32)>,
+    pub insn: String,
+    /// Register index and the lower 64 bits of the written value.
+    pub reg_writes: Vec<(u32, u64)>,
+    pub mem_accesses: Vec<MemAccess>,
+}
+
+impl TracedInstruction {
+    /// Two instructions match if they are the same instruction with the same effects.
+    /// PCs and source locations are not compared, as they change between compiler versions.
+    pub fn matches(&self, other: &Self) -> bool {
+        self.insn == other.insn
+            && self.reg_writes == other.reg_writes
+            && self.mem_accesses == other.mem_accesses
+    }
+}
+
+impl Display for TracedInstruction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "pc {:>6}: {}", self.pc, self.insn)?;
+        for (reg, value) in &self.reg_writes {
+            write!(f, "; x{reg} <- 0x{value:x}")?;
+        }
+        for access in &self.mem_accesses {
+            let op = match access.kind {
+                MemOperationKind::Read => "->",
+                MemOperationKind::Write => "<-",
+            };
+            write!(f, "; [0x{:08x}] {op} 0x{:x}", access.address, access.value)?;
+        }
+        Ok(())
+    }
+}
+
+/// An instruction trace read from a file.
+#[derive(Debug, Default)]
+pub struct InstructionTrace {
+    /// Source files as (directory, file name). The file with index `i` is at position `i - 1`.
+    pub files: Vec<(String, String)>,
+    pub instructions: Vec<TracedInstruction>,
+}
+
+impl InstructionTrace {
+    pub fn read<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let mut r = BufReader::new(File::open(path)?);
+
+        let mut magic = [0u8; 8];
+        r.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid_data("not an instruction trace".to_string()));
+        }
+        let version = read_uint(&mut r)?;
+        if version != VERSION {
+            return Err(invalid_data(format!(
+                "unsupported instruction trace version {version}"
+            )));
+        }
+
+        let files = (0..read_uint(&mut r)?)
+            .map(|_| Ok((read_string(&mut r)?, read_string(&mut r)?)))
+            .collect::<io::Result<_>>()?;
+
+        let mut texts: Vec<String> = vec![];
+        let mut instructions = vec![];
+        // Records continue until the end of the file.
+        while let Some(pc) = read_uint_or_eof(&mut r)? {
+            let file = read_uint(&mut r)? as u32;
+            let line = read_uint(&mut r)? as u32;
+            let id = read_uint(&mut r)? as usize;
+            if id == texts.len() {
+                texts.push(read_string(&mut r)?);
+            }
+            let insn = texts
+                .get(id)
+                .ok_or_else(|| invalid_data(format!("unknown instruction id {id}")))?
+                .clone();
+            let reg_writes = (0..read_uint(&mut r)?)
+                .map(|_| Ok((read_uint(&mut r)? as u32, read_uint(&mut r)?)))
+                .collect::<io::Result<_>>()?;
+            let mem_accesses = (0..read_uint(&mut r)?)
+                .map(|_| {
+                    let kind = match read_uint(&mut r)? {
+                        0 => MemOperationKind::Read,
+                        1 => MemOperationKind::Write,
+                        k => return Err(invalid_data(format!("invalid memory access kind {k}"))),
+                    };
+                    Ok(MemAccess {
+                        kind,
+                        address: read_uint(&mut r)? as u32,
+                        value: read_uint(&mut r)?,
+                    })
+                })
+                .collect::<io::Result<_>>()?;
+            instructions.push(TracedInstruction {
+                pc: pc as u32,
+                loc: (file != 0).then_some((file, line)),
+                insn,
+                reg_writes,
+                mem_accesses,
+            });
+        }
+
+        Ok(Self {
+            files,
+            instructions,
+        })
+    }
+
+    /// Renders the source location of an instruction as `dir/file:line`.
+    pub fn location(&self, insn: &TracedInstruction) -> Option<String> {
+        let (file, line) = insn.loc?;
+        let (dir, name) = self.files.get(file as usize - 1)?;
+        Some(format!("{dir}/{name}:{line}"))
+    }
+}
+
+/// Returns the index of the first instruction at which the two traces diverge, or
+/// `None` if they are equal. If one trace is a prefix of the other, the index is the
+/// length of the shorter one.
+pub fn first_divergence(left: &InstructionTrace, right: &InstructionTrace) -> Option<usize> {
+    left.instructions
+        .iter()
+        .zip(&right.instructions)
+        .position(|(l, r)| !l.matches(r))
+        .or_else(|| {
+            (left.instructions.len() != right.instructions.len())
+                .then(|| left.instructions.len().min(right.instructions.len()))
+        })
+}
+
+/// Records the instructions executed by the executor into a trace file.
+pub(crate) struct InstructionTracer {
+    writer: BufWriter<File>,
+    texts: HashMap<String, u64>,
+    loc: Option<(u32, u32)>,
+    current: Option<TracedInstruction>,
+    /// Whether the current instruction was started by a `.debug insn` directive,
+    /// in which case it spans all statements up to the next directive.
+    current_is_original: bool,
+}
+
+impl InstructionTracer {
+    pub fn new<P: AsRef<Path>>(path: P, debug_files: &[(&str, &str)]) -> io::Result<Self> {
+        log::info!("Writing instruction trace to {:?}", path.as_ref());
+        let mut writer = BufWriter::new(File::create(path)?);
+        writer.write_all(MAGIC)?;
+        write_uint(&mut writer, VERSION)?;
+        write_uint(&mut writer, debug_files.len() as u64)?;
+        for (dir, file) in debug_files {
+            write_string(&mut writer, dir)?;
+            write_string(&mut writer, file)?;
+        }
+        Ok(Self {
+            writer,
+            texts: Default::default(),
+            loc: None,
+            current: None,
+            current_is_original: false,
+        })
+    }
+
+    pub fn set_location(&mut self, file: usize, line: usize) {
+        self.loc = Some((file as u32, line as u32));
+    }
+
+    /// Starts a new instruction at a `.debug insn` directive.
+    pub fn original_instruction(&mut self, pc: u32, insn: &str) -> io::Result<()> {
+        self.start(pc, insn.to_string())?;
+        self.current_is_original = true;
+        Ok(())
+    }
+
+    /// Called before a statement is executed. Starts a new instruction unless the statement
+    /// belongs to the current original instruction.
+    pub fn statement(&mut self, pc: u32, statement: impl FnOnce() -> String) -> io::Result<()> {
+        if !self.current_is_original || self.current.is_none() {
+            self.start(pc, statement())?;
+            self.current_is_original = false;
+        }
+        Ok(())
+    }
+
+    pub fn reg_write(&mut self, reg: u32, value: u64) {
+        if let Some(current) = &mut self.current {
+            current.reg_writes.push((reg, value));
+        }
+    }
+
+    pub fn mem_access(&mut self, kind: MemOperationKind, address: u32, value: u64) {
+        if let Some(current) = &mut self.current {
+            current.mem_accesses.push(MemAccess {
+                kind,
+                address,
+                value,
+            });
+        }
+    }
+
+    pub fn finish(&mut self) -> io::Result<()> {
+        self.flush_current()?;
+        self.writer.flush()
+    }
+
+    fn start(&mut self, pc: u32, insn: String) -> io::Result<()> {
+        self.flush_current()?;
+        self.current = Some(TracedInstruction {
+            pc,
+            loc: self.loc,
+            insn,
+            reg_writes: vec![],
+            mem_accesses: vec![],
+        });
+        Ok(())
+    }
+
+    fn flush_current(&mut self) -> io::Result<()> {
+        match self.current.take() {
+            Some(insn) => self.write(&insn),
+            None => Ok(()),
+        }
+    }
+
+    fn write(&mut self, insn: &TracedInstruction) -> io::Result<()> {
+        let w = &mut self.writer;
+        write_uint(w, insn.pc as u64)?;
+        let (file, line) = insn.loc.unwrap_or_default();
+        write_uint(w, file as u64)?;
+        write_uint(w, line as u64)?;
+        match self.texts.get(&insn.insn) {
+            Some(id) => write_uint(w, *id)?,
+            None => {
+                let id = self.texts.len() as u64;
+                write_uint(w, id)?;
+                write_string(w, &insn.insn)?;
+                self.texts.insert(insn.insn.clone(), id);
+            }
+        }
+        write_uint(w, insn.reg_writes.len() as u64)?;
+        for (reg, value) in &insn.reg_writes {
+            write_uint(w, *reg as u64)?;
+            write_uint(w, *value)?;
+        }
+        write_uint(w, insn.mem_accesses.len() as u64)?;
+        for access in &insn.mem_accesses {
+            let kind = match access.kind {
+                MemOperationKind::Read => 0,
+                MemOperationKind::Write => 1,
+            };
+            write_uint(w, kind)?;
+            write_uint(w, access.address as u64)?;
+            write_uint(w, access.value)?;
+        }
+        Ok(())
+    }
+}
+
+fn invalid_data(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn write_uint(w: &mut impl Write, mut v: u64) -> io::Result<()> {
+    loop {
+        let byte = (v & 0x7f) as u8;
+        v >>= 7;
+        if v == 0 {
+            return w.write_all(&[byte]);
+        }
+        w.write_all(&[byte | 0x80])?;
+    }
+}
+
+fn write_string(w: &mut impl Write, s: &str) -> io::Result<()> {
+    write_uint(w, s.len() as u64)?;
+    w.write_all(s.as_bytes())
+}
+
+/// Reads an integer, returning `None` if the reader is at the end of the input.
+fn read_uint_or_eof(r: &mut impl Read) -> io::Result<Option<u64>> {
+    let mut v = 0u64;
+    for shift in (0..64).step_by(7) {
+        let mut byte = [0u8];
+        if r.read(&mut byte)? == 0 {
+            return if shift == 0 {
+                Ok(None)
+            } else {
+                Err(io::ErrorKind::UnexpectedEof.into())
+            };
+        }
+        v |= ((byte[0] & 0x7f) as u64) << shift;
+        if byte[0] & 0x80 == 0 {
+            return Ok(Some(v));
+        }
+    }
+    Err(invalid_data("integer too large".to_string()))
+}
+
+fn read_uint(r: &mut impl Read) -> io::Result<u64> {
+    read_uint_or_eof(r)?.ok_or_else(|| io::ErrorKind::UnexpectedEof.into())
+}
+
+/// Reads a string. The buffer grows with the bytes actually read, so that a corrupt length
+/// does not cause a huge allocation.
+fn read_string(r: &mut impl Read) -> io::Result<String> {
+    let len = read_uint(r)?;
+    let mut bytes = vec![];
+    r.take(len).read_to_end(&mut bytes)?;
+    if bytes.len() as u64 != len {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    String::from_utf8(bytes).map_err(|e| invalid_data(e.to_string()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn write_and_read() {
+        let dir = std::env::temp_dir().join(format!("powdr_trace_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("test.trace");
+
+        let mut tracer = InstructionTracer::new(&path, &[("src", "main.rs")]).unwrap();
+        tracer.statement(0, || "set_reg 0, 0;".to_string()).unwrap();
+        tracer.reg_write(0, 0);
+        tracer.set_location(1, 42);
+        tracer.original_instruction(3, "sw x5, x2, 8").unwrap();
+        tracer.statement(3, || unreachable!()).unwrap();
+        tracer.mem_access(MemOperationKind::Write, 0x1000, u64::MAX);
+        tracer.statement(4, || unreachable!()).unwrap();
+        tracer.reg_write(5, 300);
+        tracer.original_instruction(5, "sw x5, x2, 8").unwrap();
+        tracer.finish().unwrap();
+        drop(tracer);
+
+        let trace = InstructionTrace::read(&path).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            trace.files,
+            vec![("src".to_string(), "main.rs".to_string())]
+        );
+        assert_eq!(trace.instructions.len(), 3);
+        let sw = &trace.instructions[1];
+        assert_eq!(sw.pc, 3);
+        assert_eq!(trace.location(sw).unwrap(), "src/main.rs:42");
+        assert_eq!(sw.reg_writes, vec![(5, 300)]);
+        assert_eq!(
+            sw.mem_accesses,
+            vec![MemAccess {
+                kind: MemOperationKind::Write,
+                address: 0x1000,
+                value: u64::MAX
+            }]
+        );
+        assert_eq!(trace.instructions[0].loc, None);
+        assert_eq!(trace.instructions[2].insn, sw.insn);
+    }
+
+    #[test]
+    fn corrupt_string_length() {
+        let mut bytes = vec![];
+        write_uint(&mut bytes, u64::MAX >> 1).unwrap();
+        bytes.extend_from_slice(b"abc");
+        assert_eq!(
+            read_string(&mut bytes.as_slice()).unwrap_err().kind(),
+            io::ErrorKind::UnexpectedEof
+        );
+    }
+
+    #[test]
+    fn divergence() {
+        let insn = |insn: &str, value| TracedInstruction {
+            pc: 0,
+            loc: None,
+            insn: insn.to_string(),
+            reg_writes: vec![(1, value)],
+            mem_accesses: vec![],
+        };
+        let trace = |instructions| InstructionTrace {
+            files: vec![],
+            instructions,
+        };
+        let a = trace(vec![insn("add", 1), insn("add", 2)]);
+        let b = trace(vec![insn("add", 1), insn("add", 3)]);
+        let c = trace(vec![insn("add", 1)]);
+
+        assert_eq!(first_divergence(&a, &a), None);
+        assert_eq!(first_divergence(&a, &b), Some(1));
+        assert_eq!(first_divergence(&a, &c), Some(1));
+    }
+}
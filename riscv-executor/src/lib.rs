@@ -12,7 +12,8 @@
 use std::{
     collections::{BTreeMap, HashMap},
     fmt::{self, Display, Formatter},
-    path::Path,
+    io,
+    path::{Path, PathBuf},
     sync::Arc,
     time::Instant,
 };
@@ -21,14 +22,10 @@ use num_derive::{FromPrimitive, ToPrimitive};
 
 use builder::TraceBuilder;
 
-use itertools::Itertools;
 use powdr_ast::{
     analyzed::{AlgebraicExpression, Analyzed, Identity, LookupIdentity},
     asm_analysis::{AnalysisASMFile, CallableSymbol, FunctionStatement, LabelStatement, Machine},
-    parsed::{
-        asm::{parse_absolute_path, AssignmentRegister, DebugDirective},
-        BinaryOperation, Expression, FunctionCall, Number, UnaryOperation,
-    },
+    parsed::asm::{parse_absolute_path, DebugDirective},
 };
 use tiny_keccak::keccakf;
 
@@ -37,6 +34,8 @@ use powdr_number::{write_polys_csv_file, FieldElement, LargeInt};
 pub use profiler::ProfilerOptions;
 
 pub mod arith;
+mod instruction_trace;
+pub use instruction_trace::{first_divergence, InstructionTrace, MemAccess, TracedInstruction};
 mod poseidon2_gl;
 pub mod poseidon_gl;
 mod profiler;
@@ -44,8 +43,11 @@ mod submachines;
 use submachines::*;
 mod memory;
 use memory::*;
+mod decode;
 mod pil;
+use decode::{DecodedStatement, Operand};
 
+use crate::instruction_trace::InstructionTracer;
 use crate::profiler::Profiler;
 
 #[derive(Debug)]
@@ -517,7 +519,7 @@ impl<F: FieldElement> Display for Elem<F> {
 pub type MemoryState<F> = HashMap<u32, Elem<F>>;
 pub type RegisterMemoryState<F> = HashMap<u32, F>;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum MemOperationKind {
     Read,
     Write,
@@ -658,6 +660,15 @@ mod builder {
             .collect()
     }
 
+    /// Maps the name of each register of the register bank to its index.
+    pub(crate) fn register_map(main: &Machine) -> HashMap<String, u16> {
+        register_names(main)
+            .into_iter()
+            .enumerate()
+            .map(|(i, name)| (name.to_string(), i as u16))
+            .collect()
+    }
+
     pub struct TraceBuilder<'b, F: FieldElement> {
         trace: ExecutionTrace<F>,
 
@@ -693,6 +704,9 @@ mod builder {
         /// Fast: do not save the register's trace and memory accesses.
         /// Trace: save everything - needed for continuations.
         mode: ExecMode,
+
+        /// Records the executed instructions, if enabled.
+        pub(crate) instruction_trace: Option<InstructionTracer>,
     }
 
     impl<'a, 'b: 'a, F: FieldElement> TraceBuilder<'b, F> {
@@ -710,11 +724,7 @@ mod builder {
             max_rows_len: usize,
             mode: ExecMode,
         ) -> Result<Self, Box<Execution<F>>> {
-            let reg_map = register_names(main)
-                .into_iter()
-                .enumerate()
-                .map(|(i, name)| (name.to_string(), i as u16))
-                .collect::<HashMap<String, u16>>();
+            let reg_map = register_map(main);
 
             let reg_len = reg_map.len();
 
@@ -780,6 +790,7 @@ mod builder {
                 mem,
                 reg_mem: Default::default(),
                 mode,
+                instruction_trace: None,
             };
 
             if ret.has_enough_rows() || ret.set_next_pc().is_none() {
@@ -857,13 +868,8 @@ mod builder {
             self.regs[self.pc_idx as usize]
         }
 
-        /// get current value of register
-        pub(crate) fn get_reg(&self, idx: &str) -> Elem<F> {
-            self.get_reg_idx(self.trace.reg_map[idx])
-        }
-
-        /// get current value of register by register index instead of name
-        fn get_reg_idx(&self, idx: u16) -> Elem<F> {
+        /// get current value of register, by its index in the register bank
+        pub(crate) fn get_reg(&self, idx: u16) -> Elem<F> {
             if idx == self.pc_idx {
                 return self.get_pc();
             }
@@ -877,15 +883,10 @@ mod builder {
             self.set_reg_idx(self.pc_idx, value);
         }
 
-        /// set next value of register, accounting to x0 writes
+        /// set next value of register (by its index in the register bank), accounting to x0 writes
         ///
         /// to set the PC, use set_pc() instead of this
-        pub(crate) fn set_reg(&mut self, idx: &str, value: impl Into<Elem<F>>) {
-            self.set_reg_impl(idx, value.into())
-        }
-
-        fn set_reg_impl(&mut self, idx: &str, value: Elem<F>) {
-            let idx = self.trace.reg_map[idx];
+        pub(crate) fn set_reg(&mut self, idx: u16, value: Elem<F>) {
             assert!(idx != self.pc_idx);
             self.set_reg_idx(idx, value);
         }
@@ -982,6 +983,9 @@ mod builder {
             val: Elem<F>,
             kind: MemOperationKind,
         ) {
+            if let Some(t) = &mut self.instruction_trace {
+                t.mem_access(kind, addr, elem_to_u64(val));
+            }
             if let ExecMode::Witness = self.mode {
                 let selector = match kind {
                     MemOperationKind::Read => F::zero(),
@@ -1307,9 +1311,8 @@ fn preprocess_main_function<F: FieldElement>(machine: &Machine) -> PreprocessedM
 
 type Callback<'a, F> = dyn powdr_executor::witgen::QueryCallback<F> + 'a;
 
-struct Executor<'a, 'b, F: FieldElement> {
+struct Executor<'b, F: FieldElement> {
     proc: TraceBuilder<'b, F>,
-    label_map: HashMap<&'a str, Elem<F>>,
     inputs: &'b Callback<'b, F>,
     bootloader_inputs: Vec<Elem<F>>,
     fixed: Arc<Vec<(String, VariablySizedColumn<F>)>>,
@@ -1327,7 +1330,7 @@ struct Executor<'a, 'b, F: FieldElement> {
     cached_fixed_cols: Vec<Vec<F>>,
 }
 
-impl<F: FieldElement> Executor<'_, '_, F> {
+impl<F: FieldElement> Executor<'_, F> {
     fn init(&mut self) {
         if let ExecMode::Witness = self.mode {
             for c in KnownFixedCol::all() {
@@ -1382,6 +1385,9 @@ impl<F: FieldElement> Executor<'_, '_, F> {
             ],
             &[],
         );
+        if let Some(t) = &mut self.proc.instruction_trace {
+            t.reg_write(reg, elem_to_u64(val));
+        }
         self.proc.set_reg_mem(reg, val);
     }
 
@@ -1417,7 +1423,7 @@ impl<F: FieldElement> Executor<'_, '_, F> {
         }
     }
 
-    fn exec_instruction(&mut self, name: &str, args: &[Expression]) -> Option<Elem<F>> {
+    fn exec_instruction(&mut self, instr: Instruction, args: &[Elem<F>]) -> Option<Elem<F>> {
         // shorthand macros for setting/getting main machine witness values in the current row
         macro_rules! set_col {
             ($name:ident, $val:expr) => {
@@ -1443,11 +1449,6 @@ impl<F: FieldElement> Executor<'_, '_, F> {
             };
         }
 
-        let args = args
-            .iter()
-            .map(|expr| self.eval_expression(expr).unwrap())
-            .collect::<Vec<_>>();
-
         self.proc.backup_reg_mem();
 
         if self.proc.col_is_defined("main::X_const") {
@@ -1466,8 +1467,6 @@ impl<F: FieldElement> Executor<'_, '_, F> {
             set_col!(W, get_fixed!(W_const));
         }
 
-        let instr = Instruction::from_name(name).expect("unknown instruction");
-
         let r = match instr {
             Instruction::set_reg => {
                 let addr = args[0].u();
@@ -2672,42 +2671,25 @@ impl<F: FieldElement> Executor<'_, '_, F> {
         r
     }
 
-    fn eval_expression(&mut self, expression: &Expression) -> Option<Elem<F>> {
-        match expression {
-            Expression::Reference(_, r) => {
-                // an identifier looks like this:
-                let name = r.try_to_identifier().unwrap();
+    /// Evaluates the operands into `values` and returns the number of operands.
+    fn eval_operands(
+        &mut self,
+        operands: &[Operand<F>],
+        values: &mut [Elem<F>; decode::MAX_ARGS],
+    ) -> usize {
+        for (value, operand) in values.iter_mut().zip(operands) {
+            *value = self.eval_operand(operand).unwrap();
+        }
+        operands.len()
+    }
 
-                // labels share the identifier space with registers:
-                // try one, then the other
-                let val = self
-                    .label_map
-                    .get(name.as_str())
-                    .cloned()
-                    .unwrap_or_else(|| self.proc.get_reg(name.as_str()));
-                Some(val)
-            }
-            Expression::Number(_, Number { value: n, .. }) => {
-                let unsigned: u32 = n
-                    .try_into()
-                    .unwrap_or_else(|_| panic!("Value does not fit in 32 bits."));
-
-                Some(unsigned.into())
-            }
-            Expression::String(_, _) => todo!(),
-            Expression::Tuple(_, _) => todo!(),
-            Expression::LambdaExpression(_, _) => todo!(),
-            Expression::ArrayLiteral(_, _) => todo!(),
-            Expression::BinaryOperation(
-                _,
-                BinaryOperation {
-                    left: l,
-                    op,
-                    right: r,
-                },
-            ) => {
-                let l = &self.eval_expression(l).unwrap();
-                let r = &self.eval_expression(r).unwrap();
+    fn eval_operand(&mut self, operand: &Operand<F>) -> Option<Elem<F>> {
+        match operand {
+            Operand::Const(val) => Some(*val),
+            Operand::Reg(idx) => Some(self.proc.get_reg(*idx)),
+            Operand::Binary(op, l, r) => {
+                let l = &self.eval_operand(l).unwrap();
+                let r = &self.eval_operand(r).unwrap();
 
                 let result = match (l, r) {
                     (Elem::Binary(l), Elem::Binary(r)) => match op {
@@ -2734,13 +2716,13 @@ impl<F: FieldElement> Executor<'_, '_, F> {
                         powdr_ast::parsed::BinaryOperator::Pow => {
                             Elem::Binary(l.pow(u32::try_from(*r).unwrap()))
                         }
-                        _ => todo!(),
+                        _ => panic!("Operator {op} is not supported on binary values"),
                     },
                     (Elem::Field(l), Elem::Field(r)) => {
                         let result = match op {
                             // We need to subtract field elements in the bootloader:
                             powdr_ast::parsed::BinaryOperator::Sub => *l - *r,
-                            _ => todo!(),
+                            _ => panic!("Operator {op} is not supported on field values"),
                         };
                         Elem::Field(result)
                     }
@@ -2748,7 +2730,9 @@ impl<F: FieldElement> Executor<'_, '_, F> {
                         // We need to add a field element to a binary when calling poseidon_gl:
                         let result = match op {
                             powdr_ast::parsed::BinaryOperator::Add => F::from(*l) + *r,
-                            _ => todo!(),
+                            _ => panic!(
+                                "Operator {op} is not supported on a binary and a field value"
+                            ),
                         };
                         Elem::Field(result)
                     }
@@ -2757,62 +2741,19 @@ impl<F: FieldElement> Executor<'_, '_, F> {
 
                 Some(result)
             }
-            Expression::UnaryOperation(_, UnaryOperation { op, expr: arg }) => {
-                let arg = self.eval_expression(arg).unwrap().bin();
-                let result = match op {
-                    powdr_ast::parsed::UnaryOperator::Minus => -arg,
-                    powdr_ast::parsed::UnaryOperator::LogicalNot => todo!(),
-                    powdr_ast::parsed::UnaryOperator::Next => unreachable!(),
-                };
-
-                Some(Elem::Binary(result))
-            }
-            Expression::FunctionCall(
-                _,
-                FunctionCall {
-                    function,
-                    arguments,
-                },
-            ) => match function.as_ref() {
-                Expression::Reference(_, f) if f.to_string() == "std::prover::eval" => {
-                    self.eval_expression(&arguments[0])
-                }
-                Expression::Reference(_, f) if f.to_string() == "std::convert::int" => {
-                    // whatever. we don't need to convert anything
-                    self.eval_expression(&arguments[0])
-                }
-                Expression::Reference(_, f) => {
-                    self.exec_instruction(f.try_to_identifier().unwrap(), arguments)
-                }
-                _ => {
-                    unimplemented!(
-                        "Function call not implemented: {function}{}",
-                        arguments.iter().format(", ")
-                    )
-                }
-            },
-            Expression::FreeInput(_, expr) => {
-                let Expression::FunctionCall(
-                    _,
-                    FunctionCall {
-                        function,
-                        arguments,
-                    },
-                ) = expr.as_ref()
-                else {
-                    panic!("Free input does not match pattern: {expr}");
-                };
-                let Expression::Reference(_, f) = function.as_ref() else {
-                    panic!("Free input does not match pattern: {expr}");
-                };
-                let variant = f
-                    .to_string()
-                    .strip_prefix("std::prelude::Query::")
-                    .unwrap_or_else(|| panic!("Free input does not match pattern: {expr}"))
-                    .to_string();
+            Operand::Neg(arg) => {
+                let arg = self.eval_operand(arg).unwrap().bin();
+                Some(Elem::Binary(-arg))
+            }
+            Operand::Call(instr, arguments) => {
+                let mut values = [Elem::default(); decode::MAX_ARGS];
+                let len = self.eval_operands(arguments, &mut values);
+                self.exec_instruction(*instr, &values[..len])
+            }
+            Operand::Query(variant, arguments) => {
                 let values = arguments
                     .iter()
-                    .map(|arg| self.eval_expression(arg).unwrap().to_string())
+                    .map(|arg| self.eval_operand(arg).unwrap().to_string())
                     .collect::<Vec<_>>();
                 let query = format!("{variant}({})", values.join(","));
                 match (self.inputs)(&query).unwrap() {
@@ -2826,11 +2767,6 @@ impl<F: FieldElement> Executor<'_, '_, F> {
                     }
                 }
             }
-            Expression::MatchExpression(_, _) => todo!(),
-            Expression::IfExpression(_, _) => panic!(),
-            Expression::BlockExpression(_, _) => panic!(),
-            Expression::IndexAccess(_, _) => todo!(),
-            Expression::StructExpression(_, _) => todo!(),
         }
     }
 
@@ -2955,6 +2891,15 @@ pub fn execute_with_witness<F: FieldElement>(
 }
 
 #[allow(clippy::too_many_arguments)]
+/// Stops recording the instruction trace if it could not be written, e.g. because the disk
+/// is full, so that the execution itself is not affected.
+fn stop_trace_on_error(instruction_trace: &mut Option<InstructionTracer>, result: io::Result<()>) {
+    if let Err(err) = result {
+        log::error!("Could not write the instruction trace, stopping it: {err}");
+        *instruction_trace = None;
+    }
+}
+
 fn execute_inner<F: FieldElement>(
     asm: &AnalysisASMFile,
     opt_pil: Option<&Analyzed<F>>,
@@ -2978,6 +2923,11 @@ fn execute_inner<F: FieldElement>(
         location_starts,
     } = preprocess_main_function(main_machine);
 
+    let reg_map = builder::register_map(main_machine);
+    let program = decode::decode_statements(&statements, &label_map, &reg_map);
+    let query_arg_1 = reg_map["query_arg_1"];
+    let query_arg_2 = reg_map["query_arg_2"];
+
     let witness_cols: Vec<String> = opt_pil
         .map(|pil| {
             pil.committed_polys_in_source_order()
@@ -3042,7 +2992,6 @@ fn execute_inner<F: FieldElement>(
     (prover_ctx)("Clear").unwrap();
     let mut e = Executor {
         proc,
-        label_map,
         inputs: prover_ctx,
         bootloader_inputs,
         fixed: fixed.unwrap_or_default(),
@@ -3057,6 +3006,18 @@ fn execute_inner<F: FieldElement>(
 
     e.init();
 
+    e.proc.instruction_trace = profiling
+        .as_ref()
+        .filter(|opt| opt.instruction_trace)
+        .and_then(|opt| {
+            let path = PathBuf::from(&opt.output_directory)
+                .join(opt.file_stem.as_deref().unwrap_or("out"))
+                .with_extension("trace");
+            InstructionTracer::new(path, &debug_files)
+                .map_err(|err| log::error!("Could not create the instruction trace: {err}"))
+                .ok()
+        });
+
     let mut profiler =
         profiling.map(|opt| Profiler::new(opt, &debug_files[..], function_starts, location_starts));
 
@@ -3064,8 +3025,10 @@ fn execute_inner<F: FieldElement>(
     e.proc.push_row(curr_pc);
     let mut last = Instant::now();
     let mut count = 0;
+    let mut values = [Elem::default(); decode::MAX_ARGS];
     loop {
         let stm = statements[curr_pc as usize];
+        let decoded = &program[curr_pc as usize];
 
         // step is updated by 4 because we have instructions that need that many memory accesses,
         // except on a DebugDirective which is a noop
@@ -3073,6 +3036,14 @@ fn execute_inner<F: FieldElement>(
 
         log::trace!("l {curr_pc}: {stm}",);
 
+        if let Some(t) = &mut e.proc.instruction_trace {
+            if !matches!(decoded, DecodedStatement::DebugDirective(_)) {
+                let pc = e.proc.get_pc().u();
+                let result = t.statement(pc, || stm.to_string());
+                stop_trace_on_error(&mut e.proc.instruction_trace, result);
+            }
+        }
+
         count += 1;
         if count % 10000 == 0 {
             let now = Instant::now();
@@ -3084,74 +3055,68 @@ fn execute_inner<F: FieldElement>(
             }
         }
 
-        match stm {
-            FunctionStatement::Assignment(a) => {
+        match decoded {
+            DecodedStatement::Assignment {
+                dest,
+                rhs,
+                free_input,
+            } => {
                 let pc = e.proc.get_pc().u();
                 if let Some(p) = &mut profiler {
                     p.add_instruction_cost(pc as usize);
                 }
 
-                let results = e.eval_expression(a.rhs.as_ref());
-                assert_eq!(a.lhs_with_reg.len(), 1);
-
-                let asgn_reg = a.lhs_with_reg[0].1.clone();
-                if let AssignmentRegister::Register(x) = asgn_reg {
-                    assert_eq!(x, "X"); // we currently only assign through X
-                    let x_const =
-                        Elem::Field(e.get_known_fixed(KnownFixedCol::X_const, pc as usize));
-
-                    match a.rhs.as_ref() {
-                        Expression::FreeInput(_, _expr) => {
-                            // we currently only use X for free inputs
-                            assert!(x_const.is_zero());
-                            e.proc.set_col(KnownWitnessCol::X, results.unwrap());
-                            e.proc
-                                .set_col(KnownWitnessCol::X_free_value, results.unwrap());
-                        }
-                        _ => {
-                            // We're assinging a value or the result of an instruction.
-                            // Currently, only X used as the assignment register in this case.
-                            let x = results.unwrap();
-                            e.proc.set_col(KnownWitnessCol::X, x);
-
-                            let x_read_free = Elem::Field(
-                                e.get_known_fixed(KnownFixedCol::X_read_free, pc as usize),
-                            );
+                let result = e.eval_operand(rhs);
 
-                            // We need to solve for X_free_value:
-                            // X = X_const + X_read_free * X_free_value
-                            // X - X_const = X_read_free * X_free_value
-                            // X_free_value = (X - X_const) / X_read_free
-                            let x_free_value = if x_read_free.is_zero() {
-                                Elem::Field(F::zero())
-                            } else {
-                                x.sub(&x_const).div(&x_read_free)
-                            };
-                            e.proc.set_col(KnownWitnessCol::X_free_value, x_free_value);
-                        }
-                    }
+                let x_const = Elem::Field(e.get_known_fixed(KnownFixedCol::X_const, pc as usize));
+
+                if *free_input {
+                    // we currently only use X for free inputs
+                    assert!(x_const.is_zero());
+                    e.proc.set_col(KnownWitnessCol::X, result.unwrap());
+                    e.proc
+                        .set_col(KnownWitnessCol::X_free_value, result.unwrap());
                 } else {
-                    panic!("should be an assignment register");
+                    // We're assinging a value or the result of an instruction.
+                    // Currently, only X used as the assignment register in this case.
+                    let x = result.unwrap();
+                    e.proc.set_col(KnownWitnessCol::X, x);
+
+                    let x_read_free =
+                        Elem::Field(e.get_known_fixed(KnownFixedCol::X_read_free, pc as usize));
+
+                    // We need to solve for X_free_value:
+                    // X = X_const + X_read_free * X_free_value
+                    // X - X_const = X_read_free * X_free_value
+                    // X_free_value = (X - X_const) / X_read_free
+                    let x_free_value = if x_read_free.is_zero() {
+                        Elem::Field(F::zero())
+                    } else {
+                        x.sub(&x_const).div(&x_read_free)
+                    };
+                    e.proc.set_col(KnownWitnessCol::X_free_value, x_free_value);
                 }
 
-                for ((dest, _), val) in a.lhs_with_reg.iter().zip(results) {
-                    e.proc.set_reg(dest, val);
+                if let Some(val) = result {
+                    e.proc.set_reg(*dest, val);
                 }
             }
-            FunctionStatement::Instruction(i) => {
+            DecodedStatement::Instruction(instr, args) => {
                 if let Some(p) = &mut profiler {
                     p.add_instruction_cost(e.proc.get_pc().u() as usize);
                 }
 
-                if ["jump", "jump_dyn"].contains(&i.instruction.as_str()) {
+                let len = e.eval_operands(args, &mut values);
+
+                if let Instruction::jump | Instruction::jump_dyn = instr {
                     let pc_before = e.proc.get_pc().u();
 
-                    e.exec_instruction(&i.instruction, &i.inputs);
+                    e.exec_instruction(*instr, &values[..len]);
 
                     // we can't use `get_pc/get_reg`, as its value is only updated when moving to the next row
                     let pc_after = e.proc.get_next_pc().u();
 
-                    let target_reg = e.eval_expression(&i.inputs[1]).unwrap().u();
+                    let target_reg = values[1].u();
 
                     if let Some(p) = &mut profiler {
                         let pc_return = e.proc.get_reg_mem(target_reg).u();
@@ -3167,46 +3132,54 @@ fn execute_inner<F: FieldElement>(
                         }
                     }
                 } else {
-                    e.exec_instruction(&i.instruction, &i.inputs);
+                    e.exec_instruction(*instr, &values[..len]);
                 }
             }
-            FunctionStatement::Return(_) => {
+            DecodedStatement::Return => {
                 e.proc.set_col(KnownWitnessCol::pc_update, e.proc.get_pc());
                 e.proc.set_col(
                     KnownWitnessCol::query_arg_1_update,
-                    e.proc.get_reg("query_arg_1"),
+                    e.proc.get_reg(query_arg_1),
                 );
                 e.proc.set_col(
                     KnownWitnessCol::query_arg_2_update,
-                    e.proc.get_reg("query_arg_2"),
+                    e.proc.get_reg(query_arg_2),
                 );
                 break;
             }
-            FunctionStatement::DebugDirective(dd) => {
+            DecodedStatement::DebugDirective(directive) => {
                 step_update = 0;
-                match &dd.directive {
+                match directive {
                     DebugDirective::Loc(file, line, column) => {
-                        let (dir, file) = debug_files[file - 1];
-                        log::trace!("Executed {dir}/{file}:{line}:{column}");
+                        let (dir, file_name) = debug_files[file - 1];
+                        log::trace!("Executed {dir}/{file_name}:{line}:{column}");
+                        if let Some(t) = &mut e.proc.instruction_trace {
+                            t.set_location(*file, *line);
+                        }
                     }
                     DebugDirective::OriginalInstruction(insn) => {
                         log::trace!("  {insn}");
+                        if let Some(t) = &mut e.proc.instruction_trace {
+                            let pc = e.proc.get_pc().u();
+                            let result = t.original_instruction(pc, insn);
+                            stop_trace_on_error(&mut e.proc.instruction_trace, result);
+                        }
                     }
                     DebugDirective::File(_, _, _) => unreachable!(),
                 };
             }
-            FunctionStatement::Label(_) => {
-                unreachable!()
+            DecodedStatement::Unsupported(message) => {
+                panic!("Cannot execute statement {stm}: {message}")
             }
         };
 
         e.proc.set_col(
             KnownWitnessCol::query_arg_1_update,
-            e.proc.get_reg("query_arg_1"),
+            e.proc.get_reg(query_arg_1),
         );
         e.proc.set_col(
             KnownWitnessCol::query_arg_2_update,
-            e.proc.get_reg("query_arg_2"),
+            e.proc.get_reg(query_arg_2),
         );
 
         curr_pc = match e.proc.advance() {
@@ -3220,6 +3193,11 @@ fn execute_inner<F: FieldElement>(
     if let Some(mut p) = profiler {
         p.finish();
     }
+    if let Some(mut t) = e.proc.instruction_trace.take() {
+        if let Err(err) = t.finish() {
+            log::error!("Could not write the instruction trace: {err}");
+        }
+    }
 
     let mut program_columns = vec![];
 
@@ -3277,6 +3255,11 @@ fn is_multiple_of_4(n: u32) -> bool {
     n % 4 == 0
 }
 
+/// The canonical field value of `v`, truncated to 64 bits.
+fn elem_to_u64<F: FieldElement>(v: Elem<F>) -> u64 {
+    Elem::<F>::Field(v.into_fe()).as_i64_from_lower_bytes() as u64
+}
+
 pub fn hash_map_to_memory_state<F: FieldElement>(map: HashMap<u32, u32>) -> MemoryState<F> {
     map.into_iter().map(|(k, v)| (k, v.into())).collect()
 }
@@ -47,6 +47,8 @@ pub struct ProfilerOptions {
     pub file_stem: Option<String>,
     pub flamegraph: bool,
     pub callgrind: bool,
+    /// Write an instruction-level execution trace ("[file].trace")
+    pub instruction_trace: bool,
 }
 
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord)]
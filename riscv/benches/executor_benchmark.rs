@@ -19,8 +19,37 @@ fn executor_benchmark(c: &mut Criterion) {
     let options = CompilerOptions::new_gl();
     let contents = elf::translate(&executable, options);
     let mut pipeline = Pipeline::<T>::default().from_asm_string(contents, None);
-    pipeline.compute_optimized_pil().unwrap();
-    pipeline.compute_fixed_cols().unwrap();
+    let analyzed = pipeline.compute_analyzed_asm().unwrap().clone();
+    let pil = pipeline.compute_optimized_pil().unwrap();
+    let fixed = pipeline.compute_fixed_cols().unwrap();
+
+    // Fast mode is what the continuations dry run uses.
+    group.bench_function("keccak_fast", |b| {
+        b.iter(|| {
+            powdr_riscv_executor::execute(
+                &analyzed,
+                Default::default(),
+                pipeline.data_callback().unwrap(),
+                &[],
+                None,
+            )
+        })
+    });
+
+    group.bench_function("keccak_trace", |b| {
+        b.iter(|| {
+            powdr_riscv_executor::execute_with_trace(
+                &analyzed,
+                &pil,
+                fixed.clone(),
+                Default::default(),
+                pipeline.data_callback().unwrap(),
+                &[],
+                None,
+                None,
+            )
+        })
+    });
 
     group.bench_function("keccak", |b| {
         b.iter(|| pipeline.clone().compute_witness().unwrap())
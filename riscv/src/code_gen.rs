@@ -136,7 +136,9 @@ pub fn translate_program(program: impl RiscVProgram, options: CompilerOptions) -
     }
 }
 
-pub trait InstructionArgs {
+/// The arguments of a RISC-V instruction. The `Display` implementation is used
+/// to annotate the generated code with the original instruction.
+pub trait InstructionArgs: fmt::Display {
     type Error: fmt::Display;
 
     fn l(&self) -> Result<impl AsRef<str>, Self::Error>;
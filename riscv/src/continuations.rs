@@ -1,10 +1,11 @@
 use std::{
     collections::{BTreeSet, HashMap},
-    fs::{create_dir_all, hard_link, remove_file},
+    fs::{create_dir_all, hard_link, remove_file, File},
+    io::BufWriter,
 };
 
 use powdr_ast::{
-    asm_analysis::{AnalysisASMFile, Machine},
+    asm_analysis::{AnalysisASMFile, CallableSymbol, FunctionStatement, Machine},
     parsed::{asm::parse_absolute_path, Expression, Number, PilStatement},
 };
 use powdr_number::{FieldElement, KnownField, LargeInt};
@@ -14,11 +15,13 @@ use powdr_riscv_executor::{
 };
 
 pub mod bootloader;
+pub mod manifest;
 mod memory_merkle_tree;
 
 use crate::code_gen::{REGISTER_MEMORY_NAMES, REGISTER_NAMES};
 use bootloader::split_fe;
 use bootloader::{default_input, PAGE_SIZE_BYTES_LOG, PC_INDEX};
+use manifest::{ChunkManifest, ContinuationsManifest, ExecutionBounds, MANIFEST_FILE_NAME};
 use memory_merkle_tree::MerkleTree;
 use rand::Rng;
 
@@ -51,13 +54,18 @@ fn render_memory_hash<F: FieldElement>(hash: &[F]) -> String {
 ///    with all chunk-specific information set (witness, fixed cols, inputs, optimized pil)
 /// - `bootloader_inputs`: The inputs to the bootloader and the index of the row at which the shutdown routine
 ///   is supposed to execute, for each chunk, as returned by `rust_continuations_dry_run`.
+///
+/// Returns the manifest of the state transitions of all chunks, i.e., the values each chunk
+/// exposes as public outputs. If the pipeline has an output directory, the manifest is also
+/// written to it as `continuations_manifest.json`.
 pub fn rust_continuations<F: FieldElement, PipelineCallback, E>(
     pipeline: &mut Pipeline<F>,
-    pipeline_callback: PipelineCallback,
+    mut pipeline_callback: PipelineCallback,
     dry_run_result: DryRunResult<F>,
-) -> Result<(), E>
+) -> Result<ContinuationsManifest<F>, E>
 where
-    PipelineCallback: Fn(&mut Pipeline<F>) -> Result<(), E>,
+    PipelineCallback: FnMut(&mut Pipeline<F>) -> Result<(), E>,
+    E: From<Vec<String>>,
 {
     let bootloader_inputs = dry_run_result.bootloader_inputs;
     let num_chunks = bootloader_inputs.len();
@@ -69,13 +77,16 @@ where
     // in every chunk.
     pipeline.compute_optimized_pil().unwrap();
 
-    bootloader_inputs
+    let chunks = bootloader_inputs
         .into_iter()
         .enumerate()
         .map(
-            |(i, (bootloader_inputs, start_of_shutdown_routine))| -> Result<(), E> {
+            |(i, (bootloader_inputs, start_of_shutdown_routine))| -> Result<ChunkManifest<F>, E> {
                 log::info!("\nRunning chunk {} / {}...", i + 1, num_chunks);
 
+                let chunk_manifest = ChunkManifest::from_bootloader_inputs(&bootloader_inputs)
+                    .map_err(|e| vec![format!("Chunk {i}: {e}")])?;
+
                 let parent_dir = pipeline.output_dir().clone();
                 let force_overwrite = pipeline.is_force_overwrite();
 
@@ -134,11 +145,100 @@ where
                     pipeline.set_output(original_dir, force_overwrite);
                 }
 
-                Ok(())
+                Ok(chunk_manifest)
             },
         )
         .collect::<Result<Vec<_>, E>>()?;
-    Ok(())
+
+    let manifest = ContinuationsManifest { chunks };
+    if let Some(output_dir) = pipeline.output_dir() {
+        let path = output_dir.join(MANIFEST_FILE_NAME);
+        log::info!("Writing continuations manifest to {}", path.display());
+        let file = File::create(&path)
+            .map_err(|e| vec![format!("Error creating {}: {e}", path.display())])?;
+        serde_json::to_writer_pretty(BufWriter::new(file), &manifest)
+            .map_err(|e| vec![format!("Error writing {}: {e}", path.display())])?;
+    }
+
+    Ok(manifest)
+}
+
+/// Verifies an execution proven with continuations: the proof of each chunk must be
+/// valid for its public values, and the chunks must be linked, i.e., each chunk must
+/// start with the registers, PC and memory root the previous chunk ended with.
+/// The first chunk must start with the initial memory of the pipeline's program, and the
+/// last one must end with the program terminated, see [execution_bounds].
+///
+/// `chunks` contains the proof and the public values of each chunk, in execution order.
+/// Returns the manifest of the verified state transitions.
+pub fn verify_continuations<F: FieldElement>(
+    pipeline: &mut Pipeline<F>,
+    chunks: &[(Vec<u8>, Vec<(String, F)>)],
+) -> Result<ContinuationsManifest<F>, Vec<String>> {
+    let chunks = chunks
+        .iter()
+        .enumerate()
+        .map(|(i, (proof, publics))| {
+            let instances = publics.iter().map(|(_, v)| *v).collect();
+            pipeline.verify(proof, &[instances]).map_err(|mut e| {
+                e.insert(0, format!("Proof of chunk {i} is invalid"));
+                e
+            })?;
+            ChunkManifest::from_publics(publics).map_err(|e| vec![format!("Chunk {i}: {e}")])
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let manifest = ContinuationsManifest { chunks };
+    manifest.verify_chaining(&execution_bounds(pipeline)?)?;
+    Ok(manifest)
+}
+
+/// Returns the states an execution of the pipeline's program has to start and end in:
+/// the root of its initial memory (including the data added with
+/// [Pipeline::add_to_initial_memory]) and the PC at which it terminates.
+pub fn execution_bounds<F: FieldElement>(
+    pipeline: &mut Pipeline<F>,
+) -> Result<ExecutionBounds<F>, Vec<String>> {
+    let asm = pipeline.compute_analyzed_asm()?.clone();
+    let main_machine = asm
+        .get_machine(&parse_absolute_path("::Main"))
+        .ok_or_else(|| vec!["Main machine not found".to_string()])?;
+    let initial_memory = load_initial_memory(&asm, pipeline.initial_memory());
+    let mut merkle_tree = MerkleTree::<F>::new();
+    merkle_tree.update(hash_map_to_memory_state(initial_memory).into_iter());
+    Ok(ExecutionBounds {
+        initial_memory_root: memory_root(&merkle_tree),
+        final_pc: termination_pc(main_machine)?.into(),
+    })
+}
+
+/// The root hash of the memory Merkle tree, as it is given to the bootloader.
+fn memory_root<F: FieldElement>(merkle_tree: &MerkleTree<F>) -> Vec<F> {
+    merkle_tree
+        .root_hash()
+        .iter()
+        .flat_map(|e| split_fe(*e))
+        .collect()
+}
+
+/// Returns the PC of the batch that returns from the main function. The executor stops
+/// there, so it is the final PC of the last chunk.
+fn termination_pc(main_machine: &Machine) -> Result<u64, Vec<String>> {
+    let Some(CallableSymbol::Function(main_function)) = main_machine.callable.0.get("main") else {
+        return Err(vec!["Main function not found".to_string()]);
+    };
+    main_function
+        .body
+        .statements
+        .iter_batches()
+        .position(|batch| {
+            batch
+                .statements
+                .iter()
+                .any(|s| matches!(s, FunctionStatement::Return(_)))
+        })
+        .map(|pc| pc as u64)
+        .ok_or_else(|| vec!["Main function does not return".to_string()])
 }
 
 fn sanity_check(main_machine: &Machine, field: KnownField) {
@@ -334,10 +434,13 @@ fn has_all_needed_pages<F: FieldElement>(
 /// Runs the entire execution using the RISC-V executor. For each chunk, it collects:
 /// - The inputs to the bootloader, needed to restore the correct state.
 /// - The number of rows after which the prover should jump to the shutdown routine.
+///
+/// Returns an error if the last chunk does not end at the PC at which the program
+/// terminates, i.e., where verifiers expect the execution to end.
 pub fn rust_continuations_dry_run<F: FieldElement>(
     pipeline: &mut Pipeline<F>,
     profiler_opt: Option<ProfilerOptions>,
-) -> DryRunResult<F> {
+) -> Result<DryRunResult<F>, Vec<String>> {
     let field = F::known_field().unwrap();
 
     // All inputs for all chunks.
@@ -351,6 +454,7 @@ pub fn rust_continuations_dry_run<F: FieldElement>(
     let fixed = pipeline.compute_fixed_cols().unwrap();
     let main_machine = asm.get_machine(&parse_absolute_path("::Main")).unwrap();
     sanity_check(main_machine, field);
+    let final_pc = F::from(termination_pc(main_machine)?);
 
     log::info!("Initializing memory merkle tree...");
 
@@ -597,13 +701,8 @@ pub fn rust_continuations_dry_run<F: FieldElement>(
 
         // Replace the updated root hash
         let updated_root_hash_index = MEMORY_HASH_START_INDEX + 8;
-        bootloader_inputs[updated_root_hash_index..updated_root_hash_index + 8].copy_from_slice(
-            &merkle_tree
-                .root_hash()
-                .iter()
-                .flat_map(|e| split_fe(*e))
-                .collect::<Vec<_>>(),
-        );
+        bootloader_inputs[updated_root_hash_index..updated_root_hash_index + 8]
+            .copy_from_slice(&memory_root(&merkle_tree));
 
         log::info!(
             "Initial memory root hash: {}",
@@ -660,6 +759,12 @@ pub fn rust_continuations_dry_run<F: FieldElement>(
         }
 
         if chunk_exec.trace_len < length {
+            if register_values[PC_INDEX] != final_pc {
+                return Err(vec![format!(
+                    "The last chunk ends at PC {}, but the program terminates at PC {final_pc}",
+                    register_values[PC_INDEX]
+                )]);
+            }
             log::info!("Done!");
             break;
         }
@@ -679,8 +784,8 @@ pub fn rust_continuations_dry_run<F: FieldElement>(
 
         chunk_index += 1;
     }
-    DryRunResult {
+    Ok(DryRunResult {
         bootloader_inputs: bootloader_inputs_and_num_rows,
         trace_len: full_trace_length,
-    }
+    })
 }
@@ -0,0 +1,324 @@
+use std::collections::BTreeMap;
+
+use powdr_number::FieldElement;
+use serde::{Deserialize, Serialize};
+
+use crate::code_gen::{REGISTER_MEMORY_NAMES, REGISTER_NAMES};
+
+use super::bootloader::{
+    default_register_values, MEMORY_HASH_START_INDEX, PC_INDEX, WORDS_PER_HASH,
+};
+
+/// The name of the manifest file written to the output directory by `rust_continuations`.
+pub const MANIFEST_FILE_NAME: &str = "continuations_manifest.json";
+
+/// The machine state at the beginning or at the end of a chunk.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound = "F: FieldElement")]
+pub struct ChunkState<F: FieldElement> {
+    /// The register values, in the order of the bootloader inputs. The PC is the last one.
+    pub registers: Vec<F>,
+    /// The root of the memory Merkle tree.
+    pub memory_root: Vec<F>,
+}
+
+/// The public state transition proven by a single chunk.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound = "F: FieldElement")]
+pub struct ChunkManifest<F: FieldElement> {
+    pub initial: ChunkState<F>,
+    #[serde(rename = "final")]
+    pub final_state: ChunkState<F>,
+}
+
+/// The states an execution has to start and end in. These are derived from the program and
+/// its initial memory (see `execution_bounds`), independently of the chunk proofs.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound = "F: FieldElement")]
+pub struct ExecutionBounds<F: FieldElement> {
+    /// The root of the memory Merkle tree of the initial memory, which is what the
+    /// bootloader of the first chunk has to be given.
+    pub initial_memory_root: Vec<F>,
+    /// The PC of the main machine once the program has terminated.
+    pub final_pc: F,
+}
+
+/// The state transitions of all chunks of an execution, in execution order.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound = "F: FieldElement")]
+pub struct ContinuationsManifest<F: FieldElement> {
+    pub chunks: Vec<ChunkManifest<F>>,
+}
+
+fn register_names() -> impl Iterator<Item = &'static str> {
+    REGISTER_MEMORY_NAMES
+        .into_iter()
+        .chain(REGISTER_NAMES.map(|r| r.strip_prefix("main::").unwrap()))
+}
+
+impl<F: FieldElement> ChunkState<F> {
+    pub fn pc(&self) -> F {
+        self.registers[PC_INDEX]
+    }
+}
+
+impl<F: FieldElement> ChunkManifest<F> {
+    /// Reads the state transition from the bootloader inputs of the chunk.
+    /// These are the values the bootloader exposes as public outputs.
+    pub fn from_bootloader_inputs(bootloader_inputs: &[F]) -> Result<Self, String> {
+        let num_registers = REGISTER_MEMORY_NAMES.len() + REGISTER_NAMES.len();
+        let min_len = MEMORY_HASH_START_INDEX + 2 * WORDS_PER_HASH;
+        if bootloader_inputs.len() < min_len {
+            return Err(format!(
+                "Expected at least {min_len} bootloader inputs, but got {}",
+                bootloader_inputs.len()
+            ));
+        }
+        let memory_root =
+            |offset: usize| bootloader_inputs[offset..offset + WORDS_PER_HASH].to_vec();
+        Ok(Self {
+            initial: ChunkState {
+                registers: bootloader_inputs[..num_registers].to_vec(),
+                memory_root: memory_root(MEMORY_HASH_START_INDEX),
+            },
+            final_state: ChunkState {
+                registers: bootloader_inputs[num_registers..2 * num_registers].to_vec(),
+                memory_root: memory_root(MEMORY_HASH_START_INDEX + WORDS_PER_HASH),
+            },
+        })
+    }
+
+    /// Reads the state transition from the public values of a chunk proof.
+    /// Public names may be prefixed by their namespace.
+    pub fn from_publics(publics: &[(String, F)]) -> Result<Self, String> {
+        let publics = publics
+            .iter()
+            .map(|(name, value)| (name.rsplit("::").next().unwrap(), *value))
+            .collect::<BTreeMap<_, _>>();
+        let get = |name: String| {
+            publics
+                .get(name.as_str())
+                .copied()
+                .ok_or_else(|| format!("Public value {name} not found"))
+        };
+        let state = |prefix: &str| -> Result<ChunkState<F>, String> {
+            Ok(ChunkState {
+                registers: register_names()
+                    .map(|reg| get(format!("{prefix}_{reg}")))
+                    .collect::<Result<_, _>>()?,
+                memory_root: (1..=WORDS_PER_HASH)
+                    .map(|i| get(format!("{prefix}_memory_hash_{i}")))
+                    .collect::<Result<_, _>>()?,
+            })
+        };
+        Ok(Self {
+            initial: state("initial")?,
+            final_state: state("final")?,
+        })
+    }
+}
+
+impl<F: FieldElement> ContinuationsManifest<F> {
+    /// Checks that the chunks form a single, complete execution: the first chunk starts at
+    /// the default PC with cleared registers and the initial memory root of `bounds`, each
+    /// chunk starts in the state (registers, PC and memory root) in which the previous one
+    /// ended, and the last chunk ends in the terminated state.
+    ///
+    /// This does not verify the chunk proofs themselves, see `verify_continuations`.
+    pub fn verify_chaining(&self, bounds: &ExecutionBounds<F>) -> Result<(), Vec<String>> {
+        let (Some(first), Some(last)) = (self.chunks.first(), self.chunks.last()) else {
+            return Err(vec!["Manifest does not contain any chunk".to_string()]);
+        };
+
+        let mut errors = vec![];
+        if first.initial.registers != default_register_values::<F>() {
+            errors.push(format!(
+                "Chunk 0 does not start in the initial register state (PC = {})",
+                first.initial.pc()
+            ));
+        }
+        if first.initial.memory_root != bounds.initial_memory_root {
+            errors.push(
+                "Initial memory root of chunk 0 does not match the initial memory".to_string(),
+            );
+        }
+        if last.final_state.pc() != bounds.final_pc {
+            errors.push(format!(
+                "Chunk {} ends at PC {}, but the program terminates at PC {}",
+                self.chunks.len() - 1,
+                last.final_state.pc(),
+                bounds.final_pc
+            ));
+        }
+        for (i, (prev, next)) in self.chunks.iter().zip(&self.chunks[1..]).enumerate() {
+            if prev.final_state.memory_root != next.initial.memory_root {
+                errors.push(format!(
+                    "Final memory root of chunk {i} does not match the initial memory root of chunk {}",
+                    i + 1
+                ));
+            }
+            if prev.final_state.pc() != next.initial.pc() {
+                errors.push(format!(
+                    "Chunk {i} ends at PC {}, but chunk {} starts at PC {}",
+                    prev.final_state.pc(),
+                    i + 1,
+                    next.initial.pc()
+                ));
+            }
+            for ((name, a), b) in register_names()
+                .zip(&prev.final_state.registers)
+                .zip(&next.initial.registers)
+                .take(PC_INDEX)
+            {
+                if a != b {
+                    errors.push(format!(
+                        "Register {name} is {a} at the end of chunk {i}, but {b} at the start of chunk {}",
+                        i + 1
+                    ));
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use powdr_number::GoldilocksField;
+
+    use super::*;
+    use crate::continuations::bootloader::NUM_PAGES_INDEX;
+
+    fn state(pc: u64, root: u64) -> ChunkState<GoldilocksField> {
+        let mut registers = default_register_values();
+        registers[PC_INDEX] = pc.into();
+        ChunkState {
+            registers,
+            memory_root: vec![root.into(); WORDS_PER_HASH],
+        }
+    }
+
+    fn chunk(
+        initial: ChunkState<GoldilocksField>,
+        final_state: ChunkState<GoldilocksField>,
+    ) -> ChunkManifest<GoldilocksField> {
+        ChunkManifest {
+            initial,
+            final_state,
+        }
+    }
+
+    #[test]
+    fn from_bootloader_inputs_and_publics_agree() {
+        let inputs = (0..NUM_PAGES_INDEX as u64 + 1)
+            .map(GoldilocksField::from)
+            .collect::<Vec<_>>();
+        let from_inputs = ChunkManifest::from_bootloader_inputs(&inputs).unwrap();
+
+        let publics = register_names()
+            .enumerate()
+            .map(|(i, reg)| (format!("main::initial_{reg}"), inputs[i]))
+            .chain(
+                register_names()
+                    .enumerate()
+                    .map(|(i, reg)| (format!("main::final_{reg}"), inputs[i + PC_INDEX + 1])),
+            )
+            .chain((0..2 * WORDS_PER_HASH).map(|i| {
+                let prefix = if i < WORDS_PER_HASH {
+                    "initial"
+                } else {
+                    "final"
+                };
+                (
+                    format!("main::{prefix}_memory_hash_{}", i % WORDS_PER_HASH + 1),
+                    inputs[MEMORY_HASH_START_INDEX + i],
+                )
+            }))
+            .collect::<Vec<_>>();
+        let from_publics = ChunkManifest::from_publics(&publics).unwrap();
+
+        assert_eq!(from_inputs, from_publics);
+        assert_eq!(from_inputs.final_state.pc(), inputs[2 * PC_INDEX + 1]);
+    }
+
+    #[test]
+    fn too_few_bootloader_inputs() {
+        let inputs = vec![GoldilocksField::from(0); MEMORY_HASH_START_INDEX];
+        assert!(ChunkManifest::from_bootloader_inputs(&inputs).is_err());
+    }
+
+    fn bounds() -> ExecutionBounds<GoldilocksField> {
+        ExecutionBounds {
+            initial_memory_root: vec![7.into(); WORDS_PER_HASH],
+            final_pc: 30.into(),
+        }
+    }
+
+    #[test]
+    fn chaining() {
+        let manifest = ContinuationsManifest {
+            chunks: vec![
+                chunk(state(1, 7), state(20, 8)),
+                chunk(state(20, 8), state(30, 9)),
+            ],
+        };
+        manifest.verify_chaining(&bounds()).unwrap();
+
+        let manifest = ContinuationsManifest {
+            chunks: vec![
+                chunk(state(1, 7), state(20, 8)),
+                chunk(state(21, 9), state(30, 9)),
+            ],
+        };
+        assert_eq!(manifest.verify_chaining(&bounds()).unwrap_err().len(), 2);
+
+        let manifest = ContinuationsManifest {
+            chunks: vec![chunk(state(5, 7), state(30, 8))],
+        };
+        assert!(manifest.verify_chaining(&bounds()).is_err());
+    }
+
+    #[test]
+    fn single_chunk() {
+        let manifest = ContinuationsManifest {
+            chunks: vec![chunk(state(1, 7), state(30, 8))],
+        };
+        manifest.verify_chaining(&bounds()).unwrap();
+    }
+
+    #[test]
+    fn truncated_chain() {
+        let manifest = ContinuationsManifest {
+            chunks: vec![chunk(state(1, 7), state(20, 8))],
+        };
+        assert_eq!(
+            manifest.verify_chaining(&bounds()).unwrap_err(),
+            vec!["Chunk 0 ends at PC 20, but the program terminates at PC 30"]
+        );
+    }
+
+    #[test]
+    fn re_rooted_chain() {
+        let manifest = ContinuationsManifest {
+            chunks: vec![
+                chunk(state(1, 6), state(20, 8)),
+                chunk(state(20, 8), state(30, 9)),
+            ],
+        };
+        assert_eq!(
+            manifest.verify_chaining(&bounds()).unwrap_err(),
+            vec!["Initial memory root of chunk 0 does not match the initial memory"]
+        );
+    }
+
+    #[test]
+    fn empty_manifest() {
+        let manifest = ContinuationsManifest::<GoldilocksField>::default();
+        assert!(manifest.verify_chaining(&bounds()).is_err());
+    }
+}
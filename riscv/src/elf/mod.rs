@@ -2,7 +2,7 @@ use std::{
     cell::Cell,
     cmp::Ordering,
     collections::{btree_map::Entry, BTreeMap, BTreeSet},
-    fs,
+    fmt, fs,
     path::Path,
 };
 
@@ -400,6 +400,25 @@ struct WrappedArgs<'a> {
     symbol_table: &'a SymbolTable,
 }
 
+/// Renders the arguments as `rd, rs1, rs2, imm`, omitting the absent ones.
+/// Code labels are rendered as addresses.
+impl fmt::Display for WrappedArgs<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let HighLevelArgs { rd, rs1, rs2, imm } = self.args;
+        let imm = match imm {
+            HighLevelImmediate::None => None,
+            HighLevelImmediate::CodeLabel(addr) => Some(format!("0x{addr:08x}")),
+            HighLevelImmediate::Value(v) => Some(v.to_string()),
+        };
+        let args = [rd, rs1, rs2]
+            .into_iter()
+            .flatten()
+            .map(|r| format!("x{r}"))
+            .chain(imm);
+        write!(f, "{}", args.format(", "))
+    }
+}
+
 impl InstructionArgs for WrappedArgs<'_> {
     type Error = String;
 
@@ -36,7 +36,8 @@ pub fn bootloader_preamble() -> String {
     let jump_to_shutdown_routine;
     jump_to_shutdown_routine * (1 - jump_to_shutdown_routine) = 0;
 
-    // Expose initial register values as public outputs
+    // Expose the initial and final register values and memory roots as public outputs,
+    // so that consecutive chunks can be linked by the verifier.
 "#.to_string();
 
     for (i, reg) in REGISTER_MEMORY_NAMES
@@ -49,7 +50,7 @@ pub fn bootloader_preamble() -> String {
         .enumerate()
     {
         preamble.push_str(&format!(
-            "    //public initial_{reg} = main_bootloader_inputs::value({i});\n"
+            "    public initial_{reg} = main_bootloader_inputs::value({i});\n"
         ));
     }
     for (i, reg) in REGISTER_MEMORY_NAMES
@@ -62,20 +63,20 @@ pub fn bootloader_preamble() -> String {
         .enumerate()
     {
         preamble.push_str(&format!(
-            "    //public final_{reg} = main_bootloader_inputs::value({});\n",
+            "    public final_{reg} = main_bootloader_inputs::value({});\n",
             i + REGISTER_MEMORY_NAMES.len() + REGISTER_NAMES.len()
         ));
     }
     preamble.push_str(&format!(
         r#"
-    //public initial_memory_hash_1 = main_bootloader_inputs::value({});
-    //public initial_memory_hash_2 = main_bootloader_inputs::value({});
-    //public initial_memory_hash_3 = main_bootloader_inputs::value({});
-    //public initial_memory_hash_4 = main_bootloader_inputs::value({});
-    //public initial_memory_hash_5 = main_bootloader_inputs::value({});
-    //public initial_memory_hash_6 = main_bootloader_inputs::value({});
-    //public initial_memory_hash_7 = main_bootloader_inputs::value({});
-    //public initial_memory_hash_8 = main_bootloader_inputs::value({});
+    public initial_memory_hash_1 = main_bootloader_inputs::value({});
+    public initial_memory_hash_2 = main_bootloader_inputs::value({});
+    public initial_memory_hash_3 = main_bootloader_inputs::value({});
+    public initial_memory_hash_4 = main_bootloader_inputs::value({});
+    public initial_memory_hash_5 = main_bootloader_inputs::value({});
+    public initial_memory_hash_6 = main_bootloader_inputs::value({});
+    public initial_memory_hash_7 = main_bootloader_inputs::value({});
+    public initial_memory_hash_8 = main_bootloader_inputs::value({});
 "#,
         MEMORY_HASH_START_INDEX,
         MEMORY_HASH_START_INDEX + 1,
@@ -88,14 +89,14 @@ pub fn bootloader_preamble() -> String {
     ));
     preamble.push_str(&format!(
         r#"
-    //public final_memory_hash_1 = main_bootloader_inputs::value({});
-    //public final_memory_hash_2 = main_bootloader_inputs::value({});
-    //public final_memory_hash_3 = main_bootloader_inputs::value({});
-    //public final_memory_hash_4 = main_bootloader_inputs::value({});
-    //public final_memory_hash_5 = main_bootloader_inputs::value({});
-    //public final_memory_hash_6 = main_bootloader_inputs::value({});
-    //public final_memory_hash_7 = main_bootloader_inputs::value({});
-    //public final_memory_hash_8 = main_bootloader_inputs::value({});
+    public final_memory_hash_1 = main_bootloader_inputs::value({});
+    public final_memory_hash_2 = main_bootloader_inputs::value({});
+    public final_memory_hash_3 = main_bootloader_inputs::value({});
+    public final_memory_hash_4 = main_bootloader_inputs::value({});
+    public final_memory_hash_5 = main_bootloader_inputs::value({});
+    public final_memory_hash_6 = main_bootloader_inputs::value({});
+    public final_memory_hash_7 = main_bootloader_inputs::value({});
+    public final_memory_hash_8 = main_bootloader_inputs::value({});
 "#,
         MEMORY_HASH_START_INDEX + 8,
         MEMORY_HASH_START_INDEX + 9,
@@ -24,8 +24,7 @@ pub fn translate_program(program: impl RiscVProgram, options: CompilerOptions) -
     let prover_data_bounds = program.prover_data_bounds();
 
     // Do this in a separate function to avoid most of the code being generic on F.
-    let (initial_mem, instructions) =
-        translate_program_impl(program, options.field, &runtime, options.continuations);
+    let (initial_mem, instructions) = translate_program_impl(program, &runtime, options);
 
     riscv_machine(
         options,
@@ -38,10 +37,15 @@ pub fn translate_program(program: impl RiscVProgram, options: CompilerOptions) -
 
 fn translate_program_impl(
     mut program: impl RiscVProgram,
-    field: KnownField,
     runtime: &Runtime,
-    continuations: bool,
+    options: CompilerOptions,
 ) -> (Vec<String>, Vec<String>) {
+    let CompilerOptions {
+        field,
+        continuations,
+        debug_instructions,
+        ..
+    } = options;
     let mut initial_mem = Vec::new();
     let mut data_code = Vec::new();
     for MemEntry { label, addr, value } in program.take_initial_mem() {
@@ -157,6 +161,10 @@ fn translate_program_impl(
             }
             Statement::Label(l) => statements.push(format!("{}:", escape_label(l.as_ref()))),
             Statement::Instruction { op, args } => {
+                if debug_instructions {
+                    let original = format!("{op} {args}");
+                    statements.push(format!(".debug insn \"{}\";", original.trim_end()));
+                }
                 let processed_instr = match process_instruction(op, args, runtime) {
                     Ok(s) => s,
                     Err(e) => panic!("Failed to process instruction '{op}'. {e}"),
@@ -264,9 +272,9 @@ fn riscv_machine(
 
 fn mul_instruction(field: KnownField, runtime: &Runtime) -> &'static str {
     match field {
-        KnownField::Bn254Field => {
-            // The BN254 field can fit any 64-bit number, so we can naively de-compose
-            // Z * W into 8 bytes and put them together to get the upper and lower word.
+        KnownField::Bn254Field | KnownField::Bls12_381Field => {
+            // The BN254 and BLS12-381 fields can fit any 64-bit number, so we can naively
+            // de-compose Z * W into 8 bytes and put them together to get the upper and lower word.
             r#"
     // Computes V = val(X) * val(Y) and
     // stores the lower 32 bits in register Z and the upper 32 bits in register W.
@@ -64,6 +64,9 @@ pub struct CompilerOptions {
     pub continuations: bool,
     pub min_degree_log: u8,
     pub max_degree_log: u8,
+    /// Annotates each translated instruction with the original RISC-V instruction,
+    /// which is needed for instruction-level execution traces.
+    pub debug_instructions: bool,
 }
 
 impl CompilerOptions {
@@ -74,6 +77,7 @@ impl CompilerOptions {
             continuations,
             min_degree_log: 5,
             max_degree_log: 18,
+            debug_instructions: false,
         }
     }
 
@@ -84,6 +88,7 @@ impl CompilerOptions {
             continuations: false,
             min_degree_log: 5,
             max_degree_log: 18,
+            debug_instructions: false,
         }
     }
 
@@ -94,6 +99,7 @@ impl CompilerOptions {
             continuations: false,
             min_degree_log: 5,
             max_degree_log: 18,
+            debug_instructions: false,
         }
     }
 
@@ -118,6 +124,13 @@ impl CompilerOptions {
         }
     }
 
+    pub fn with_debug_instructions(self) -> Self {
+        Self {
+            debug_instructions: true,
+            ..self
+        }
+    }
+
     pub fn with_runtime_libs(self, libs: RuntimeLibs) -> Self {
         Self { libs, ..self }
     }
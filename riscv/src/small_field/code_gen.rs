@@ -24,8 +24,7 @@ use crate::small_field::runtime::Runtime;
 pub fn translate_program(program: impl RiscVProgram, options: CompilerOptions) -> String {
     let runtime = Runtime::new(options.libs, options.continuations);
 
-    let (initial_mem, instructions) =
-        translate_program_impl(program, options.field, &runtime, options.continuations);
+    let (initial_mem, instructions) = translate_program_impl(program, &runtime, options);
 
     riscv_machine(
         options,
@@ -38,10 +37,15 @@ pub fn translate_program(program: impl RiscVProgram, options: CompilerOptions) -
 
 fn translate_program_impl(
     mut program: impl RiscVProgram,
-    field: KnownField,
     runtime: &Runtime,
-    continuations: bool,
+    options: CompilerOptions,
 ) -> (Vec<String>, Vec<String>) {
+    let CompilerOptions {
+        field,
+        continuations,
+        debug_instructions,
+        ..
+    } = options;
     let mut initial_mem = Vec::new();
     let mut data_code = Vec::new();
     for MemEntry { label, addr, value } in program.take_initial_mem() {
@@ -165,6 +169,10 @@ fn translate_program_impl(
             }
             Statement::Label(l) => statements.push(format!("{}:", escape_label(l.as_ref()))),
             Statement::Instruction { op, args } => {
+                if debug_instructions {
+                    let original = format!("{op} {args}");
+                    statements.push(format!(".debug insn \"{}\";", original.trim_end()));
+                }
                 let processed_instr = match process_instruction(op, args, runtime) {
                     Ok(s) => s,
                     Err(e) => panic!("Failed to process instruction '{op}'. {e}"),
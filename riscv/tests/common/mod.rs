@@ -132,7 +132,7 @@ pub fn verify_riscv_asm_file(
     options: CompilerOptions,
     use_pie: bool,
     executor_witgen: bool,
-) {
+) -> Result<(), String> {
     let powdr_asm = compile_riscv_asm_file(asm_file, options, use_pie);
     let case_name = asm_file.file_stem().unwrap().to_str().unwrap();
 
@@ -155,7 +155,9 @@ pub fn verify_riscv_asm_file(
                 false,
             );
         }
-        KnownField::Mersenne31Field => todo!(),
+        field @ KnownField::Mersenne31Field => {
+            return Err(format!("RISC-V is not supported over the {field} field."));
+        }
         KnownField::GoldilocksField => {
             verify_riscv_asm_string::<GoldilocksField, ()>(
                 &format!("{case_name}.asm"),
@@ -165,6 +167,9 @@ pub fn verify_riscv_asm_file(
                 executor_witgen,
             );
         }
-        KnownField::Bn254Field => todo!(),
+        field @ (KnownField::Bn254Field | KnownField::Bls12_381Field) => {
+            return Err(format!("RISC-V is not supported over the {field} field."));
+        }
     }
+    Ok(())
 }
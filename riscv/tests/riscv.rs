@@ -7,12 +7,12 @@ use powdr_pipeline::{
     test_util::{run_pilcom_with_backend_variant, BackendVariant},
     Pipeline,
 };
-use powdr_riscv_executor::ProfilerOptions;
+use powdr_riscv_executor::{first_divergence, InstructionTrace, ProfilerOptions};
 use std::path::{Path, PathBuf};
 use test_log::test;
 
 use powdr_riscv::{
-    continuations::{rust_continuations, rust_continuations_dry_run},
+    continuations::{execution_bounds, rust_continuations, rust_continuations_dry_run},
     CompilerOptions, RuntimeLibs,
 };
 
@@ -46,13 +46,16 @@ fn run_continuations_test(case: &str, powdr_asm: String, prover_data: Vec<Vec<u8
         pipeline = pipeline.add_to_initial_memory(v);
     }
 
-    let pipeline_callback = |pipeline: &mut Pipeline<GoldilocksField>| -> Result<(), ()> {
+    let pipeline_callback = |pipeline: &mut Pipeline<GoldilocksField>| -> Result<(), Vec<String>> {
         run_pilcom_with_backend_variant(pipeline.clone(), BackendVariant::Composite).unwrap();
 
         Ok(())
     };
-    let bootloader_inputs = rust_continuations_dry_run(&mut pipeline, Default::default());
-    rust_continuations(&mut pipeline, pipeline_callback, bootloader_inputs).unwrap();
+    let bootloader_inputs = rust_continuations_dry_run(&mut pipeline, Default::default()).unwrap();
+    let manifest = rust_continuations(&mut pipeline, pipeline_callback, bootloader_inputs).unwrap();
+    manifest
+        .verify_chaining(&execution_bounds(&mut pipeline).unwrap())
+        .unwrap();
 }
 
 /*
@@ -459,13 +462,15 @@ fn dispatch_table_pie_relocation() {
         CompilerOptions::new_bb(),
         true,
         false,
-    );
+    )
+    .unwrap();
     verify_riscv_asm_file(
         Path::new(DISPATCH_TABLE_S),
         CompilerOptions::new_gl(),
         true,
         true,
-    );
+    )
+    .unwrap();
 }
 
 /// Tests that the dispatch table is correctly relocated when PIE is disabled.
@@ -477,13 +482,15 @@ fn dispatch_table_static_relocation() {
         CompilerOptions::new_bb(),
         false,
         false,
-    );
+    )
+    .unwrap();
     verify_riscv_asm_file(
         Path::new(DISPATCH_TABLE_S),
         CompilerOptions::new_gl(),
         false,
         true,
-    );
+    )
+    .unwrap();
 }
 
 #[test]
@@ -584,7 +591,7 @@ fn many_chunks_dry() {
     let mut pipeline = Pipeline::default()
         .from_asm_string(powdr_asm, Some(PathBuf::from(case)))
         .with_prover_inputs(Default::default());
-    rust_continuations_dry_run::<GoldilocksField>(&mut pipeline, Default::default());
+    rust_continuations_dry_run::<GoldilocksField>(&mut pipeline, Default::default()).unwrap();
 }
 
 use serde::{Deserialize, Serialize};
@@ -728,7 +735,11 @@ fn profiler_sanity_check() {
     );
 
     let options = CompilerOptions::new(KnownField::GoldilocksField, RuntimeLibs::new(), false);
+    // The original instructions are only annotated if requested.
     let asm = powdr_riscv::elf::translate(&executable, options);
+    assert!(!asm.contains(".debug insn"));
+    let asm = powdr_riscv::elf::translate(&executable, options.with_debug_instructions());
+    assert!(asm.contains(".debug insn \"sw "));
 
     let temp_dir = mktemp::Temp::new_dir().unwrap().release();
     let file_name = format!("{case}.asm");
@@ -741,6 +752,7 @@ fn profiler_sanity_check() {
         output_directory: temp_dir.to_path_buf().to_str().unwrap().to_string(),
         flamegraph: true,
         callgrind: true,
+        instruction_trace: true,
     };
     powdr_riscv_executor::execute(
         &analyzed,
@@ -760,6 +772,15 @@ fn profiler_sanity_check() {
     callgrind_path.push("{case}.callgrind");
     let callgrind = std::fs::read_to_string(callgrind_path);
     assert!(!callgrind.unwrap().is_empty());
+
+    let mut trace_path = temp_dir.to_path_buf();
+    trace_path.push("{case}.trace");
+    let trace = InstructionTrace::read(trace_path).unwrap();
+    assert!(trace
+        .instructions
+        .iter()
+        .any(|insn| insn.insn.starts_with("sw ") && trace.location(insn).is_some()));
+    assert_eq!(first_divergence(&trace, &trace), None);
 }
 
 #[cfg(feature = "plonky3")]
@@ -5,11 +5,24 @@ use serde::{Deserialize, Serialize};
 use powdr_ast::analyzed::Analyzed;
 use powdr_number::{FieldElement, KnownField};
 
+use crate::{body::Body, migrations};
+
 // This is the magic number for the .pilo file format. It spells "powdr" in ASCII.
 // The UNIX magic file format for this file would be
 // 8       bestring16          powdr      Powdr PIL binary object
 const MAGIC: [u8; 5] = [0x70, 0x6f, 0x77, 0x64, 0x72];
 
+/// The version of the .pilo format written by this version of powdr.
+///
+/// Version 1 stored the CBOR serialization of the in-memory `Analyzed`, which broke
+/// whenever the AST changed. Since version 2, the body is the CBOR serialization of
+/// [`Body`], a frozen representation that is converted from and into `Analyzed`.
+/// Changes to it require a new version and a migration in [`migrations`].
+/// The golden objects in `files/` are loaded by the tests to detect such changes.
+pub const CURRENT_VERSION: u32 = 2;
+
+/// A .pilo object: a header with the format version and the field, followed by the
+/// (versioned) body.
 #[derive(Serialize, Deserialize)]
 pub struct SerializedAnalyzed {
     magic: [u8; 5],
@@ -24,9 +37,10 @@ impl<T: FieldElement> TryFrom<&Analyzed<T>> for SerializedAnalyzed {
     fn try_from(analyzed: &Analyzed<T>) -> Result<Self, Self::Error> {
         Ok(Self {
             magic: MAGIC,
-            version: include!("../analyzed_type.version"),
+            version: CURRENT_VERSION,
             field: T::known_field().ok_or("Field not known")?,
-            analyzed: analyzed.serialize()?,
+            analyzed: serde_cbor::to_vec(&Body::from(analyzed))
+                .map_err(|e| format!("Failed to serialize .pilo body: {e}"))?,
         })
     }
 }
@@ -36,24 +50,32 @@ impl<T: FieldElement> TryFrom<SerializedAnalyzed> for Analyzed<T> {
 
     fn try_from(serialized: SerializedAnalyzed) -> Result<Self, Self::Error> {
         serialized.check::<T>()?;
-        Analyzed::deserialize(&serialized.analyzed)
+        let body = migrations::migrate_to_current::<T>(serialized.version, serialized.analyzed)?;
+        let body: Body = serde_cbor::from_slice(&body)
+            .map_err(|e| format!("Failed to deserialize .pilo body: {e}"))?;
+        body.try_into()
+            .map_err(|e| format!("Invalid .pilo body: {e}"))
     }
 }
 
 impl SerializedAnalyzed {
-    pub fn check<T: FieldElement>(&self) -> Result<(), String> {
-        let actual_version = include!("../analyzed_type.version");
+    /// The format version of this object.
+    pub fn version(&self) -> u32 {
+        self.version
+    }
 
+    /// Checks the header of the object. Objects of older versions pass the check,
+    /// they are migrated when converting them into an `Analyzed`.
+    pub fn check<T: FieldElement>(&self) -> Result<(), String> {
         if self.magic != MAGIC {
             return Err("Invalid .pilo magic number".to_string());
         }
 
-        if self.version != actual_version {
+        if self.version == 0 || self.version > CURRENT_VERSION {
             return Err(format!(
-                "Invalid .pilo version number. Expected {} but got {}",
-                actual_version, self.version
-            )
-            .to_string());
+                "Unsupported .pilo version number {}. This version of powdr supports versions 1 to {CURRENT_VERSION}",
+                self.version
+            ));
         }
 
         let actual_field = T::known_field().ok_or("Field not known")?;
@@ -61,7 +83,7 @@ impl SerializedAnalyzed {
         if self.field != actual_field {
             return Err(format!(
                 "Invalid .pilo field. Expected {:?} but got {:?}",
-                self.field, actual_field
+                actual_field, self.field
             )
             .to_string());
         }
@@ -84,3 +106,89 @@ impl SerializedAnalyzed {
         .map_err(|e| format!("Failed to deserialize from file: {e}"))
     }
 }
+
+#[cfg(test)]
+mod test {
+    use powdr_number::{Bn254Field, GoldilocksField};
+
+    use super::*;
+
+    const PIL: &str = r#"
+namespace Fibonacci(4);
+    col fixed ISLAST(i) { if i == 3 { 1 } else { 0 } };
+    col witness x, y;
+    ISLAST * (y' - 1) = 0;
+    (1 - ISLAST) * (x' - y) = 0;
+    (1 - ISLAST) * (y' - (x + y)) = 0;
+    public out = y(3);
+"#;
+
+    fn analyzed() -> Analyzed<GoldilocksField> {
+        powdr_pil_analyzer::analyze_string(PIL).unwrap()
+    }
+
+    fn reload(serialized: &SerializedAnalyzed) -> SerializedAnalyzed {
+        serde_cbor::from_slice(&serde_cbor::to_vec(serialized).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn roundtrip() {
+        let analyzed = analyzed();
+        let serialized = reload(&SerializedAnalyzed::try_from(&analyzed).unwrap());
+        assert_eq!(serialized.version(), CURRENT_VERSION);
+        let deserialized: Analyzed<GoldilocksField> = serialized.try_into().unwrap();
+        assert_eq!(analyzed.to_string(), deserialized.to_string());
+    }
+
+    /// Loads one of the golden .pilo objects written by older versions of powdr.
+    fn golden(bytes: &[u8]) -> Analyzed<GoldilocksField> {
+        let serialized: SerializedAnalyzed = serde_cbor::from_slice(bytes).unwrap();
+        serialized.try_into().unwrap()
+    }
+
+    #[test]
+    fn load_version_1() {
+        let pil = "namespace main(4);\n    col witness x;\n    main::x' = main::x;\n";
+        let expected: Analyzed<GoldilocksField> = powdr_pil_analyzer::analyze_string(pil).unwrap();
+        let loaded = golden(include_bytes!("../files/version_1.pilo"));
+        assert_eq!(loaded.to_string(), expected.to_string());
+    }
+
+    #[test]
+    fn load_version_2() {
+        let pil = r#"namespace main(4);
+    let f: int -> int = |x| x + 1_int;
+    col fixed FIRST = [1_fe] + [0_fe]*;
+    col fixed LINE(i) { i };
+    col witness x;
+    col witness y;
+    main::FIRST * (main::y - 1) = 0;
+    main::x' = main::y;
+    [main::x] in [main::LINE];
+    public out = main::y(3);
+"#;
+        let expected: Analyzed<GoldilocksField> = powdr_pil_analyzer::analyze_string(pil).unwrap();
+        let loaded = golden(include_bytes!("../files/version_2.pilo"));
+        assert_eq!(loaded.to_string(), expected.to_string());
+    }
+
+    #[test]
+    fn wrong_field() {
+        let serialized = SerializedAnalyzed::try_from(&analyzed()).unwrap();
+        let err = Analyzed::<Bn254Field>::try_from(serialized).err().unwrap();
+        assert_eq!(
+            err,
+            "Invalid .pilo field. Expected Bn254Field but got GoldilocksField"
+        );
+    }
+
+    #[test]
+    fn newer_version() {
+        let mut serialized = SerializedAnalyzed::try_from(&analyzed()).unwrap();
+        serialized.version = CURRENT_VERSION + 1;
+        let err = Analyzed::<GoldilocksField>::try_from(serialized)
+            .err()
+            .unwrap();
+        assert!(err.starts_with("Unsupported .pilo version number"));
+    }
+}
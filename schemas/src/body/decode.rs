@@ -0,0 +1,704 @@
+//! Conversion of a [`Body`] into an `Analyzed`.
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    fmt::Display,
+    str::FromStr,
+    sync::Arc,
+};
+
+use powdr_ast::{
+    analyzed::{self, Analyzed, SolvedTraitImpls},
+    parsed::{self, asm, types},
+};
+use powdr_number::{BigInt, BigUint, FieldElement, LargeInt};
+use powdr_parser_util::SourceRef as AstSourceRef;
+
+use super::*;
+
+impl<T: FieldElement> TryFrom<Body> for Analyzed<T> {
+    type Error = String;
+
+    fn try_from(body: Body) -> Result<Self, Self::Error> {
+        let decoder = Decoder {
+            files: body
+                .files
+                .into_iter()
+                .map(|file| (file.name.map(Arc::from), file.contents.map(Arc::from)))
+                .collect(),
+        };
+
+        let definitions = body
+            .definitions
+            .into_iter()
+            .map(|definition| {
+                Ok((
+                    definition.name,
+                    (
+                        definition.symbol.decode(&decoder)?,
+                        definition.value.decode(&decoder)?,
+                    ),
+                ))
+            })
+            .collect::<Result<HashMap<_, _>, String>>()?;
+
+        let mut solved_impls = SolvedTraitImpls::default();
+        for solved_impl in body.solved_impls {
+            solved_impls.insert(
+                solved_impl.trait_function,
+                solved_impl.type_args.decode(&decoder)?,
+                solved_impl.index,
+                Arc::new(solved_impl.function.decode(&decoder)?),
+            );
+        }
+
+        let intermediate_columns = body
+            .intermediate_columns
+            .into_iter()
+            .map(|column| {
+                Ok((
+                    column.name,
+                    (
+                        column.symbol.decode(&decoder)?,
+                        column.expressions.decode(&decoder)?,
+                    ),
+                ))
+            })
+            .collect::<Result<HashMap<_, _>, String>>()?;
+
+        Ok(Analyzed {
+            definitions,
+            solved_impls,
+            intermediate_columns,
+            identities: body.identities.decode(&decoder)?,
+            prover_functions: body.prover_functions.decode(&decoder)?,
+            trait_impls: body.trait_impls.decode(&decoder)?,
+            source_order: body.source_order.decode(&decoder)?,
+            auto_added_symbols: body.auto_added_symbols.into_iter().collect(),
+        })
+    }
+}
+
+struct Decoder {
+    /// The names and contents of the source files, shared by all source references.
+    files: Vec<(Option<Arc<str>>, Option<Arc<str>>)>,
+}
+
+/// Converts a serialized type into its in-memory counterpart `D`.
+trait Decode<D> {
+    fn decode(self, decoder: &Decoder) -> Result<D, String>;
+}
+
+impl<D, E: Decode<D>> Decode<Vec<D>> for Vec<E> {
+    fn decode(self, decoder: &Decoder) -> Result<Vec<D>, String> {
+        self.into_iter().map(|e| e.decode(decoder)).collect()
+    }
+}
+
+impl<D, E: Decode<D>> Decode<Option<D>> for Option<E> {
+    fn decode(self, decoder: &Decoder) -> Result<Option<D>, String> {
+        self.map(|e| e.decode(decoder)).transpose()
+    }
+}
+
+impl<D, E: Decode<D>> Decode<Box<D>> for Box<E> {
+    fn decode(self, decoder: &Decoder) -> Result<Box<D>, String> {
+        Ok(Box::new((*self).decode(decoder)?))
+    }
+}
+
+fn parse_number<N: FromStr>(number: &str) -> Result<N, String>
+where
+    N::Err: Display,
+{
+    number
+        .parse()
+        .map_err(|e| format!("Invalid number {number}: {e}"))
+}
+
+impl Decode<AstSourceRef> for SourceRef {
+    fn decode(self, decoder: &Decoder) -> Result<AstSourceRef, String> {
+        let (file_name, file_contents) = decoder
+            .files
+            .get(self.file)
+            .ok_or_else(|| format!("Invalid source file index {}", self.file))?
+            .clone();
+        Ok(AstSourceRef {
+            file_name,
+            file_contents,
+            start: self.start,
+            end: self.end,
+        })
+    }
+}
+
+impl Decode<analyzed::StatementIdentifier> for StatementIdentifier {
+    fn decode(self, _: &Decoder) -> Result<analyzed::StatementIdentifier, String> {
+        Ok(match self {
+            Self::Definition(name) => analyzed::StatementIdentifier::Definition(name),
+            Self::ProofItem(index) => analyzed::StatementIdentifier::ProofItem(index),
+            Self::ProverFunction(index) => analyzed::StatementIdentifier::ProverFunction(index),
+            Self::TraitImplementation(index) => {
+                analyzed::StatementIdentifier::TraitImplementation(index)
+            }
+        })
+    }
+}
+
+impl Decode<analyzed::Symbol> for Symbol {
+    fn decode(self, decoder: &Decoder) -> Result<analyzed::Symbol, String> {
+        Ok(analyzed::Symbol {
+            id: self.id,
+            source: self.source.decode(decoder)?,
+            absolute_name: self.absolute_name,
+            stage: self.stage,
+            kind: match self.kind {
+                SymbolKind::Poly(ptype) => analyzed::SymbolKind::Poly(ptype.decode(decoder)?),
+                SymbolKind::Public => analyzed::SymbolKind::Public(),
+                SymbolKind::Other => analyzed::SymbolKind::Other(),
+            },
+            length: self.length,
+            degree: self.degree.map(|degree| analyzed::DegreeRange {
+                min: degree.min,
+                max: degree.max,
+            }),
+        })
+    }
+}
+
+impl Decode<analyzed::PolynomialType> for PolynomialType {
+    fn decode(self, _: &Decoder) -> Result<analyzed::PolynomialType, String> {
+        Ok(match self {
+            Self::Committed => analyzed::PolynomialType::Committed,
+            Self::Constant => analyzed::PolynomialType::Constant,
+            Self::Intermediate => analyzed::PolynomialType::Intermediate,
+        })
+    }
+}
+
+impl Decode<analyzed::FunctionValueDefinition> for FunctionValueDefinition {
+    fn decode(self, decoder: &Decoder) -> Result<analyzed::FunctionValueDefinition, String> {
+        Ok(match self {
+            Self::Array(array) => analyzed::FunctionValueDefinition::Array(array.decode(decoder)?),
+            Self::Expression(e) => {
+                analyzed::FunctionValueDefinition::Expression(analyzed::TypedExpression {
+                    e: e.e.decode(decoder)?,
+                    type_scheme: e.type_scheme.decode(decoder)?,
+                })
+            }
+            Self::TypeDeclaration(declaration) => {
+                analyzed::FunctionValueDefinition::TypeDeclaration(match declaration {
+                    TypeDeclaration::Enum(e) => parsed::TypeDeclaration::Enum(e.decode(decoder)?),
+                    TypeDeclaration::Struct(s) => {
+                        parsed::TypeDeclaration::Struct(parsed::StructDeclaration {
+                            name: s.name,
+                            type_vars: s.type_vars.decode(decoder)?,
+                            fields: s.fields.decode(decoder)?,
+                        })
+                    }
+                })
+            }
+            Self::TypeConstructor(declaration, variant) => {
+                analyzed::FunctionValueDefinition::TypeConstructor(
+                    Arc::new(declaration.decode(decoder)?),
+                    variant.decode(decoder)?,
+                )
+            }
+            Self::TraitDeclaration(declaration) => {
+                analyzed::FunctionValueDefinition::TraitDeclaration(declaration.decode(decoder)?)
+            }
+            Self::TraitFunction(declaration, function) => {
+                analyzed::FunctionValueDefinition::TraitFunction(
+                    Arc::new(declaration.decode(decoder)?),
+                    function.decode(decoder)?,
+                )
+            }
+            Self::PublicDeclaration(declaration) => {
+                analyzed::FunctionValueDefinition::PublicDeclaration(analyzed::PublicDeclaration {
+                    id: declaration.id,
+                    source: declaration.source.decode(decoder)?,
+                    name: declaration.name,
+                    value: declaration.value.decode(decoder)?,
+                })
+            }
+        })
+    }
+}
+
+impl<T: FieldElement> Decode<analyzed::Identity<T>> for Identity {
+    fn decode(self, decoder: &Decoder) -> Result<analyzed::Identity<T>, String> {
+        Ok(match self {
+            Self::Polynomial(i) => analyzed::PolynomialIdentity {
+                id: i.id,
+                source: i.source.decode(decoder)?,
+                expression: i.expression.decode(decoder)?,
+            }
+            .into(),
+            Self::Lookup(i) => analyzed::LookupIdentity {
+                id: i.id,
+                source: i.source.decode(decoder)?,
+                left: i.left.decode(decoder)?,
+                right: i.right.decode(decoder)?,
+            }
+            .into(),
+            Self::PhantomLookup(i) => analyzed::PhantomLookupIdentity {
+                id: i.id,
+                source: i.source.decode(decoder)?,
+                left: i.left.decode(decoder)?,
+                right: i.right.decode(decoder)?,
+                multiplicity: i.multiplicity.decode(decoder)?,
+            }
+            .into(),
+            Self::Permutation(i) => analyzed::PermutationIdentity {
+                id: i.id,
+                source: i.source.decode(decoder)?,
+                left: i.left.decode(decoder)?,
+                right: i.right.decode(decoder)?,
+            }
+            .into(),
+            Self::PhantomPermutation(i) => analyzed::PhantomPermutationIdentity {
+                id: i.id,
+                source: i.source.decode(decoder)?,
+                left: i.left.decode(decoder)?,
+                right: i.right.decode(decoder)?,
+            }
+            .into(),
+            Self::Connect(i) => analyzed::ConnectIdentity {
+                id: i.id,
+                source: i.source.decode(decoder)?,
+                left: i.left.decode(decoder)?,
+                right: i.right.decode(decoder)?,
+            }
+            .into(),
+            Self::BusInteraction(i) => analyzed::BusInteractionIdentity {
+                id: i.id,
+                source: i.source.decode(decoder)?,
+                multiplicity: i.multiplicity.decode(decoder)?,
+                bus_id: i.bus_id.decode(decoder)?,
+                payload: analyzed::ExpressionList(i.payload.decode(decoder)?),
+                latch: i.latch.decode(decoder)?,
+            }
+            .into(),
+            Self::PhantomBusInteraction(i) => analyzed::PhantomBusInteractionIdentity {
+                id: i.id,
+                source: i.source.decode(decoder)?,
+                multiplicity: i.multiplicity.decode(decoder)?,
+                bus_id: i.bus_id.decode(decoder)?,
+                payload: analyzed::ExpressionList(i.payload.decode(decoder)?),
+                latch: i.latch.decode(decoder)?,
+                folded_expressions: analyzed::ExpressionList(i.folded_expressions.decode(decoder)?),
+                accumulator_columns: i.accumulator_columns.decode(decoder)?,
+                helper_columns: i.helper_columns.decode(decoder)?,
+            }
+            .into(),
+        })
+    }
+}
+
+impl<T: FieldElement> Decode<analyzed::SelectedExpressions<T>> for SelectedExpressions {
+    fn decode(self, decoder: &Decoder) -> Result<analyzed::SelectedExpressions<T>, String> {
+        Ok(analyzed::SelectedExpressions {
+            selector: self.selector.decode(decoder)?,
+            expressions: self.expressions.decode(decoder)?,
+        })
+    }
+}
+
+impl<T: FieldElement> Decode<analyzed::AlgebraicExpression<T>> for AlgebraicExpression {
+    fn decode(self, decoder: &Decoder) -> Result<analyzed::AlgebraicExpression<T>, String> {
+        Ok(match self {
+            Self::Reference(r) => {
+                analyzed::AlgebraicExpression::Reference(analyzed::AlgebraicReference {
+                    name: r.name,
+                    poly_id: analyzed::PolyID {
+                        id: r.id,
+                        ptype: r.ptype.decode(decoder)?,
+                    },
+                    next: r.next,
+                })
+            }
+            Self::PublicReference(name) => analyzed::AlgebraicExpression::PublicReference(name),
+            Self::Challenge { id, stage } => {
+                analyzed::AlgebraicExpression::Challenge(analyzed::Challenge { id, stage })
+            }
+            Self::Number(n) => {
+                let value: BigUint = parse_number(&n)?;
+                if value >= T::modulus().to_arbitrary_integer() {
+                    return Err(format!("Field element {n} is not smaller than the modulus"));
+                }
+                analyzed::AlgebraicExpression::Number(T::from(value))
+            }
+            Self::BinaryOperation(left, op, right) => {
+                analyzed::AlgebraicExpression::BinaryOperation(analyzed::AlgebraicBinaryOperation {
+                    left: left.decode(decoder)?,
+                    op: match op {
+                        AlgebraicBinaryOperator::Add => analyzed::AlgebraicBinaryOperator::Add,
+                        AlgebraicBinaryOperator::Sub => analyzed::AlgebraicBinaryOperator::Sub,
+                        AlgebraicBinaryOperator::Mul => analyzed::AlgebraicBinaryOperator::Mul,
+                        AlgebraicBinaryOperator::Pow => analyzed::AlgebraicBinaryOperator::Pow,
+                    },
+                    right: right.decode(decoder)?,
+                })
+            }
+            Self::UnaryOperation(op, expr) => {
+                analyzed::AlgebraicExpression::UnaryOperation(analyzed::AlgebraicUnaryOperation {
+                    op: match op {
+                        AlgebraicUnaryOperator::Minus => analyzed::AlgebraicUnaryOperator::Minus,
+                    },
+                    expr: expr.decode(decoder)?,
+                })
+            }
+        })
+    }
+}
+
+impl Decode<analyzed::Reference> for Reference {
+    fn decode(self, decoder: &Decoder) -> Result<analyzed::Reference, String> {
+        Ok(match self {
+            Self::LocalVar(id, name) => analyzed::Reference::LocalVar(id, name),
+            Self::Poly(name, type_args) => {
+                analyzed::Reference::Poly(analyzed::PolynomialReference {
+                    name,
+                    type_args: type_args.decode(decoder)?,
+                })
+            }
+        })
+    }
+}
+
+impl Decode<analyzed::Expression> for Expression {
+    fn decode(self, decoder: &Decoder) -> Result<analyzed::Expression, String> {
+        use analyzed::Expression as E;
+        Ok(match self {
+            Self::Reference(source, r) => E::Reference(source.decode(decoder)?, r.decode(decoder)?),
+            Self::Number(source, value, type_) => E::Number(
+                source.decode(decoder)?,
+                parsed::Number {
+                    value: parse_number(&value)?,
+                    type_: type_.decode(decoder)?,
+                },
+            ),
+            Self::String(source, s) => E::String(source.decode(decoder)?, s),
+            Self::Tuple(source, items) => E::Tuple(source.decode(decoder)?, items.decode(decoder)?),
+            Self::LambdaExpression(source, lambda) => E::LambdaExpression(
+                source.decode(decoder)?,
+                parsed::LambdaExpression {
+                    kind: match lambda.kind {
+                        FunctionKind::Pure => parsed::FunctionKind::Pure,
+                        FunctionKind::Constr => parsed::FunctionKind::Constr,
+                        FunctionKind::Query => parsed::FunctionKind::Query,
+                    },
+                    params: lambda.params.decode(decoder)?,
+                    body: lambda.body.decode(decoder)?,
+                    param_types: lambda.param_types.decode(decoder)?,
+                },
+            ),
+            Self::ArrayLiteral(source, items) => E::ArrayLiteral(
+                source.decode(decoder)?,
+                parsed::ArrayLiteral {
+                    items: items.decode(decoder)?,
+                },
+            ),
+            Self::UnaryOperation(source, op, expr) => E::UnaryOperation(
+                source.decode(decoder)?,
+                parsed::UnaryOperation {
+                    op: op.decode(decoder)?,
+                    expr: expr.decode(decoder)?,
+                },
+            ),
+            Self::BinaryOperation(source, left, op, right) => E::BinaryOperation(
+                source.decode(decoder)?,
+                parsed::BinaryOperation {
+                    left: left.decode(decoder)?,
+                    op: op.decode(decoder)?,
+                    right: right.decode(decoder)?,
+                },
+            ),
+            Self::IndexAccess(source, array, index) => E::IndexAccess(
+                source.decode(decoder)?,
+                parsed::IndexAccess {
+                    array: array.decode(decoder)?,
+                    index: index.decode(decoder)?,
+                },
+            ),
+            Self::FunctionCall(source, function, arguments) => E::FunctionCall(
+                source.decode(decoder)?,
+                parsed::FunctionCall {
+                    function: function.decode(decoder)?,
+                    arguments: arguments.decode(decoder)?,
+                },
+            ),
+            Self::FreeInput(source, e) => E::FreeInput(source.decode(decoder)?, e.decode(decoder)?),
+            Self::MatchExpression(source, scrutinee, arms) => E::MatchExpression(
+                source.decode(decoder)?,
+                parsed::MatchExpression {
+                    scrutinee: scrutinee.decode(decoder)?,
+                    arms: arms
+                        .into_iter()
+                        .map(|arm| {
+                            Ok(parsed::MatchArm {
+                                pattern: arm.pattern.decode(decoder)?,
+                                value: arm.value.decode(decoder)?,
+                            })
+                        })
+                        .collect::<Result<_, String>>()?,
+                },
+            ),
+            Self::IfExpression(source, condition, body, else_body) => E::IfExpression(
+                source.decode(decoder)?,
+                parsed::IfExpression {
+                    condition: condition.decode(decoder)?,
+                    body: body.decode(decoder)?,
+                    else_body: else_body.decode(decoder)?,
+                },
+            ),
+            Self::BlockExpression(source, statements, expr) => E::BlockExpression(
+                source.decode(decoder)?,
+                parsed::BlockExpression {
+                    statements: statements
+                        .into_iter()
+                        .map(|statement| {
+                            Ok(match statement {
+                                StatementInsideBlock::LetStatement(pattern, ty, value) => {
+                                    parsed::StatementInsideBlock::LetStatement(
+                                        parsed::LetStatementInsideBlock {
+                                            pattern: pattern.decode(decoder)?,
+                                            ty: ty.decode(decoder)?,
+                                            value: value.decode(decoder)?,
+                                        },
+                                    )
+                                }
+                                StatementInsideBlock::Expression(e) => {
+                                    parsed::StatementInsideBlock::Expression(e.decode(decoder)?)
+                                }
+                            })
+                        })
+                        .collect::<Result<_, String>>()?,
+                    expr: expr.decode(decoder)?,
+                },
+            ),
+            Self::StructExpression(source, name, fields) => E::StructExpression(
+                source.decode(decoder)?,
+                parsed::StructExpression {
+                    name: name.decode(decoder)?,
+                    fields: fields.decode(decoder)?,
+                },
+            ),
+        })
+    }
+}
+
+impl Decode<parsed::NamedExpression<Box<analyzed::Expression>>> for NamedExpression {
+    fn decode(
+        self,
+        decoder: &Decoder,
+    ) -> Result<parsed::NamedExpression<Box<analyzed::Expression>>, String> {
+        Ok(parsed::NamedExpression {
+            name: self.name,
+            body: Box::new(self.body.decode(decoder)?),
+        })
+    }
+}
+
+impl Decode<parsed::NamedExpression<Arc<analyzed::Expression>>> for NamedExpression {
+    fn decode(
+        self,
+        decoder: &Decoder,
+    ) -> Result<parsed::NamedExpression<Arc<analyzed::Expression>>, String> {
+        Ok(parsed::NamedExpression {
+            name: self.name,
+            body: Arc::new(self.body.decode(decoder)?),
+        })
+    }
+}
+
+impl Decode<parsed::UnaryOperator> for UnaryOperator {
+    fn decode(self, _: &Decoder) -> Result<parsed::UnaryOperator, String> {
+        Ok(match self {
+            Self::Minus => parsed::UnaryOperator::Minus,
+            Self::LogicalNot => parsed::UnaryOperator::LogicalNot,
+            Self::Next => parsed::UnaryOperator::Next,
+        })
+    }
+}
+
+impl Decode<parsed::BinaryOperator> for BinaryOperator {
+    fn decode(self, _: &Decoder) -> Result<parsed::BinaryOperator, String> {
+        use parsed::BinaryOperator as Op;
+        Ok(match self {
+            Self::Add => Op::Add,
+            Self::Sub => Op::Sub,
+            Self::Mul => Op::Mul,
+            Self::Div => Op::Div,
+            Self::Mod => Op::Mod,
+            Self::Pow => Op::Pow,
+            Self::BinaryAnd => Op::BinaryAnd,
+            Self::BinaryXor => Op::BinaryXor,
+            Self::BinaryOr => Op::BinaryOr,
+            Self::ShiftLeft => Op::ShiftLeft,
+            Self::ShiftRight => Op::ShiftRight,
+            Self::LogicalOr => Op::LogicalOr,
+            Self::LogicalAnd => Op::LogicalAnd,
+            Self::Less => Op::Less,
+            Self::LessEqual => Op::LessEqual,
+            Self::Equal => Op::Equal,
+            Self::Identity => Op::Identity,
+            Self::NotEqual => Op::NotEqual,
+            Self::GreaterEqual => Op::GreaterEqual,
+            Self::Greater => Op::Greater,
+            Self::In => Op::In,
+            Self::Is => Op::Is,
+            Self::Connect => Op::Connect,
+            Self::Select => Op::Select,
+        })
+    }
+}
+
+impl Decode<parsed::ArrayExpression<analyzed::Reference>> for ArrayExpression {
+    fn decode(
+        self,
+        decoder: &Decoder,
+    ) -> Result<parsed::ArrayExpression<analyzed::Reference>, String> {
+        Ok(match self {
+            Self::Value(items) => parsed::ArrayExpression::Value(items.decode(decoder)?),
+            Self::RepeatedValue(items) => {
+                parsed::ArrayExpression::RepeatedValue(items.decode(decoder)?)
+            }
+            Self::Concat(left, right) => {
+                parsed::ArrayExpression::Concat(left.decode(decoder)?, right.decode(decoder)?)
+            }
+        })
+    }
+}
+
+impl Decode<parsed::Pattern> for Pattern {
+    fn decode(self, decoder: &Decoder) -> Result<parsed::Pattern, String> {
+        use parsed::Pattern as P;
+        Ok(match self {
+            Self::CatchAll(source) => P::CatchAll(source.decode(decoder)?),
+            Self::Ellipsis(source) => P::Ellipsis(source.decode(decoder)?),
+            Self::Number(source, n) => {
+                P::Number(source.decode(decoder)?, parse_number::<BigInt>(&n)?)
+            }
+            Self::String(source, s) => P::String(source.decode(decoder)?, s),
+            Self::Tuple(source, items) => P::Tuple(source.decode(decoder)?, items.decode(decoder)?),
+            Self::Array(source, items) => P::Array(source.decode(decoder)?, items.decode(decoder)?),
+            Self::Variable(source, name) => P::Variable(source.decode(decoder)?, name),
+            Self::Enum(source, path, items) => P::Enum(
+                source.decode(decoder)?,
+                path.decode(decoder)?,
+                items.decode(decoder)?,
+            ),
+        })
+    }
+}
+
+impl Decode<asm::SymbolPath> for Vec<Part> {
+    fn decode(self, _: &Decoder) -> Result<asm::SymbolPath, String> {
+        Ok(asm::SymbolPath::from_parts(self.into_iter().map(
+            |part| match part {
+                Part::Super => asm::Part::Super,
+                Part::Named(name) => asm::Part::Named(name),
+            },
+        )))
+    }
+}
+
+impl Decode<types::Type> for Type {
+    fn decode(self, decoder: &Decoder) -> Result<types::Type, String> {
+        Ok(match self {
+            Self::Bottom => types::Type::Bottom,
+            Self::Bool => types::Type::Bool,
+            Self::Int => types::Type::Int,
+            Self::Fe => types::Type::Fe,
+            Self::String => types::Type::String,
+            Self::Col => types::Type::Col,
+            Self::Inter => types::Type::Inter,
+            Self::Expr => types::Type::Expr,
+            Self::Array(base, length) => types::Type::Array(types::ArrayType {
+                base: base.decode(decoder)?,
+                length,
+            }),
+            Self::Tuple(items) => types::Type::Tuple(types::TupleType {
+                items: items.decode(decoder)?,
+            }),
+            Self::Function(params, value) => types::Type::Function(types::FunctionType {
+                params: params.decode(decoder)?,
+                value: value.decode(decoder)?,
+            }),
+            Self::TypeVar(name) => types::Type::TypeVar(name),
+            Self::NamedType(path, args) => {
+                types::Type::NamedType(path.decode(decoder)?, args.decode(decoder)?)
+            }
+        })
+    }
+}
+
+impl Decode<types::TypeBounds> for Vec<TypeVar> {
+    fn decode(self, _: &Decoder) -> Result<types::TypeBounds, String> {
+        Ok(types::TypeBounds::new(self.into_iter().map(|var| {
+            (var.name, var.bounds.into_iter().collect::<BTreeSet<_>>())
+        })))
+    }
+}
+
+impl Decode<types::TypeScheme> for TypeScheme {
+    fn decode(self, decoder: &Decoder) -> Result<types::TypeScheme, String> {
+        Ok(types::TypeScheme {
+            vars: self.vars.decode(decoder)?,
+            ty: self.ty.decode(decoder)?,
+        })
+    }
+}
+
+impl Decode<parsed::EnumDeclaration> for EnumDeclaration {
+    fn decode(self, decoder: &Decoder) -> Result<parsed::EnumDeclaration, String> {
+        Ok(parsed::EnumDeclaration {
+            name: self.name,
+            type_vars: self.type_vars.decode(decoder)?,
+            variants: self.variants.decode(decoder)?,
+        })
+    }
+}
+
+impl Decode<parsed::EnumVariant> for EnumVariant {
+    fn decode(self, decoder: &Decoder) -> Result<parsed::EnumVariant, String> {
+        Ok(parsed::EnumVariant {
+            name: self.name,
+            fields: self.fields.decode(decoder)?,
+        })
+    }
+}
+
+impl Decode<parsed::NamedType> for NamedType {
+    fn decode(self, decoder: &Decoder) -> Result<parsed::NamedType, String> {
+        Ok(parsed::NamedType {
+            name: self.name,
+            ty: self.ty.decode(decoder)?,
+        })
+    }
+}
+
+impl Decode<parsed::TraitDeclaration> for TraitDeclaration {
+    fn decode(self, decoder: &Decoder) -> Result<parsed::TraitDeclaration, String> {
+        Ok(parsed::TraitDeclaration {
+            name: self.name,
+            type_vars: self.type_vars,
+            functions: self.functions.decode(decoder)?,
+        })
+    }
+}
+
+impl Decode<parsed::TraitImplementation<analyzed::Expression>> for TraitImplementation {
+    fn decode(
+        self,
+        decoder: &Decoder,
+    ) -> Result<parsed::TraitImplementation<analyzed::Expression>, String> {
+        Ok(parsed::TraitImplementation {
+            name: self.name.decode(decoder)?,
+            source_ref: self.source.decode(decoder)?,
+            type_scheme: self.type_scheme.decode(decoder)?,
+            functions: self.functions.decode(decoder)?,
+        })
+    }
+}
@@ -0,0 +1,696 @@
+//! Conversion of an `Analyzed` into a [`Body`].
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+};
+
+use powdr_ast::{
+    analyzed::{self, Analyzed},
+    parsed::{self, asm, types},
+};
+use powdr_number::FieldElement;
+use powdr_parser_util::SourceRef as AstSourceRef;
+
+use super::*;
+
+impl<T: FieldElement> From<&Analyzed<T>> for Body {
+    fn from(analyzed: &Analyzed<T>) -> Self {
+        let mut encoder = Encoder::default();
+
+        let definitions = analyzed
+            .definitions
+            .iter()
+            .collect::<BTreeMap<_, _>>()
+            .into_iter()
+            .map(|(name, (symbol, value))| Definition {
+                name: name.clone(),
+                symbol: symbol.encode(&mut encoder),
+                value: value.encode(&mut encoder),
+            })
+            .collect();
+
+        let mut solved_impls = analyzed.solved_impls.iter().collect::<Vec<_>>();
+        solved_impls
+            .sort_by(|(name1, args1, ..), (name2, args2, ..)| (name1, args1).cmp(&(name2, args2)));
+        let solved_impls = solved_impls
+            .into_iter()
+            .map(|(name, type_args, index, function)| SolvedImpl {
+                trait_function: name.clone(),
+                type_args: type_args.encode(&mut encoder),
+                index,
+                function: function.encode(&mut encoder),
+            })
+            .collect();
+
+        let intermediate_columns = analyzed
+            .intermediate_columns
+            .iter()
+            .collect::<BTreeMap<_, _>>()
+            .into_iter()
+            .map(|(name, (symbol, expressions))| IntermediateColumn {
+                name: name.clone(),
+                symbol: symbol.encode(&mut encoder),
+                expressions: expressions.encode(&mut encoder),
+            })
+            .collect();
+
+        let mut auto_added_symbols = analyzed
+            .auto_added_symbols
+            .iter()
+            .cloned()
+            .collect::<Vec<_>>();
+        auto_added_symbols.sort();
+
+        Body {
+            definitions,
+            solved_impls,
+            intermediate_columns,
+            identities: analyzed.identities.encode(&mut encoder),
+            prover_functions: analyzed.prover_functions.encode(&mut encoder),
+            trait_impls: analyzed.trait_impls.encode(&mut encoder),
+            source_order: analyzed.source_order.encode(&mut encoder),
+            auto_added_symbols,
+            files: encoder.files.into_files(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Encoder {
+    files: SourceFiles,
+    /// The file indices by the addresses of the shared file name and contents,
+    /// so that the contents are only compared once per file and not for every
+    /// source reference.
+    indices_by_address: HashMap<(usize, usize), usize>,
+}
+
+impl Encoder {
+    fn file_index(&mut self, source: &AstSourceRef) -> usize {
+        fn address(s: &Option<Arc<str>>) -> usize {
+            s.as_ref().map_or(0, |s| s.as_ptr() as usize)
+        }
+        let address = (address(&source.file_name), address(&source.file_contents));
+        if let Some(index) = self.indices_by_address.get(&address) {
+            return *index;
+        }
+        let index = self.files.index(SourceFile {
+            name: source.file_name.as_deref().map(str::to_string),
+            contents: source.file_contents.as_deref().map(str::to_string),
+        });
+        self.indices_by_address.insert(address, index);
+        index
+    }
+}
+
+/// Converts an in-memory type into its serialized counterpart.
+trait Encode {
+    type Encoded;
+
+    fn encode(&self, encoder: &mut Encoder) -> Self::Encoded;
+}
+
+impl<E: Encode> Encode for Vec<E> {
+    type Encoded = Vec<E::Encoded>;
+
+    fn encode(&self, encoder: &mut Encoder) -> Self::Encoded {
+        self.iter().map(|e| e.encode(encoder)).collect()
+    }
+}
+
+impl<E: Encode> Encode for Option<E> {
+    type Encoded = Option<E::Encoded>;
+
+    fn encode(&self, encoder: &mut Encoder) -> Self::Encoded {
+        self.as_ref().map(|e| e.encode(encoder))
+    }
+}
+
+impl<E: Encode> Encode for Box<E> {
+    type Encoded = Box<E::Encoded>;
+
+    fn encode(&self, encoder: &mut Encoder) -> Self::Encoded {
+        Box::new((**self).encode(encoder))
+    }
+}
+
+impl<E: Encode> Encode for Arc<E> {
+    type Encoded = E::Encoded;
+
+    fn encode(&self, encoder: &mut Encoder) -> Self::Encoded {
+        (**self).encode(encoder)
+    }
+}
+
+impl Encode for AstSourceRef {
+    type Encoded = SourceRef;
+
+    fn encode(&self, encoder: &mut Encoder) -> Self::Encoded {
+        SourceRef {
+            file: encoder.file_index(self),
+            start: self.start,
+            end: self.end,
+        }
+    }
+}
+
+impl Encode for analyzed::StatementIdentifier {
+    type Encoded = StatementIdentifier;
+
+    fn encode(&self, _: &mut Encoder) -> Self::Encoded {
+        match self {
+            Self::Definition(name) => StatementIdentifier::Definition(name.clone()),
+            Self::ProofItem(index) => StatementIdentifier::ProofItem(*index),
+            Self::ProverFunction(index) => StatementIdentifier::ProverFunction(*index),
+            Self::TraitImplementation(index) => StatementIdentifier::TraitImplementation(*index),
+        }
+    }
+}
+
+impl Encode for analyzed::Symbol {
+    type Encoded = Symbol;
+
+    fn encode(&self, encoder: &mut Encoder) -> Self::Encoded {
+        Symbol {
+            id: self.id,
+            source: self.source.encode(encoder),
+            absolute_name: self.absolute_name.clone(),
+            stage: self.stage,
+            kind: match self.kind {
+                analyzed::SymbolKind::Poly(ptype) => SymbolKind::Poly(ptype.encode(encoder)),
+                analyzed::SymbolKind::Public() => SymbolKind::Public,
+                analyzed::SymbolKind::Other() => SymbolKind::Other,
+            },
+            length: self.length,
+            degree: self.degree.map(|degree| DegreeRange {
+                min: degree.min,
+                max: degree.max,
+            }),
+        }
+    }
+}
+
+impl Encode for analyzed::PolynomialType {
+    type Encoded = PolynomialType;
+
+    fn encode(&self, _: &mut Encoder) -> Self::Encoded {
+        match self {
+            Self::Committed => PolynomialType::Committed,
+            Self::Constant => PolynomialType::Constant,
+            Self::Intermediate => PolynomialType::Intermediate,
+        }
+    }
+}
+
+impl Encode for analyzed::FunctionValueDefinition {
+    type Encoded = FunctionValueDefinition;
+
+    fn encode(&self, encoder: &mut Encoder) -> Self::Encoded {
+        match self {
+            Self::Array(array) => FunctionValueDefinition::Array(array.encode(encoder)),
+            Self::Expression(e) => FunctionValueDefinition::Expression(TypedExpression {
+                e: e.e.encode(encoder),
+                type_scheme: e.type_scheme.encode(encoder),
+            }),
+            Self::TypeDeclaration(declaration) => {
+                FunctionValueDefinition::TypeDeclaration(match declaration {
+                    parsed::TypeDeclaration::Enum(e) => TypeDeclaration::Enum(e.encode(encoder)),
+                    parsed::TypeDeclaration::Struct(s) => {
+                        TypeDeclaration::Struct(StructDeclaration {
+                            name: s.name.clone(),
+                            type_vars: s.type_vars.encode(encoder),
+                            fields: s.fields.encode(encoder),
+                        })
+                    }
+                })
+            }
+            Self::TypeConstructor(declaration, variant) => {
+                FunctionValueDefinition::TypeConstructor(
+                    declaration.encode(encoder),
+                    variant.encode(encoder),
+                )
+            }
+            Self::TraitDeclaration(declaration) => {
+                FunctionValueDefinition::TraitDeclaration(declaration.encode(encoder))
+            }
+            Self::TraitFunction(declaration, function) => FunctionValueDefinition::TraitFunction(
+                declaration.encode(encoder),
+                function.encode(encoder),
+            ),
+            Self::PublicDeclaration(declaration) => {
+                FunctionValueDefinition::PublicDeclaration(PublicDeclaration {
+                    id: declaration.id,
+                    source: declaration.source.encode(encoder),
+                    name: declaration.name.clone(),
+                    value: declaration.value.encode(encoder),
+                })
+            }
+        }
+    }
+}
+
+impl<T: FieldElement> Encode for analyzed::Identity<T> {
+    type Encoded = Identity;
+
+    fn encode(&self, encoder: &mut Encoder) -> Self::Encoded {
+        match self {
+            Self::Polynomial(i) => Identity::Polynomial(PolynomialIdentity {
+                id: i.id,
+                source: i.source.encode(encoder),
+                expression: i.expression.encode(encoder),
+            }),
+            Self::Lookup(i) => Identity::Lookup(LookupIdentity {
+                id: i.id,
+                source: i.source.encode(encoder),
+                left: i.left.encode(encoder),
+                right: i.right.encode(encoder),
+            }),
+            Self::PhantomLookup(i) => Identity::PhantomLookup(PhantomLookupIdentity {
+                id: i.id,
+                source: i.source.encode(encoder),
+                left: i.left.encode(encoder),
+                right: i.right.encode(encoder),
+                multiplicity: i.multiplicity.encode(encoder),
+            }),
+            Self::Permutation(i) => Identity::Permutation(PermutationIdentity {
+                id: i.id,
+                source: i.source.encode(encoder),
+                left: i.left.encode(encoder),
+                right: i.right.encode(encoder),
+            }),
+            Self::PhantomPermutation(i) => Identity::PhantomPermutation(PermutationIdentity {
+                id: i.id,
+                source: i.source.encode(encoder),
+                left: i.left.encode(encoder),
+                right: i.right.encode(encoder),
+            }),
+            Self::Connect(i) => Identity::Connect(ConnectIdentity {
+                id: i.id,
+                source: i.source.encode(encoder),
+                left: i.left.encode(encoder),
+                right: i.right.encode(encoder),
+            }),
+            Self::BusInteraction(i) => Identity::BusInteraction(BusInteractionIdentity {
+                id: i.id,
+                source: i.source.encode(encoder),
+                multiplicity: i.multiplicity.encode(encoder),
+                bus_id: i.bus_id.encode(encoder),
+                payload: i.payload.0.encode(encoder),
+                latch: i.latch.encode(encoder),
+            }),
+            Self::PhantomBusInteraction(i) => {
+                Identity::PhantomBusInteraction(PhantomBusInteractionIdentity {
+                    id: i.id,
+                    source: i.source.encode(encoder),
+                    multiplicity: i.multiplicity.encode(encoder),
+                    bus_id: i.bus_id.encode(encoder),
+                    payload: i.payload.0.encode(encoder),
+                    latch: i.latch.encode(encoder),
+                    folded_expressions: i.folded_expressions.0.encode(encoder),
+                    accumulator_columns: i.accumulator_columns.encode(encoder),
+                    helper_columns: i.helper_columns.encode(encoder),
+                })
+            }
+        }
+    }
+}
+
+impl<T: FieldElement> Encode for analyzed::SelectedExpressions<T> {
+    type Encoded = SelectedExpressions;
+
+    fn encode(&self, encoder: &mut Encoder) -> Self::Encoded {
+        SelectedExpressions {
+            selector: self.selector.encode(encoder),
+            expressions: self.expressions.encode(encoder),
+        }
+    }
+}
+
+impl<T: FieldElement> Encode for analyzed::AlgebraicExpression<T> {
+    type Encoded = AlgebraicExpression;
+
+    fn encode(&self, encoder: &mut Encoder) -> Self::Encoded {
+        match self {
+            Self::Reference(r) => AlgebraicExpression::Reference(AlgebraicReference {
+                name: r.name.clone(),
+                id: r.poly_id.id,
+                ptype: r.poly_id.ptype.encode(encoder),
+                next: r.next,
+            }),
+            Self::PublicReference(name) => AlgebraicExpression::PublicReference(name.clone()),
+            Self::Challenge(challenge) => AlgebraicExpression::Challenge {
+                id: challenge.id,
+                stage: challenge.stage,
+            },
+            Self::Number(n) => AlgebraicExpression::Number(n.to_arbitrary_integer().to_string()),
+            Self::BinaryOperation(operation) => AlgebraicExpression::BinaryOperation(
+                operation.left.encode(encoder),
+                match operation.op {
+                    analyzed::AlgebraicBinaryOperator::Add => AlgebraicBinaryOperator::Add,
+                    analyzed::AlgebraicBinaryOperator::Sub => AlgebraicBinaryOperator::Sub,
+                    analyzed::AlgebraicBinaryOperator::Mul => AlgebraicBinaryOperator::Mul,
+                    analyzed::AlgebraicBinaryOperator::Pow => AlgebraicBinaryOperator::Pow,
+                },
+                operation.right.encode(encoder),
+            ),
+            Self::UnaryOperation(operation) => AlgebraicExpression::UnaryOperation(
+                match operation.op {
+                    analyzed::AlgebraicUnaryOperator::Minus => AlgebraicUnaryOperator::Minus,
+                },
+                operation.expr.encode(encoder),
+            ),
+        }
+    }
+}
+
+impl Encode for analyzed::Reference {
+    type Encoded = Reference;
+
+    fn encode(&self, encoder: &mut Encoder) -> Self::Encoded {
+        match self {
+            Self::LocalVar(id, name) => Reference::LocalVar(*id, name.clone()),
+            Self::Poly(r) => Reference::Poly(r.name.clone(), r.type_args.encode(encoder)),
+        }
+    }
+}
+
+impl Encode for analyzed::Expression {
+    type Encoded = Expression;
+
+    fn encode(&self, encoder: &mut Encoder) -> Self::Encoded {
+        match self {
+            Self::Reference(source, r) => {
+                Expression::Reference(source.encode(encoder), r.encode(encoder))
+            }
+            Self::Number(source, n) => Expression::Number(
+                source.encode(encoder),
+                n.value.to_string(),
+                n.type_.encode(encoder),
+            ),
+            Self::String(source, s) => Expression::String(source.encode(encoder), s.clone()),
+            Self::Tuple(source, items) => {
+                Expression::Tuple(source.encode(encoder), items.encode(encoder))
+            }
+            Self::LambdaExpression(source, lambda) => Expression::LambdaExpression(
+                source.encode(encoder),
+                LambdaExpression {
+                    kind: match lambda.kind {
+                        parsed::FunctionKind::Pure => FunctionKind::Pure,
+                        parsed::FunctionKind::Constr => FunctionKind::Constr,
+                        parsed::FunctionKind::Query => FunctionKind::Query,
+                    },
+                    params: lambda.params.encode(encoder),
+                    body: lambda.body.encode(encoder),
+                    param_types: lambda.param_types.encode(encoder),
+                },
+            ),
+            Self::ArrayLiteral(source, array) => {
+                Expression::ArrayLiteral(source.encode(encoder), array.items.encode(encoder))
+            }
+            Self::UnaryOperation(source, operation) => Expression::UnaryOperation(
+                source.encode(encoder),
+                operation.op.encode(encoder),
+                operation.expr.encode(encoder),
+            ),
+            Self::BinaryOperation(source, operation) => Expression::BinaryOperation(
+                source.encode(encoder),
+                operation.left.encode(encoder),
+                operation.op.encode(encoder),
+                operation.right.encode(encoder),
+            ),
+            Self::IndexAccess(source, access) => Expression::IndexAccess(
+                source.encode(encoder),
+                access.array.encode(encoder),
+                access.index.encode(encoder),
+            ),
+            Self::FunctionCall(source, call) => Expression::FunctionCall(
+                source.encode(encoder),
+                call.function.encode(encoder),
+                call.arguments.encode(encoder),
+            ),
+            Self::FreeInput(source, e) => {
+                Expression::FreeInput(source.encode(encoder), e.encode(encoder))
+            }
+            Self::MatchExpression(source, m) => Expression::MatchExpression(
+                source.encode(encoder),
+                m.scrutinee.encode(encoder),
+                m.arms
+                    .iter()
+                    .map(|arm| MatchArm {
+                        pattern: arm.pattern.encode(encoder),
+                        value: arm.value.encode(encoder),
+                    })
+                    .collect(),
+            ),
+            Self::IfExpression(source, e) => Expression::IfExpression(
+                source.encode(encoder),
+                e.condition.encode(encoder),
+                e.body.encode(encoder),
+                e.else_body.encode(encoder),
+            ),
+            Self::BlockExpression(source, block) => Expression::BlockExpression(
+                source.encode(encoder),
+                block
+                    .statements
+                    .iter()
+                    .map(|statement| match statement {
+                        parsed::StatementInsideBlock::LetStatement(s) => {
+                            StatementInsideBlock::LetStatement(
+                                s.pattern.encode(encoder),
+                                s.ty.encode(encoder),
+                                s.value.encode(encoder),
+                            )
+                        }
+                        parsed::StatementInsideBlock::Expression(e) => {
+                            StatementInsideBlock::Expression(e.encode(encoder))
+                        }
+                    })
+                    .collect(),
+                block.expr.encode(encoder),
+            ),
+            Self::StructExpression(source, s) => Expression::StructExpression(
+                source.encode(encoder),
+                s.name.encode(encoder),
+                s.fields.encode(encoder),
+            ),
+        }
+    }
+}
+
+impl<E: AsRef<analyzed::Expression>> Encode for parsed::NamedExpression<E> {
+    type Encoded = NamedExpression;
+
+    fn encode(&self, encoder: &mut Encoder) -> Self::Encoded {
+        NamedExpression {
+            name: self.name.clone(),
+            body: self.body.as_ref().encode(encoder),
+        }
+    }
+}
+
+impl Encode for parsed::UnaryOperator {
+    type Encoded = UnaryOperator;
+
+    fn encode(&self, _: &mut Encoder) -> Self::Encoded {
+        match self {
+            Self::Minus => UnaryOperator::Minus,
+            Self::LogicalNot => UnaryOperator::LogicalNot,
+            Self::Next => UnaryOperator::Next,
+        }
+    }
+}
+
+impl Encode for parsed::BinaryOperator {
+    type Encoded = BinaryOperator;
+
+    fn encode(&self, _: &mut Encoder) -> Self::Encoded {
+        match self {
+            Self::Add => BinaryOperator::Add,
+            Self::Sub => BinaryOperator::Sub,
+            Self::Mul => BinaryOperator::Mul,
+            Self::Div => BinaryOperator::Div,
+            Self::Mod => BinaryOperator::Mod,
+            Self::Pow => BinaryOperator::Pow,
+            Self::BinaryAnd => BinaryOperator::BinaryAnd,
+            Self::BinaryXor => BinaryOperator::BinaryXor,
+            Self::BinaryOr => BinaryOperator::BinaryOr,
+            Self::ShiftLeft => BinaryOperator::ShiftLeft,
+            Self::ShiftRight => BinaryOperator::ShiftRight,
+            Self::LogicalOr => BinaryOperator::LogicalOr,
+            Self::LogicalAnd => BinaryOperator::LogicalAnd,
+            Self::Less => BinaryOperator::Less,
+            Self::LessEqual => BinaryOperator::LessEqual,
+            Self::Equal => BinaryOperator::Equal,
+            Self::Identity => BinaryOperator::Identity,
+            Self::NotEqual => BinaryOperator::NotEqual,
+            Self::GreaterEqual => BinaryOperator::GreaterEqual,
+            Self::Greater => BinaryOperator::Greater,
+            Self::In => BinaryOperator::In,
+            Self::Is => BinaryOperator::Is,
+            Self::Connect => BinaryOperator::Connect,
+            Self::Select => BinaryOperator::Select,
+        }
+    }
+}
+
+impl Encode for parsed::ArrayExpression<analyzed::Reference> {
+    type Encoded = ArrayExpression;
+
+    fn encode(&self, encoder: &mut Encoder) -> Self::Encoded {
+        match self {
+            Self::Value(items) => ArrayExpression::Value(items.encode(encoder)),
+            Self::RepeatedValue(items) => ArrayExpression::RepeatedValue(items.encode(encoder)),
+            Self::Concat(left, right) => {
+                ArrayExpression::Concat(left.encode(encoder), right.encode(encoder))
+            }
+        }
+    }
+}
+
+impl Encode for parsed::Pattern {
+    type Encoded = Pattern;
+
+    fn encode(&self, encoder: &mut Encoder) -> Self::Encoded {
+        match self {
+            Self::CatchAll(source) => Pattern::CatchAll(source.encode(encoder)),
+            Self::Ellipsis(source) => Pattern::Ellipsis(source.encode(encoder)),
+            Self::Number(source, n) => Pattern::Number(source.encode(encoder), n.to_string()),
+            Self::String(source, s) => Pattern::String(source.encode(encoder), s.clone()),
+            Self::Tuple(source, items) => {
+                Pattern::Tuple(source.encode(encoder), items.encode(encoder))
+            }
+            Self::Array(source, items) => {
+                Pattern::Array(source.encode(encoder), items.encode(encoder))
+            }
+            Self::Variable(source, name) => Pattern::Variable(source.encode(encoder), name.clone()),
+            Self::Enum(source, path, items) => Pattern::Enum(
+                source.encode(encoder),
+                path.encode(encoder),
+                items.encode(encoder),
+            ),
+        }
+    }
+}
+
+impl Encode for asm::SymbolPath {
+    type Encoded = Vec<Part>;
+
+    fn encode(&self, _: &mut Encoder) -> Self::Encoded {
+        self.parts()
+            .map(|part| match part {
+                asm::Part::Super => Part::Super,
+                asm::Part::Named(name) => Part::Named(name.clone()),
+            })
+            .collect()
+    }
+}
+
+impl Encode for types::Type {
+    type Encoded = Type;
+
+    fn encode(&self, encoder: &mut Encoder) -> Self::Encoded {
+        match self {
+            Self::Bottom => Type::Bottom,
+            Self::Bool => Type::Bool,
+            Self::Int => Type::Int,
+            Self::Fe => Type::Fe,
+            Self::String => Type::String,
+            Self::Col => Type::Col,
+            Self::Inter => Type::Inter,
+            Self::Expr => Type::Expr,
+            Self::Array(array) => Type::Array(array.base.encode(encoder), array.length),
+            Self::Tuple(tuple) => Type::Tuple(tuple.items.encode(encoder)),
+            Self::Function(function) => Type::Function(
+                function.params.encode(encoder),
+                function.value.encode(encoder),
+            ),
+            Self::TypeVar(name) => Type::TypeVar(name.clone()),
+            Self::NamedType(path, args) => {
+                Type::NamedType(path.encode(encoder), args.encode(encoder))
+            }
+        }
+    }
+}
+
+impl Encode for types::TypeBounds {
+    type Encoded = Vec<TypeVar>;
+
+    fn encode(&self, _: &mut Encoder) -> Self::Encoded {
+        self.bounds()
+            .map(|(name, bounds)| TypeVar {
+                name: name.clone(),
+                bounds: bounds.iter().cloned().collect(),
+            })
+            .collect()
+    }
+}
+
+impl Encode for types::TypeScheme {
+    type Encoded = TypeScheme;
+
+    fn encode(&self, encoder: &mut Encoder) -> Self::Encoded {
+        TypeScheme {
+            vars: self.vars.encode(encoder),
+            ty: self.ty.encode(encoder),
+        }
+    }
+}
+
+impl Encode for parsed::EnumDeclaration {
+    type Encoded = EnumDeclaration;
+
+    fn encode(&self, encoder: &mut Encoder) -> Self::Encoded {
+        EnumDeclaration {
+            name: self.name.clone(),
+            type_vars: self.type_vars.encode(encoder),
+            variants: self.variants.encode(encoder),
+        }
+    }
+}
+
+impl Encode for parsed::EnumVariant {
+    type Encoded = EnumVariant;
+
+    fn encode(&self, encoder: &mut Encoder) -> Self::Encoded {
+        EnumVariant {
+            name: self.name.clone(),
+            fields: self.fields.encode(encoder),
+        }
+    }
+}
+
+impl Encode for parsed::NamedType {
+    type Encoded = NamedType;
+
+    fn encode(&self, encoder: &mut Encoder) -> Self::Encoded {
+        NamedType {
+            name: self.name.clone(),
+            ty: self.ty.encode(encoder),
+        }
+    }
+}
+
+impl Encode for parsed::TraitDeclaration {
+    type Encoded = TraitDeclaration;
+
+    fn encode(&self, encoder: &mut Encoder) -> Self::Encoded {
+        TraitDeclaration {
+            name: self.name.clone(),
+            type_vars: self.type_vars.clone(),
+            functions: self.functions.encode(encoder),
+        }
+    }
+}
+
+impl Encode for parsed::TraitImplementation<analyzed::Expression> {
+    type Encoded = TraitImplementation;
+
+    fn encode(&self, encoder: &mut Encoder) -> Self::Encoded {
+        TraitImplementation {
+            name: self.name.encode(encoder),
+            source: self.source_ref.encode(encoder),
+            type_scheme: self.type_scheme.encode(encoder),
+            functions: self.functions.encode(encoder),
+        }
+    }
+}
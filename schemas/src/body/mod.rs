@@ -0,0 +1,487 @@
+//! The body of a .pilo object of the current version.
+//!
+//! These types are the serialized format and are independent of the in-memory `Analyzed`.
+//! They must not change: any change to them requires a new version, a frozen copy of the
+//! old types in [`crate::migrations`] and a migration from the old to the new body.
+//!
+//! Compared to the in-memory types, source files are stored once in [`Body::files`] and
+//! referenced by index, maps are stored as vectors sorted by their keys, and numbers
+//! (including field elements) are stored as decimal strings.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+mod decode;
+mod encode;
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct Body {
+    pub files: Vec<SourceFile>,
+    /// The definitions, sorted by name.
+    pub definitions: Vec<Definition>,
+    /// The solved trait implementations, sorted by trait function name and type arguments.
+    pub solved_impls: Vec<SolvedImpl>,
+    /// The intermediate columns, sorted by name.
+    pub intermediate_columns: Vec<IntermediateColumn>,
+    pub identities: Vec<Identity>,
+    pub prover_functions: Vec<Expression>,
+    pub trait_impls: Vec<TraitImplementation>,
+    pub source_order: Vec<StatementIdentifier>,
+    /// The automatically added symbols, sorted by name.
+    pub auto_added_symbols: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct SourceFile {
+    pub name: Option<String>,
+    pub contents: Option<String>,
+}
+
+/// A location in the source file at index `file` of [`Body::files`].
+#[derive(Serialize, Deserialize)]
+pub(crate) struct SourceRef {
+    pub file: usize,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Collects the source files of a body, storing each distinct file once.
+#[derive(Default)]
+pub(crate) struct SourceFiles {
+    files: Vec<SourceFile>,
+    indices: HashMap<SourceFile, usize>,
+}
+
+impl SourceFiles {
+    /// Returns the index of the file, adding it if it is not known yet.
+    pub fn index(&mut self, file: SourceFile) -> usize {
+        *self.indices.entry(file).or_insert_with_key(|file| {
+            self.files.push(file.clone());
+            self.files.len() - 1
+        })
+    }
+
+    pub fn into_files(self) -> Vec<SourceFile> {
+        self.files
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct Definition {
+    pub name: String,
+    pub symbol: Symbol,
+    pub value: Option<FunctionValueDefinition>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct IntermediateColumn {
+    pub name: String,
+    pub symbol: Symbol,
+    pub expressions: Vec<AlgebraicExpression>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct SolvedImpl {
+    pub trait_function: String,
+    pub type_args: Vec<Type>,
+    /// The index into [`Body::trait_impls`].
+    pub index: usize,
+    pub function: Expression,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) enum StatementIdentifier {
+    Definition(String),
+    ProofItem(usize),
+    ProverFunction(usize),
+    TraitImplementation(usize),
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct Symbol {
+    pub id: u64,
+    pub source: SourceRef,
+    pub absolute_name: String,
+    pub stage: Option<u32>,
+    pub kind: SymbolKind,
+    pub length: Option<u64>,
+    pub degree: Option<DegreeRange>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct DegreeRange {
+    pub min: u64,
+    pub max: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) enum SymbolKind {
+    Poly(PolynomialType),
+    Public,
+    Other,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) enum PolynomialType {
+    Committed,
+    Constant,
+    Intermediate,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) enum FunctionValueDefinition {
+    Array(ArrayExpression),
+    Expression(TypedExpression),
+    TypeDeclaration(TypeDeclaration),
+    TypeConstructor(EnumDeclaration, EnumVariant),
+    TraitDeclaration(TraitDeclaration),
+    TraitFunction(TraitDeclaration, NamedType),
+    PublicDeclaration(PublicDeclaration),
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct PublicDeclaration {
+    pub id: u64,
+    pub source: SourceRef,
+    pub name: String,
+    pub value: Expression,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) enum Identity {
+    Polynomial(PolynomialIdentity),
+    Lookup(LookupIdentity),
+    PhantomLookup(PhantomLookupIdentity),
+    Permutation(PermutationIdentity),
+    PhantomPermutation(PermutationIdentity),
+    Connect(ConnectIdentity),
+    BusInteraction(BusInteractionIdentity),
+    PhantomBusInteraction(PhantomBusInteractionIdentity),
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct PolynomialIdentity {
+    pub id: u64,
+    pub source: SourceRef,
+    pub expression: AlgebraicExpression,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct SelectedExpressions {
+    pub selector: AlgebraicExpression,
+    pub expressions: Vec<AlgebraicExpression>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct LookupIdentity {
+    pub id: u64,
+    pub source: SourceRef,
+    pub left: SelectedExpressions,
+    pub right: SelectedExpressions,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct PhantomLookupIdentity {
+    pub id: u64,
+    pub source: SourceRef,
+    pub left: SelectedExpressions,
+    pub right: SelectedExpressions,
+    pub multiplicity: AlgebraicExpression,
+}
+
+/// Used for both permutations and phantom permutations.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct PermutationIdentity {
+    pub id: u64,
+    pub source: SourceRef,
+    pub left: SelectedExpressions,
+    pub right: SelectedExpressions,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct ConnectIdentity {
+    pub id: u64,
+    pub source: SourceRef,
+    pub left: Vec<AlgebraicExpression>,
+    pub right: Vec<AlgebraicExpression>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct BusInteractionIdentity {
+    pub id: u64,
+    pub source: SourceRef,
+    pub multiplicity: AlgebraicExpression,
+    pub bus_id: AlgebraicExpression,
+    pub payload: Vec<AlgebraicExpression>,
+    pub latch: AlgebraicExpression,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct PhantomBusInteractionIdentity {
+    pub id: u64,
+    pub source: SourceRef,
+    pub multiplicity: AlgebraicExpression,
+    pub bus_id: AlgebraicExpression,
+    pub payload: Vec<AlgebraicExpression>,
+    pub latch: AlgebraicExpression,
+    pub folded_expressions: Vec<AlgebraicExpression>,
+    pub accumulator_columns: Vec<AlgebraicExpression>,
+    pub helper_columns: Option<Vec<AlgebraicExpression>>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) enum AlgebraicExpression {
+    Reference(AlgebraicReference),
+    PublicReference(String),
+    Challenge {
+        id: u64,
+        stage: u32,
+    },
+    /// A field element.
+    Number(String),
+    BinaryOperation(
+        Box<AlgebraicExpression>,
+        AlgebraicBinaryOperator,
+        Box<AlgebraicExpression>,
+    ),
+    UnaryOperation(AlgebraicUnaryOperator, Box<AlgebraicExpression>),
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct AlgebraicReference {
+    pub name: String,
+    pub id: u64,
+    pub ptype: PolynomialType,
+    pub next: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) enum AlgebraicBinaryOperator {
+    Add,
+    Sub,
+    Mul,
+    Pow,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) enum AlgebraicUnaryOperator {
+    Minus,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) enum Reference {
+    LocalVar(u64, String),
+    Poly(String, Option<Vec<Type>>),
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct TypedExpression {
+    pub e: Expression,
+    pub type_scheme: Option<TypeScheme>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) enum Expression {
+    Reference(SourceRef, Reference),
+    /// A number literal in decimal and its type.
+    Number(SourceRef, String, Option<Type>),
+    String(SourceRef, String),
+    Tuple(SourceRef, Vec<Expression>),
+    LambdaExpression(SourceRef, LambdaExpression),
+    ArrayLiteral(SourceRef, Vec<Expression>),
+    UnaryOperation(SourceRef, UnaryOperator, Box<Expression>),
+    BinaryOperation(SourceRef, Box<Expression>, BinaryOperator, Box<Expression>),
+    /// The array and the index.
+    IndexAccess(SourceRef, Box<Expression>, Box<Expression>),
+    /// The function and the arguments.
+    FunctionCall(SourceRef, Box<Expression>, Vec<Expression>),
+    FreeInput(SourceRef, Box<Expression>),
+    /// The scrutinee and the arms.
+    MatchExpression(SourceRef, Box<Expression>, Vec<MatchArm>),
+    /// The condition, the body and the else body.
+    IfExpression(SourceRef, Box<Expression>, Box<Expression>, Box<Expression>),
+    /// The statements and the optional final expression.
+    BlockExpression(
+        SourceRef,
+        Vec<StatementInsideBlock>,
+        Option<Box<Expression>>,
+    ),
+    /// The name of the struct and its fields.
+    StructExpression(SourceRef, Reference, Vec<NamedExpression>),
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct LambdaExpression {
+    pub kind: FunctionKind,
+    pub params: Vec<Pattern>,
+    pub body: Box<Expression>,
+    pub param_types: Vec<Type>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) enum FunctionKind {
+    Pure,
+    Constr,
+    Query,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) enum UnaryOperator {
+    Minus,
+    LogicalNot,
+    Next,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) enum BinaryOperator {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    Pow,
+    BinaryAnd,
+    BinaryXor,
+    BinaryOr,
+    ShiftLeft,
+    ShiftRight,
+    LogicalOr,
+    LogicalAnd,
+    Less,
+    LessEqual,
+    Equal,
+    Identity,
+    NotEqual,
+    GreaterEqual,
+    Greater,
+    In,
+    Is,
+    Connect,
+    Select,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct MatchArm {
+    pub pattern: Pattern,
+    pub value: Expression,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) enum StatementInsideBlock {
+    /// The pattern, the declared type and the value.
+    LetStatement(Pattern, Option<Type>, Option<Expression>),
+    Expression(Expression),
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct NamedExpression {
+    pub name: String,
+    pub body: Expression,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) enum ArrayExpression {
+    Value(Vec<Expression>),
+    RepeatedValue(Vec<Expression>),
+    Concat(Box<ArrayExpression>, Box<ArrayExpression>),
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) enum Pattern {
+    CatchAll(SourceRef),
+    Ellipsis(SourceRef),
+    /// A (possibly negative) integer in decimal.
+    Number(SourceRef, String),
+    String(SourceRef, String),
+    Tuple(SourceRef, Vec<Pattern>),
+    Array(SourceRef, Vec<Pattern>),
+    Variable(SourceRef, String),
+    Enum(SourceRef, Vec<Part>, Option<Vec<Pattern>>),
+}
+
+/// A part of a symbol path.
+#[derive(Serialize, Deserialize)]
+pub(crate) enum Part {
+    Super,
+    Named(String),
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) enum Type {
+    Bottom,
+    Bool,
+    Int,
+    Fe,
+    String,
+    Col,
+    Inter,
+    Expr,
+    /// The base type and the length.
+    Array(Box<Type>, Option<u64>),
+    Tuple(Vec<Type>),
+    /// The parameter types and the return type.
+    Function(Vec<Type>, Box<Type>),
+    TypeVar(String),
+    NamedType(Vec<Part>, Option<Vec<Type>>),
+}
+
+/// A type variable and the names of the traits bounding it.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct TypeVar {
+    pub name: String,
+    pub bounds: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct TypeScheme {
+    pub vars: Vec<TypeVar>,
+    pub ty: Type,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) enum TypeDeclaration {
+    Enum(EnumDeclaration),
+    Struct(StructDeclaration),
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct EnumDeclaration {
+    pub name: String,
+    pub type_vars: Vec<TypeVar>,
+    pub variants: Vec<EnumVariant>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct EnumVariant {
+    pub name: String,
+    pub fields: Option<Vec<Type>>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct StructDeclaration {
+    pub name: String,
+    pub type_vars: Vec<TypeVar>,
+    pub fields: Vec<NamedType>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct NamedType {
+    pub name: String,
+    pub ty: Type,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct TraitDeclaration {
+    pub name: String,
+    pub type_vars: Vec<String>,
+    pub functions: Vec<NamedType>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct TraitImplementation {
+    pub name: Vec<Part>,
+    pub source: SourceRef,
+    pub type_scheme: TypeScheme,
+    pub functions: Vec<NamedExpression>,
+}
@@ -1,3 +1,5 @@
 mod analyzed;
+mod body;
+mod migrations;
 
-pub use analyzed::SerializedAnalyzed;
+pub use analyzed::{SerializedAnalyzed, CURRENT_VERSION};
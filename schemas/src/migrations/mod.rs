@@ -0,0 +1,39 @@
+//! Migrations of .pilo bodies between format versions.
+//!
+//! Each module contains a frozen copy of the types of the body of one version,
+//! together with the conversion into the body of the next version.
+
+use powdr_number::FieldElement;
+
+use crate::analyzed::CURRENT_VERSION;
+
+mod v1;
+
+/// Converts the body of a .pilo object of some version into the body of the next version.
+type Migration = fn(Vec<u8>) -> Result<Vec<u8>, String>;
+
+/// All migrations, the first one migrates from version 1 to version 2.
+fn migrations<T: FieldElement>() -> [Migration; CURRENT_VERSION as usize - 1] {
+    [v1::to_version_2::<T>]
+}
+
+/// Migrates the body of a .pilo object of the given version to [`CURRENT_VERSION`].
+pub(crate) fn migrate_to_current<T: FieldElement>(
+    version: u32,
+    mut body: Vec<u8>,
+) -> Result<Vec<u8>, String> {
+    for (from, migration) in migrations::<T>()
+        .into_iter()
+        .enumerate()
+        .skip(version as usize - 1)
+    {
+        body = migration(body).map_err(|e| {
+            format!(
+                "Failed to migrate .pilo object from version {} to {}: {e}",
+                from + 1,
+                from + 2
+            )
+        })?;
+    }
+    Ok(body)
+}
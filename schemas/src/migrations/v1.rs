@@ -0,0 +1,1191 @@
+//! Version 1 of the .pilo body: the CBOR serialization of the in-memory `Analyzed`
+//! as of that version.
+//!
+//! The types in this module are a frozen copy of the in-memory types at the time.
+//! They only exist to decode version 1 bodies and must not be changed.
+
+use std::collections::BTreeMap;
+
+use powdr_number::{BigInt, BigUint, FieldElement};
+use serde::Deserialize;
+
+use crate::body::{self, Body, SourceFiles};
+
+/// Converts a version 1 body into a version 2 body.
+pub(super) fn to_version_2<T: FieldElement>(body: Vec<u8>) -> Result<Vec<u8>, String> {
+    let analyzed: Analyzed<T> = serde_cbor::from_slice(&body)
+        .map_err(|e| format!("Failed to decode version 1 body: {e}"))?;
+    serde_cbor::to_vec(&Body::from(analyzed))
+        .map_err(|e| format!("Failed to encode version 2 body: {e}"))
+}
+
+#[derive(Deserialize)]
+struct Analyzed<T> {
+    definitions: BTreeMap<String, (Symbol, Option<FunctionValueDefinition>)>,
+    solved_impls: SolvedTraitImpls,
+    intermediate_columns: BTreeMap<String, (Symbol, Vec<AlgebraicExpression<T>>)>,
+    identities: Vec<Identity<T>>,
+    prover_functions: Vec<Expression>,
+    trait_impls: Vec<TraitImplementation>,
+    source_order: Vec<StatementIdentifier>,
+    auto_added_symbols: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct SolvedTraitImpls {
+    impls: BTreeMap<String, BTreeMap<Vec<Type>, ImplData>>,
+}
+
+#[derive(Deserialize)]
+struct ImplData {
+    index: usize,
+    function: Expression,
+}
+
+#[derive(Deserialize)]
+struct SourceRef {
+    file_name: Option<String>,
+    file_contents: Option<String>,
+    start: usize,
+    end: usize,
+}
+
+#[derive(Deserialize)]
+enum StatementIdentifier {
+    Definition(String),
+    ProofItem(usize),
+    ProverFunction(usize),
+    TraitImplementation(usize),
+}
+
+#[derive(Deserialize)]
+struct Symbol {
+    id: u64,
+    source: SourceRef,
+    absolute_name: String,
+    stage: Option<u32>,
+    kind: SymbolKind,
+    length: Option<u64>,
+    degree: Option<DegreeRange>,
+}
+
+#[derive(Deserialize)]
+struct DegreeRange {
+    min: u64,
+    max: u64,
+}
+
+#[derive(Deserialize)]
+enum SymbolKind {
+    Poly(PolynomialType),
+    Public(),
+    Other(),
+}
+
+#[derive(Deserialize)]
+enum PolynomialType {
+    Committed,
+    Constant,
+    Intermediate,
+}
+
+#[derive(Deserialize)]
+enum FunctionValueDefinition {
+    Array(ArrayExpression),
+    Expression(TypedExpression),
+    TypeDeclaration(TypeDeclaration),
+    TypeConstructor(EnumDeclaration, EnumVariant),
+    TraitDeclaration(TraitDeclaration),
+    TraitFunction(TraitDeclaration, NamedType),
+    PublicDeclaration(PublicDeclaration),
+}
+
+#[derive(Deserialize)]
+struct PublicDeclaration {
+    id: u64,
+    source: SourceRef,
+    name: String,
+    value: Expression,
+}
+
+#[derive(Deserialize)]
+enum Identity<T> {
+    Polynomial(PolynomialIdentity<T>),
+    Lookup(LookupIdentity<T>),
+    PhantomLookup(PhantomLookupIdentity<T>),
+    Permutation(PermutationIdentity<T>),
+    PhantomPermutation(PermutationIdentity<T>),
+    Connect(ConnectIdentity<T>),
+    BusInteraction(BusInteractionIdentity<T>),
+    PhantomBusInteraction(PhantomBusInteractionIdentity<T>),
+}
+
+#[derive(Deserialize)]
+struct PolynomialIdentity<T> {
+    id: u64,
+    source: SourceRef,
+    expression: AlgebraicExpression<T>,
+}
+
+#[derive(Deserialize)]
+struct SelectedExpressions<T> {
+    selector: AlgebraicExpression<T>,
+    expressions: Vec<AlgebraicExpression<T>>,
+}
+
+#[derive(Deserialize)]
+struct LookupIdentity<T> {
+    id: u64,
+    source: SourceRef,
+    left: SelectedExpressions<T>,
+    right: SelectedExpressions<T>,
+}
+
+#[derive(Deserialize)]
+struct PhantomLookupIdentity<T> {
+    id: u64,
+    source: SourceRef,
+    left: SelectedExpressions<T>,
+    right: SelectedExpressions<T>,
+    multiplicity: AlgebraicExpression<T>,
+}
+
+/// Permutations and phantom permutations had the same fields.
+#[derive(Deserialize)]
+struct PermutationIdentity<T> {
+    id: u64,
+    source: SourceRef,
+    left: SelectedExpressions<T>,
+    right: SelectedExpressions<T>,
+}
+
+#[derive(Deserialize)]
+struct ConnectIdentity<T> {
+    id: u64,
+    source: SourceRef,
+    left: Vec<AlgebraicExpression<T>>,
+    right: Vec<AlgebraicExpression<T>>,
+}
+
+#[derive(Deserialize)]
+struct ExpressionList<T>(Vec<AlgebraicExpression<T>>);
+
+#[derive(Deserialize)]
+struct BusInteractionIdentity<T> {
+    id: u64,
+    source: SourceRef,
+    multiplicity: AlgebraicExpression<T>,
+    bus_id: AlgebraicExpression<T>,
+    payload: ExpressionList<T>,
+    latch: AlgebraicExpression<T>,
+}
+
+#[derive(Deserialize)]
+struct PhantomBusInteractionIdentity<T> {
+    id: u64,
+    source: SourceRef,
+    multiplicity: AlgebraicExpression<T>,
+    bus_id: AlgebraicExpression<T>,
+    payload: ExpressionList<T>,
+    latch: AlgebraicExpression<T>,
+    folded_expressions: ExpressionList<T>,
+    accumulator_columns: Vec<AlgebraicExpression<T>>,
+    helper_columns: Option<Vec<AlgebraicExpression<T>>>,
+}
+
+#[derive(Deserialize)]
+enum AlgebraicExpression<T> {
+    Reference(AlgebraicReference),
+    PublicReference(String),
+    Challenge(Challenge),
+    Number(T),
+    BinaryOperation(AlgebraicBinaryOperation<T>),
+    UnaryOperation(AlgebraicUnaryOperation<T>),
+}
+
+#[derive(Deserialize)]
+struct AlgebraicReference {
+    name: String,
+    poly_id: PolyID,
+    next: bool,
+}
+
+#[derive(Deserialize)]
+struct PolyID {
+    id: u64,
+    ptype: PolynomialType,
+}
+
+#[derive(Deserialize)]
+struct Challenge {
+    id: u64,
+    stage: u32,
+}
+
+#[derive(Deserialize)]
+struct AlgebraicBinaryOperation<T> {
+    left: Box<AlgebraicExpression<T>>,
+    op: AlgebraicBinaryOperator,
+    right: Box<AlgebraicExpression<T>>,
+}
+
+#[derive(Deserialize)]
+enum AlgebraicBinaryOperator {
+    Add,
+    Sub,
+    Mul,
+    Pow,
+}
+
+#[derive(Deserialize)]
+struct AlgebraicUnaryOperation<T> {
+    op: AlgebraicUnaryOperator,
+    expr: Box<AlgebraicExpression<T>>,
+}
+
+#[derive(Deserialize)]
+enum AlgebraicUnaryOperator {
+    Minus,
+}
+
+#[derive(Deserialize)]
+enum Reference {
+    LocalVar(u64, String),
+    Poly(PolynomialReference),
+}
+
+#[derive(Deserialize)]
+struct PolynomialReference {
+    name: String,
+    type_args: Option<Vec<Type>>,
+}
+
+#[derive(Deserialize)]
+struct TypedExpression {
+    e: Expression,
+    type_scheme: Option<TypeScheme>,
+}
+
+#[derive(Deserialize)]
+enum Expression {
+    Reference(SourceRef, Reference),
+    Number(SourceRef, Number),
+    String(SourceRef, String),
+    Tuple(SourceRef, Vec<Expression>),
+    LambdaExpression(SourceRef, LambdaExpression),
+    ArrayLiteral(SourceRef, ArrayLiteral),
+    UnaryOperation(SourceRef, UnaryOperation),
+    BinaryOperation(SourceRef, BinaryOperation),
+    IndexAccess(SourceRef, IndexAccess),
+    FunctionCall(SourceRef, FunctionCall),
+    FreeInput(SourceRef, Box<Expression>),
+    MatchExpression(SourceRef, MatchExpression),
+    IfExpression(SourceRef, IfExpression),
+    BlockExpression(SourceRef, BlockExpression),
+    StructExpression(SourceRef, StructExpression),
+}
+
+#[derive(Deserialize)]
+struct Number {
+    value: BigUint,
+    type_: Option<Type>,
+}
+
+#[derive(Deserialize)]
+struct LambdaExpression {
+    kind: FunctionKind,
+    params: Vec<Pattern>,
+    body: Box<Expression>,
+    param_types: Vec<Type>,
+}
+
+#[derive(Deserialize)]
+enum FunctionKind {
+    Pure,
+    Constr,
+    Query,
+}
+
+#[derive(Deserialize)]
+struct ArrayLiteral {
+    items: Vec<Expression>,
+}
+
+#[derive(Deserialize)]
+struct UnaryOperation {
+    op: UnaryOperator,
+    expr: Box<Expression>,
+}
+
+#[derive(Deserialize)]
+enum UnaryOperator {
+    Minus,
+    LogicalNot,
+    Next,
+}
+
+#[derive(Deserialize)]
+struct BinaryOperation {
+    left: Box<Expression>,
+    op: BinaryOperator,
+    right: Box<Expression>,
+}
+
+#[derive(Deserialize)]
+enum BinaryOperator {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    Pow,
+    BinaryAnd,
+    BinaryXor,
+    BinaryOr,
+    ShiftLeft,
+    ShiftRight,
+    LogicalOr,
+    LogicalAnd,
+    Less,
+    LessEqual,
+    Equal,
+    Identity,
+    NotEqual,
+    GreaterEqual,
+    Greater,
+    In,
+    Is,
+    Connect,
+    Select,
+}
+
+#[derive(Deserialize)]
+struct IndexAccess {
+    array: Box<Expression>,
+    index: Box<Expression>,
+}
+
+#[derive(Deserialize)]
+struct FunctionCall {
+    function: Box<Expression>,
+    arguments: Vec<Expression>,
+}
+
+#[derive(Deserialize)]
+struct MatchExpression {
+    scrutinee: Box<Expression>,
+    arms: Vec<MatchArm>,
+}
+
+#[derive(Deserialize)]
+struct MatchArm {
+    pattern: Pattern,
+    value: Expression,
+}
+
+#[derive(Deserialize)]
+struct IfExpression {
+    condition: Box<Expression>,
+    body: Box<Expression>,
+    else_body: Box<Expression>,
+}
+
+#[derive(Deserialize)]
+struct BlockExpression {
+    statements: Vec<StatementInsideBlock>,
+    expr: Option<Box<Expression>>,
+}
+
+#[derive(Deserialize)]
+enum StatementInsideBlock {
+    LetStatement(LetStatementInsideBlock),
+    Expression(Expression),
+}
+
+#[derive(Deserialize)]
+struct LetStatementInsideBlock {
+    pattern: Pattern,
+    ty: Option<Type>,
+    value: Option<Expression>,
+}
+
+#[derive(Deserialize)]
+struct StructExpression {
+    name: Reference,
+    fields: Vec<NamedExpression>,
+}
+
+#[derive(Deserialize)]
+struct NamedExpression {
+    name: String,
+    body: Expression,
+}
+
+#[derive(Deserialize)]
+enum ArrayExpression {
+    Value(Vec<Expression>),
+    RepeatedValue(Vec<Expression>),
+    Concat(Box<ArrayExpression>, Box<ArrayExpression>),
+}
+
+#[derive(Deserialize)]
+enum Pattern {
+    CatchAll(SourceRef),
+    Ellipsis(SourceRef),
+    Number(SourceRef, BigInt),
+    String(SourceRef, String),
+    Tuple(SourceRef, Vec<Pattern>),
+    Array(SourceRef, Vec<Pattern>),
+    Variable(SourceRef, String),
+    Enum(SourceRef, SymbolPath, Option<Vec<Pattern>>),
+}
+
+#[derive(Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+struct SymbolPath {
+    parts: Vec<Part>,
+}
+
+#[derive(Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+enum Part {
+    Super,
+    Named(String),
+}
+
+#[derive(Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+enum Type {
+    Bottom,
+    Bool,
+    Int,
+    Fe,
+    String,
+    Col,
+    Inter,
+    Expr,
+    Array(ArrayType),
+    Tuple(TupleType),
+    Function(FunctionType),
+    TypeVar(String),
+    NamedType(SymbolPath, Option<Vec<Type>>),
+}
+
+#[derive(Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+struct ArrayType {
+    base: Box<Type>,
+    length: Option<u64>,
+}
+
+#[derive(Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+struct TupleType {
+    items: Vec<Type>,
+}
+
+#[derive(Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+struct FunctionType {
+    params: Vec<Type>,
+    value: Box<Type>,
+}
+
+#[derive(Deserialize)]
+struct TypeScheme {
+    vars: TypeBounds,
+    ty: Type,
+}
+
+#[derive(Deserialize)]
+struct TypeBounds(Vec<(String, Vec<String>)>);
+
+#[derive(Deserialize)]
+enum TypeDeclaration {
+    Enum(EnumDeclaration),
+    Struct(StructDeclaration),
+}
+
+#[derive(Deserialize)]
+struct EnumDeclaration {
+    name: String,
+    type_vars: TypeBounds,
+    variants: Vec<EnumVariant>,
+}
+
+#[derive(Deserialize)]
+struct EnumVariant {
+    name: String,
+    fields: Option<Vec<Type>>,
+}
+
+#[derive(Deserialize)]
+struct StructDeclaration {
+    name: String,
+    type_vars: TypeBounds,
+    fields: Vec<NamedType>,
+}
+
+#[derive(Deserialize)]
+struct NamedType {
+    name: String,
+    ty: Type,
+}
+
+#[derive(Deserialize)]
+struct TraitDeclaration {
+    name: String,
+    type_vars: Vec<String>,
+    functions: Vec<NamedType>,
+}
+
+#[derive(Deserialize)]
+struct TraitImplementation {
+    name: SymbolPath,
+    source_ref: SourceRef,
+    type_scheme: TypeScheme,
+    functions: Vec<NamedExpression>,
+}
+
+impl<T: FieldElement> From<Analyzed<T>> for Body {
+    fn from(analyzed: Analyzed<T>) -> Self {
+        let mut files = SourceFiles::default();
+
+        let definitions = analyzed
+            .definitions
+            .into_iter()
+            .map(|(name, (symbol, value))| body::Definition {
+                name,
+                symbol: symbol.migrate(&mut files),
+                value: value.migrate(&mut files),
+            })
+            .collect();
+        let solved_impls = analyzed
+            .solved_impls
+            .impls
+            .into_iter()
+            .flat_map(|(name, impls)| {
+                impls
+                    .into_iter()
+                    .map(move |(type_args, data)| (name.clone(), type_args, data))
+            })
+            .map(|(trait_function, type_args, data)| body::SolvedImpl {
+                trait_function,
+                type_args: type_args.migrate(&mut files),
+                index: data.index,
+                function: data.function.migrate(&mut files),
+            })
+            .collect();
+        let intermediate_columns = analyzed
+            .intermediate_columns
+            .into_iter()
+            .map(|(name, (symbol, expressions))| body::IntermediateColumn {
+                name,
+                symbol: symbol.migrate(&mut files),
+                expressions: expressions.migrate(&mut files),
+            })
+            .collect();
+        let mut auto_added_symbols = analyzed.auto_added_symbols;
+        auto_added_symbols.sort();
+
+        Body {
+            definitions,
+            solved_impls,
+            intermediate_columns,
+            identities: analyzed.identities.migrate(&mut files),
+            prover_functions: analyzed.prover_functions.migrate(&mut files),
+            trait_impls: analyzed.trait_impls.migrate(&mut files),
+            source_order: analyzed.source_order.migrate(&mut files),
+            auto_added_symbols,
+            files: files.into_files(),
+        }
+    }
+}
+
+/// Converts a version 1 type into its version 2 counterpart.
+trait Migrate {
+    type Migrated;
+
+    fn migrate(self, files: &mut SourceFiles) -> Self::Migrated;
+}
+
+impl<M: Migrate> Migrate for Vec<M> {
+    type Migrated = Vec<M::Migrated>;
+
+    fn migrate(self, files: &mut SourceFiles) -> Self::Migrated {
+        self.into_iter().map(|m| m.migrate(files)).collect()
+    }
+}
+
+impl<M: Migrate> Migrate for Option<M> {
+    type Migrated = Option<M::Migrated>;
+
+    fn migrate(self, files: &mut SourceFiles) -> Self::Migrated {
+        self.map(|m| m.migrate(files))
+    }
+}
+
+impl<M: Migrate> Migrate for Box<M> {
+    type Migrated = Box<M::Migrated>;
+
+    fn migrate(self, files: &mut SourceFiles) -> Self::Migrated {
+        Box::new((*self).migrate(files))
+    }
+}
+
+impl Migrate for SourceRef {
+    type Migrated = body::SourceRef;
+
+    fn migrate(self, files: &mut SourceFiles) -> Self::Migrated {
+        body::SourceRef {
+            file: files.index(body::SourceFile {
+                name: self.file_name,
+                contents: self.file_contents,
+            }),
+            start: self.start,
+            end: self.end,
+        }
+    }
+}
+
+impl Migrate for StatementIdentifier {
+    type Migrated = body::StatementIdentifier;
+
+    fn migrate(self, _: &mut SourceFiles) -> Self::Migrated {
+        match self {
+            Self::Definition(name) => body::StatementIdentifier::Definition(name),
+            Self::ProofItem(index) => body::StatementIdentifier::ProofItem(index),
+            Self::ProverFunction(index) => body::StatementIdentifier::ProverFunction(index),
+            Self::TraitImplementation(index) => {
+                body::StatementIdentifier::TraitImplementation(index)
+            }
+        }
+    }
+}
+
+impl Migrate for Symbol {
+    type Migrated = body::Symbol;
+
+    fn migrate(self, files: &mut SourceFiles) -> Self::Migrated {
+        body::Symbol {
+            id: self.id,
+            source: self.source.migrate(files),
+            absolute_name: self.absolute_name,
+            stage: self.stage,
+            kind: match self.kind {
+                SymbolKind::Poly(ptype) => body::SymbolKind::Poly(ptype.migrate(files)),
+                SymbolKind::Public() => body::SymbolKind::Public,
+                SymbolKind::Other() => body::SymbolKind::Other,
+            },
+            length: self.length,
+            degree: self.degree.map(|degree| body::DegreeRange {
+                min: degree.min,
+                max: degree.max,
+            }),
+        }
+    }
+}
+
+impl Migrate for PolynomialType {
+    type Migrated = body::PolynomialType;
+
+    fn migrate(self, _: &mut SourceFiles) -> Self::Migrated {
+        match self {
+            Self::Committed => body::PolynomialType::Committed,
+            Self::Constant => body::PolynomialType::Constant,
+            Self::Intermediate => body::PolynomialType::Intermediate,
+        }
+    }
+}
+
+impl Migrate for FunctionValueDefinition {
+    type Migrated = body::FunctionValueDefinition;
+
+    fn migrate(self, files: &mut SourceFiles) -> Self::Migrated {
+        match self {
+            Self::Array(array) => body::FunctionValueDefinition::Array(array.migrate(files)),
+            Self::Expression(e) => {
+                body::FunctionValueDefinition::Expression(body::TypedExpression {
+                    e: e.e.migrate(files),
+                    type_scheme: e.type_scheme.migrate(files),
+                })
+            }
+            Self::TypeDeclaration(declaration) => {
+                body::FunctionValueDefinition::TypeDeclaration(match declaration {
+                    TypeDeclaration::Enum(e) => body::TypeDeclaration::Enum(e.migrate(files)),
+                    TypeDeclaration::Struct(s) => {
+                        body::TypeDeclaration::Struct(body::StructDeclaration {
+                            name: s.name,
+                            type_vars: s.type_vars.migrate(files),
+                            fields: s.fields.migrate(files),
+                        })
+                    }
+                })
+            }
+            Self::TypeConstructor(declaration, variant) => {
+                body::FunctionValueDefinition::TypeConstructor(
+                    declaration.migrate(files),
+                    variant.migrate(files),
+                )
+            }
+            Self::TraitDeclaration(declaration) => {
+                body::FunctionValueDefinition::TraitDeclaration(declaration.migrate(files))
+            }
+            Self::TraitFunction(declaration, function) => {
+                body::FunctionValueDefinition::TraitFunction(
+                    declaration.migrate(files),
+                    function.migrate(files),
+                )
+            }
+            Self::PublicDeclaration(declaration) => {
+                body::FunctionValueDefinition::PublicDeclaration(body::PublicDeclaration {
+                    id: declaration.id,
+                    source: declaration.source.migrate(files),
+                    name: declaration.name,
+                    value: declaration.value.migrate(files),
+                })
+            }
+        }
+    }
+}
+
+impl<T: FieldElement> Migrate for Identity<T> {
+    type Migrated = body::Identity;
+
+    fn migrate(self, files: &mut SourceFiles) -> Self::Migrated {
+        match self {
+            Self::Polynomial(i) => body::Identity::Polynomial(body::PolynomialIdentity {
+                id: i.id,
+                source: i.source.migrate(files),
+                expression: i.expression.migrate(files),
+            }),
+            Self::Lookup(i) => body::Identity::Lookup(body::LookupIdentity {
+                id: i.id,
+                source: i.source.migrate(files),
+                left: i.left.migrate(files),
+                right: i.right.migrate(files),
+            }),
+            Self::PhantomLookup(i) => body::Identity::PhantomLookup(body::PhantomLookupIdentity {
+                id: i.id,
+                source: i.source.migrate(files),
+                left: i.left.migrate(files),
+                right: i.right.migrate(files),
+                multiplicity: i.multiplicity.migrate(files),
+            }),
+            Self::Permutation(i) => body::Identity::Permutation(i.migrate(files)),
+            Self::PhantomPermutation(i) => body::Identity::PhantomPermutation(i.migrate(files)),
+            Self::Connect(i) => body::Identity::Connect(body::ConnectIdentity {
+                id: i.id,
+                source: i.source.migrate(files),
+                left: i.left.migrate(files),
+                right: i.right.migrate(files),
+            }),
+            Self::BusInteraction(i) => {
+                body::Identity::BusInteraction(body::BusInteractionIdentity {
+                    id: i.id,
+                    source: i.source.migrate(files),
+                    multiplicity: i.multiplicity.migrate(files),
+                    bus_id: i.bus_id.migrate(files),
+                    payload: i.payload.0.migrate(files),
+                    latch: i.latch.migrate(files),
+                })
+            }
+            Self::PhantomBusInteraction(i) => {
+                body::Identity::PhantomBusInteraction(body::PhantomBusInteractionIdentity {
+                    id: i.id,
+                    source: i.source.migrate(files),
+                    multiplicity: i.multiplicity.migrate(files),
+                    bus_id: i.bus_id.migrate(files),
+                    payload: i.payload.0.migrate(files),
+                    latch: i.latch.migrate(files),
+                    folded_expressions: i.folded_expressions.0.migrate(files),
+                    accumulator_columns: i.accumulator_columns.migrate(files),
+                    helper_columns: i.helper_columns.migrate(files),
+                })
+            }
+        }
+    }
+}
+
+impl<T: FieldElement> Migrate for PermutationIdentity<T> {
+    type Migrated = body::PermutationIdentity;
+
+    fn migrate(self, files: &mut SourceFiles) -> Self::Migrated {
+        body::PermutationIdentity {
+            id: self.id,
+            source: self.source.migrate(files),
+            left: self.left.migrate(files),
+            right: self.right.migrate(files),
+        }
+    }
+}
+
+impl<T: FieldElement> Migrate for SelectedExpressions<T> {
+    type Migrated = body::SelectedExpressions;
+
+    fn migrate(self, files: &mut SourceFiles) -> Self::Migrated {
+        body::SelectedExpressions {
+            selector: self.selector.migrate(files),
+            expressions: self.expressions.migrate(files),
+        }
+    }
+}
+
+impl<T: FieldElement> Migrate for AlgebraicExpression<T> {
+    type Migrated = body::AlgebraicExpression;
+
+    fn migrate(self, files: &mut SourceFiles) -> Self::Migrated {
+        match self {
+            Self::Reference(r) => body::AlgebraicExpression::Reference(body::AlgebraicReference {
+                name: r.name,
+                id: r.poly_id.id,
+                ptype: r.poly_id.ptype.migrate(files),
+                next: r.next,
+            }),
+            Self::PublicReference(name) => body::AlgebraicExpression::PublicReference(name),
+            Self::Challenge(challenge) => body::AlgebraicExpression::Challenge {
+                id: challenge.id,
+                stage: challenge.stage,
+            },
+            Self::Number(n) => {
+                body::AlgebraicExpression::Number(n.to_arbitrary_integer().to_string())
+            }
+            Self::BinaryOperation(operation) => body::AlgebraicExpression::BinaryOperation(
+                operation.left.migrate(files),
+                match operation.op {
+                    AlgebraicBinaryOperator::Add => body::AlgebraicBinaryOperator::Add,
+                    AlgebraicBinaryOperator::Sub => body::AlgebraicBinaryOperator::Sub,
+                    AlgebraicBinaryOperator::Mul => body::AlgebraicBinaryOperator::Mul,
+                    AlgebraicBinaryOperator::Pow => body::AlgebraicBinaryOperator::Pow,
+                },
+                operation.right.migrate(files),
+            ),
+            Self::UnaryOperation(operation) => body::AlgebraicExpression::UnaryOperation(
+                match operation.op {
+                    AlgebraicUnaryOperator::Minus => body::AlgebraicUnaryOperator::Minus,
+                },
+                operation.expr.migrate(files),
+            ),
+        }
+    }
+}
+
+impl Migrate for Reference {
+    type Migrated = body::Reference;
+
+    fn migrate(self, files: &mut SourceFiles) -> Self::Migrated {
+        match self {
+            Self::LocalVar(id, name) => body::Reference::LocalVar(id, name),
+            Self::Poly(r) => body::Reference::Poly(r.name, r.type_args.migrate(files)),
+        }
+    }
+}
+
+impl Migrate for Expression {
+    type Migrated = body::Expression;
+
+    fn migrate(self, files: &mut SourceFiles) -> Self::Migrated {
+        use body::Expression as E;
+        match self {
+            Self::Reference(source, r) => E::Reference(source.migrate(files), r.migrate(files)),
+            Self::Number(source, n) => E::Number(
+                source.migrate(files),
+                n.value.to_string(),
+                n.type_.migrate(files),
+            ),
+            Self::String(source, s) => E::String(source.migrate(files), s),
+            Self::Tuple(source, items) => E::Tuple(source.migrate(files), items.migrate(files)),
+            Self::LambdaExpression(source, lambda) => E::LambdaExpression(
+                source.migrate(files),
+                body::LambdaExpression {
+                    kind: match lambda.kind {
+                        FunctionKind::Pure => body::FunctionKind::Pure,
+                        FunctionKind::Constr => body::FunctionKind::Constr,
+                        FunctionKind::Query => body::FunctionKind::Query,
+                    },
+                    params: lambda.params.migrate(files),
+                    body: lambda.body.migrate(files),
+                    param_types: lambda.param_types.migrate(files),
+                },
+            ),
+            Self::ArrayLiteral(source, array) => {
+                E::ArrayLiteral(source.migrate(files), array.items.migrate(files))
+            }
+            Self::UnaryOperation(source, operation) => E::UnaryOperation(
+                source.migrate(files),
+                operation.op.migrate(files),
+                operation.expr.migrate(files),
+            ),
+            Self::BinaryOperation(source, operation) => E::BinaryOperation(
+                source.migrate(files),
+                operation.left.migrate(files),
+                operation.op.migrate(files),
+                operation.right.migrate(files),
+            ),
+            Self::IndexAccess(source, access) => E::IndexAccess(
+                source.migrate(files),
+                access.array.migrate(files),
+                access.index.migrate(files),
+            ),
+            Self::FunctionCall(source, call) => E::FunctionCall(
+                source.migrate(files),
+                call.function.migrate(files),
+                call.arguments.migrate(files),
+            ),
+            Self::FreeInput(source, e) => E::FreeInput(source.migrate(files), e.migrate(files)),
+            Self::MatchExpression(source, m) => E::MatchExpression(
+                source.migrate(files),
+                m.scrutinee.migrate(files),
+                m.arms
+                    .into_iter()
+                    .map(|arm| body::MatchArm {
+                        pattern: arm.pattern.migrate(files),
+                        value: arm.value.migrate(files),
+                    })
+                    .collect(),
+            ),
+            Self::IfExpression(source, e) => E::IfExpression(
+                source.migrate(files),
+                e.condition.migrate(files),
+                e.body.migrate(files),
+                e.else_body.migrate(files),
+            ),
+            Self::BlockExpression(source, block) => E::BlockExpression(
+                source.migrate(files),
+                block
+                    .statements
+                    .into_iter()
+                    .map(|statement| match statement {
+                        StatementInsideBlock::LetStatement(s) => {
+                            body::StatementInsideBlock::LetStatement(
+                                s.pattern.migrate(files),
+                                s.ty.migrate(files),
+                                s.value.migrate(files),
+                            )
+                        }
+                        StatementInsideBlock::Expression(e) => {
+                            body::StatementInsideBlock::Expression(e.migrate(files))
+                        }
+                    })
+                    .collect(),
+                block.expr.migrate(files),
+            ),
+            Self::StructExpression(source, s) => E::StructExpression(
+                source.migrate(files),
+                s.name.migrate(files),
+                s.fields.migrate(files),
+            ),
+        }
+    }
+}
+
+impl Migrate for NamedExpression {
+    type Migrated = body::NamedExpression;
+
+    fn migrate(self, files: &mut SourceFiles) -> Self::Migrated {
+        body::NamedExpression {
+            name: self.name,
+            body: self.body.migrate(files),
+        }
+    }
+}
+
+impl Migrate for UnaryOperator {
+    type Migrated = body::UnaryOperator;
+
+    fn migrate(self, _: &mut SourceFiles) -> Self::Migrated {
+        match self {
+            Self::Minus => body::UnaryOperator::Minus,
+            Self::LogicalNot => body::UnaryOperator::LogicalNot,
+            Self::Next => body::UnaryOperator::Next,
+        }
+    }
+}
+
+impl Migrate for BinaryOperator {
+    type Migrated = body::BinaryOperator;
+
+    fn migrate(self, _: &mut SourceFiles) -> Self::Migrated {
+        use body::BinaryOperator as Op;
+        match self {
+            Self::Add => Op::Add,
+            Self::Sub => Op::Sub,
+            Self::Mul => Op::Mul,
+            Self::Div => Op::Div,
+            Self::Mod => Op::Mod,
+            Self::Pow => Op::Pow,
+            Self::BinaryAnd => Op::BinaryAnd,
+            Self::BinaryXor => Op::BinaryXor,
+            Self::BinaryOr => Op::BinaryOr,
+            Self::ShiftLeft => Op::ShiftLeft,
+            Self::ShiftRight => Op::ShiftRight,
+            Self::LogicalOr => Op::LogicalOr,
+            Self::LogicalAnd => Op::LogicalAnd,
+            Self::Less => Op::Less,
+            Self::LessEqual => Op::LessEqual,
+            Self::Equal => Op::Equal,
+            Self::Identity => Op::Identity,
+            Self::NotEqual => Op::NotEqual,
+            Self::GreaterEqual => Op::GreaterEqual,
+            Self::Greater => Op::Greater,
+            Self::In => Op::In,
+            Self::Is => Op::Is,
+            Self::Connect => Op::Connect,
+            Self::Select => Op::Select,
+        }
+    }
+}
+
+impl Migrate for ArrayExpression {
+    type Migrated = body::ArrayExpression;
+
+    fn migrate(self, files: &mut SourceFiles) -> Self::Migrated {
+        match self {
+            Self::Value(items) => body::ArrayExpression::Value(items.migrate(files)),
+            Self::RepeatedValue(items) => {
+                body::ArrayExpression::RepeatedValue(items.migrate(files))
+            }
+            Self::Concat(left, right) => {
+                body::ArrayExpression::Concat(left.migrate(files), right.migrate(files))
+            }
+        }
+    }
+}
+
+impl Migrate for Pattern {
+    type Migrated = body::Pattern;
+
+    fn migrate(self, files: &mut SourceFiles) -> Self::Migrated {
+        use body::Pattern as P;
+        match self {
+            Self::CatchAll(source) => P::CatchAll(source.migrate(files)),
+            Self::Ellipsis(source) => P::Ellipsis(source.migrate(files)),
+            Self::Number(source, n) => P::Number(source.migrate(files), n.to_string()),
+            Self::String(source, s) => P::String(source.migrate(files), s),
+            Self::Tuple(source, items) => P::Tuple(source.migrate(files), items.migrate(files)),
+            Self::Array(source, items) => P::Array(source.migrate(files), items.migrate(files)),
+            Self::Variable(source, name) => P::Variable(source.migrate(files), name),
+            Self::Enum(source, path, items) => P::Enum(
+                source.migrate(files),
+                path.migrate(files),
+                items.migrate(files),
+            ),
+        }
+    }
+}
+
+impl Migrate for SymbolPath {
+    type Migrated = Vec<body::Part>;
+
+    fn migrate(self, _: &mut SourceFiles) -> Self::Migrated {
+        self.parts
+            .into_iter()
+            .map(|part| match part {
+                Part::Super => body::Part::Super,
+                Part::Named(name) => body::Part::Named(name),
+            })
+            .collect()
+    }
+}
+
+impl Migrate for Type {
+    type Migrated = body::Type;
+
+    fn migrate(self, files: &mut SourceFiles) -> Self::Migrated {
+        match self {
+            Self::Bottom => body::Type::Bottom,
+            Self::Bool => body::Type::Bool,
+            Self::Int => body::Type::Int,
+            Self::Fe => body::Type::Fe,
+            Self::String => body::Type::String,
+            Self::Col => body::Type::Col,
+            Self::Inter => body::Type::Inter,
+            Self::Expr => body::Type::Expr,
+            Self::Array(array) => body::Type::Array(array.base.migrate(files), array.length),
+            Self::Tuple(tuple) => body::Type::Tuple(tuple.items.migrate(files)),
+            Self::Function(function) => body::Type::Function(
+                function.params.migrate(files),
+                function.value.migrate(files),
+            ),
+            Self::TypeVar(name) => body::Type::TypeVar(name),
+            Self::NamedType(path, args) => {
+                body::Type::NamedType(path.migrate(files), args.migrate(files))
+            }
+        }
+    }
+}
+
+impl Migrate for TypeBounds {
+    type Migrated = Vec<body::TypeVar>;
+
+    fn migrate(self, _: &mut SourceFiles) -> Self::Migrated {
+        self.0
+            .into_iter()
+            .map(|(name, bounds)| body::TypeVar { name, bounds })
+            .collect()
+    }
+}
+
+impl Migrate for TypeScheme {
+    type Migrated = body::TypeScheme;
+
+    fn migrate(self, files: &mut SourceFiles) -> Self::Migrated {
+        body::TypeScheme {
+            vars: self.vars.migrate(files),
+            ty: self.ty.migrate(files),
+        }
+    }
+}
+
+impl Migrate for EnumDeclaration {
+    type Migrated = body::EnumDeclaration;
+
+    fn migrate(self, files: &mut SourceFiles) -> Self::Migrated {
+        body::EnumDeclaration {
+            name: self.name,
+            type_vars: self.type_vars.migrate(files),
+            variants: self.variants.migrate(files),
+        }
+    }
+}
+
+impl Migrate for EnumVariant {
+    type Migrated = body::EnumVariant;
+
+    fn migrate(self, files: &mut SourceFiles) -> Self::Migrated {
+        body::EnumVariant {
+            name: self.name,
+            fields: self.fields.migrate(files),
+        }
+    }
+}
+
+impl Migrate for NamedType {
+    type Migrated = body::NamedType;
+
+    fn migrate(self, files: &mut SourceFiles) -> Self::Migrated {
+        body::NamedType {
+            name: self.name,
+            ty: self.ty.migrate(files),
+        }
+    }
+}
+
+impl Migrate for TraitDeclaration {
+    type Migrated = body::TraitDeclaration;
+
+    fn migrate(self, files: &mut SourceFiles) -> Self::Migrated {
+        body::TraitDeclaration {
+            name: self.name,
+            type_vars: self.type_vars,
+            functions: self.functions.migrate(files),
+        }
+    }
+}
+
+impl Migrate for TraitImplementation {
+    type Migrated = body::TraitImplementation;
+
+    fn migrate(self, files: &mut SourceFiles) -> Self::Migrated {
+        body::TraitImplementation {
+            name: self.name.migrate(files),
+            source: self.source_ref.migrate(files),
+            type_scheme: self.type_scheme.migrate(files),
+            functions: self.functions.migrate(files),
+        }
+    }
+}
@@ -3,6 +3,7 @@
 let modulus: -> int = [];
 
 let BN254_PRIME: int = 0x30644e72e131a029b85045b68181585d2833e84879b9709143e1f593f0000001;
+let BLS12_381_PRIME: int = 0x73eda753299d7d483339d80809a1d80553bda402fffe5bfeffffffff00000001;
 let GOLDILOCKS_PRIME: int = 0xffffffff00000001;
 let KOALABEAR_PRIME: int = 2**31 - 2**24 + 1;
 let BABYBEAR_PRIME: int = 0x78000001;
@@ -11,6 +12,7 @@ let M31_PRIME: int = 2**31 - 1;
 /// All known fields
 enum KnownField {
     BN254,
+    BLS12_381,
     Goldilocks,
     KoalaBear,
     BabyBear,
@@ -34,7 +36,11 @@ let known_field: -> Option<KnownField> = || if modulus() == BABYBEAR_PRIME {
                 if modulus() == M31_PRIME {
                     Option::Some(KnownField::M31)
                 } else {
-                    Option::None
+                    if modulus() == BLS12_381_PRIME {
+                        Option::Some(KnownField::BLS12_381)
+                    } else {
+                        Option::None
+                    }
                 }
             }
         }
@@ -43,6 +49,7 @@ let known_field: -> Option<KnownField> = || if modulus() == BABYBEAR_PRIME {
 
 let require_known_field: KnownField, (-> string) -> () = |f, err| match (f, known_field()) {
     (KnownField::BN254, Option::Some(KnownField::BN254)) => (),
+    (KnownField::BLS12_381, Option::Some(KnownField::BLS12_381)) => (),
     (KnownField::Goldilocks, Option::Some(KnownField::Goldilocks)) => (),
     (KnownField::KoalaBear, Option::Some(KnownField::KoalaBear)) => (),
     (KnownField::BabyBear, Option::Some(KnownField::BabyBear)) => (),
@@ -26,6 +26,7 @@ machine ByteBinary with
             0 => a(i) & b(i),
             1 => a(i) | b(i),
             2 => a(i) ^ b(i),
+            _ => std::check::panic("Unknown binary operation"),
         }
     };
 }
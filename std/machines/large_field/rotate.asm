@@ -31,6 +31,7 @@ machine ByteRotate with
         match op(i) {
             0 => a(i) << (b(i) + (row(i) * 8)) | (a(i) << (row(i) * 8)) >> (32 - b(i)),
             1 => ((a(i) << (row(i) * 8)) >> b(i)) | (a(i) << (32 - b(i) + (row(i) * 8))),
+            _ => std::check::panic("Unknown rotate operation"),
         } & 0xffffffff
     };
 }
@@ -31,6 +31,7 @@ machine ByteShift with
         match op(i) {
             0 => a(i) << (b(i) + (row(i) * 8)),
             1 => (a(i) << (row(i) * 8)) >> b(i),
+            _ => std::check::panic("Unknown shift operation"),
         } & 0xffffffff
     };
 }
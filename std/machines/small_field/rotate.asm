@@ -32,6 +32,7 @@ machine ByteRotate with
     let P_C: int -> int = |i| match op(i) {
         0 => a(i) << (b(i) + (row(i) * 8)) | (a(i) << (row(i) * 8)) >> (32 - b(i)),
         1 => ((a(i) << (row(i) * 8)) >> b(i)) | (a(i) << (32 - b(i) + (row(i) * 8))),
+        _ => std::check::panic("Unknown rotate operation"),
     };
     col fixed P_C0(i) { P_C(i) & 0xffff };
     col fixed P_C1(i) { (P_C(i) >> 16) & 0xffff };
@@ -31,7 +31,8 @@ machine ByteShift with
     let P_operation: col = op;
     let c: int -> int = |i| match op(i) {
         0 => a(i) << (b(i) + (row(i) * 8)),
-        1 => (a(i) << (row(i) * 8)) >> b(i)
+        1 => (a(i) << (row(i) * 8)) >> b(i),
+        _ => std::check::panic("Unknown shift operation")
     };
     col fixed P_CLow(i) { c(i) & 0xffff };
     col fixed P_CHi(i) { (c(i) >> 16) & 0xffff };
@@ -1,3 +1,4 @@
+mod split_bls12_381;
 mod split_bn254;
 mod split_gl;
 mod split_gl_vec;
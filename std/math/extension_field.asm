@@ -11,6 +11,7 @@ let needs_extension: -> bool = || required_extension_size() > 1;
 let required_extension_size: -> int = || match known_field() {
     Option::Some(KnownField::Goldilocks) => 2,
     Option::Some(KnownField::BN254) => 1,
+    Option::Some(KnownField::BLS12_381) => 1,
     Option::Some(KnownField::BabyBear) => 4,
     Option::Some(KnownField::KoalaBear) => 4,
     Option::Some(KnownField::M31) => 4,
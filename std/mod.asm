@@ -11,5 +11,6 @@ mod prelude;
 mod protocols;
 mod prover;
 mod test;
+mod testing;
 mod utils;
 mod well_known;
@@ -44,6 +44,8 @@ let materialize_folded: -> bool = || match known_field() {
     // (the only prover that supports BN254) does not have a hard degree bound. So, we can
     // in-line the expression here.
     Option::Some(KnownField::BN254) => false,
+    // Same as BN254: no extension field and automatic witness generation.
+    Option::Some(KnownField::BLS12_381) => false,
     _ => panic("Unexpected field!")
 };
 
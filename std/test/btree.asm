@@ -27,7 +27,7 @@ let spaces: int -> string = |n|
         let s = spaces(n / 2);
         match n % 2 {
             0 => s + s,
-            1 => s + s +  " ",
+            _ => s + s +  " ",
         }
     };
 let print_item: (int, string), int -> () = |(k, v), indent| {
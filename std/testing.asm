@@ -0,0 +1,24 @@
+/// A test of a single operation of a machine, run by `powdr test` through
+/// witness generation and the mock backend.
+/// Machine tests are found in the same way as test functions: they are symbols of type
+/// `MachineTest` whose name starts with `test_` and that are inside a module called `test`
+/// (or a sub-module thereof).
+/// The machine is referenced by its absolute path (like "std::machines::split::split_gl::SplitGL"
+/// or "Add" for a machine at the root of the file). It is instantiated once for each test,
+/// and its machine parameters are instantiated recursively.
+enum MachineTest {
+    /// Calls the operation (second field) of the machine (first field) with the
+    /// given inputs and asserts that it returns the given outputs.
+    Returns(string, string, int[], int[]),
+    /// Asserts that calling the operation (second field) of the machine (first field)
+    /// with the given inputs and outputs is rejected in the given way.
+    Rejects(string, string, int[], int[], Rejection),
+}
+
+/// How a call in a `MachineTest::Rejects` is expected to be rejected.
+enum Rejection {
+    /// Witness generation fails with a message that contains the given string.
+    WitnessGeneration(string),
+    /// Witness generation succeeds, but the witness does not satisfy the constraints.
+    Constraints,
+}
@@ -11,7 +11,7 @@ machine Empty with degree: 16 {
     // The permutation (0 1) (2 3) (4 5) ...
     let r: col = |i| match i % 2 {
         0 => power_of_omega(i + 1),
-        1 => power_of_omega(i - 1),
+        _ => power_of_omega(i - 1),
     };
     let w: col;
     let f: col = |i| i / 2;
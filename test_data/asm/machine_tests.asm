@@ -0,0 +1,42 @@
+use std::testing::MachineTest;
+use std::testing::Rejection;
+
+machine Arith with
+    degree: 8,
+    latch: latch,
+    operation_id: operation_id
+{
+    operation add<0> x, y -> z;
+    operation double<1> x, y -> z;
+
+    col fixed latch = [1]*;
+    col witness operation_id;
+    col witness x, y, z;
+    z = (1 - operation_id) * (x + y) + operation_id * 2 * x;
+}
+
+machine Wrapper(arith: Arith) with
+    degree: 8,
+    latch: latch,
+    operation_id: operation_id
+{
+    operation add_one<0> x -> y;
+
+    link => y = arith.add(x, 1);
+
+    col fixed latch = [1]*;
+    col witness operation_id;
+    col witness x, y;
+}
+
+mod test {
+    use super::MachineTest;
+    use super::Rejection;
+
+    let test_add: MachineTest = MachineTest::Returns("Arith", "add", [2, 3], [5]);
+    let test_double: MachineTest = MachineTest::Returns("Arith", "double", [7, 0], [14]);
+    let test_wrong_sum: MachineTest = MachineTest::Rejects(
+        "Arith", "add", [2, 3], [6], Rejection::WitnessGeneration("Witness generation failed")
+    );
+    let test_with_parameter: MachineTest = MachineTest::Returns("Wrapper", "add_one", [4], [5]);
+}
@@ -0,0 +1,25 @@
+use std::testing::MachineTest;
+use std::testing::Rejection;
+
+machine Arith with
+    degree: 8,
+    latch: latch,
+    operation_id: operation_id
+{
+    operation add<0> x, y -> z;
+
+    col fixed latch = [1]*;
+    col witness operation_id;
+    col witness x, y, z;
+    z = x + y;
+}
+
+mod test {
+    use super::MachineTest;
+    use super::Rejection;
+
+    // The call is rejected by witness generation, not by the constraints.
+    let test_wrong_sum: MachineTest = MachineTest::Rejects(
+        "Arith", "add", [2, 3], [6], Rejection::Constraints
+    );
+}
@@ -29,7 +29,7 @@ mod R {
     machine FullConstant with degree: 4 {
         let C: int -> fe = |i| match i % 2 {
             0 => x,
-            1 => y,
+            _ => y,
         };
         // Use some weird type just for the sake of it.
         // We cannot call generic functions here.
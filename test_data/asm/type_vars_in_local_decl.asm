@@ -11,6 +11,7 @@ let q: int -> int = |i| {
     let r = x(i);
     match r {
         [[O::A(x)]] => i,
+        _ => 0,
     }
 };
 machine Main with degree: 4 {
@@ -0,0 +1,70 @@
+// Add and Mul have static sizes and only polynomial identities without next references,
+// so the composite backend stacks them into one machine, with a selector column each.
+// Add has 4 rows, so the first prover input, the number of additions, is at most 4.
+machine Main with degree: 256 {
+    Add add(4, 4);
+    Mul mul(8, 8);
+
+    reg pc[@pc];
+    reg X[<=];
+    reg Y[<=];
+    reg Z[<=];
+    reg A;
+    reg CNT;
+
+    col witness XInv;
+    col witness XIsZero;
+    XIsZero = 1 - X * XInv;
+    XIsZero * X = 0;
+    XIsZero * (1 - XIsZero) = 0;
+
+    instr add X, Y -> Z link => Z = add.add(X, Y);
+    instr mul X, Y -> Z link => Z = mul.mul(X, Y);
+    instr jmpz X, l: label { pc' = XIsZero * l + (1 - XIsZero) * (pc + 1) }
+    instr jmp l: label { pc' = l }
+
+    function main {
+        CNT <=X= ${ std::prelude::Query::Input(0, 1) };
+        A <=X= 0;
+
+        start:
+        jmpz CNT, done;
+        A <== add(A, 2);
+        CNT <=X= CNT - 1;
+        jmp start;
+
+        done:
+        A <== mul(A, 3);
+        A <== mul(A, 5);
+        A <== mul(A, 7);
+        return;
+    }
+}
+
+machine Add with
+    latch: latch,
+    operation_id: operation_id
+{
+    operation add<0> x, y -> z;
+
+    col fixed latch = [1]*;
+    col fixed operation_id = [0]*;
+    col witness x;
+    col witness y;
+    col witness z;
+    z = x + y;
+}
+
+machine Mul with
+    latch: latch,
+    operation_id: operation_id
+{
+    operation mul<0> x, y -> z;
+
+    col fixed latch = [1]*;
+    col fixed operation_id = [0]*;
+    col witness x;
+    col witness y;
+    col witness z;
+    z = x * y;
+}
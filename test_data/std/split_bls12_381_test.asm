@@ -0,0 +1,88 @@
+use std::machines::split::ByteCompare;
+use std::machines::split::split_bls12_381::SplitBLS12_381;
+
+let main_degree: int = 2**10;
+let split_degree: int = 2**12;
+
+machine Main with degree: main_degree {
+    reg pc[@pc];
+    reg X0[<=];
+    reg X1[<=];
+    reg X2[<=];
+    reg X3[<=];
+    reg X4[<=];
+    reg X5[<=];
+    reg X6[<=];
+    reg X7[<=];
+    reg X8[<=];
+    reg A1;
+    reg A2;
+    reg A3;
+    reg A4;
+    reg A5;
+    reg A6;
+    reg A7;
+    reg A8;
+
+    ByteCompare byte_compare;
+    SplitBLS12_381 split_machine(byte_compare, split_degree, split_degree);
+
+    instr split X0 -> X1, X2, X3, X4, X5, X6, X7, X8 link ~> (X1, X2, X3, X4, X5, X6, X7, X8) = split_machine.split(X0);
+
+    instr assert_eq X0, X1 {
+        X0 = X1
+    }
+
+    function main {
+
+        // Min value
+        // Note that this has two byte decompositions, 0 and p.
+        // The second would lead to a different split value, but should be ruled
+        // out by the overflow check.
+        A1, A2, A3, A4, A5, A6, A7, A8 <== split(0);
+        assert_eq A1, 0;
+        assert_eq A2, 0;
+        assert_eq A3, 0;
+        assert_eq A4, 0;
+        assert_eq A5, 0;
+        assert_eq A6, 0;
+        assert_eq A7, 0;
+        assert_eq A8, 0;
+
+        // Max value
+        // On BLS12-381, this is 0x73eda753 299d7d48 3339d808 09a1d805 53bda402 fffe5bfe ffffffff 00000000
+        A1, A2, A3, A4, A5, A6, A7, A8 <== split(-1);
+        assert_eq A1, 0;
+        assert_eq A2, 0xffffffff;
+        assert_eq A3, 0xfffe5bfe;
+        assert_eq A4, 0x53bda402;
+        assert_eq A5, 0x09a1d805;
+        assert_eq A6, 0x3339d808;
+        assert_eq A7, 0x299d7d48;
+        assert_eq A8, 0x73eda753;
+
+        // Max low values
+        A1, A2, A3, A4, A5, A6, A7, A8 <== split(0x72ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff);
+        assert_eq A1, 0xffffffff;
+        assert_eq A2, 0xffffffff;
+        assert_eq A3, 0xffffffff;
+        assert_eq A4, 0xffffffff;
+        assert_eq A5, 0xffffffff;
+        assert_eq A6, 0xffffffff;
+        assert_eq A7, 0xffffffff;
+        assert_eq A8, 0x72ffffff;
+
+        // Some other value
+        A1, A2, A3, A4, A5, A6, A7, A8 <== split(0xabcdef0123456789);
+        assert_eq A1, 0x23456789;
+        assert_eq A2, 0xabcdef01;
+        assert_eq A3, 0;
+        assert_eq A4, 0;
+        assert_eq A5, 0;
+        assert_eq A6, 0;
+        assert_eq A7, 0;
+        assert_eq A8, 0;
+
+        return;
+    }
+}